@@ -0,0 +1,342 @@
+use alloy::dyn_abi::EventExt;
+use alloy::json_abi::{Event, JsonAbi};
+use alloy::primitives::{Address, B256};
+use alloy::rpc::types::Log;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+use crate::{decode, profile, sourcify};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS abis (
+        chain_id INTEGER NOT NULL,
+        address TEXT NOT NULL,
+        name TEXT,
+        abi TEXT NOT NULL,
+        source TEXT NOT NULL,
+        added_at INTEGER NOT NULL,
+        PRIMARY KEY (chain_id, address)
+    );
+    CREATE TABLE IF NOT EXISTS misses (
+        chain_id INTEGER NOT NULL,
+        address TEXT NOT NULL,
+        checked_at INTEGER NOT NULL,
+        PRIMARY KEY (chain_id, address)
+    );
+";
+
+// Contracts Sourcify had no ABI for are asked again after a day, in case they've been verified since
+const MISS_TTL_SECS: u64 = 24 * 60 * 60;
+
+// Where a registered ABI came from. The user's own always wins over a Sourcify lookup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AbiSource {
+    User,
+    Sourcify,
+}
+
+impl AbiSource {
+    fn as_str(self) -> &'static str {
+        match self {
+            AbiSource::User => "user",
+            AbiSource::Sourcify => "sourcify",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisteredAbi {
+    pub chain_id: u64,
+    pub address: Address,
+    pub name: Option<String>,
+    pub abi: JsonAbi,
+    pub source: AbiSource,
+    pub added_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedParam {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub indexed: bool,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedEvent {
+    pub name: String,
+    pub signature: String,
+    pub params: Vec<DecodedParam>,
+}
+
+// A log as eth_getLogs returns it, with the event it decodes to when its contract's ABI is known
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedLog {
+    #[serde(flatten)]
+    pub log: Log,
+    pub decoded: Option<DecodedEvent>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn hex_address(address: Address) -> String {
+    format!("0x{:x}", address)
+}
+
+pub fn registry_path(app: &AppHandle) -> Option<PathBuf> {
+    profile::data_dir(app).map(|dir| dir.join("abis.sqlite"))
+}
+
+// Contract ABIs by chain and address, registered by the user or saved from Sourcify
+pub struct AbiRegistry {
+    conn: Connection,
+}
+
+impl AbiRegistry {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create ABI registry dir: {}", e))?;
+        }
+        let conn = Connection::open(path).map_err(|e| format!("Failed to open ABI registry: {}", e))?;
+        conn.execute_batch(SCHEMA).map_err(|e| format!("Failed to create ABI registry: {}", e))?;
+        Ok(Self { conn })
+    }
+
+    // A Sourcify ABI never replaces one the user registered
+    pub fn register(&self, chain_id: u64, address: Address, name: Option<&str>, abi: &JsonAbi, source: AbiSource) -> Result<(), String> {
+        let abi = serde_json::to_string(abi).map_err(|e| format!("Failed to serialize ABI: {}", e))?;
+        let statement = match source {
+            AbiSource::User => "INSERT OR REPLACE INTO abis (chain_id, address, name, abi, source, added_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            AbiSource::Sourcify => "INSERT OR IGNORE INTO abis (chain_id, address, name, abi, source, added_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        };
+        self.conn
+            .execute(statement, params![chain_id as i64, hex_address(address), name, abi, source.as_str(), now() as i64])
+            .map(|_| ())
+            .map_err(|e| format!("Failed to write ABI registry: {}", e))
+    }
+
+    pub fn remove(&self, chain_id: u64, address: Address) -> Result<bool, String> {
+        self.conn
+            .execute("DELETE FROM abis WHERE chain_id = ?1 AND address = ?2", params![chain_id as i64, hex_address(address)])
+            .map(|removed| removed > 0)
+            .map_err(|e| format!("Failed to write ABI registry: {}", e))
+    }
+
+    pub fn get(&self, chain_id: u64, address: Address) -> Result<Option<RegisteredAbi>, String> {
+        self.conn
+            .query_row(
+                "SELECT chain_id, address, name, abi, source, added_at FROM abis WHERE chain_id = ?1 AND address = ?2",
+                params![chain_id as i64, hex_address(address)],
+                row_to_abi,
+            )
+            .optional()
+            .map_err(|e| format!("Failed to query ABI registry: {}", e))
+    }
+
+    fn recently_missed(&self, chain_id: u64, address: Address) -> Result<bool, String> {
+        self.conn
+            .query_row(
+                "SELECT checked_at FROM misses WHERE chain_id = ?1 AND address = ?2",
+                params![chain_id as i64, hex_address(address)],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()
+            .map(|checked_at| checked_at.is_some_and(|checked_at| now().saturating_sub(checked_at as u64) < MISS_TTL_SECS))
+            .map_err(|e| format!("Failed to query ABI registry: {}", e))
+    }
+
+    fn record_miss(&self, chain_id: u64, address: Address) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO misses (chain_id, address, checked_at) VALUES (?1, ?2, ?3)",
+                params![chain_id as i64, hex_address(address), now() as i64],
+            )
+            .map(|_| ())
+            .map_err(|e| format!("Failed to write ABI registry: {}", e))
+    }
+
+    // The registered ABI, otherwise Sourcify's for a verified contract, which is then registered so
+    // later lookups stay local. Contracts without one are remembered for a while so they aren't looked
+    // up again on every log. Takes `&mut self` so the future stays Send, a connection can't be shared
+    // between threads
+    pub async fn resolve(
+        &mut self,
+        sourcify_cache: Option<&Path>,
+        chain_id: u64,
+        address: Address,
+    ) -> Result<Option<JsonAbi>, String> {
+        if let Some(registered) = self.get(chain_id, address)? {
+            return Ok(Some(registered.abi));
+        }
+        if self.recently_missed(chain_id, address)? {
+            return Ok(None);
+        }
+
+        let metadata = sourcify::get_contract_metadata(sourcify_cache, address, chain_id).await?;
+        let Some(abi) = metadata.abi.and_then(|abi| serde_json::from_value::<JsonAbi>(abi).ok()) else {
+            self.record_miss(chain_id, address)?;
+            return Ok(None);
+        };
+        self.register(chain_id, address, metadata.name.as_deref(), &abi, AbiSource::Sourcify)?;
+        Ok(Some(abi))
+    }
+
+    pub fn list(&self, chain_id: Option<u64>) -> Result<Vec<RegisteredAbi>, String> {
+        let mut statement = self.conn
+            .prepare("SELECT chain_id, address, name, abi, source, added_at FROM abis WHERE ?1 IS NULL OR chain_id = ?1 ORDER BY chain_id, address")
+            .map_err(|e| format!("Failed to query ABI registry: {}", e))?;
+        let rows = statement
+            .query_map(params![chain_id.map(|id| id as i64)], row_to_abi)
+            .map_err(|e| format!("Failed to query ABI registry: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read ABI registry: {}", e))
+    }
+}
+
+fn conversion_error(column: usize, e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, e.into())
+}
+
+fn row_to_abi(row: &rusqlite::Row<'_>) -> rusqlite::Result<RegisteredAbi> {
+    let address: String = row.get(1)?;
+    let abi: String = row.get(3)?;
+    let source: String = row.get(4)?;
+    Ok(RegisteredAbi {
+        chain_id: row.get::<_, i64>(0)? as u64,
+        address: address.parse().map_err(|e| conversion_error(1, e))?,
+        name: row.get(2)?,
+        abi: serde_json::from_str(&abi).map_err(|e| conversion_error(3, e))?,
+        source: match source.as_str() {
+            "user" => AbiSource::User,
+            "sourcify" => AbiSource::Sourcify,
+            other => return Err(conversion_error(4, format!("unknown ABI source {}", other))),
+        },
+        added_at: row.get::<_, i64>(5)? as u64,
+    })
+}
+
+fn decode_event(event: &Event, topics: &[B256], data: &[u8]) -> Option<DecodedEvent> {
+    let decoded = event.decode_log_parts(topics.iter().copied(), data, true).ok()?;
+    let mut indexed = decoded.indexed.iter();
+    let mut body = decoded.body.iter();
+    let params = event.inputs
+        .iter()
+        .map(|input| {
+            let value = if input.indexed { indexed.next() } else { body.next() }?;
+            Some(DecodedParam {
+                name: input.name.clone(),
+                kind: input.selector_type().into_owned(),
+                indexed: input.indexed,
+                value: decode::value_to_json(value),
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(DecodedEvent { name: event.name.clone(), signature: event.signature(), params })
+}
+
+// Decodes a log with its contract's ABI. Anonymous events have no selector topic, so they're only
+// tried once no named event matches
+pub fn decode_log(abi: &JsonAbi, topics: &[B256], data: &[u8]) -> Option<DecodedEvent> {
+    let (named, anonymous): (Vec<&Event>, Vec<&Event>) = abi.events().partition(|event| !event.anonymous);
+    named
+        .into_iter()
+        .filter(|event| topics.first() == Some(&event.selector()))
+        .chain(anonymous)
+        .find_map(|event| decode_event(event, topics, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{address, U256};
+
+    fn abi(with_anonymous: bool) -> JsonAbi {
+        let mut events = vec![
+            serde_json::json!({
+                "type": "event",
+                "name": "Transfer",
+                "anonymous": false,
+                "inputs": [
+                    { "name": "from", "type": "address", "indexed": true },
+                    { "name": "to", "type": "address", "indexed": true },
+                    { "name": "value", "type": "uint256", "indexed": false }
+                ]
+            }),
+        ];
+        if with_anonymous {
+            events.push(serde_json::json!({
+                "type": "event",
+                "name": "Ping",
+                "anonymous": true,
+                "inputs": [
+                    { "name": "id", "type": "uint256", "indexed": true },
+                    { "name": "amount", "type": "uint256", "indexed": false }
+                ]
+            }));
+        }
+        serde_json::from_value(serde_json::json!(events)).unwrap()
+    }
+
+    fn word(value: u64) -> B256 {
+        B256::from(U256::from(value))
+    }
+
+    #[test]
+    fn decodes_named_event() {
+        let abi = abi(true);
+        let from = address!("1111111111111111111111111111111111111111");
+        let to = address!("2222222222222222222222222222222222222222");
+        let topics = [abi.events().find(|e| e.name == "Transfer").unwrap().selector(), from.into_word(), to.into_word()];
+
+        let decoded = decode_log(&abi, &topics, word(5).as_slice()).unwrap();
+        assert_eq!(decoded.name, "Transfer");
+        assert_eq!(decoded.signature, "Transfer(address,address,uint256)");
+        let params: Vec<_> = decoded.params.iter().map(|p| (p.name.as_str(), p.indexed, p.value.clone())).collect();
+        assert_eq!(params, vec![
+            ("from", true, serde_json::json!(from.to_checksum(None))),
+            ("to", true, serde_json::json!(to.to_checksum(None))),
+            ("value", false, serde_json::json!("5")),
+        ]);
+    }
+
+    #[test]
+    fn decodes_anonymous_event_without_selector_topic() {
+        let decoded = decode_log(&abi(true), &[word(7)], word(9).as_slice()).unwrap();
+        assert_eq!(decoded.name, "Ping");
+        assert_eq!(decoded.params[0].value, serde_json::json!("7"));
+        assert_eq!(decoded.params[1].value, serde_json::json!("9"));
+    }
+
+    #[test]
+    fn mismatched_topic_does_not_decode() {
+        let topics = [B256::repeat_byte(0xab), word(1), word(2)];
+        assert!(decode_log(&abi(false), &topics, word(5).as_slice()).is_none());
+    }
+
+    #[test]
+    fn corrupt_rows_are_errors() {
+        let dir = std::env::temp_dir().join(format!("abi-registry-{}", rand::random::<u64>()));
+        let registry = AbiRegistry::open(&dir.join("abis.sqlite")).unwrap();
+        let contract = address!("3333333333333333333333333333333333333333");
+        registry.register(1, contract, None, &abi(false), AbiSource::User).unwrap();
+        registry.conn.execute("UPDATE abis SET abi = 'not json'", []).unwrap();
+
+        assert!(registry.get(1, contract).is_err());
+        assert!(registry.list(None).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,129 @@
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, EventTarget, Webview};
+
+// Where a request came from: a dapp webview, or a client of the local RPC server. Per-window
+// provider state is keyed by the whole caller, so a window that navigates to another dapp starts
+// fresh and two windows never share filters or rate limits
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Caller {
+    // Origin of the page, e.g. `https://app.uniswap.org`
+    pub origin: String,
+    pub webview: Option<String>,
+}
+
+impl Caller {
+    pub fn from_webview(webview: &Webview) -> Self {
+        Self {
+            origin: webview.url()
+                .map(|url| url.origin().ascii_serialization())
+                .unwrap_or_default(),
+            webview: Some(webview.label().to_string()),
+        }
+    }
+}
+
+// A dapp session, from approval of eth_requestAccounts until it is revoked
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Connection {
+    pub origin: String,
+    // Accounts exposed to the origin, the first one is the active account
+    pub accounts: Vec<Address>,
+    // Chain the dapp was connected on
+    pub chain_id: Option<u64>,
+    pub connected_at: u64,
+    // Webviews that have made requests for this origin and receive its events
+    #[serde(skip)]
+    webviews: HashSet<String>,
+}
+
+// Accounts each dapp origin has been given access to
+#[derive(Default)]
+pub struct Connections {
+    by_origin: HashMap<String, Connection>,
+    // Origin each webview last made a request from
+    webview_origins: HashMap<String, String>,
+}
+
+impl Connections {
+    pub fn accounts(&self, origin: &str) -> Vec<Address> {
+        self.by_origin.get(origin).map(|c| c.accounts.clone()).unwrap_or_default()
+    }
+
+    pub fn is_exposed(&self, origin: &str, address: Address) -> bool {
+        self.by_origin.get(origin).is_some_and(|c| c.accounts.contains(&address))
+    }
+
+    // Remembers the webview so events reach every window showing a connected origin. Returns true
+    // when the webview has navigated away from the origin it last made requests from, which stops
+    // receiving that origin's events
+    pub fn seen(&mut self, origin: &str, webview: Option<&str>) -> bool {
+        let Some(webview) = webview else {
+            return false;
+        };
+        let previous = self.webview_origins.insert(webview.to_string(), origin.to_string());
+        let navigated = previous.as_deref().is_some_and(|previous| previous != origin);
+        if navigated {
+            if let Some(connection) = previous.and_then(|previous| self.by_origin.get_mut(&previous)) {
+                connection.webviews.remove(webview);
+            }
+        }
+        if let Some(connection) = self.by_origin.get_mut(origin) {
+            connection.webviews.insert(webview.to_string());
+        }
+        navigated
+    }
+
+    pub fn connect(&mut self, origin: &str, webview: Option<&str>, accounts: Vec<Address>, chain_id: Option<u64>) {
+        let connected_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let connection = self.by_origin.entry(origin.to_string()).or_default();
+        connection.origin = origin.to_string();
+        connection.accounts = accounts;
+        connection.chain_id = chain_id;
+        connection.connected_at = connected_at;
+        connection.webviews.extend(webview.map(str::to_string));
+    }
+
+    pub fn list(&self) -> Vec<Connection> {
+        let mut sessions: Vec<Connection> = self.by_origin.values().cloned().collect();
+        sessions.sort_by_key(|session| session.connected_at);
+        sessions
+    }
+
+    // Brings back a session from a backup, an origin that is already connected keeps its current session
+    pub fn restore(&mut self, connection: Connection) -> bool {
+        if self.by_origin.contains_key(&connection.origin) {
+            return false;
+        }
+        self.by_origin.insert(connection.origin.clone(), connection);
+        true
+    }
+
+    // Forgets the session, the returned connection still knows which webviews to notify
+    pub fn revoke(&mut self, origin: &str) -> Option<Connection> {
+        self.by_origin.remove(origin)
+    }
+
+    // Makes `address` the origin's active account, exposing it if it wasn't already
+    pub fn set_active(&mut self, origin: &str, address: Address) -> Result<&Connection, String> {
+        let connection = self.by_origin
+            .get_mut(origin)
+            .ok_or_else(|| format!("{} is not connected", origin))?;
+        connection.accounts.retain(|account| *account != address);
+        connection.accounts.insert(0, address);
+        Ok(connection)
+    }
+}
+
+// Sends `accountsChanged` only to the webviews of the affected origin
+pub fn emit_accounts_changed(app: &AppHandle, connection: &Connection, accounts: &[Address]) {
+    for label in &connection.webviews {
+        let _ = app.emit_to(EventTarget::webview(label), "accountsChanged", accounts);
+    }
+}
@@ -0,0 +1,148 @@
+use alloy::primitives::{b256, Address, Log, B256, U256};
+use alloy::rpc::types::TransactionRequest;
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use serde::Serialize;
+use std::path::Path;
+
+use crate::sourcify;
+
+sol! {
+    function approve(address spender, uint256 amount);
+    function increaseAllowance(address spender, uint256 addedValue);
+    function setApprovalForAll(address operator, bool approved);
+    function permit(address owner, address spender, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s);
+}
+
+// `Approval(address,address,uint256)`, ERC-721 indexes the token id instead of logging an amount
+const APPROVAL_TOPIC: B256 = b256!("8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925");
+// `ApprovalForAll(address,address,bool)`
+const APPROVAL_FOR_ALL_TOPIC: B256 = b256!("17307eab39ab6107e8899845ad3d59bd9653f200f220920489ca2b5937696c31");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ApprovalKind {
+    Approve,
+    IncreaseAllowance,
+    SetApprovalForAll,
+    Permit,
+}
+
+// Something the approval UI should call out before the user signs
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalWarning {
+    pub kind: ApprovalKind,
+    pub token: Address,
+    pub spender: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<U256>,
+    pub unlimited: bool,
+    pub token_symbol: Option<String>,
+    // Filled in from Sourcify so the UI can tell a known router from an unverified contract
+    pub spender_verified: Option<bool>,
+    pub spender_name: Option<String>,
+}
+
+// No real token supply comes close to 2^128, so anything above it is treated as "infinite"
+fn is_unlimited(amount: U256) -> bool {
+    amount >= U256::from(u128::MAX)
+}
+
+fn allowance(kind: ApprovalKind, token: Address, spender: Address, amount: U256) -> Option<ApprovalWarning> {
+    (!amount.is_zero()).then(|| ApprovalWarning {
+        kind,
+        token,
+        spender,
+        amount: Some(amount),
+        unlimited: is_unlimited(amount),
+        token_symbol: None,
+        spender_verified: None,
+        spender_name: None,
+    })
+}
+
+fn operator(token: Address, spender: Address) -> ApprovalWarning {
+    ApprovalWarning {
+        kind: ApprovalKind::SetApprovalForAll,
+        token,
+        spender,
+        amount: None,
+        unlimited: true,
+        token_symbol: None,
+        spender_verified: None,
+        spender_name: None,
+    }
+}
+
+// Recognizes approvals in the top-level calldata of a transaction
+pub fn from_calldata(tx: &TransactionRequest) -> Option<ApprovalWarning> {
+    let token = *tx.to?.to()?;
+    let data = tx.input.input()?;
+    let selector: [u8; 4] = data.get(..4)?.try_into().ok()?;
+
+    match selector {
+        approveCall::SELECTOR => {
+            let call = approveCall::abi_decode(data, true).ok()?;
+            allowance(ApprovalKind::Approve, token, call.spender, call.amount)
+        },
+        increaseAllowanceCall::SELECTOR => {
+            let call = increaseAllowanceCall::abi_decode(data, true).ok()?;
+            allowance(ApprovalKind::IncreaseAllowance, token, call.spender, call.addedValue)
+        },
+        setApprovalForAllCall::SELECTOR => {
+            let call = setApprovalForAllCall::abi_decode(data, true).ok()?;
+            call.approved.then(|| operator(token, call.operator))
+        },
+        permitCall::SELECTOR => {
+            let call = permitCall::abi_decode(data, true).ok()?;
+            allowance(ApprovalKind::Permit, token, call.spender, call.value)
+        },
+        _ => None,
+    }
+}
+
+// Recognizes approvals granted by `owner` anywhere in a simulated transaction, including ones
+// made indirectly through routers or multicalls
+pub fn from_logs(owner: Address, logs: &[Log]) -> Vec<ApprovalWarning> {
+    let mut warnings = Vec::new();
+    for log in logs {
+        let topics = log.data.topics();
+        if topics.len() < 3 || Address::from_word(topics[1]) != owner {
+            continue;
+        }
+        let spender = Address::from_word(topics[2]);
+
+        if topics[0] == APPROVAL_FOR_ALL_TOPIC {
+            if log.data.data.iter().any(|byte| *byte != 0) {
+                warnings.push(operator(log.address, spender));
+            }
+        } else if topics[0] == APPROVAL_TOPIC && topics.len() == 3 {
+            let amount = log.data.data.get(..32).map(U256::from_be_slice).unwrap_or_default();
+            warnings.extend(allowance(ApprovalKind::Approve, log.address, spender, amount));
+        }
+    }
+    warnings
+}
+
+// Calldata tells us what kind of approval it is, events catch the indirect ones
+pub fn detect(owner: Address, tx: &TransactionRequest, logs: &[Log]) -> Vec<ApprovalWarning> {
+    let mut warnings: Vec<ApprovalWarning> = from_calldata(tx).into_iter().collect();
+    for warning in from_logs(owner, logs) {
+        let seen = warnings.iter().any(|w| w.token == warning.token && w.spender == warning.spender);
+        if !seen {
+            warnings.push(warning);
+        }
+    }
+    warnings
+}
+
+// Labels each spender with its Sourcify verification status. Lookup failures leave it unknown
+pub async fn label_spenders(warnings: &mut [ApprovalWarning], cache_dir: Option<&Path>, chain_id: u64) {
+    for warning in warnings {
+        if let Ok(metadata) = sourcify::get_contract_metadata(cache_dir, warning.spender, chain_id).await {
+            warning.spender_verified = Some(metadata.verified);
+            warning.spender_name = metadata.name;
+        }
+    }
+}
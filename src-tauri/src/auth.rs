@@ -0,0 +1,147 @@
+use robius_authentication::{AndroidText, BiometricStrength, Context, PolicyBuilder, Text, WindowsText};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+use crate::profile;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthSettings {
+    pub enabled: bool,
+    // Signing again within this many seconds of a successful authentication doesn't prompt
+    pub grace_period_secs: u64,
+}
+
+impl Default for AuthSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            grace_period_secs: 60,
+        }
+    }
+}
+
+impl AuthSettings {
+    // Whether moving from `current` to these settings asks for authentication less often
+    pub fn weakens(&self, current: &AuthSettings) -> bool {
+        (current.enabled && !self.enabled) || (self.enabled && self.grace_period_secs > current.grace_period_secs)
+    }
+}
+
+// Kept apart from settings.json so set_setting and backups can't switch authentication off
+fn settings_path(app: &AppHandle) -> Option<PathBuf> {
+    profile::data_dir(app).map(|dir| dir.join("auth.json"))
+}
+
+// Proof that the OS authenticated the user, required by every signing function so that a
+// code path which skips the gate doesn't compile
+pub struct Authorization(());
+
+// Touch ID, Windows Hello or polkit in front of the signer. Managed outside AppState so the
+// prompt doesn't hold the state lock while the user responds
+#[derive(Default)]
+pub struct AuthGate {
+    settings: Mutex<AuthSettings>,
+    last_authenticated: Mutex<Option<Instant>>,
+}
+
+impl AuthGate {
+    pub fn settings(&self) -> AuthSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn configure(&self, settings: AuthSettings) {
+        *self.settings.lock().unwrap() = settings;
+        // A changed grace period shouldn't extend an earlier authentication
+        *self.last_authenticated.lock().unwrap() = None;
+    }
+
+    // Loads the active profile's settings, the defaults if it saved none
+    pub async fn restore(&self, app: &AppHandle) {
+        let saved = match settings_path(app) {
+            Some(path) => tokio::fs::read(path).await.ok(),
+            None => None,
+        };
+        let settings = match saved {
+            Some(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring invalid auth settings: {}", e);
+                AuthSettings::default()
+            }),
+            None => AuthSettings::default(),
+        };
+        self.configure(settings);
+    }
+
+    pub async fn save(&self, app: &AppHandle) -> Result<(), String> {
+        let Some(path) = settings_path(app) else {
+            return Ok(());
+        };
+        let bytes = serde_json::to_vec_pretty(&self.settings()).map_err(|e| format!("Failed to serialize auth settings: {}", e))?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Failed to create data dir: {}", e))?;
+        }
+        tokio::fs::write(path, bytes)
+            .await
+            .map_err(|e| format!("Failed to write auth settings: {}", e))
+    }
+
+    // The next signature prompts again, e.g. after switching to another profile
+    pub fn forget(&self) {
+        *self.last_authenticated.lock().unwrap() = None;
+    }
+
+    pub async fn authorize(&self, reason: &str) -> Result<Authorization, String> {
+        let settings = self.settings.lock().unwrap().clone();
+        if !settings.enabled {
+            return Ok(Authorization(()));
+        }
+        let grace_period = Duration::from_secs(settings.grace_period_secs);
+        if self.last_authenticated.lock().unwrap().is_some_and(|at| at.elapsed() < grace_period) {
+            return Ok(Authorization(()));
+        }
+        self.prompt(reason).await
+    }
+
+    // Prompts even within the grace period, for changes that weaken the gate itself
+    pub async fn reauthorize(&self, reason: &str) -> Result<Authorization, String> {
+        if !self.settings.lock().unwrap().enabled {
+            return Ok(Authorization(()));
+        }
+        self.prompt(reason).await
+    }
+
+    async fn prompt(&self, reason: &str) -> Result<Authorization, String> {
+        let reason = reason.to_string();
+        tokio::task::spawn_blocking(move || authenticate(&reason))
+            .await
+            .map_err(|e| format!("Authentication task failed: {}", e))??;
+        *self.last_authenticated.lock().unwrap() = Some(Instant::now());
+        Ok(Authorization(()))
+    }
+}
+
+// Blocks on the platform prompt, falling back to the device password where biometrics are unavailable
+fn authenticate(reason: &str) -> Result<(), String> {
+    let policy = PolicyBuilder::new()
+        .biometrics(Some(BiometricStrength::Strong))
+        .password(true)
+        .build()
+        .ok_or("OS authentication is not supported on this platform")?;
+    let text = Text {
+        android: AndroidText {
+            title: "Confirm signing",
+            subtitle: None,
+            description: Some(reason),
+        },
+        apple: reason,
+        windows: WindowsText::new("Confirm signing", reason).ok_or("Authentication reason is too long")?,
+    };
+    Context::new(())
+        .blocking_authenticate(text, &policy)
+        .map_err(|e| format!("OS authentication failed: {:?}", e))
+}
@@ -0,0 +1,195 @@
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+use crate::accounts::Connection;
+use crate::keystore::{self, StoredAccount};
+use crate::policy::{self, Policy};
+use crate::settings::{self, Settings};
+use crate::watch::{self, WatchedAddress};
+use crate::{AppState, ClientConfig};
+
+// Bumped whenever the backup layout changes, older versions are upgraded on import
+pub const BACKUP_VERSION: u64 = 1;
+
+// A keystore entry with its encrypted key file, which stays under the account's own password
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupAccount {
+    pub account: StoredAccount,
+    pub key_file: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Backup {
+    pub version: u64,
+    pub created_at: u64,
+    pub accounts: Vec<BackupAccount>,
+    pub address_book: Vec<WatchedAddress>,
+    pub settings: Settings,
+    pub networks: Vec<ClientConfig>,
+    // Dapp connections and per-account signing policies
+    pub connections: Vec<Connection>,
+    pub policies: HashMap<Address, Policy>,
+}
+
+// What an import added, entries already on this machine are left as they are
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub accounts: usize,
+    pub addresses: usize,
+    pub networks: usize,
+    pub connections: usize,
+    pub policies: usize,
+}
+
+// Snapshot of everything worth moving to another machine. Signers unlocked in memory are not
+// included, only the encrypted key files
+pub async fn collect(app: &AppHandle, state_guard: &mut AppState) -> Result<Backup, String> {
+    keystore::load_keystore(app, &mut state_guard.keystore).await;
+    watch::load_watch_list(app, &mut state_guard.watched).await;
+    policy::load_policies(app, &mut state_guard.policies).await;
+
+    let mut accounts = Vec::new();
+    if let Some(dir) = keystore::keystore_dir(app) {
+        for account in state_guard.keystore.accounts() {
+            let bytes = tokio::fs::read(dir.join(&account.file))
+                .await
+                .map_err(|e| format!("Failed to read key file for 0x{:x}: {}", account.address, e))?;
+            let key_file = serde_json::from_slice(&bytes)
+                .map_err(|e| format!("Invalid key file for 0x{:x}: {}", account.address, e))?;
+            accounts.push(BackupAccount { account: account.clone(), key_file });
+        }
+    }
+
+    Ok(Backup {
+        version: BACKUP_VERSION,
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        accounts,
+        address_book: state_guard.watched.list().to_vec(),
+        settings: Settings::from_state(state_guard),
+        networks: state_guard.known_networks.values().cloned().collect(),
+        connections: state_guard.connections.list(),
+        policies: state_guard.policies.list(),
+    })
+}
+
+// The file is a v3 keystore whose secret is the JSON backup, so it gets the same scrypt and MAC
+// as account keys. Key derivation is slow and runs off the async runtime
+pub async fn write(path: PathBuf, backup: &Backup, password: String) -> Result<(), String> {
+    let bytes = serde_json::to_vec(backup).map_err(|e| format!("Failed to serialize backup: {}", e))?;
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string)
+        .ok_or("Backup path must end in a file name")?;
+    tokio::task::spawn_blocking(move || {
+        eth_keystore::encrypt_key(&dir, &mut rand::thread_rng(), bytes, password, Some(&name))
+            .map(|_| ())
+            .map_err(|e| format!("Failed to encrypt backup: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Backup encryption task failed: {}", e))?
+}
+
+pub async fn read(path: PathBuf, password: String) -> Result<Backup, String> {
+    let bytes = tokio::task::spawn_blocking(move || {
+        eth_keystore::decrypt_key(path, password).map_err(|e| match e {
+            eth_keystore::KeystoreError::MacMismatch => "Wrong password or corrupted backup".to_string(),
+            e => format!("Failed to decrypt backup: {}", e),
+        })
+    })
+    .await
+    .map_err(|e| format!("Backup decryption task failed: {}", e))??;
+
+    let value: serde_json::Value = serde_json::from_slice(&bytes).map_err(|e| format!("Invalid backup: {}", e))?;
+    match value.get("version").and_then(|version| version.as_u64()) {
+        Some(1) => serde_json::from_value(value).map_err(|e| format!("Invalid backup: {}", e)),
+        Some(version) if version > BACKUP_VERSION => {
+            Err(format!("Backup format {} was written by a newer version of Mana", version))
+        },
+        Some(version) => Err(format!("Unsupported backup format {}", version)),
+        None => Err("Backup is missing its format version".to_string()),
+    }
+}
+
+// Adds what this machine doesn't have yet. Settings are taken from the backup, everything else
+// keeps the local copy when both have an entry
+pub async fn merge(app: &AppHandle, state_guard: &mut AppState, backup: Backup) -> Result<ImportSummary, String> {
+    backup.settings.validate()?;
+    let mut summary = ImportSummary::default();
+
+    keystore::load_keystore(app, &mut state_guard.keystore).await;
+    let dir = keystore::keystore_dir(app).ok_or("App data dir unavailable")?;
+    for BackupAccount { mut account, key_file } in backup.accounts {
+        if state_guard.keystore.contains(account.address) {
+            continue;
+        }
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .map_err(|e| format!("Failed to create keystore dir: {}", e))?;
+        if tokio::fs::try_exists(dir.join(&account.file)).await.unwrap_or(false) {
+            account.file = format!("{:x}-{}", account.address, account.file);
+        }
+        let bytes = serde_json::to_vec(&key_file).map_err(|e| format!("Failed to serialize key file: {}", e))?;
+        tokio::fs::write(dir.join(&account.file), bytes)
+            .await
+            .map_err(|e| format!("Failed to write key file: {}", e))?;
+        state_guard.keystore.add(account);
+        summary.accounts += 1;
+    }
+    if summary.accounts > 0 {
+        state_guard.keystore.save(&dir).await?;
+    }
+
+    watch::load_watch_list(app, &mut state_guard.watched).await;
+    for watched in backup.address_book {
+        if state_guard.watched.list().iter().any(|entry| entry.address == watched.address) {
+            continue;
+        }
+        state_guard.watched.upsert(watched);
+        summary.addresses += 1;
+    }
+    if let (true, Some(path)) = (summary.addresses > 0, watch::watch_list_path(app)) {
+        state_guard.watched.save(&path).await?;
+    }
+
+    policy::load_policies(app, &mut state_guard.policies).await;
+    let existing = state_guard.policies.list();
+    for (account, policy) in backup.policies {
+        if !existing.contains_key(&account) {
+            state_guard.policies.set(account, policy);
+            summary.policies += 1;
+        }
+    }
+    if let (true, Some(path)) = (summary.policies > 0, policy::policy_path(app)) {
+        state_guard.policies.save(&path).await?;
+    }
+
+    for connection in backup.connections {
+        if state_guard.connections.restore(connection) {
+            summary.connections += 1;
+        }
+    }
+
+    for config in backup.networks {
+        if !state_guard.known_networks.contains_key(&config.chain_id) {
+            state_guard.known_networks.insert(config.chain_id, config);
+            summary.networks += 1;
+        }
+    }
+
+    backup.settings.apply(state_guard);
+    settings::commit(app, state_guard, "").await?;
+
+    Ok(summary)
+}
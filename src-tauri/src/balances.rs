@@ -0,0 +1,67 @@
+use alloy::primitives::utils::format_units;
+use alloy::primitives::{Address, U256};
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use helios::core::types::BlockTag;
+use serde::Serialize;
+
+use crate::client::EthClientApi;
+use crate::multicall::{self, AggregateCall};
+use crate::tokens::TokenInfo;
+
+// Keeps each aggregate3 call comfortably under the eth_call gas cap
+const BATCH_SIZE: usize = 200;
+
+sol! {
+    function balanceOf(address owner) external view returns (uint256);
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenBalance {
+    #[serde(flatten)]
+    pub token: TokenInfo,
+    pub balance: U256,
+    // Balance with decimals applied, e.g. "1250.5"
+    pub formatted: String,
+}
+
+// Reads `balanceOf(owner)` for every token through Multicall3 against verified latest state.
+// Tokens whose call fails are skipped, and zero balances are dropped unless `include_zero` is set
+pub async fn token_balances(
+    client: &dyn EthClientApi,
+    owner: Address,
+    tokens: Vec<TokenInfo>,
+    include_zero: bool,
+) -> Result<Vec<TokenBalance>, String> {
+    let mut balances = Vec::new();
+
+    for batch in tokens.chunks(BATCH_SIZE) {
+        let calls: Vec<AggregateCall> = batch
+            .iter()
+            .map(|token| AggregateCall {
+                target: token.address,
+                call_data: balanceOfCall { owner }.abi_encode().into(),
+                allow_failure: true,
+            })
+            .collect();
+        let results = multicall::aggregate(client, &calls, BlockTag::Latest).await?;
+
+        for (token, result) in batch.iter().zip(results) {
+            let balance = match balanceOfCall::abi_decode_returns(&result.return_data, true) {
+                Ok(decoded) if result.success => decoded._0,
+                _ => continue,
+            };
+            if balance.is_zero() && !include_zero {
+                continue;
+            }
+            balances.push(TokenBalance {
+                token: token.clone(),
+                balance,
+                formatted: format_units(balance, token.decimals).unwrap_or_default(),
+            });
+        }
+    }
+
+    Ok(balances)
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+
+use crate::accounts::Caller;
+use crate::AppState;
+
+// Benchmark requests go through the dispatcher like any page's, under their own origin in the metrics
+const BENCHMARK_ORIGIN: &str = "benchmark";
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BenchmarkProfile {
+    #[default]
+    Quick,
+    Standard,
+    Thorough,
+}
+
+impl BenchmarkProfile {
+    fn iterations(self) -> usize {
+        match self {
+            BenchmarkProfile::Quick => 5,
+            BenchmarkProfile::Standard => 20,
+            BenchmarkProfile::Thorough => 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationReport {
+    pub method: String,
+    pub samples: usize,
+    pub errors: usize,
+    pub error_rate: f64,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkReport {
+    pub profile: BenchmarkProfile,
+    pub rpc_url: String,
+    pub chain_id: u64,
+    pub block_number: u64,
+    pub duration_ms: u64,
+    pub operations: Vec<OperationReport>,
+}
+
+// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[Duration], percentile: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (sorted.len() * percentile).div_ceil(100).max(1);
+    sorted[rank - 1].as_millis() as u64
+}
+
+async fn call(app: &AppHandle, method: &str, params: serde_json::Value) -> Result<serde_json::Value, String> {
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    let caller = Caller { origin: BENCHMARK_ORIGIN.to_string(), webview: None };
+    let response = crate::handle_request(app.clone(), caller, app.state::<Mutex<AppState>>(), request).await?;
+    match response.get("error") {
+        Some(error) => Err(error.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error").to_string()),
+        None => Ok(response.get("result").cloned().unwrap_or(json!(null))),
+    }
+}
+
+async fn measure(app: &AppHandle, iterations: usize, method: &str, params: serde_json::Value) -> OperationReport {
+    let mut samples = Vec::with_capacity(iterations);
+    let mut errors = 0;
+    for _ in 0..iterations {
+        let started = Instant::now();
+        if call(app, method, params.clone()).await.is_err() {
+            errors += 1;
+        }
+        samples.push(started.elapsed());
+    }
+    samples.sort_unstable();
+
+    OperationReport {
+        method: method.to_string(),
+        samples: samples.len(),
+        errors,
+        error_rate: errors as f64 / samples.len().max(1) as f64,
+        p50_ms: percentile(&samples, 50),
+        p90_ms: percentile(&samples, 90),
+        p99_ms: percentile(&samples, 99),
+        max_ms: samples.last().map(|d| d.as_millis() as u64).unwrap_or_default(),
+    }
+}
+
+// Runs blocks, balances, calls and logs against the running client, one request at a time so the
+// latencies reflect the execution RPC rather than contention
+pub async fn run(app: &AppHandle, profile: BenchmarkProfile) -> Result<BenchmarkReport, String> {
+    let (rpc_url, chain_id) = {
+        let state = app.state::<Mutex<AppState>>();
+        let state_guard = state.lock().await;
+        let config = state_guard.config.as_ref().filter(|_| state_guard.client.is_some()).ok_or("Light client not initialized")?;
+        (config.rpc_url.clone(), config.chain_id)
+    };
+
+    // Pin the workload to one verified block so every iteration does the same work
+    let block = call(app, "eth_getBlockByNumber", json!(["latest", false])).await?;
+    let number = block.get("number").cloned().ok_or("Latest block has no number")?;
+    let miner = block.get("miner").cloned().ok_or("Latest block has no miner")?;
+    let block_number = number
+        .as_str()
+        .and_then(|n| u64::from_str_radix(n.trim_start_matches("0x"), 16).ok())
+        .ok_or("Invalid block number")?;
+
+    let iterations = profile.iterations();
+    let started = Instant::now();
+    let operations = vec![
+        measure(app, iterations, "eth_getBlockByNumber", json!([number, false])).await,
+        measure(app, iterations, "eth_getBalance", json!([miner, number])).await,
+        measure(app, iterations, "eth_getTransactionCount", json!([miner, number])).await,
+        measure(app, iterations, "eth_call", json!([{ "to": miner, "data": "0x" }, number])).await,
+        measure(app, iterations, "eth_getLogs", json!([{ "fromBlock": number, "toBlock": number, "address": miner }])).await,
+    ];
+
+    Ok(BenchmarkReport {
+        profile,
+        rpc_url,
+        chain_id,
+        block_number,
+        duration_ms: started.elapsed().as_millis() as u64,
+        operations,
+    })
+}
@@ -0,0 +1,101 @@
+use alloy::hex;
+use alloy::primitives::{keccak256, Bytes, B256};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::outbound;
+
+pub const DEFAULT_BUNDLE_RELAY: &str = "https://relay.flashbots.net";
+
+// Relays accept inclusion ranges of at most this many blocks
+const MAX_BLOCK_RANGE: u64 = 30;
+const DEFAULT_BLOCK_RANGE: u64 = 25;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleTransaction {
+    pub tx: Bytes,
+    #[serde(default)]
+    pub can_revert: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleResult {
+    pub bundle_hash: B256,
+}
+
+// Inclusion range for a bundle, defaulting to the blocks right after the verified head
+pub fn inclusion_range(head: u64, block: Option<u64>, max_block: Option<u64>) -> Result<(u64, u64), String> {
+    let block = block.unwrap_or(head + 1);
+    if block <= head {
+        return Err(format!("Target block {} is not after the current head {}", block, head));
+    }
+    let max_block = max_block.unwrap_or(block + DEFAULT_BLOCK_RANGE - 1);
+    if max_block < block || max_block - block >= MAX_BLOCK_RANGE {
+        return Err(format!("maxBlock must be within {} blocks of the target block", MAX_BLOCK_RANGE));
+    }
+    Ok((block, max_block))
+}
+
+// Flashbots authenticates requests with `X-Flashbots-Signature: <address>:<signature>`, an EIP-191
+// signature over the hex-encoded keccak of the body. The key only identifies the searcher
+fn flashbots_signature(signer: &PrivateKeySigner, body: &[u8]) -> Result<String, String> {
+    let digest = format!("0x{}", hex::encode(keccak256(body)));
+    let signature = signer.sign_message_sync(digest.as_bytes())
+        .map_err(|e| format!("Failed to sign bundle: {}", e))?;
+    Ok(format!("{}:0x{}", signer.address().to_checksum(None), hex::encode(signature.as_bytes())))
+}
+
+// Submits a bundle through `mev_sendBundle` (MEV-Share bundle API v0.1)
+pub async fn send_bundle(
+    relay_url: &str,
+    signer: &PrivateKeySigner,
+    transactions: &[BundleTransaction],
+    block: u64,
+    max_block: u64,
+) -> Result<BundleResult, String> {
+    if transactions.is_empty() {
+        return Err("A bundle needs at least one transaction".to_string());
+    }
+    let body: Vec<serde_json::Value> = transactions
+        .iter()
+        .map(|tx| json!({ "tx": tx.tx, "canRevert": tx.can_revert }))
+        .collect();
+    let payload = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "mev_sendBundle",
+        "params": [{
+            "version": "v0.1",
+            "inclusion": {
+                "block": format!("0x{:x}", block),
+                "maxBlock": format!("0x{:x}", max_block),
+            },
+            "body": body,
+        }],
+    });
+    let payload = serde_json::to_vec(&payload).map_err(|e| format!("Failed to serialize bundle: {}", e))?;
+
+    let response = outbound::client()
+        .post(relay_url)
+        .header("Content-Type", "application/json")
+        .header("X-Flashbots-Signature", flashbots_signature(signer, &payload)?)
+        .body(payload)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Bundle relay request failed: {}", e))?
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| format!("Invalid bundle relay response: {}", e))?;
+
+    if let Some(error) = response.get("error") {
+        let message = error.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error");
+        return Err(format!("Bundle rejected: {}", message));
+    }
+    serde_json::from_value(response.get("result").cloned().unwrap_or_default())
+        .map_err(|e| format!("Invalid bundle relay response: {}", e))
+}
@@ -0,0 +1,276 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_MAX_BYTES: u64 = 256 * 1024 * 1024;
+pub const DEFAULT_MAX_READS: usize = 4_096;
+
+// Caches that can be cleared one at a time
+pub const CATEGORIES: &[&str] = &["responses", "reads", "prices", "signatures", "sourcify", "nft", "ipfs"];
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS answers (
+        key TEXT PRIMARY KEY,
+        chain_id INTEGER NOT NULL,
+        method TEXT NOT NULL,
+        result TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        stored_at INTEGER NOT NULL,
+        last_used INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS answers_last_used ON answers (last_used);
+";
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+// Immutable lookups kept on disk. A TTL of 0 keeps answers until they're evicted for space
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResponseCacheLimits {
+    pub max_bytes: u64,
+    pub ttl_secs: u64,
+}
+
+impl Default for ResponseCacheLimits {
+    fn default() -> Self {
+        Self { max_bytes: DEFAULT_MAX_BYTES, ttl_secs: 0 }
+    }
+}
+
+// Last answer to each read, served while offline. A max age of 0 serves answers however old they are
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReadCacheLimits {
+    pub max_entries: usize,
+    pub max_age_secs: u64,
+}
+
+impl Default for ReadCacheLimits {
+    fn default() -> Self {
+        Self { max_entries: DEFAULT_MAX_READS, max_age_secs: 0 }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CachePolicy {
+    pub responses: ResponseCacheLimits,
+    pub reads: ReadCacheLimits,
+}
+
+impl CachePolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.reads.max_entries == 0 {
+            return Err("The read cache needs room for at least one answer".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HitCounts {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl HitCounts {
+    pub fn record(&mut self, hit: bool) {
+        if hit {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryStats {
+    #[serde(flatten)]
+    pub counts: HitCounts,
+    pub entries: u64,
+    // Bytes on disk, unset for caches held in memory
+    pub bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub responses: CategoryStats,
+    pub reads: CategoryStats,
+}
+
+fn key(chain_id: u64, method: &str, params: &serde_json::Value) -> String {
+    format!("{}:{}:{}", chain_id, method, params)
+}
+
+// Lookups by hash. A block hash commits to the block's contents, so those answers never change.
+// Which block a transaction is in can, until that block is final
+pub fn is_immutable(method: &str, params: &serde_json::Value) -> bool {
+    match method {
+        "eth_getBlockByHash"
+        | "eth_getBlockTransactionCountByHash"
+        | "eth_getTransactionByHash"
+        | "eth_getTransactionReceipt" => true,
+        // Also takes tags, only a hash pins it
+        "eth_getBlockReceipts" => params.get(0).and_then(|block| block.as_str()).is_some_and(|block| block.len() == 66),
+        _ => false,
+    }
+}
+
+// Whether an answer to an immutable lookup can be stored. Transactions are held back until their
+// block is at or below `finalized`
+pub fn is_settled(method: &str, result: &serde_json::Value, finalized: Option<u64>) -> bool {
+    if result.is_null() {
+        return false;
+    }
+    match method {
+        "eth_getTransactionByHash" | "eth_getTransactionReceipt" => {
+            let number = result.get("blockNumber")
+                .and_then(|number| number.as_str())
+                .and_then(|number| u64::from_str_radix(number.trim_start_matches("0x"), 16).ok());
+            matches!((number, finalized), (Some(number), Some(finalized)) if number <= finalized)
+        },
+        _ => true,
+    }
+}
+
+// Answers to immutable lookups kept across restarts, so blocks and receipts fetched in an earlier
+// session don't go to the network again
+pub struct DiskCache {
+    conn: Connection,
+}
+
+// The one connection to the response cache, reopened when switching profiles moves it. Queries
+// run on the blocking pool so a slow disk doesn't hold up the runtime
+#[derive(Default)]
+pub struct SharedDiskCache {
+    db: Arc<Mutex<Option<(PathBuf, DiskCache)>>>,
+}
+
+impl SharedDiskCache {
+    pub async fn with<T, F>(&self, path: PathBuf, f: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&DiskCache) -> Result<T, String> + Send + 'static,
+    {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut db = db.lock().unwrap();
+            if db.as_ref().map_or(true, |(open, _)| *open != path) {
+                *db = Some((path.clone(), DiskCache::open(&path)?));
+            }
+            f(&db.as_ref().unwrap().1)
+        })
+        .await
+        .map_err(|e| format!("Response cache task failed: {}", e))?
+    }
+}
+
+impl DiskCache {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create response cache dir: {}", e))?;
+        }
+        let conn = Connection::open(path).map_err(|e| format!("Failed to open response cache: {}", e))?;
+        conn.execute_batch(SCHEMA).map_err(|e| format!("Failed to create response cache: {}", e))?;
+        Ok(Self { conn })
+    }
+
+    pub fn get(
+        &self,
+        chain_id: u64,
+        method: &str,
+        params: &serde_json::Value,
+        limits: &ResponseCacheLimits,
+    ) -> Result<Option<serde_json::Value>, String> {
+        let key = key(chain_id, method, params);
+        let result: Option<(String, i64)> = self.conn
+            .query_row("SELECT result, stored_at FROM answers WHERE key = ?1", params![key], |row| Ok((row.get(0)?, row.get(1)?)))
+            .optional()
+            .map_err(|e| format!("Failed to query response cache: {}", e))?;
+        let Some((result, stored_at)) = result else {
+            return Ok(None);
+        };
+        if limits.ttl_secs > 0 && now() - stored_at > limits.ttl_secs as i64 {
+            self.conn
+                .execute("DELETE FROM answers WHERE key = ?1", params![key])
+                .map_err(|e| format!("Failed to write response cache: {}", e))?;
+            return Ok(None);
+        }
+        self.conn
+            .execute("UPDATE answers SET last_used = ?1 WHERE key = ?2", params![now(), key])
+            .map_err(|e| format!("Failed to write response cache: {}", e))?;
+        serde_json::from_str(&result)
+            .map(Some)
+            .map_err(|e| format!("Invalid response cache entry: {}", e))
+    }
+
+    pub fn put(
+        &self,
+        chain_id: u64,
+        method: &str,
+        params: &serde_json::Value,
+        result: &serde_json::Value,
+        limits: &ResponseCacheLimits,
+    ) -> Result<(), String> {
+        let result = result.to_string();
+        let now = now();
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO answers (key, chain_id, method, result, size, stored_at, last_used) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+                params![key(chain_id, method, params), chain_id as i64, method, result, result.len() as i64, now],
+            )
+            .map_err(|e| format!("Failed to write response cache: {}", e))?;
+        self.evict(limits.max_bytes)
+    }
+
+    // Entry count and payload bytes
+    pub fn usage(&self) -> Result<(u64, u64), String> {
+        self.conn
+            .query_row("SELECT COUNT(*), COALESCE(SUM(size), 0) FROM answers", [], |row| {
+                Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64))
+            })
+            .map_err(|e| format!("Failed to query response cache: {}", e))
+    }
+
+    pub fn clear(&self) -> Result<(), String> {
+        self.conn
+            .execute_batch("DELETE FROM answers; VACUUM;")
+            .map_err(|e| format!("Failed to clear response cache: {}", e))
+    }
+
+    // Gives the space freed by evictions back to the filesystem
+    pub fn compact(&self) -> Result<(), String> {
+        self.conn
+            .execute_batch("VACUUM")
+            .map_err(|e| format!("Failed to compact response cache: {}", e))
+    }
+
+    // Drops least recently used answers until the cache fits in `max_bytes`
+    pub fn evict(&self, max_bytes: u64) -> Result<(), String> {
+        let (_, total) = self.usage()?;
+        if total <= max_bytes {
+            return Ok(());
+        }
+        self.conn
+            .execute(
+                "DELETE FROM answers WHERE key IN (
+                    SELECT key FROM (
+                        SELECT key, size, SUM(size) OVER (ORDER BY last_used, key) AS running FROM answers
+                    ) WHERE running - size < ?1
+                )",
+                params![(total - max_bytes) as i64],
+            )
+            .map_err(|e| format!("Failed to evict response cache: {}", e))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use alloy::hex;
+use alloy::primitives::{Address, Bytes, TxKind, B256, U256, U64};
+use alloy::rpc::types::TransactionRequest;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::client::EthClientApi;
+
+// EIP-5792 capabilities per hex chain id. Atomic batches and paymasters both go through an
+// ERC-4337 bundler, so they are only offered for smart accounts on chains that have one configured
+pub fn capabilities(active_chain: Option<u64>, bundlers: &HashMap<u64, String>, smart_account: bool) -> serde_json::Value {
+    let chains: BTreeSet<u64> = active_chain.into_iter().chain(bundlers.keys().copied()).collect();
+    let mut capabilities = serde_json::Map::new();
+    for chain_id in chains {
+        let bundler = smart_account && bundlers.contains_key(&chain_id);
+        capabilities.insert(
+            format!("0x{:x}", chain_id),
+            json!({
+                "atomicBatch": { "supported": bundler },
+                "paymasterService": { "supported": bundler },
+            }),
+        );
+    }
+    serde_json::Value::Object(capabilities)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Call {
+    pub to: Option<Address>,
+    pub data: Option<Bytes>,
+    pub value: Option<U256>,
+}
+
+// `wallet_sendCalls` params, requested capabilities are ignored since none are supported for
+// keystore accounts
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendCallsParams {
+    pub chain_id: U64,
+    pub from: Address,
+    pub calls: Vec<Call>,
+}
+
+impl SendCallsParams {
+    pub fn transactions(&self) -> Vec<TransactionRequest> {
+        self.calls
+            .iter()
+            .map(|call| TransactionRequest {
+                from: Some(self.from),
+                to: Some(call.to.map_or(TxKind::Create, TxKind::Call)),
+                input: call.data.clone().into(),
+                value: call.value,
+                chain_id: Some(self.chain_id.to()),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+struct CallBatch {
+    hashes: Vec<B256>,
+}
+
+// Batches submitted this session, by the id returned from `wallet_sendCalls`
+#[derive(Default)]
+pub struct CallBatches {
+    batches: HashMap<String, CallBatch>,
+}
+
+impl CallBatches {
+    pub fn insert(&mut self, hashes: Vec<B256>) -> String {
+        let id = format!("0x{}", hex::encode(rand::random::<[u8; 32]>()));
+        self.batches.insert(id.clone(), CallBatch { hashes });
+        id
+    }
+
+    // `wallet_getCallsStatus` result, CONFIRMED once every transaction has a verified receipt
+    pub async fn status(&self, client: &dyn EthClientApi, id: &str) -> Result<serde_json::Value, String> {
+        let batch = self.batches.get(id).ok_or_else(|| format!("Unknown batch {}", id))?;
+        let mut receipts = Vec::with_capacity(batch.hashes.len());
+        for hash in &batch.hashes {
+            match client.get_transaction_receipt(*hash).await.map_err(|e| e.to_string())? {
+                Some(receipt) => receipts.push(json!({
+                    "logs": receipt.inner.logs(),
+                    "status": if receipt.status() { "0x1" } else { "0x0" },
+                    "blockHash": receipt.block_hash,
+                    "blockNumber": receipt.block_number.map(|n| format!("0x{:x}", n)),
+                    "gasUsed": format!("0x{:x}", receipt.gas_used),
+                    "transactionHash": receipt.transaction_hash,
+                })),
+                None => break,
+            }
+        }
+        let status = if receipts.len() == batch.hashes.len() { "CONFIRMED" } else { "PENDING" };
+        Ok(json!({ "status": status, "receipts": receipts }))
+    }
+}
@@ -0,0 +1,127 @@
+use alloy::dyn_abi::DynSolValue;
+use alloy::hex;
+use alloy::primitives::{Bytes, TxKind};
+use alloy::rpc::types::TransactionRequest;
+use alloy::sol;
+use alloy::sol_types::SolError;
+use helios::core::types::BlockTag;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::client::EthClientApi;
+use crate::outbound;
+
+// EIP-3668 allows clients to cap how many lookups a single call may chain
+const MAX_LOOKUPS: usize = 4;
+const OFFCHAIN_LOOKUP_SELECTOR: &str = "0x556f1830";
+
+sol! {
+    error OffchainLookup(address sender, string[] urls, bytes callData, bytes4 callbackFunction, bytes extraData);
+}
+
+#[derive(Deserialize)]
+struct GatewayResponse {
+    data: Bytes,
+}
+
+// helios surfaces reverts as error messages, so the revert data is recovered from the text
+fn extract_offchain_lookup(message: &str) -> Option<OffchainLookup> {
+    let start = message.find(OFFCHAIN_LOOKUP_SELECTOR)?;
+    let revert: String = message[start + 2..].chars().take_while(|c| c.is_ascii_hexdigit()).collect();
+    let revert = hex::decode(revert).ok()?;
+    OffchainLookup::abi_decode(&revert, true).ok()
+}
+
+fn is_allowed_gateway(url: &str) -> bool {
+    url.starts_with("https://")
+        || url.starts_with("http://localhost")
+        || url.starts_with("http://127.0.0.1")
+}
+
+async fn query_gateways(lookup: &OffchainLookup) -> Result<Bytes, String> {
+    let http = outbound::client();
+    let sender = format!("0x{:x}", lookup.sender);
+    let data = format!("0x{}", hex::encode(&lookup.callData));
+    let mut errors = Vec::new();
+
+    for url in &lookup.urls {
+        if !is_allowed_gateway(url) {
+            errors.push(format!("{}: gateway must use https", url));
+            continue;
+        }
+
+        let resolved = url.replace("{sender}", &sender).replace("{data}", &data);
+        let request = if url.contains("{data}") {
+            http.get(&resolved)
+        } else {
+            http.post(&resolved).json(&json!({ "data": data, "sender": sender }))
+        };
+
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => {
+                errors.push(format!("{}: {}", url, e));
+                continue;
+            }
+        };
+
+        // Client errors are final per the spec, server errors fall through to the next gateway
+        let status = response.status();
+        if status.is_client_error() {
+            return Err(format!("CCIP gateway {} rejected the request: {}", url, status));
+        }
+        if !status.is_success() {
+            errors.push(format!("{}: {}", url, status));
+            continue;
+        }
+
+        match response.json::<GatewayResponse>().await {
+            Ok(body) => return Ok(body.data),
+            Err(e) => errors.push(format!("{}: invalid response: {}", url, e)),
+        }
+    }
+
+    Err(format!("No CCIP gateway answered: {}", errors.join("; ")))
+}
+
+// eth_call that follows EIP-3668 OffchainLookup reverts through the listed gateways and calls
+// back into the contract with the gateway response
+pub async fn call(
+    client: &dyn EthClientApi,
+    tx: &TransactionRequest,
+    block_tag: BlockTag,
+) -> Result<Bytes, String> {
+    let to = match tx.to {
+        Some(TxKind::Call(to)) => Some(to),
+        _ => None,
+    };
+    let mut tx = tx.clone();
+
+    for _ in 0..=MAX_LOOKUPS {
+        let error = match client.call(&tx, block_tag).await {
+            Ok(output) => return Ok(output),
+            Err(e) => e.to_string(),
+        };
+
+        let (Some(to), Some(lookup)) = (to, extract_offchain_lookup(&error)) else {
+            return Err(error);
+        };
+        if lookup.sender != to {
+            return Err("OffchainLookup sender does not match the called contract".to_string());
+        }
+
+        let response = query_gateways(&lookup).await?;
+
+        let mut calldata = lookup.callbackFunction.to_vec();
+        calldata.extend(
+            DynSolValue::Tuple(vec![
+                DynSolValue::Bytes(response.to_vec()),
+                DynSolValue::Bytes(lookup.extraData.to_vec()),
+            ])
+            .abi_encode_params(),
+        );
+        tx = tx.input(Bytes::from(calldata).into());
+    }
+
+    Err("Too many chained CCIP-Read lookups".to_string())
+}
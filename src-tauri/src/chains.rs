@@ -0,0 +1,102 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::accounts::Caller;
+use crate::client::EthClientApi;
+
+// A client started for windows on a chain other than the one the app runs on
+struct TabClient {
+    client: Arc<dyn EthClientApi>,
+    rpc_url: String,
+}
+
+// Chain each window switched to with wallet_switchEthereumChain. Windows that never switched
+// follow the app's chain, the others are served by a client of their own chain
+#[derive(Default)]
+pub struct TabChains {
+    by_caller: HashMap<Caller, u64>,
+    clients: HashMap<u64, TabClient>,
+}
+
+impl TabChains {
+    pub fn selected(&self, caller: &Caller) -> Option<u64> {
+        self.by_caller.get(caller).copied()
+    }
+
+    pub fn select(&mut self, caller: &Caller, chain_id: u64) {
+        self.by_caller.insert(caller.clone(), chain_id);
+    }
+
+    pub fn selections(&self) -> impl Iterator<Item = (&Caller, u64)> {
+        self.by_caller.iter().map(|(caller, chain_id)| (caller, *chain_id))
+    }
+
+    // Moves every window on `chain_id` back to the app's chain, returning them
+    pub fn release(&mut self, chain_id: u64) -> Vec<Caller> {
+        let released: Vec<Caller> = self.selections().filter(|(_, selected)| *selected == chain_id).map(|(caller, _)| caller.clone()).collect();
+        for caller in &released {
+            self.by_caller.remove(caller);
+        }
+        released
+    }
+
+    // A window that moved to another dapp starts back on the app's chain
+    pub fn release_navigated(&mut self, webview: &str, origin: &str) {
+        self.by_caller.retain(|caller, _| caller.webview.as_deref() != Some(webview) || caller.origin == origin);
+    }
+
+    // Client serving `chain_id`: the app's own when it runs that chain, otherwise a window's
+    pub fn resolve<'a>(
+        &'a self,
+        app_client: Option<&'a dyn EthClientApi>,
+        app_chain: u64,
+        chain_id: u64,
+    ) -> Option<&'a dyn EthClientApi> {
+        match app_client {
+            Some(client) if app_chain == chain_id => Some(client),
+            _ => self.clients.get(&chain_id).map(|tab| tab.client.as_ref()),
+        }
+    }
+
+    // Every client the windows started, with the chain it serves
+    pub fn clients(&self) -> impl Iterator<Item = (u64, &dyn EthClientApi)> {
+        self.clients.iter().map(|(chain_id, tab)| (*chain_id, tab.client.as_ref()))
+    }
+
+    // Owned handle on a window's client, for calls made after the state lock is released
+    pub fn handle(&self, chain_id: u64) -> Option<Arc<dyn EthClientApi>> {
+        self.clients.get(&chain_id).map(|tab| tab.client.clone())
+    }
+
+    pub fn rpc_url(&self, chain_id: u64) -> Option<&str> {
+        self.clients.get(&chain_id).map(|tab| tab.rpc_url.as_str())
+    }
+
+    // Keeps the first client when two windows raced to start the same chain, returning the other
+    pub fn insert_client(&mut self, chain_id: u64, client: Arc<dyn EthClientApi>, rpc_url: String) -> Option<Arc<dyn EthClientApi>> {
+        if self.clients.contains_key(&chain_id) {
+            return Some(client);
+        }
+        self.clients.insert(chain_id, TabClient { client, rpc_url });
+        None
+    }
+
+    // Removes clients no window is on any more, or whose chain the app itself now runs on, so the
+    // caller can shut them down
+    pub fn take_unused(&mut self, app_chain: u64) -> Vec<(u64, Arc<dyn EthClientApi>)> {
+        let in_use: HashSet<u64> = self.by_caller.values().copied().collect();
+        let unused: Vec<u64> = self.clients
+            .keys()
+            .filter(|chain_id| **chain_id == app_chain || !in_use.contains(chain_id))
+            .copied()
+            .collect();
+        unused.into_iter()
+            .filter_map(|chain_id| self.clients.remove(&chain_id).map(|tab| (chain_id, tab.client)))
+            .collect()
+    }
+
+    pub fn take_all(&mut self) -> Vec<Arc<dyn EthClientApi>> {
+        self.by_caller.clear();
+        self.clients.drain().map(|(_, tab)| tab.client).collect()
+    }
+}
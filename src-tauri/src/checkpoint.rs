@@ -0,0 +1,245 @@
+use alloy::primitives::B256;
+use alloy::transports::http::reqwest;
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+
+use crate::headers::VerifiedHeader;
+use crate::outbound;
+
+// File name FileDB uses for the last saved checkpoint inside the data dir
+const CHECKPOINT_FILE: &str = "checkpoint";
+// Append-only log of every checkpoint accepted from a pinned fallback service
+const CHECKPOINT_HISTORY_FILE: &str = "checkpoint_history.jsonl";
+// Bumped whenever the snapshot layout changes
+pub const SNAPSHOT_VERSION: u64 = 1;
+
+// Checkpoint accepted at startup along with the service that provided it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointRecord {
+    pub checkpoint: B256,
+    pub slot: u64,
+    pub source: String,
+    pub accepted_at: u64,
+}
+
+// Verified state exported from one install so another can bootstrap from it instead of from
+// public checkpoint services. Only as trustworthy as the machine and file it came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderSnapshot {
+    pub version: u64,
+    pub chain_id: u64,
+    pub checkpoint: B256,
+    // Verified execution headers, oldest first and without gaps
+    pub headers: Vec<VerifiedHeader>,
+    pub exported_at: u64,
+}
+
+impl HeaderSnapshot {
+    pub fn new(chain_id: u64, checkpoint: B256, headers: Vec<VerifiedHeader>) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            chain_id,
+            checkpoint,
+            headers,
+            exported_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotImport {
+    pub checkpoint: B256,
+    // Headers staged to join the header store once sync anchors them
+    pub headers: usize,
+    pub restarted: bool,
+}
+
+// Subset of the checkpointz `/beacon/slots` response
+#[derive(Deserialize)]
+struct SlotsResponse {
+    data: SlotsData,
+}
+
+#[derive(Deserialize)]
+struct SlotsData {
+    slots: Vec<Slot>,
+}
+
+#[derive(Deserialize)]
+struct Slot {
+    slot: u64,
+    block_root: Option<B256>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointInfo {
+    pub data_dir: String,
+    pub checkpoint: Option<B256>,
+    pub saved_at: Option<u64>,
+    pub running: bool,
+    pub ephemeral: bool,
+    pub consensus_rpc: Option<String>,
+    pub accepted: Option<CheckpointRecord>,
+}
+
+pub async fn read_checkpoint_info(data_dir: &Path) -> Result<CheckpointInfo, String> {
+    let path = data_dir.join(CHECKPOINT_FILE);
+
+    let (checkpoint, saved_at) = match tokio::fs::read(&path).await {
+        Ok(bytes) => {
+            if bytes.len() != 32 {
+                return Err(format!("Corrupted checkpoint: expected 32 bytes, found {}", bytes.len()));
+            }
+            let saved_at = tokio::fs::metadata(&path)
+                .await
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs());
+            (Some(B256::from_slice(&bytes)), saved_at)
+        },
+        Err(e) if e.kind() == ErrorKind::NotFound => (None, None),
+        Err(e) => return Err(format!("Failed to read checkpoint: {}", e)),
+    };
+
+    Ok(CheckpointInfo {
+        data_dir: data_dir.display().to_string(),
+        checkpoint,
+        saved_at,
+        running: false,
+        ephemeral: false,
+        consensus_rpc: None,
+        accepted: None,
+    })
+}
+
+// Overwrites the checkpoint FileDB loads on the next start
+pub async fn save_checkpoint(data_dir: &Path, checkpoint: B256) -> Result<(), String> {
+    tokio::fs::create_dir_all(data_dir)
+        .await
+        .map_err(|e| format!("Failed to create data dir: {}", e))?;
+    tokio::fs::write(data_dir.join(CHECKPOINT_FILE), checkpoint.as_slice())
+        .await
+        .map_err(|e| format!("Failed to write checkpoint: {}", e))
+}
+
+pub async fn clear_data_dir(data_dir: &Path) -> Result<(), String> {
+    match tokio::fs::remove_dir_all(data_dir).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to clear data dir: {}", e)),
+    }
+}
+
+// Queries the pinned services in order and returns the first checkpoint any of them serves
+pub async fn fetch_from_services(services: &[String]) -> Result<CheckpointRecord, String> {
+    let http = outbound::client();
+    let mut errors = Vec::new();
+
+    for service in services {
+        match fetch_from_service(&http, service).await {
+            Ok((checkpoint, slot)) => {
+                return Ok(CheckpointRecord {
+                    checkpoint,
+                    slot,
+                    source: service.clone(),
+                    accepted_at: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or_default(),
+                });
+            },
+            Err(e) => errors.push(format!("{}: {}", service, e)),
+        }
+    }
+
+    Err(format!("No checkpoint fallback service returned a checkpoint: {}", errors.join("; ")))
+}
+
+async fn fetch_from_service(http: &reqwest::Client, service: &str) -> Result<(B256, u64), String> {
+    let url = format!("{}/checkpointz/v1/beacon/slots", service.trim_end_matches('/'));
+    let response = http.get(&url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("request failed: {}", e))?
+        .json::<SlotsResponse>()
+        .await
+        .map_err(|e| format!("invalid response: {}", e))?;
+
+    response.data.slots
+        .into_iter()
+        .find_map(|slot| slot.block_root.map(|root| (root, slot.slot)))
+        .ok_or_else(|| "no finalized slots available".to_string())
+}
+
+pub async fn record_checkpoint(data_dir: &Path, record: &CheckpointRecord) -> Result<(), String> {
+    let line = serde_json::to_string(record)
+        .map_err(|e| format!("Failed to serialize checkpoint record: {}", e))?;
+
+    tokio::fs::create_dir_all(data_dir)
+        .await
+        .map_err(|e| format!("Failed to create data dir: {}", e))?;
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(data_dir.join(CHECKPOINT_HISTORY_FILE))
+        .await
+        .map_err(|e| format!("Failed to open checkpoint history: {}", e))?;
+
+    file.write_all(format!("{}\n", line).as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write checkpoint history: {}", e))
+}
+
+pub async fn read_checkpoint_history(data_dir: &Path) -> Result<Vec<CheckpointRecord>, String> {
+    let contents = match tokio::fs::read_to_string(data_dir.join(CHECKPOINT_HISTORY_FILE)).await {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read checkpoint history: {}", e)),
+    };
+
+    Ok(contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+pub async fn write_snapshot(path: &Path, snapshot: &HeaderSnapshot) -> Result<(), String> {
+    let bytes = serde_json::to_vec_pretty(snapshot).map_err(|e| format!("Failed to serialize snapshot: {}", e))?;
+    tokio::fs::write(path, bytes)
+        .await
+        .map_err(|e| format!("Failed to write snapshot: {}", e))
+}
+
+// Reads a snapshot and checks its headers link up, so a truncated or edited file is refused
+// rather than loaded into the header store
+pub async fn read_snapshot(path: &Path) -> Result<HeaderSnapshot, String> {
+    let bytes = tokio::fs::read(path).await.map_err(|e| format!("Failed to read snapshot: {}", e))?;
+    let value: serde_json::Value = serde_json::from_slice(&bytes).map_err(|e| format!("Invalid snapshot: {}", e))?;
+    let snapshot: HeaderSnapshot = match value.get("version").and_then(|version| version.as_u64()) {
+        Some(SNAPSHOT_VERSION) => serde_json::from_value(value).map_err(|e| format!("Invalid snapshot: {}", e))?,
+        Some(version) => return Err(format!("Unsupported snapshot format {}", version)),
+        None => return Err("Snapshot is missing its format version".to_string()),
+    };
+    if snapshot.checkpoint.is_zero() {
+        return Err("Snapshot has no checkpoint".to_string());
+    }
+    for pair in snapshot.headers.windows(2) {
+        if pair[1].block_number != pair[0].block_number + 1 || pair[1].parent_hash != pair[0].block_hash {
+            return Err(format!("Snapshot headers don't link up at block {}", pair[1].block_number));
+        }
+    }
+    Ok(snapshot)
+}
@@ -0,0 +1,365 @@
+use alloy::primitives::{Address, Bytes, B256, U256};
+use alloy::rpc::types::{Filter, Log, SyncStatus, Transaction, TransactionReceipt, TransactionRequest};
+use async_trait::async_trait;
+use eyre::Result;
+use helios::core::types::{Block, BlockTag};
+use helios::ethereum::EthereumClient;
+
+use crate::db::AppDB;
+
+// The light client calls the app makes, so AppState can hold either Helios or an in-memory backend
+#[async_trait]
+pub trait EthClientApi: Send + Sync {
+    async fn chain_id(&self) -> u64;
+    async fn syncing(&self) -> Result<SyncStatus>;
+    async fn shutdown(&self);
+
+    async fn get_block_by_number(&self, block: BlockTag, full_tx: bool) -> Result<Option<Block<Transaction>>>;
+    async fn get_block_by_hash(&self, hash: B256, full_tx: bool) -> Result<Option<Block<Transaction>>>;
+    async fn get_block_transaction_count_by_number(&self, block: BlockTag) -> Result<Option<u64>>;
+    async fn get_block_transaction_count_by_hash(&self, hash: B256) -> Result<Option<u64>>;
+    async fn get_coinbase(&self) -> Result<Address>;
+
+    async fn get_balance(&self, address: Address, block: BlockTag) -> Result<U256>;
+    async fn get_nonce(&self, address: Address, block: BlockTag) -> Result<u64>;
+    async fn get_code(&self, address: Address, block: BlockTag) -> Result<Bytes>;
+    async fn get_storage_at(&self, address: Address, slot: B256, block: BlockTag) -> Result<U256>;
+
+    async fn get_transaction_by_hash(&self, hash: B256) -> Option<Transaction>;
+    async fn get_transaction_by_block_hash_and_index(&self, block_hash: B256, index: u64) -> Option<Transaction>;
+    async fn get_transaction_receipt(&self, hash: B256) -> Result<Option<TransactionReceipt>>;
+    async fn send_raw_transaction(&self, bytes: &[u8]) -> Result<B256>;
+
+    async fn call(&self, tx: &TransactionRequest, block: BlockTag) -> Result<Bytes>;
+    async fn estimate_gas(&self, tx: &TransactionRequest) -> Result<u64>;
+    async fn get_gas_price(&self) -> Result<U256>;
+    async fn get_priority_fee(&self) -> Result<U256>;
+
+    async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>>;
+    async fn new_filter(&self, filter: &Filter) -> Result<U256>;
+    async fn new_block_filter(&self) -> Result<U256>;
+    async fn new_pending_transaction_filter(&self) -> Result<U256>;
+    async fn get_filter_changes(&self, filter_id: U256) -> Result<Vec<Log>>;
+    async fn uninstall_filter(&self, filter_id: U256) -> Result<bool>;
+}
+
+// Helios mixes eyre and its own error type, `?` turns both into eyre
+#[async_trait]
+impl EthClientApi for EthereumClient<AppDB> {
+    async fn chain_id(&self) -> u64 {
+        EthereumClient::chain_id(self).await
+    }
+
+    async fn syncing(&self) -> Result<SyncStatus> {
+        Ok(EthereumClient::syncing(self).await?)
+    }
+
+    async fn shutdown(&self) {
+        EthereumClient::shutdown(self).await
+    }
+
+    async fn get_block_by_number(&self, block: BlockTag, full_tx: bool) -> Result<Option<Block<Transaction>>> {
+        Ok(EthereumClient::get_block_by_number(self, block, full_tx).await?)
+    }
+
+    async fn get_block_by_hash(&self, hash: B256, full_tx: bool) -> Result<Option<Block<Transaction>>> {
+        Ok(EthereumClient::get_block_by_hash(self, hash, full_tx).await?)
+    }
+
+    async fn get_block_transaction_count_by_number(&self, block: BlockTag) -> Result<Option<u64>> {
+        Ok(EthereumClient::get_block_transaction_count_by_number(self, block).await?)
+    }
+
+    async fn get_block_transaction_count_by_hash(&self, hash: B256) -> Result<Option<u64>> {
+        Ok(EthereumClient::get_block_transaction_count_by_hash(self, hash).await?)
+    }
+
+    async fn get_coinbase(&self) -> Result<Address> {
+        Ok(EthereumClient::get_coinbase(self).await?)
+    }
+
+    async fn get_balance(&self, address: Address, block: BlockTag) -> Result<U256> {
+        Ok(EthereumClient::get_balance(self, address, block).await?)
+    }
+
+    async fn get_nonce(&self, address: Address, block: BlockTag) -> Result<u64> {
+        Ok(EthereumClient::get_nonce(self, address, block).await?)
+    }
+
+    async fn get_code(&self, address: Address, block: BlockTag) -> Result<Bytes> {
+        Ok(EthereumClient::get_code(self, address, block).await?.into())
+    }
+
+    async fn get_storage_at(&self, address: Address, slot: B256, block: BlockTag) -> Result<U256> {
+        Ok(EthereumClient::get_storage_at(self, address, slot, block).await?)
+    }
+
+    async fn get_transaction_by_hash(&self, hash: B256) -> Option<Transaction> {
+        EthereumClient::get_transaction_by_hash(self, hash).await
+    }
+
+    async fn get_transaction_by_block_hash_and_index(&self, block_hash: B256, index: u64) -> Option<Transaction> {
+        EthereumClient::get_transaction_by_block_hash_and_index(self, block_hash, index).await
+    }
+
+    async fn get_transaction_receipt(&self, hash: B256) -> Result<Option<TransactionReceipt>> {
+        Ok(EthereumClient::get_transaction_receipt(self, hash).await?)
+    }
+
+    async fn send_raw_transaction(&self, bytes: &[u8]) -> Result<B256> {
+        Ok(EthereumClient::send_raw_transaction(self, bytes).await?)
+    }
+
+    async fn call(&self, tx: &TransactionRequest, block: BlockTag) -> Result<Bytes> {
+        Ok(EthereumClient::call(self, tx, block).await?.into())
+    }
+
+    async fn estimate_gas(&self, tx: &TransactionRequest) -> Result<u64> {
+        Ok(EthereumClient::estimate_gas(self, tx).await?)
+    }
+
+    async fn get_gas_price(&self) -> Result<U256> {
+        Ok(EthereumClient::get_gas_price(self).await?)
+    }
+
+    async fn get_priority_fee(&self) -> Result<U256> {
+        Ok(EthereumClient::get_priority_fee(self).await?)
+    }
+
+    async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>> {
+        Ok(EthereumClient::get_logs(self, filter).await?)
+    }
+
+    async fn new_filter(&self, filter: &Filter) -> Result<U256> {
+        Ok(EthereumClient::new_filter(self, filter).await?)
+    }
+
+    async fn new_block_filter(&self) -> Result<U256> {
+        Ok(EthereumClient::new_block_filter(self).await?)
+    }
+
+    async fn new_pending_transaction_filter(&self) -> Result<U256> {
+        Ok(EthereumClient::new_pending_transaction_filter(self).await?)
+    }
+
+    async fn get_filter_changes(&self, filter_id: U256) -> Result<Vec<Log>> {
+        Ok(EthereumClient::get_filter_changes(self, filter_id).await?)
+    }
+
+    async fn uninstall_filter(&self, filter_id: U256) -> Result<bool> {
+        Ok(EthereumClient::uninstall_filter(self, filter_id).await?)
+    }
+}
+
+#[cfg(any(test, feature = "mock-client"))]
+pub use mock::{mock_block, MockAccount, MockClient};
+
+// Canned chain data for running the dispatcher without a network. Blocks are kept oldest first and
+// the last one answers `latest` and `finalized`
+#[cfg(any(test, feature = "mock-client"))]
+mod mock {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Mutex;
+
+    #[derive(Debug, Clone, Default)]
+    pub struct MockAccount {
+        pub balance: U256,
+        pub nonce: u64,
+        pub code: Bytes,
+        pub storage: HashMap<B256, U256>,
+    }
+
+    #[derive(Default)]
+    pub struct MockClient {
+        pub chain_id: u64,
+        pub blocks: Vec<Block<Transaction>>,
+        pub accounts: HashMap<Address, MockAccount>,
+        pub transactions: HashMap<B256, Transaction>,
+        pub receipts: HashMap<B256, TransactionReceipt>,
+        // Returned by every get_logs, whatever the filter
+        pub logs: Vec<Log>,
+        // eth_call output per destination, calls elsewhere return empty bytes
+        pub call_results: HashMap<Address, Bytes>,
+        pub gas_estimate: u64,
+        pub gas_price: U256,
+        pub priority_fee: U256,
+        // Raw transactions handed to send_raw_transaction, in order
+        pub sent: Mutex<Vec<Bytes>>,
+        next_filter_id: AtomicU64,
+        filters: Mutex<HashSet<U256>>,
+    }
+
+    // An empty block whose hash and parent hash follow from its number, so consecutive numbers
+    // link up
+    pub fn mock_block(number: u64, timestamp: u64) -> Block<Transaction> {
+        let hash = |number: u64| alloy::primitives::keccak256(number.to_be_bytes());
+        serde_json::from_value(serde_json::json!({
+            "number": format!("0x{:x}", number),
+            "hash": hash(number),
+            "parentHash": hash(number.saturating_sub(1)),
+            "timestamp": format!("0x{:x}", timestamp),
+            "gasLimit": "0x1c9c380",
+            "gasUsed": "0x0",
+            "baseFeePerGas": "0x3b9aca00",
+            "difficulty": "0x0",
+            "totalDifficulty": "0x0",
+            "extraData": "0x",
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "miner": Address::ZERO,
+            "mixHash": B256::ZERO,
+            "nonce": "0x0000000000000000",
+            "receiptsRoot": B256::ZERO,
+            "sha3Uncles": B256::ZERO,
+            "size": "0x0",
+            "stateRoot": B256::ZERO,
+            "transactionsRoot": B256::ZERO,
+            "withdrawalsRoot": B256::ZERO,
+            "withdrawals": [],
+            "blobGasUsed": "0x0",
+            "excessBlobGas": "0x0",
+            "parentBeaconBlockRoot": B256::ZERO,
+            "transactions": [],
+            "uncles": [],
+        }))
+        .expect("mock block")
+    }
+
+    impl MockClient {
+        fn block(&self, tag: BlockTag) -> Option<&Block<Transaction>> {
+            match tag {
+                BlockTag::Number(number) => self.blocks.iter().find(|block| block.number.to::<u64>() == number),
+                _ => self.blocks.last(),
+            }
+        }
+
+        fn account(&self, address: Address) -> MockAccount {
+            self.accounts.get(&address).cloned().unwrap_or_default()
+        }
+
+        fn install_filter(&self) -> U256 {
+            let id = U256::from(self.next_filter_id.fetch_add(1, Ordering::Relaxed) + 1);
+            self.filters.lock().unwrap().insert(id);
+            id
+        }
+    }
+
+    #[async_trait]
+    impl EthClientApi for MockClient {
+        async fn chain_id(&self) -> u64 {
+            self.chain_id
+        }
+
+        async fn syncing(&self) -> Result<SyncStatus> {
+            Ok(SyncStatus::None)
+        }
+
+        async fn shutdown(&self) {}
+
+        async fn get_block_by_number(&self, block: BlockTag, _full_tx: bool) -> Result<Option<Block<Transaction>>> {
+            Ok(self.block(block).cloned())
+        }
+
+        async fn get_block_by_hash(&self, hash: B256, _full_tx: bool) -> Result<Option<Block<Transaction>>> {
+            Ok(self.blocks.iter().find(|block| block.hash == hash).cloned())
+        }
+
+        async fn get_block_transaction_count_by_number(&self, block: BlockTag) -> Result<Option<u64>> {
+            Ok(self.block(block).map(|block| block.transactions.hashes().len() as u64))
+        }
+
+        async fn get_block_transaction_count_by_hash(&self, hash: B256) -> Result<Option<u64>> {
+            Ok(self.blocks
+                .iter()
+                .find(|block| block.hash == hash)
+                .map(|block| block.transactions.hashes().len() as u64))
+        }
+
+        async fn get_coinbase(&self) -> Result<Address> {
+            Ok(Address::ZERO)
+        }
+
+        async fn get_balance(&self, address: Address, _block: BlockTag) -> Result<U256> {
+            Ok(self.account(address).balance)
+        }
+
+        async fn get_nonce(&self, address: Address, _block: BlockTag) -> Result<u64> {
+            Ok(self.account(address).nonce)
+        }
+
+        async fn get_code(&self, address: Address, _block: BlockTag) -> Result<Bytes> {
+            Ok(self.account(address).code)
+        }
+
+        async fn get_storage_at(&self, address: Address, slot: B256, _block: BlockTag) -> Result<U256> {
+            Ok(self.account(address).storage.get(&slot).copied().unwrap_or_default())
+        }
+
+        async fn get_transaction_by_hash(&self, hash: B256) -> Option<Transaction> {
+            self.transactions.get(&hash).cloned()
+        }
+
+        async fn get_transaction_by_block_hash_and_index(&self, block_hash: B256, index: u64) -> Option<Transaction> {
+            let block = self.blocks.iter().find(|block| block.hash == block_hash)?;
+            let hash = block.transactions.hashes().into_iter().nth(index as usize)?;
+            self.transactions.get(&hash).cloned()
+        }
+
+        async fn get_transaction_receipt(&self, hash: B256) -> Result<Option<TransactionReceipt>> {
+            Ok(self.receipts.get(&hash).cloned())
+        }
+
+        async fn send_raw_transaction(&self, bytes: &[u8]) -> Result<B256> {
+            self.sent.lock().unwrap().push(Bytes::copy_from_slice(bytes));
+            Ok(alloy::primitives::keccak256(bytes))
+        }
+
+        async fn call(&self, tx: &TransactionRequest, _block: BlockTag) -> Result<Bytes> {
+            let to = match tx.to {
+                Some(alloy::primitives::TxKind::Call(to)) => to,
+                _ => return Ok(Bytes::new()),
+            };
+            Ok(self.call_results.get(&to).cloned().unwrap_or_default())
+        }
+
+        async fn estimate_gas(&self, _tx: &TransactionRequest) -> Result<u64> {
+            Ok(self.gas_estimate)
+        }
+
+        async fn get_gas_price(&self) -> Result<U256> {
+            Ok(self.gas_price)
+        }
+
+        async fn get_priority_fee(&self) -> Result<U256> {
+            Ok(self.priority_fee)
+        }
+
+        async fn get_logs(&self, _filter: &Filter) -> Result<Vec<Log>> {
+            Ok(self.logs.clone())
+        }
+
+        async fn new_filter(&self, _filter: &Filter) -> Result<U256> {
+            Ok(self.install_filter())
+        }
+
+        async fn new_block_filter(&self) -> Result<U256> {
+            Ok(self.install_filter())
+        }
+
+        async fn new_pending_transaction_filter(&self) -> Result<U256> {
+            Ok(self.install_filter())
+        }
+
+        async fn get_filter_changes(&self, filter_id: U256) -> Result<Vec<Log>> {
+            match self.filters.lock().unwrap().contains(&filter_id) {
+                true => Ok(Vec::new()),
+                false => Err(eyre::eyre!("filter not found")),
+            }
+        }
+
+        async fn uninstall_filter(&self, filter_id: U256) -> Result<bool> {
+            Ok(self.filters.lock().unwrap().remove(&filter_id))
+        }
+    }
+}
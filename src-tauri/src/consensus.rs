@@ -0,0 +1,210 @@
+use alloy::hex;
+use alloy::primitives::B256;
+use alloy::transports::http::reqwest;
+use helios::core::types::BlockTag;
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::client::EthClientApi;
+use crate::outbound;
+
+const SLOTS_PER_EPOCH: u64 = 32;
+const EPOCHS_PER_SYNC_COMMITTEE_PERIOD: u64 = 256;
+const SLOTS_PER_SYNC_COMMITTEE_PERIOD: u64 = SLOTS_PER_EPOCH * EPOCHS_PER_SYNC_COMMITTEE_PERIOD;
+const SYNC_COMMITTEE_SIZE: u32 = 512;
+
+// The beacon API sends 64-bit integers as decimal strings
+fn quoted<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+}
+
+// Subset of the light client update responses
+#[derive(Deserialize)]
+struct UpdateResponse {
+    data: Update,
+}
+
+#[derive(Deserialize)]
+struct Update {
+    attested_header: LightClientHeader,
+    finalized_header: Option<LightClientHeader>,
+    sync_aggregate: SyncAggregate,
+    #[serde(deserialize_with = "quoted")]
+    signature_slot: u64,
+}
+
+#[derive(Deserialize)]
+struct LightClientHeader {
+    beacon: BeaconBlockHeader,
+    execution: Option<ExecutionHeader>,
+}
+
+#[derive(Deserialize)]
+struct BeaconBlockHeader {
+    #[serde(deserialize_with = "quoted")]
+    slot: u64,
+    #[serde(deserialize_with = "quoted")]
+    proposer_index: u64,
+    parent_root: B256,
+    state_root: B256,
+    body_root: B256,
+}
+
+#[derive(Deserialize)]
+struct ExecutionHeader {
+    #[serde(deserialize_with = "quoted")]
+    block_number: u64,
+    block_hash: B256,
+}
+
+#[derive(Deserialize)]
+struct SyncAggregate {
+    sync_committee_bits: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BeaconHeader {
+    pub slot: u64,
+    pub proposer_index: u64,
+    pub parent_root: B256,
+    pub state_root: B256,
+    pub body_root: B256,
+    pub execution_block_number: Option<u64>,
+    pub execution_block_hash: Option<B256>,
+    // Whether the light client has verified the execution block this header commits to
+    pub verified: bool,
+    // Whether the beacon fields above are verified too, which takes the light client's next
+    // execution block committing to this header's root. Never the case for the newest header
+    pub beacon_verified: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncCommitteeInfo {
+    pub period: u64,
+    pub period_start_slot: u64,
+    pub next_period_slot: u64,
+    // Members that signed the head, out of `size`
+    pub participants: u32,
+    pub size: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsensusHead {
+    pub consensus_rpc: String,
+    pub head: BeaconHeader,
+    pub finalized: Option<BeaconHeader>,
+    pub signature_slot: u64,
+    pub sync_committee: SyncCommitteeInfo,
+}
+
+async fn fetch_update(http: &reqwest::Client, consensus_rpc: &str, kind: &str) -> Result<Update, String> {
+    let url = format!("{}/eth/v1/beacon/light_client/{}", consensus_rpc.trim_end_matches('/'), kind);
+    let response = http.get(&url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("{} request failed: {}", kind, e))?
+        .json::<UpdateResponse>()
+        .await
+        .map_err(|e| format!("invalid {}: {}", kind, e))?;
+    Ok(response.data)
+}
+
+// SSZ hash tree root of a beacon block header, the five fields padded out to eight leaves
+fn header_root(header: &BeaconBlockHeader) -> B256 {
+    let uint = |value: u64| {
+        let mut leaf = [0u8; 32];
+        leaf[..8].copy_from_slice(&value.to_le_bytes());
+        leaf
+    };
+    let mut layer = vec![
+        uint(header.slot),
+        uint(header.proposer_index),
+        header.parent_root.0,
+        header.state_root.0,
+        header.body_root.0,
+        [0u8; 32],
+        [0u8; 32],
+        [0u8; 32],
+    ];
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| Sha256::new().chain_update(pair[0]).chain_update(pair[1]).finalize().into())
+            .collect();
+    }
+    B256::from(layer[0])
+}
+
+async fn describe(client: &dyn EthClientApi, header: LightClientHeader) -> BeaconHeader {
+    let verified = match &header.execution {
+        Some(execution) => matches!(
+            client.get_block_by_hash(execution.block_hash, false).await,
+            Ok(Some(block)) if block.number.to::<u64>() == execution.block_number
+        ),
+        None => false,
+    };
+    // The child execution block carries the root of its parent's beacon block
+    let beacon_verified = match header.execution.as_ref().filter(|_| verified) {
+        Some(execution) => matches!(
+            client.get_block_by_number(BlockTag::Number(execution.block_number + 1), false).await,
+            Ok(Some(child)) if child.parent_hash == execution.block_hash
+                && child.parent_beacon_block_root == header_root(&header.beacon)
+        ),
+        None => false,
+    };
+    BeaconHeader {
+        slot: header.beacon.slot,
+        proposer_index: header.beacon.proposer_index,
+        parent_root: header.beacon.parent_root,
+        state_root: header.beacon.state_root,
+        body_root: header.beacon.body_root,
+        execution_block_number: header.execution.as_ref().map(|execution| execution.block_number),
+        execution_block_hash: header.execution.as_ref().map(|execution| execution.block_hash),
+        verified,
+        beacon_verified,
+    }
+}
+
+fn participants(bits: &str) -> u32 {
+    hex::decode(bits.trim_start_matches("0x"))
+        .map(|bytes| bytes.iter().map(|byte| byte.count_ones()).sum())
+        .unwrap_or_default()
+}
+
+// The latest beacon headers from the consensus RPC the client follows. Each is marked verified
+// only if the light client has accepted the execution block it commits to, so a header the RPC
+// serves that helios rejected never shows as verified. The slot, proposer and roots are the RPC's
+// own unless `beaconVerified` is set
+pub async fn head(client: &dyn EthClientApi, consensus_rpc: &str) -> Result<ConsensusHead, String> {
+    let http = outbound::client();
+    let (optimistic, finality) = tokio::try_join!(
+        fetch_update(&http, consensus_rpc, "optimistic_update"),
+        fetch_update(&http, consensus_rpc, "finality_update"),
+    )?;
+
+    let signature_slot = optimistic.signature_slot;
+    let period = signature_slot / SLOTS_PER_SYNC_COMMITTEE_PERIOD;
+    let sync_committee = SyncCommitteeInfo {
+        period,
+        period_start_slot: period * SLOTS_PER_SYNC_COMMITTEE_PERIOD,
+        next_period_slot: (period + 1) * SLOTS_PER_SYNC_COMMITTEE_PERIOD,
+        participants: participants(&optimistic.sync_aggregate.sync_committee_bits),
+        size: SYNC_COMMITTEE_SIZE,
+    };
+    let finalized = match finality.finalized_header {
+        Some(header) => Some(describe(client, header).await),
+        None => None,
+    };
+
+    Ok(ConsensusHead {
+        consensus_rpc: consensus_rpc.to_string(),
+        head: describe(client, optimistic.attested_header).await,
+        finalized,
+        signature_slot,
+        sync_committee,
+    })
+}
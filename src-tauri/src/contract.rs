@@ -0,0 +1,17 @@
+use alloy::primitives::{Address, Bytes};
+use alloy::rpc::types::TransactionRequest;
+use helios::core::types::BlockTag;
+
+use crate::client::EthClientApi;
+use crate::ccip;
+
+// Read-only call against verified latest state, following CCIP-Read redirects
+pub async fn call(client: &dyn EthClientApi, to: Address, calldata: Vec<u8>) -> Result<Bytes, String> {
+    let tx = TransactionRequest::default()
+        .to(to)
+        .input(Bytes::from(calldata).into());
+
+    ccip::call(client, &tx, BlockTag::Latest)
+        .await
+        .map_err(|e| format!("eth_call to 0x{:x} failed: {}", to, e))
+}
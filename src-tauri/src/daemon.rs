@@ -0,0 +1,97 @@
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+
+use crate::mempool;
+use crate::rpc_server;
+use crate::{AppState, ClientConfig, DEFAULT_CONSENSUS_RPC};
+
+const USAGE: &str = "usage: mana --headless --execution-rpc <url> [--execution-ws <url>] [--consensus-rpc <url>] [--chain-id <id>] [--rpc-port <port>] [--profile <name>]";
+
+// `--headless` runs the light client and the local RPC server without opening a window
+pub struct DaemonArgs {
+    execution_rpc: String,
+    execution_ws: Option<String>,
+    consensus_rpc: String,
+    chain_id: u64,
+    rpc_port: u16,
+}
+
+impl DaemonArgs {
+    // None when the binary wasn't started with `--headless`
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut headless = false;
+        let mut execution_rpc = None;
+        let mut execution_ws = None;
+        let mut consensus_rpc = DEFAULT_CONSENSUS_RPC.to_string();
+        let mut chain_id = 1;
+        let mut rpc_port = rpc_server::DEFAULT_RPC_PORT;
+
+        let mut args = args.into_iter().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--headless" {
+                headless = true;
+                continue;
+            }
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value\n{}", arg, USAGE));
+            match arg.as_str() {
+                "--execution-rpc" => execution_rpc = Some(value()?),
+                "--execution-ws" => execution_ws = Some(value()?),
+                "--consensus-rpc" => consensus_rpc = value()?,
+                "--chain-id" => chain_id = value()?.parse().map_err(|_| format!("invalid --chain-id\n{}", USAGE))?,
+                "--rpc-port" => rpc_port = value()?.parse().map_err(|_| format!("invalid --rpc-port\n{}", USAGE))?,
+                // Read by profile::at_launch before settings are restored
+                "--profile" => {
+                    value()?;
+                },
+                // Other arguments belong to the platform, e.g. deep links passed on launch
+                _ => {},
+            }
+        }
+
+        if !headless {
+            return Ok(None);
+        }
+        let execution_rpc = execution_rpc.ok_or_else(|| format!("--execution-rpc is required\n{}", USAGE))?;
+        if execution_ws.as_deref().is_some_and(|url| !mempool::is_ws_url(url)) {
+            return Err(format!("--execution-ws must be a ws(s) URL\n{}", USAGE));
+        }
+        Ok(Some(Self { execution_rpc, execution_ws, consensus_rpc, chain_id, rpc_port }))
+    }
+}
+
+// Starts the client and RPC server, exiting the app if either fails since nobody can see the error
+pub async fn run(app: AppHandle, args: DaemonArgs) {
+    let config = ClientConfig {
+        rpc_url: args.execution_rpc,
+        ws_url: args.execution_ws,
+        consensus_rpcs: vec![args.consensus_rpc],
+        chain_id: args.chain_id,
+        ephemeral: false,
+        checkpoint_fallbacks: Vec::new(),
+    };
+
+    let launched = match crate::launch_client(&app, &config).await {
+        Ok(launched) => launched,
+        Err(e) => {
+            tracing::error!("Failed to start light client: {}", e);
+            app.exit(1);
+            return;
+        }
+    };
+    {
+        let state = app.state::<Mutex<AppState>>();
+        let mut state_guard = state.lock().await;
+        crate::install_client(&app, &mut state_guard, launched, config);
+        let chain_id = state_guard.chain_id;
+        crate::restore_filters(&app, &mut state_guard, chain_id).await;
+    }
+    tracing::info!("Light client synced");
+
+    match rpc_server::serve(app.clone(), args.rpc_port).await {
+        Ok(server) => app.state::<Mutex<AppState>>().lock().await.rpc_server = Some(server),
+        Err(e) => {
+            tracing::error!("{}", e);
+            app.exit(1);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use alloy::primitives::B256;
+use helios::ethereum::config::Config;
+use helios::ethereum::database::{ConfigDB, Database, FileDB};
+
+// Checkpoint store chosen per session: on disk when a data dir is configured, otherwise
+// kept in memory only so ephemeral sessions leave nothing behind
+#[derive(Clone)]
+pub enum AppDB {
+    File(FileDB),
+    Memory(ConfigDB),
+}
+
+impl Database for AppDB {
+    fn new(config: &Config) -> eyre::Result<Self> {
+        if config.data_dir.is_some() {
+            FileDB::new(config).map(AppDB::File)
+        } else {
+            ConfigDB::new(config).map(AppDB::Memory)
+        }
+    }
+
+    fn save_checkpoint(&self, checkpoint: B256) -> eyre::Result<()> {
+        match self {
+            AppDB::File(db) => db.save_checkpoint(checkpoint),
+            AppDB::Memory(db) => db.save_checkpoint(checkpoint),
+        }
+    }
+
+    fn load_checkpoint(&self) -> eyre::Result<B256> {
+        match self {
+            AppDB::File(db) => db.load_checkpoint(),
+            AppDB::Memory(db) => db.load_checkpoint(),
+        }
+    }
+}
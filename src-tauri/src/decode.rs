@@ -0,0 +1,204 @@
+use alloy::dyn_abi::{DynSolType, DynSolValue, JsonAbiExt};
+use alloy::hex;
+use alloy::primitives::Address;
+use serde::Serialize;
+use serde_json::json;
+use std::path::Path;
+
+use crate::{abi, signatures};
+
+// Best-effort description of calldata for transaction previews
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedCall {
+    pub selector: String,
+    // First candidate whose parameters decode the calldata cleanly
+    pub signature: Option<String>,
+    pub candidates: Vec<String>,
+    pub arguments: Option<Vec<serde_json::Value>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CalldataSource {
+    // The contract's ABI, from the registry or Sourcify
+    Abi,
+    // A selector database, which can't tell colliding functions apart or name the arguments
+    Signatures,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedArgument {
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedCalldata {
+    pub selector: String,
+    pub function: Option<String>,
+    pub signature: Option<String>,
+    pub source: Option<CalldataSource>,
+    pub arguments: Vec<DecodedArgument>,
+    // Signatures the selector database offered, empty when the ABI decoded the call
+    pub candidates: Vec<String>,
+}
+
+// Renders decoded values as JSON, with integers as decimal strings so they survive JavaScript
+pub fn value_to_json(value: &DynSolValue) -> serde_json::Value {
+    if let Some(values) = value.as_fixed_array().or_else(|| value.as_array()).or_else(|| value.as_tuple()) {
+        return json!(values.iter().map(value_to_json).collect::<Vec<_>>());
+    }
+    if let Some(address) = value.as_address() {
+        return json!(address.to_checksum(None));
+    }
+    if let Some(b) = value.as_bool() {
+        return json!(b);
+    }
+    if let Some((uint, _)) = value.as_uint() {
+        return json!(uint.to_string());
+    }
+    if let Some((int, _)) = value.as_int() {
+        return json!(int.to_string());
+    }
+    if let Some(s) = value.as_str() {
+        return json!(s);
+    }
+    if let Some(bytes) = value.as_bytes() {
+        return json!(format!("0x{}", hex::encode(bytes)));
+    }
+    if let Some((word, size)) = value.as_fixed_bytes() {
+        return json!(format!("0x{}", hex::encode(&word[..size])));
+    }
+    json!(null)
+}
+
+// Decodes the arguments of `data` using a text signature like `transfer(address,uint256)`
+pub fn decode_arguments(signature: &str, data: &[u8]) -> Option<Vec<serde_json::Value>> {
+    let params = &signature[signature.find('(')?..];
+    let ty = DynSolType::parse(params).ok()?;
+    let decoded = ty.abi_decode_params(data.get(4..)?).ok()?;
+    let values = decoded.as_tuple()?;
+
+    // Signature databases contain collisions, so only accept an exact, canonical re-encoding
+    let reencoded = DynSolValue::Tuple(values.to_vec()).abi_encode_params();
+    (reencoded[..] == data[4..]).then(|| values.iter().map(value_to_json).collect())
+}
+
+// Resolves the selector through the signature database and decodes with the best guess
+pub async fn describe_call(signature_cache: &Path, data: &[u8]) -> Result<Option<DecodedCall>, String> {
+    let Some(selector) = data.get(..4) else {
+        return Ok(None);
+    };
+    let selector = format!("0x{}", hex::encode(selector));
+    let candidates = signatures::lookup(signature_cache, &selector).await?;
+
+    let decoded = candidates
+        .iter()
+        .find_map(|candidate| decode_arguments(candidate, data).map(|arguments| (candidate.clone(), arguments)));
+
+    Ok(Some(DecodedCall {
+        selector,
+        signature: decoded.as_ref().map(|(signature, _)| signature.clone()),
+        arguments: decoded.map(|(_, arguments)| arguments),
+        candidates,
+    }))
+}
+
+// Decodes with the function of `abi` whose selector the calldata starts with
+fn decode_with_abi(abi: &alloy::json_abi::JsonAbi, data: &[u8]) -> Option<(String, String, Vec<DecodedArgument>)> {
+    let selector = data.get(..4)?;
+    let function = abi.functions().find(|function| function.selector().as_slice() == selector)?;
+    let values = function.abi_decode_input(&data[4..], true).ok()?;
+    let arguments = function.inputs
+        .iter()
+        .zip(&values)
+        .map(|(input, value)| DecodedArgument {
+            name: Some(input.name.clone()).filter(|name| !name.is_empty()),
+            kind: input.selector_type().into_owned(),
+            value: value_to_json(value),
+        })
+        .collect();
+    Some((function.name.clone(), function.signature(), arguments))
+}
+
+// Names what calldata to `to` does: with the contract's ABI from the registry or Sourcify when
+// there is one, otherwise from the selector databases. Plain transfers and calldata too short to
+// hold a selector decode to nothing
+pub async fn decode_calldata(
+    registry: &Path,
+    sourcify_cache: Option<&Path>,
+    signature_cache: &Path,
+    chain_id: u64,
+    to: Option<Address>,
+    data: &[u8],
+) -> Result<Option<DecodedCalldata>, String> {
+    let Some(selector) = data.get(..4) else {
+        return Ok(None);
+    };
+    let selector = format!("0x{}", hex::encode(selector));
+
+    if let Some(to) = to {
+        let resolved = match abi::AbiRegistry::open(registry) {
+            Ok(mut registry) => registry.resolve(sourcify_cache, chain_id, to).await,
+            Err(e) => Err(e),
+        };
+        let abi = resolved.unwrap_or_else(|e| {
+            tracing::warn!("No ABI for 0x{:x}: {}", to, e);
+            None
+        });
+        if let Some((function, signature, arguments)) = abi.and_then(|abi| decode_with_abi(&abi, data)) {
+            return Ok(Some(DecodedCalldata {
+                selector,
+                function: Some(function),
+                signature: Some(signature),
+                source: Some(CalldataSource::Abi),
+                arguments,
+                candidates: Vec::new(),
+            }));
+        }
+    }
+
+    let candidates = signatures::lookup(signature_cache, &selector).await?;
+    let decoded = candidates
+        .iter()
+        .find_map(|candidate| decode_arguments(candidate, data).map(|values| (candidate.clone(), values)));
+    let Some((signature, values)) = decoded else {
+        return Ok(Some(DecodedCalldata {
+            selector,
+            function: None,
+            signature: None,
+            source: None,
+            arguments: Vec::new(),
+            candidates,
+        }));
+    };
+    let types = DynSolType::parse(&signature[signature.find('(').unwrap_or_default()..])
+        .ok()
+        .map(|ty| match ty {
+            DynSolType::Tuple(types) => types.iter().map(|ty| ty.sol_type_name().into_owned()).collect(),
+            _ => Vec::new(),
+        })
+        .unwrap_or_default();
+    let arguments = values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| DecodedArgument {
+            name: None,
+            kind: types.get(i).cloned().unwrap_or_default(),
+            value,
+        })
+        .collect();
+    Ok(Some(DecodedCalldata {
+        selector,
+        function: signature.split('(').next().map(str::to_string),
+        signature: Some(signature),
+        source: Some(CalldataSource::Signatures),
+        arguments,
+        candidates,
+    }))
+}
@@ -0,0 +1,91 @@
+use alloy::primitives::Address;
+use serde::Serialize;
+
+use crate::passthrough;
+
+// Only nodes on this machine, since the node signs with its own unlocked accounts and nothing it
+// returns is verified
+const LOCAL_HOSTS: &[&str] = &["localhost", "127.0.0.1", "[::1]"];
+
+// A local development node, like Anvil or Hardhat, that takes the light client's place for every
+// dapp while dev mode is on
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevNode {
+    pub url: String,
+    pub chain_id: u64,
+}
+
+pub fn validate_url(url: &str) -> Result<(), String> {
+    let parsed = url::Url::parse(url).map_err(|e| format!("Invalid node URL {}: {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("Node URL must be an http(s) URL: {}", url));
+    }
+    match parsed.host_str() {
+        Some(host) if LOCAL_HOSTS.contains(&host) => Ok(()),
+        _ => Err(format!("Dev mode only connects to a node on this machine: {}", url)),
+    }
+}
+
+// Checks the node answers and reads the chain id it reports
+pub async fn connect(url: &str) -> Result<DevNode, String> {
+    validate_url(url)?;
+    let response = passthrough::forward(url, "eth_chainId", &[]).await?;
+    let chain_id = response.get("result")
+        .and_then(|id| id.as_str())
+        .and_then(|id| u64::from_str_radix(id.trim_start_matches("0x"), 16).ok())
+        .ok_or_else(|| format!("{} didn't report a chain id", url))?;
+    Ok(DevNode { url: url.to_string(), chain_id })
+}
+
+// Node control, like anvil_impersonateAccount, anvil_setBalance, evm_mine or hardhat_reset, so
+// dapp tooling that drives the node through the wallet works as it does against the node directly
+const NODE_CONTROL_NAMESPACES: &[&str] = &["anvil_", "hardhat_", "evm_"];
+
+// Standard namespaces and node control go to the node. Wallet methods stay with the app, which
+// answers wallet_switchEthereumChain for the node's chain
+pub fn is_forwarded(method: &str) -> bool {
+    ["eth_", "net_", "web3_"].iter().chain(NODE_CONTROL_NAMESPACES).any(|namespace| method.starts_with(namespace))
+}
+
+pub fn is_node_control(method: &str) -> bool {
+    NODE_CONTROL_NAMESPACES.iter().any(|namespace| method.starts_with(namespace))
+}
+
+// Node control the sandbox implements on its fork, snapshots and time
+const SANDBOX_NODE_CONTROL: &[&str] = &[
+    "evm_snapshot",
+    "evm_revert",
+    "evm_increaseTime",
+    "evm_setNextBlockTimestamp",
+    "evm_mine",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeControl {
+    DevNode,
+    Sandbox,
+    Refused,
+}
+
+// Who answers a node control method, None for any other method. A dev node takes all of it, the
+// sandbox only what it implements, and without either there's no node to control
+pub fn node_control(method: &str, dev_mode: bool, sandbox: bool) -> Option<NodeControl> {
+    if !is_node_control(method) {
+        return None;
+    }
+    Some(match (dev_mode, sandbox) {
+        (true, _) => NodeControl::DevNode,
+        (false, true) if SANDBOX_NODE_CONTROL.contains(&method) => NodeControl::Sandbox,
+        _ => NodeControl::Refused,
+    })
+}
+
+// The node's unlocked accounts, which stand in for the wallet's while dev mode is on
+pub async fn node_accounts(url: &str) -> Result<Vec<Address>, String> {
+    let response = passthrough::forward(url, "eth_accounts", &[]).await?;
+    response.get("result")
+        .cloned()
+        .and_then(|accounts| serde_json::from_value(accounts).ok())
+        .ok_or_else(|| format!("{} didn't list its accounts", url))
+}
@@ -0,0 +1,174 @@
+use alloy::primitives::{keccak256, Address, Bytes, B256, U256, U64};
+use alloy::rlp::{Encodable, Header};
+use alloy_trie::proof::verify_proof;
+use alloy_trie::{Nibbles, EMPTY_ROOT_HASH, KECCAK_EMPTY};
+use helios::core::types::BlockTag;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::client::EthClientApi;
+use crate::passthrough;
+
+const MAX_DECOYS: usize = 8;
+
+// Spreads queries about the user's accounts over several execution providers, so no single one
+// sees the whole address set. Each address always goes to the same provider, otherwise every
+// provider would eventually see every address
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DistributionSettings {
+    pub enabled: bool,
+    // Execution RPCs of the app's chain used alongside the main one
+    pub providers: Vec<String>,
+    // Queries for random addresses sent along with each real account query
+    pub decoys: usize,
+}
+
+impl DistributionSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.decoys > MAX_DECOYS {
+            return Err(format!("At most {} decoy queries are allowed", MAX_DECOYS));
+        }
+        Ok(())
+    }
+
+    // Provider assigned to `address`, picked by the address hash among the main and extra providers
+    pub fn provider_for<'a>(&'a self, rpc_url: &'a str, address: &Address) -> &'a str {
+        let slot = u64::from_be_bytes(keccak256(address)[..8].try_into().unwrap()) as usize % (self.providers.len() + 1);
+        match slot {
+            0 => rpc_url,
+            slot => &self.providers[slot - 1],
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountProof {
+    balance: U256,
+    nonce: U64,
+    code_hash: B256,
+    storage_hash: B256,
+    account_proof: Vec<Bytes>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Account {
+    pub balance: U256,
+    pub nonce: u64,
+}
+
+fn encode_account(account: &AccountProof) -> Vec<u8> {
+    let mut payload = Vec::new();
+    account.nonce.to::<u64>().encode(&mut payload);
+    account.balance.encode(&mut payload);
+    account.storage_hash.encode(&mut payload);
+    account.code_hash.encode(&mut payload);
+    let mut out = Vec::new();
+    Header { list: true, payload_length: payload.len() }.encode(&mut out);
+    out.extend(payload);
+    out
+}
+
+// Balance and nonce of `address` from `rpc_url`, proven against the verified state root of `tag`,
+// so the extra providers don't need to be trusted any more than the main one
+pub async fn account(
+    client: &dyn EthClientApi,
+    rpc_url: &str,
+    address: Address,
+    tag: BlockTag,
+    decoys: usize,
+) -> Result<Account, String> {
+    let block = client.get_block_by_number(tag, false)
+        .await
+        .map_err(|e| format!("Failed to get block: {}", e))?
+        .ok_or("Block not available from the light client")?;
+    let number = format!("0x{:x}", block.number.to::<u64>());
+    send_decoys(rpc_url, "eth_getProof", decoys, |decoy| vec![json!(decoy), json!([]), json!(number)]);
+    let upstream = passthrough::forward(rpc_url, "eth_getProof", &[json!(address), json!([]), json!(number)]).await?;
+    let proof: AccountProof = upstream.get("result")
+        .filter(|proof| !proof.is_null())
+        .cloned()
+        .map(serde_json::from_value)
+        .ok_or("Execution RPC returned no proof")?
+        .map_err(|e| format!("Invalid proof from execution RPC: {}", e))?;
+
+    // Providers describe a missing account as an empty one, which the trie proves by exclusion
+    let empty = proof.nonce.is_zero()
+        && proof.balance.is_zero()
+        && proof.storage_hash == EMPTY_ROOT_HASH
+        && (proof.code_hash == KECCAK_EMPTY || proof.code_hash.is_zero());
+    let key = Nibbles::unpack(keccak256(address));
+    let included = verify_proof(block.state_root, key.clone(), Some(encode_account(&proof)), &proof.account_proof);
+    if included.is_err() && !(empty && verify_proof(block.state_root, key, None, &proof.account_proof).is_ok()) {
+        return Err("Account proof from execution RPC doesn't match the verified state root".to_string());
+    }
+    Ok(Account {
+        balance: proof.balance,
+        nonce: proof.nonce.to::<u64>(),
+    })
+}
+
+// Where balance and nonce reads go. With distribution on, reads on the app's chain are proven
+// eth_getProof answers from the provider assigned to each address, otherwise the light client's
+#[derive(Debug, Clone, Default)]
+pub struct AccountSource {
+    distributed: Option<(DistributionSettings, String)>,
+}
+
+impl AccountSource {
+    // Reads through the light client's own RPC, for chains distribution doesn't cover
+    pub fn direct() -> Self {
+        Self::default()
+    }
+
+    pub fn new(settings: &DistributionSettings, rpc_url: &str) -> Self {
+        Self {
+            distributed: settings.enabled.then(|| (settings.clone(), rpc_url.to_string())),
+        }
+    }
+
+    pub fn is_distributed(&self) -> bool {
+        self.distributed.is_some()
+    }
+
+    pub async fn account(&self, client: &dyn EthClientApi, address: Address, tag: BlockTag) -> Result<Account, String> {
+        match &self.distributed {
+            Some((settings, rpc_url)) => account(client, settings.provider_for(rpc_url, &address), address, tag, settings.decoys).await,
+            None => {
+                let (balance, nonce) = tokio::try_join!(client.get_balance(address, tag), client.get_nonce(address, tag))
+                    .map_err(|e| format!("Failed to read account 0x{:x}: {}", address, e))?;
+                Ok(Account { balance, nonce })
+            },
+        }
+    }
+}
+
+// Sends `count` copies of a query to `rpc_url` in the background, each built by `params` around a
+// random address, so the real address is one among several. Answers are discarded
+pub fn send_decoys(
+    rpc_url: &str,
+    method: &'static str,
+    count: usize,
+    params: impl Fn(Address) -> Vec<serde_json::Value>,
+) {
+    for _ in 0..count {
+        let params = params(Address::from(rand::random::<[u8; 20]>()));
+        let rpc_url = rpc_url.to_string();
+        tauri::async_runtime::spawn(async move {
+            let _ = passthrough::forward(&rpc_url, method, &params).await;
+        });
+    }
+}
+
+// Decoys for a log query that names addresses: the same query with the addresses swapped out
+pub fn send_log_decoys(rpc_url: &str, count: usize, filter: &serde_json::Value) {
+    if filter.get("address").map_or(true, |address| address.is_null()) {
+        return;
+    }
+    send_decoys(rpc_url, "eth_getLogs", count, |decoy| {
+        let mut filter = filter.clone();
+        filter["address"] = json!(decoy);
+        vec![filter]
+    });
+}
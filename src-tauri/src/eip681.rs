@@ -0,0 +1,171 @@
+use alloy::dyn_abi::{DynSolType, DynSolValue};
+use alloy::primitives::{keccak256, Address, Bytes, U256};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Mutex;
+
+use crate::{ens, AppState};
+
+// Query keys with a fixed meaning, everything else is a typed function argument
+const RESERVED_PARAMS: &[&str] = &["value", "gas", "gasLimit", "gasPrice"];
+
+// `ethereum:[pay-]<target>[@<chain_id>][/<function>][?<params>]`
+#[derive(Debug, Clone)]
+pub struct PaymentUri {
+    pub target: String,
+    pub chain_id: Option<u64>,
+    pub function: Option<String>,
+    pub params: Vec<(String, String)>,
+}
+
+// Pre-filled transaction for the approval UI
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRequest {
+    pub uri: String,
+    pub chain_id: Option<u64>,
+    pub to: Address,
+    pub value: Option<U256>,
+    pub gas: Option<U256>,
+    pub gas_price: Option<U256>,
+    pub function: Option<String>,
+    pub data: Option<Bytes>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PaymentUriError {
+    uri: String,
+    error: String,
+}
+
+pub fn parse(uri: &str) -> Result<PaymentUri, String> {
+    let rest = uri.strip_prefix("ethereum:").ok_or("Not an ethereum: URI")?;
+    let rest = rest.strip_prefix("pay-").unwrap_or(rest);
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let (target, function) = match path.split_once('/') {
+        Some((target, function)) if !function.is_empty() => (target, Some(function.to_string())),
+        Some((target, _)) => (target, None),
+        None => (path, None),
+    };
+    let (target, chain_id) = match target.split_once('@') {
+        Some((target, chain_id)) => {
+            let chain_id = chain_id.parse::<u64>()
+                .map_err(|_| format!("Invalid chain id: {}", chain_id))?;
+            (target, Some(chain_id))
+        },
+        None => (target, None),
+    };
+
+    if target.is_empty() {
+        return Err("Missing target address".to_string());
+    }
+
+    let params = url::form_urlencoded::parse(query.as_bytes())
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+
+    Ok(PaymentUri {
+        target: target.to_string(),
+        chain_id,
+        function,
+        params,
+    })
+}
+
+// EIP-681 numbers may be hex, plain decimal, or scientific notation like `2.014e18`
+pub fn parse_number(value: &str) -> Result<U256, String> {
+    if let Some(hex) = value.strip_prefix("0x") {
+        return U256::from_str_radix(hex, 16).map_err(|e| format!("Invalid number {}: {}", value, e));
+    }
+
+    let (mantissa, exponent) = match value.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => {
+            let exponent = exponent.trim_start_matches('+').parse::<usize>()
+                .map_err(|_| format!("Invalid exponent in {}", value))?;
+            (mantissa, exponent)
+        },
+        None => (value, 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > exponent {
+        return Err(format!("{} is not an integer amount", value));
+    }
+
+    let digits = format!("{}{}{}", integer, fraction, "0".repeat(exponent - fraction.len()));
+    U256::from_str_radix(&digits, 10).map_err(|e| format!("Invalid number {}: {}", value, e))
+}
+
+// ABI-encodes the function call described by the URI, e.g. `transfer?address=0x..&uint256=1e6`
+fn encode_call(function: &str, params: &[(String, String)]) -> Result<Bytes, String> {
+    let mut types = Vec::new();
+    let mut values = Vec::new();
+
+    for (ty, value) in params.iter().filter(|(k, _)| !RESERVED_PARAMS.contains(&k.as_str())) {
+        let sol_type = DynSolType::parse(ty).map_err(|e| format!("Invalid parameter type {}: {}", ty, e))?;
+        let sol_value = match sol_type {
+            DynSolType::Uint(bits) => DynSolValue::Uint(parse_number(value)?, bits),
+            _ => sol_type.coerce_str(value)
+                .map_err(|e| format!("Invalid {} value {}: {}", ty, value, e))?,
+        };
+        types.push(ty.as_str());
+        values.push(sol_value);
+    }
+
+    let signature = format!("{}({})", function, types.join(","));
+    let mut data = keccak256(signature.as_bytes())[..4].to_vec();
+    data.extend(DynSolValue::Tuple(values).abi_encode_params());
+    Ok(data.into())
+}
+
+pub fn build_request(uri: &str, parsed: &PaymentUri, to: Address) -> Result<PaymentRequest, String> {
+    let param = |key: &str| parsed.params.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+
+    Ok(PaymentRequest {
+        uri: uri.to_string(),
+        chain_id: parsed.chain_id,
+        to,
+        value: param("value").map(parse_number).transpose()?,
+        gas: param("gasLimit").or(param("gas")).map(parse_number).transpose()?,
+        gas_price: param("gasPrice").map(parse_number).transpose()?,
+        function: parsed.function.clone(),
+        data: parsed.function
+            .as_deref()
+            .map(|function| encode_call(function, &parsed.params))
+            .transpose()?,
+    })
+}
+
+// Turns an `ethereum:` URI into a payment request, resolving ENS targets through the verified client
+pub async fn resolve(app: &AppHandle, uri: &str) -> Result<PaymentRequest, String> {
+    let parsed = parse(uri)?;
+
+    let to = match parsed.target.parse::<Address>() {
+        Ok(address) => address,
+        Err(_) => {
+            let state = app.state::<Mutex<AppState>>();
+            let state_guard = state.lock().await;
+            let client = state_guard.client.as_ref()
+                .ok_or("Light client not initialized, cannot resolve ENS target")?;
+            ens::resolve_address(client, &parsed.target)
+                .await?
+                .ok_or_else(|| format!("{} does not resolve to an address", parsed.target))?
+        }
+    };
+
+    build_request(uri, &parsed, to)
+}
+
+// Deep-link entry point: emits the pre-filled request for the approval UI
+pub async fn handle_deep_link(app: AppHandle, uri: String) {
+    match resolve(&app, &uri).await {
+        Ok(request) => {
+            let _ = app.emit("payment-request", request);
+        },
+        Err(error) => {
+            tracing::warn!("Rejected payment URI {}: {}", uri, error);
+            let _ = app.emit("payment-request-error", PaymentUriError { uri, error });
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use alloy::primitives::{address, keccak256, Address, FixedBytes, B256};
+use alloy::sol;
+use alloy::sol_types::SolCall;
+
+use crate::client::EthClientApi;
+use crate::contract;
+
+pub const ENS_REGISTRY_ADDRESS: Address = address!("00000000000C2E074eC69A0dFb2997BA6C7d2e1e");
+
+// ENSIP-10 IExtendedResolver interface id
+const EXTENDED_RESOLVER_INTERFACE: [u8; 4] = [0x90, 0x61, 0xb9, 0x23];
+
+sol! {
+    function resolver(bytes32 node) external view returns (address);
+    function addr(bytes32 node) external view returns (address);
+    function supportsInterface(bytes4 interfaceID) external view returns (bool);
+    function resolve(bytes name, bytes data) external view returns (bytes);
+    function name(bytes32 node) external view returns (string);
+    function contenthash(bytes32 node) external view returns (bytes);
+}
+
+pub fn normalize(name: &str) -> String {
+    name.trim().trim_end_matches('.').to_lowercase()
+}
+
+pub fn namehash(name: &str) -> B256 {
+    let mut node = B256::ZERO;
+    if name.is_empty() {
+        return node;
+    }
+    for label in name.rsplit('.') {
+        node = keccak256([node.as_slice(), keccak256(label.as_bytes()).as_slice()].concat());
+    }
+    node
+}
+
+// DNS wire format used by ENSIP-10 `resolve(bytes,bytes)`
+pub fn dns_encode(name: &str) -> Result<Vec<u8>, String> {
+    let mut encoded = Vec::with_capacity(name.len() + 2);
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 255 {
+            return Err(format!("Invalid ENS name: {}", name));
+        }
+        encoded.push(label.len() as u8);
+        encoded.extend_from_slice(label.as_bytes());
+    }
+    encoded.push(0);
+    Ok(encoded)
+}
+
+pub struct Resolver {
+    pub address: Address,
+    // Whether the resolver was found on the name itself rather than on a parent (wildcard)
+    pub exact: bool,
+    pub extended: bool,
+}
+
+// Walks up the name hierarchy until a resolver is set, as required for wildcard resolution
+pub async fn find_resolver(client: &dyn EthClientApi, name: &str) -> Result<Option<Resolver>, String> {
+    let labels: Vec<&str> = name.split('.').collect();
+
+    for i in 0..labels.len() {
+        let parent = labels[i..].join(".");
+        let output = contract::call(
+            client,
+            ENS_REGISTRY_ADDRESS,
+            resolverCall { node: namehash(&parent) }.abi_encode(),
+        ).await?;
+        let address = resolverCall::abi_decode_returns(&output, true)
+            .map_err(|e| format!("Failed to decode resolver: {}", e))?
+            ._0;
+
+        if address != Address::ZERO {
+            let extended = supports_interface(client, address, EXTENDED_RESOLVER_INTERFACE).await;
+            return Ok(Some(Resolver { address, exact: i == 0, extended }));
+        }
+    }
+
+    Ok(None)
+}
+
+// Old resolvers may not implement ERC-165 at all, which counts as unsupported
+async fn supports_interface(client: &dyn EthClientApi, target: Address, interface: [u8; 4]) -> bool {
+    let calldata = supportsInterfaceCall { interfaceID: FixedBytes(interface) }.abi_encode();
+    match contract::call(client, target, calldata).await {
+        Ok(output) => supportsInterfaceCall::abi_decode_returns(&output, true)
+            .map(|r| r._0)
+            .unwrap_or(false),
+        Err(_) => false,
+    }
+}
+
+// Calls a resolver record function, going through `resolve(bytes,bytes)` for extended resolvers
+pub async fn resolve_record(
+    client: &dyn EthClientApi,
+    resolver: &Resolver,
+    name: &str,
+    calldata: Vec<u8>,
+) -> Result<Option<Vec<u8>>, String> {
+    if resolver.extended {
+        let output = contract::call(
+            client,
+            resolver.address,
+            resolveCall { name: dns_encode(name)?.into(), data: calldata.into() }.abi_encode(),
+        ).await?;
+        let decoded = resolveCall::abi_decode_returns(&output, true)
+            .map_err(|e| format!("Failed to decode resolve response: {}", e))?;
+        Ok(Some(decoded._0.to_vec()))
+    } else if resolver.exact {
+        Ok(Some(contract::call(client, resolver.address, calldata).await?.to_vec()))
+    } else {
+        Ok(None)
+    }
+}
+
+pub async fn resolve_address(client: &dyn EthClientApi, name: &str) -> Result<Option<Address>, String> {
+    let name = normalize(name);
+    let Some(resolver) = find_resolver(client, &name).await? else {
+        return Ok(None);
+    };
+
+    let calldata = addrCall { node: namehash(&name) }.abi_encode();
+    let Some(output) = resolve_record(client, &resolver, &name, calldata).await? else {
+        return Ok(None);
+    };
+
+    let address = addrCall::abi_decode_returns(&output, true)
+        .map_err(|e| format!("Failed to decode address: {}", e))?
+        ._0;
+
+    Ok(if address == Address::ZERO { None } else { Some(address) })
+}
+
+// Reverse record lookup, only trusted when the name resolves forward to the same address
+pub async fn lookup_address(client: &dyn EthClientApi, address: Address) -> Result<Option<String>, String> {
+    let reverse_name = format!("{:x}.addr.reverse", address);
+    let node = namehash(&reverse_name);
+
+    let output = contract::call(client, ENS_REGISTRY_ADDRESS, resolverCall { node }.abi_encode()).await?;
+    let resolver = resolverCall::abi_decode_returns(&output, true)
+        .map_err(|e| format!("Failed to decode resolver: {}", e))?
+        ._0;
+    if resolver == Address::ZERO {
+        return Ok(None);
+    }
+
+    let output = contract::call(client, resolver, nameCall { node }.abi_encode()).await?;
+    let name = nameCall::abi_decode_returns(&output, true)
+        .map_err(|e| format!("Failed to decode name: {}", e))?
+        ._0;
+    if name.is_empty() {
+        return Ok(None);
+    }
+
+    match resolve_address(client, &name).await? {
+        Some(forward) if forward == address => Ok(Some(name)),
+        _ => Ok(None),
+    }
+}
+
+// Raw EIP-1577 contenthash record, decoded by the ipfs module
+pub async fn resolve_contenthash(client: &dyn EthClientApi, name: &str) -> Result<Option<Vec<u8>>, String> {
+    let name = normalize(name);
+    let Some(resolver) = find_resolver(client, &name).await? else {
+        return Ok(None);
+    };
+
+    let calldata = contenthashCall { node: namehash(&name) }.abi_encode();
+    let Some(output) = resolve_record(client, &resolver, &name, calldata).await? else {
+        return Ok(None);
+    };
+
+    let contenthash = contenthashCall::abi_decode_returns(&output, true)
+        .map_err(|e| format!("Failed to decode contenthash: {}", e))?
+        ._0;
+
+    Ok(if contenthash.is_empty() { None } else { Some(contenthash.to_vec()) })
+}
@@ -0,0 +1,29 @@
+// Every error code the provider returns. JSON-RPC failures use the EIP-1474 codes and provider
+// state uses the EIP-1193 ones, so dapps can branch on the code rather than the message
+
+// JSON-RPC 2.0
+pub const PARSE_ERROR: i32 = -32700;
+pub const INVALID_REQUEST: i32 = -32600;
+pub const METHOD_NOT_FOUND: i32 = -32601;
+pub const INVALID_PARAMS: i32 = -32602;
+pub const INTERNAL_ERROR: i32 = -32603;
+
+// EIP-1474 server errors
+pub const RESOURCE_NOT_FOUND: i32 = -32001;
+pub const RESOURCE_UNAVAILABLE: i32 = -32002;
+pub const TRANSACTION_REJECTED: i32 = -32003;
+pub const METHOD_NOT_SUPPORTED: i32 = -32004;
+pub const LIMIT_EXCEEDED: i32 = -32005;
+
+// EIP-1193 provider errors
+pub const USER_REJECTED: i32 = 4001;
+pub const UNAUTHORIZED: i32 = 4100;
+// Not part of EIP-1193, returned while the keystore is locked so dapps can tell it apart from a rejection
+pub const WALLET_LOCKED: i32 = 4102;
+// Not part of EIP-1193, returned for signing and sending while the user has read-only mode on
+pub const READ_ONLY: i32 = 4103;
+pub const UNSUPPORTED_METHOD: i32 = 4200;
+// wallet_switchEthereumChain for a chain the wallet doesn't know, as MetaMask returns it
+pub const UNRECOGNIZED_CHAIN: i32 = 4902;
+// The light client isn't running, so nothing that reads chain state can be answered
+pub const DISCONNECTED: i32 = 4900;
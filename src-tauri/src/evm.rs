@@ -0,0 +1,263 @@
+use alloy::primitives::{Address, Bytes, TxKind, B256, U256, U64};
+use alloy::rpc::types::{Transaction, TransactionRequest};
+use helios::core::types::{Block, BlockTag};
+use revm::db::CacheDB;
+use revm::primitives::{AccountInfo, BlockEnv, Bytecode, ExecutionResult, ResultAndState, SpecId, TxEnv};
+use revm::{inspector_handle_register, Database, DatabaseRef, Evm, Inspector};
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio::runtime::Handle;
+
+use crate::client::EthClientApi;
+use crate::distribution::AccountSource;
+
+const SPEC_ID: SpecId = SpecId::CANCUN;
+
+// Gas given to calls that don't set a limit, matching geth's RPC gas cap
+pub const DEFAULT_GAS_CAP: u64 = 50_000_000;
+
+// revm state backend that loads every account, slot and block hash through the light client,
+// so local execution only ever sees values proven against a verified state root. Balances and
+// nonces follow the account source, so distribution covers execution too.
+// Lookups block on the async client, so execution must run inside `block_in_place`
+pub struct VerifiedState<'a> {
+    client: &'a dyn EthClientApi,
+    accounts: AccountSource,
+    block: BlockTag,
+    handle: Handle,
+}
+
+pub type VerifiedDb<'a> = CacheDB<VerifiedState<'a>>;
+
+impl<'a> VerifiedState<'a> {
+    pub fn new(client: &'a dyn EthClientApi, accounts: AccountSource, block: BlockTag) -> Self {
+        Self {
+            client,
+            accounts,
+            block,
+            handle: Handle::current(),
+        }
+    }
+}
+
+impl DatabaseRef for VerifiedState<'_> {
+    type Error = String;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, String> {
+        let (account, code) = self.handle
+            .block_on(async {
+                tokio::try_join!(
+                    self.accounts.account(self.client, address, self.block),
+                    async { self.client.get_code(address, self.block).await.map_err(|e| e.to_string()) },
+                )
+            })
+            .map_err(|e| format!("Failed to load account 0x{:x}: {}", address, e))?;
+
+        if account.balance.is_zero() && account.nonce == 0 && code.is_empty() {
+            return Ok(None);
+        }
+
+        let code = Bytecode::new_raw(code.into());
+        Ok(Some(AccountInfo::new(account.balance, account.nonce, code.hash_slow(), code)))
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, String> {
+        // Code always arrives together with its account in basic_ref
+        Err(format!("Unexpected code lookup by hash 0x{:x}", code_hash))
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, String> {
+        self.handle
+            .block_on(self.client.get_storage_at(address, B256::from(index.to_be_bytes()), self.block))
+            .map_err(|e| format!("Failed to load storage 0x{:x} of 0x{:x}: {}", index, address, e))
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, String> {
+        match self.handle.block_on(self.client.get_block_by_number(BlockTag::Number(number), false)) {
+            Ok(Some(block)) => Ok(block.hash),
+            Ok(None) => Err(format!("Block {} is not available from the light client", number)),
+            Err(e) => Err(format!("Failed to load block {}: {}", number, e)),
+        }
+    }
+}
+
+// Geth-style per-account override, used by eth_call and eth_simulateV1
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountOverride {
+    pub balance: Option<U256>,
+    pub nonce: Option<U64>,
+    pub code: Option<Bytes>,
+    pub state: Option<HashMap<B256, B256>>,
+    pub state_diff: Option<HashMap<B256, B256>>,
+}
+
+pub type StateOverride = HashMap<Address, AccountOverride>;
+
+// Accepts both the eth_simulateV1 field names and geth's eth_call aliases
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockOverrides {
+    pub number: Option<U64>,
+    pub time: Option<U64>,
+    pub gas_limit: Option<U64>,
+    #[serde(alias = "coinbase")]
+    pub fee_recipient: Option<Address>,
+    #[serde(alias = "random")]
+    pub prev_randao: Option<B256>,
+    #[serde(alias = "baseFee")]
+    pub base_fee_per_gas: Option<U256>,
+}
+
+impl BlockOverrides {
+    pub fn apply(&self, env: &mut BlockEnv) {
+        if let Some(number) = self.number {
+            env.number = U256::from(number.to::<u64>());
+        }
+        if let Some(time) = self.time {
+            env.timestamp = U256::from(time.to::<u64>());
+        }
+        if let Some(gas_limit) = self.gas_limit {
+            env.gas_limit = U256::from(gas_limit.to::<u64>());
+        }
+        if let Some(fee_recipient) = self.fee_recipient {
+            env.coinbase = fee_recipient;
+        }
+        if let Some(prev_randao) = self.prev_randao {
+            env.prevrandao = Some(prev_randao);
+        }
+        if let Some(base_fee) = self.base_fee_per_gas {
+            env.basefee = base_fee;
+        }
+    }
+}
+
+pub fn apply_state_overrides(db: &mut VerifiedDb<'_>, overrides: &StateOverride) -> Result<(), String> {
+    for (address, account) in overrides {
+        if account.state.is_some() && account.state_diff.is_some() {
+            return Err(format!("Account 0x{:x} has both state and stateDiff overrides", address));
+        }
+
+        let mut info = db.basic(*address)?.unwrap_or_default();
+        if let Some(balance) = account.balance {
+            info.balance = balance;
+        }
+        if let Some(nonce) = account.nonce {
+            info.nonce = nonce.to::<u64>();
+        }
+        if let Some(code) = &account.code {
+            let code = Bytecode::new_raw(code.clone());
+            info.code_hash = code.hash_slow();
+            info.code = Some(code);
+        }
+        db.insert_account_info(*address, info);
+
+        if let Some(state) = &account.state {
+            let storage = state
+                .iter()
+                .map(|(slot, value)| (U256::from_be_bytes(slot.0), U256::from_be_bytes(value.0)))
+                .collect();
+            db.replace_account_storage(*address, storage)?;
+        }
+        if let Some(state_diff) = &account.state_diff {
+            for (slot, value) in state_diff {
+                db.insert_account_storage(*address, U256::from_be_bytes(slot.0), U256::from_be_bytes(value.0))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn block_env(block: &Block<Transaction>) -> BlockEnv {
+    BlockEnv {
+        number: U256::from(block.number.to::<u64>()),
+        coinbase: block.miner,
+        timestamp: U256::from(block.timestamp.to::<u64>()),
+        gas_limit: U256::from(block.gas_limit.to::<u64>()),
+        basefee: block.base_fee_per_gas,
+        difficulty: block.difficulty,
+        prevrandao: Some(block.mix_hash),
+        ..Default::default()
+    }
+}
+
+// Nonce checks are only enforced when `validate` is set, like eth_call skipping them
+pub fn tx_env(tx: &TransactionRequest, gas_limit: u64, validate: bool) -> TxEnv {
+    TxEnv {
+        caller: tx.from.unwrap_or_default(),
+        gas_limit: tx.gas.map(|gas| gas as u64).unwrap_or(gas_limit),
+        gas_price: U256::from(tx.gas_price.or(tx.max_fee_per_gas).unwrap_or_default()),
+        gas_priority_fee: tx.max_priority_fee_per_gas.map(U256::from),
+        transact_to: tx.to.unwrap_or(TxKind::Create),
+        value: tx.value.unwrap_or_default(),
+        data: tx.input.input().cloned().unwrap_or_default(),
+        nonce: if validate { tx.nonce } else { None },
+        access_list: tx.access_list.clone().map(|list| list.0).unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+// Environment for replaying a mined transaction exactly as it was included
+pub fn transaction_env(tx: &Transaction) -> TxEnv {
+    tx_env(&tx.clone().into_request(), DEFAULT_GAS_CAP, true)
+}
+
+// Executes one transaction and commits its state changes into the cache
+pub fn transact(
+    db: &mut VerifiedDb<'_>,
+    block: &BlockEnv,
+    tx: TxEnv,
+    chain_id: u64,
+) -> Result<ExecutionResult, String> {
+    let mut evm = Evm::builder()
+        .with_db(db)
+        .with_spec_id(SPEC_ID)
+        .modify_cfg_env(|cfg| cfg.chain_id = chain_id)
+        .modify_block_env(|env| *env = block.clone())
+        .modify_tx_env(|env| *env = tx)
+        .build();
+
+    evm.transact_commit().map_err(|e| format!("EVM error: {}", e))
+}
+
+// Executes one transaction without committing, returning the touched state alongside the result
+pub fn execute(
+    db: &mut VerifiedDb<'_>,
+    block: &BlockEnv,
+    tx: TxEnv,
+    chain_id: u64,
+) -> Result<ResultAndState, String> {
+    let mut evm = Evm::builder()
+        .with_db(db)
+        .with_spec_id(SPEC_ID)
+        .modify_cfg_env(|cfg| cfg.chain_id = chain_id)
+        .modify_block_env(|env| *env = block.clone())
+        .modify_tx_env(|env| *env = tx)
+        .build();
+
+    evm.transact().map_err(|e| format!("EVM error: {}", e))
+}
+
+// Same as `transact`, with an inspector observing every step and call frame
+pub fn inspect<'a, I>(
+    db: &mut VerifiedDb<'a>,
+    block: &BlockEnv,
+    tx: TxEnv,
+    chain_id: u64,
+    inspector: &mut I,
+) -> Result<ExecutionResult, String>
+where
+    I: for<'b> Inspector<&'b mut VerifiedDb<'a>>,
+{
+    let mut evm = Evm::builder()
+        .with_db(db)
+        .with_external_context(inspector)
+        .with_spec_id(SPEC_ID)
+        .modify_cfg_env(|cfg| cfg.chain_id = chain_id)
+        .modify_block_env(|env| *env = block.clone())
+        .modify_tx_env(|env| *env = tx)
+        .append_handler_register(inspector_handle_register)
+        .build();
+
+    evm.transact_commit().map_err(|e| format!("EVM error: {}", e))
+}
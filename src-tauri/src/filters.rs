@@ -0,0 +1,231 @@
+use alloy::primitives::U256;
+use alloy::rpc::types::Filter;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+use crate::accounts::Caller;
+use crate::client::EthClientApi;
+use crate::profile;
+
+// Same as geth, a filter nobody polls for five minutes is removed
+pub const DEFAULT_TIMEOUT_SECS: u64 = 300;
+pub const DEFAULT_MAX_PER_ORIGIN: usize = 100;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// When idle filters expire and how many an origin may hold, so a dapp that never uninstalls its
+// filters can't grow them without bound
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FilterLimits {
+    pub timeout_secs: u64,
+    pub max_per_origin: usize,
+}
+
+impl Default for FilterLimits {
+    fn default() -> Self {
+        Self {
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            max_per_origin: DEFAULT_MAX_PER_ORIGIN,
+        }
+    }
+}
+
+impl FilterLimits {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.timeout_secs == 0 {
+            return Err("Filter timeout must be at least one second".to_string());
+        }
+        if self.max_per_origin == 0 {
+            return Err("Origins must be allowed at least one filter".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub fn filters_path(app: &AppHandle) -> Option<PathBuf> {
+    profile::data_dir(app).map(|dir| dir.join("filters.json"))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum FilterKind {
+    Logs { filter: Filter },
+    Blocks,
+    PendingTransactions,
+}
+
+impl FilterKind {
+    // Installs the filter on `client`, returning the id the client knows it by
+    pub async fn install(&self, client: &dyn EthClientApi) -> eyre::Result<U256> {
+        match self {
+            Self::Logs { filter } => client.new_filter(filter).await,
+            Self::Blocks => client.new_block_filter().await,
+            Self::PendingTransactions => client.new_pending_transaction_filter().await,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledFilter {
+    // Id handed to the dapp, stable across client restarts
+    pub id: U256,
+    pub chain_id: u64,
+    pub owner: Caller,
+    pub kind: FilterKind,
+    // Id on the running client, unset after a restart until the filter is installed again
+    #[serde(skip)]
+    pub client_id: Option<U256>,
+    // Unix seconds of the last poll. Not persisted, a restart gives every filter a fresh timeout
+    #[serde(skip)]
+    pub last_polled: u64,
+}
+
+impl InstalledFilter {
+    pub fn touch(&mut self) {
+        self.last_polled = now();
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FilterFile {
+    next_id: u64,
+    filters: Vec<InstalledFilter>,
+}
+
+// Filters dapps installed, persisted by definition so they can be installed again after a restart
+// under the id the dapp already holds. Each is only visible to the window and origin that created
+// it, on the chain it was created on
+#[derive(Default)]
+pub struct FilterStore {
+    file: FilterFile,
+    loaded: bool,
+}
+
+impl FilterStore {
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    pub async fn load(&mut self, path: &Path) {
+        if let Ok(bytes) = tokio::fs::read(path).await {
+            if let Ok(file) = serde_json::from_slice::<FilterFile>(&bytes) {
+                self.file = file;
+                let loaded_at = now();
+                for filter in &mut self.file.filters {
+                    filter.last_polled = loaded_at;
+                }
+            }
+        }
+        self.loaded = true;
+    }
+
+    pub async fn save(&self, path: &Path) -> Result<(), String> {
+        let bytes = serde_json::to_vec(&self.file).map_err(|e| format!("Failed to serialize filters: {}", e))?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Failed to create data dir: {}", e))?;
+        }
+        tokio::fs::write(path, bytes)
+            .await
+            .map_err(|e| format!("Failed to write filters: {}", e))
+    }
+
+    pub fn add(&mut self, chain_id: u64, owner: Caller, kind: FilterKind, client_id: U256) -> U256 {
+        self.file.next_id += 1;
+        let id = U256::from(self.file.next_id);
+        self.file.filters.push(InstalledFilter {
+            id,
+            chain_id,
+            owner,
+            kind,
+            client_id: Some(client_id),
+            last_polled: now(),
+        });
+        id
+    }
+
+    pub fn count_for_origin(&self, origin: &str) -> usize {
+        self.file.filters.iter().filter(|filter| filter.owner.origin == origin).count()
+    }
+
+    // Another window's filter, or one on another chain, looks the same as one that doesn't exist
+    pub fn get_mut(&mut self, id: U256, chain_id: u64, caller: &Caller) -> Option<&mut InstalledFilter> {
+        self.file.filters
+            .iter_mut()
+            .find(|filter| filter.id == id && filter.chain_id == chain_id && filter.owner == *caller)
+    }
+
+    pub fn remove(&mut self, id: U256) -> Option<InstalledFilter> {
+        let index = self.file.filters.iter().position(|filter| filter.id == id)?;
+        Some(self.file.filters.remove(index))
+    }
+
+    // Removes filters that haven't been polled within the timeout, so they can be uninstalled from
+    // their client
+    pub fn expire(&mut self, timeout_secs: u64) -> Vec<InstalledFilter> {
+        let cutoff = now().saturating_sub(timeout_secs);
+        let (expired, kept) = std::mem::take(&mut self.file.filters)
+            .into_iter()
+            .partition(|filter| filter.last_polled < cutoff);
+        self.file.filters = kept;
+        expired
+    }
+
+    // Removes the filters a webview installed for origins other than the one it now shows, so
+    // they can be uninstalled from their client
+    pub fn release_navigated(&mut self, webview: &str, origin: &str) -> Vec<InstalledFilter> {
+        let (stale, kept) = std::mem::take(&mut self.file.filters)
+            .into_iter()
+            .partition(|filter| filter.owner.webview.as_deref() == Some(webview) && filter.owner.origin != origin);
+        self.file.filters = kept;
+        stale
+    }
+
+    // The client for `chain_id` stopped, its filter ids mean nothing to the next one
+    pub fn detach(&mut self, chain_id: u64) {
+        for filter in self.file.filters.iter_mut().filter(|filter| filter.chain_id == chain_id) {
+            filter.client_id = None;
+        }
+    }
+
+    // Installs every filter of `chain_id` that isn't on `client` yet
+    pub async fn reinstall(&mut self, chain_id: u64, client: &dyn EthClientApi) {
+        let detached = self.file.filters
+            .iter_mut()
+            .filter(|filter| filter.chain_id == chain_id && filter.client_id.is_none());
+        for filter in detached {
+            match filter.kind.install(client).await {
+                Ok(client_id) => {
+                    filter.client_id = Some(client_id);
+                    filter.last_polled = now();
+                },
+                Err(e) => tracing::warn!("Failed to reinstall filter 0x{:x}: {}", filter.id, e),
+            }
+        }
+    }
+}
+
+pub async fn load_filters(app: &AppHandle, store: &mut FilterStore) {
+    if let (false, Some(path)) = (store.is_loaded(), filters_path(app)) {
+        store.load(&path).await;
+    }
+}
+
+pub async fn save_filters(app: &AppHandle, store: &FilterStore) {
+    if let Some(path) = filters_path(app) {
+        if let Err(e) = store.save(&path).await {
+            tracing::warn!("{}", e);
+        }
+    }
+}
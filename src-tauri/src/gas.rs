@@ -0,0 +1,354 @@
+use alloy::primitives::U256;
+use alloy::rpc::types::{Transaction, TransactionRequest};
+use helios::core::types::{Block, BlockTag, Transactions};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Mutex;
+
+use crate::client::EthClientApi;
+use crate::window::VERIFICATION_WINDOW;
+use crate::AppState;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(12);
+const SECONDS_PER_BLOCK: u64 = 12;
+
+// Number of recent blocks the tiers are computed from
+const HISTORY_BLOCKS: usize = 20;
+// Blocks kept for eth_maxPriorityFeePerGas, bounded by how far back the light client serves payloads
+const MAX_HISTORY_BLOCKS: usize = VERIFICATION_WINDOW as usize;
+
+// Tip percentiles per tier, and the percentile treated as a block's inclusion cutoff
+const SLOW_PERCENTILE: usize = 25;
+const AVERAGE_PERCENTILE: usize = 50;
+const FAST_PERCENTILE: usize = 75;
+const CUTOFF_PERCENTILE: usize = 10;
+
+// EIP-1559 bounds the base fee change per block to 1/8
+const BASE_FEE_CHANGE_DENOMINATOR: u128 = 8;
+const ELASTICITY_MULTIPLIER: u128 = 2;
+
+const DEFAULT_GAS_BUFFER_PERCENT: u64 = 20;
+pub const MAX_GAS_BUFFER_PERCENT: u64 = 200;
+// Refinement stops once the bracket is within this many thousandths of the limit, like geth's estimator
+const REFINE_TOLERANCE_PERMILLE: u64 = 15;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasTier {
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub estimated_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasQuotes {
+    pub block_number: u64,
+    pub base_fee_per_gas: U256,
+    // Base fee the next block will have, derived from the latest block's gas usage
+    pub next_base_fee_per_gas: U256,
+    pub slow: GasTier,
+    pub average: GasTier,
+    pub fast: GasTier,
+}
+
+// Which quote tier filled-in transactions pay
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FeeSpeed {
+    Slow,
+    #[default]
+    Average,
+    Fast,
+}
+
+impl GasQuotes {
+    pub fn tier(&self, speed: FeeSpeed) -> &GasTier {
+        match speed {
+            FeeSpeed::Slow => &self.slow,
+            FeeSpeed::Average => &self.average,
+            FeeSpeed::Fast => &self.fast,
+        }
+    }
+}
+
+// Sorted effective tips paid in one block
+struct BlockFees {
+    number: u64,
+    tips: Vec<u128>,
+}
+
+impl BlockFees {
+    fn from_block(block: &Block<Transaction>, transactions: &[Transaction]) -> Self {
+        let base_fee = block.base_fee_per_gas.saturating_to::<u128>();
+        let mut tips: Vec<u128> = transactions
+            .iter()
+            .map(|tx| match (tx.max_fee_per_gas, tx.max_priority_fee_per_gas) {
+                (Some(max_fee), Some(priority_fee)) => priority_fee.min(max_fee.saturating_sub(base_fee)),
+                _ => tx.gas_price.unwrap_or_default().saturating_sub(base_fee),
+            })
+            .collect();
+        tips.sort_unstable();
+        Self {
+            number: block.number.to::<u64>(),
+            tips,
+        }
+    }
+
+    fn percentile(&self, percentile: usize) -> Option<u128> {
+        let index = (self.tips.len() * percentile / 100).min(self.tips.len().checked_sub(1)?);
+        self.tips.get(index).copied()
+    }
+}
+
+// Recent block fee data, updated incrementally so each refresh only fetches new blocks
+#[derive(Default)]
+pub struct GasOracle {
+    blocks: VecDeque<BlockFees>,
+    quotes: Option<GasQuotes>,
+}
+
+fn next_base_fee(block: &Block<Transaction>) -> u128 {
+    let base_fee = block.base_fee_per_gas.saturating_to::<u128>();
+    let gas_used = block.gas_used.to::<u64>() as u128;
+    let target = block.gas_limit.to::<u64>() as u128 / ELASTICITY_MULTIPLIER;
+    if target == 0 || gas_used == target {
+        return base_fee;
+    }
+    if gas_used > target {
+        let delta = (base_fee * (gas_used - target) / target / BASE_FEE_CHANGE_DENOMINATOR).max(1);
+        base_fee + delta
+    } else {
+        base_fee - base_fee * (target - gas_used) / target / BASE_FEE_CHANGE_DENOMINATOR
+    }
+}
+
+impl GasOracle {
+    // The last `count` blocks, oldest first
+    fn recent(&self, count: usize) -> impl Iterator<Item = &BlockFees> {
+        self.blocks.iter().skip(self.blocks.len().saturating_sub(count))
+    }
+
+    // Mean of one percentile across the last `count` blocks that carried transactions
+    fn mean_tip(&self, percentile: usize, count: usize) -> Option<u128> {
+        let tips: Vec<u128> = self.recent(count).filter_map(|block| block.percentile(percentile)).collect();
+        if tips.is_empty() {
+            return None;
+        }
+        Some(tips.iter().sum::<u128>() / tips.len() as u128)
+    }
+
+    fn tip(&self, percentile: usize) -> u128 {
+        self.mean_tip(percentile, HISTORY_BLOCKS).unwrap_or_default()
+    }
+
+    // Tip paid at the configured percentile of recent verified blocks, None until a block with
+    // transactions has been seen
+    pub fn priority_fee(&self, settings: &PriorityFeeSettings) -> Option<U256> {
+        self.mean_tip(settings.percentile, settings.blocks).map(U256::from)
+    }
+
+    // Expected wait, treating each block as an independent chance to clear its inclusion cutoff
+    fn estimated_seconds(&self, tip: u128) -> u64 {
+        let cutoffs: Vec<u128> = self.recent(HISTORY_BLOCKS).filter_map(|block| block.percentile(CUTOFF_PERCENTILE)).collect();
+        let included = cutoffs.iter().filter(|cutoff| tip >= **cutoff).count() as u64;
+        if included == 0 {
+            return SECONDS_PER_BLOCK * cutoffs.len().max(1) as u64;
+        }
+        SECONDS_PER_BLOCK * cutoffs.len() as u64 / included
+    }
+
+    fn tier(&self, percentile: usize, next_base_fee: u128) -> GasTier {
+        let tip = self.tip(percentile);
+        GasTier {
+            max_priority_fee_per_gas: U256::from(tip),
+            // Headroom for two consecutive full blocks raising the base fee
+            max_fee_per_gas: U256::from(next_base_fee * 2 + tip),
+            estimated_seconds: self.estimated_seconds(tip),
+        }
+    }
+
+    pub async fn update(&mut self, client: &dyn EthClientApi) -> Result<GasQuotes, String> {
+        let latest = client.get_block_by_number(BlockTag::Latest, false)
+            .await
+            .map_err(|e| format!("Failed to get latest block: {}", e))?
+            .ok_or("Latest block is not available")?;
+        let head = latest.number.to::<u64>();
+        if let Some(quotes) = self.quotes.as_ref().filter(|quotes| quotes.block_number == head) {
+            return Ok(quotes.clone());
+        }
+
+        let first = head.saturating_sub(MAX_HISTORY_BLOCKS as u64 - 1);
+        let known = self.blocks.back().map(|block| block.number + 1).unwrap_or(first).max(first);
+        for number in known..=head {
+            let block = match client.get_block_by_number(BlockTag::Number(number), true).await {
+                Ok(Some(block)) => block,
+                // The light client may not have older payloads, the window just gets shorter
+                _ => continue,
+            };
+            if let Transactions::Full(transactions) = &block.transactions {
+                self.blocks.push_back(BlockFees::from_block(&block, transactions));
+            }
+        }
+        while self.blocks.front().is_some_and(|block| block.number < first) {
+            self.blocks.pop_front();
+        }
+
+        let next_base_fee = next_base_fee(&latest);
+        let quotes = GasQuotes {
+            block_number: head,
+            base_fee_per_gas: latest.base_fee_per_gas,
+            next_base_fee_per_gas: U256::from(next_base_fee),
+            slow: self.tier(SLOW_PERCENTILE, next_base_fee),
+            average: self.tier(AVERAGE_PERCENTILE, next_base_fee),
+            fast: self.tier(FAST_PERCENTILE, next_base_fee),
+        };
+        self.quotes = Some(quotes.clone());
+        Ok(quotes)
+    }
+}
+
+// How eth_maxPriorityFeePerGas is derived from the tips in recent verified blocks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PriorityFeeSettings {
+    pub percentile: usize,
+    pub blocks: usize,
+}
+
+impl Default for PriorityFeeSettings {
+    fn default() -> Self {
+        Self {
+            percentile: AVERAGE_PERCENTILE,
+            blocks: HISTORY_BLOCKS,
+        }
+    }
+}
+
+impl PriorityFeeSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.percentile > 100 {
+            return Err("Priority fee percentile must be between 0 and 100".to_string());
+        }
+        if self.blocks == 0 || self.blocks > MAX_HISTORY_BLOCKS {
+            return Err(format!("Priority fee history must be between 1 and {} blocks", MAX_HISTORY_BLOCKS));
+        }
+        Ok(())
+    }
+}
+
+// How gas limits are estimated, for transactions the wallet fills in and for eth_estimateGas
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GasEstimation {
+    // Added on top of the estimate, in percent
+    pub buffer_percent: u64,
+    // Search for the lowest limit eth_call succeeds with when the estimate itself fails, for
+    // contracts whose gas use depends on the limit they're given
+    pub refine: bool,
+}
+
+impl Default for GasEstimation {
+    fn default() -> Self {
+        Self {
+            buffer_percent: DEFAULT_GAS_BUFFER_PERCENT,
+            refine: false,
+        }
+    }
+}
+
+// The default estimation plus per-origin overrides for dapps that need more headroom
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EstimationPolicy {
+    #[serde(flatten)]
+    pub default: GasEstimation,
+    pub origins: HashMap<String, GasEstimation>,
+}
+
+impl EstimationPolicy {
+    pub fn for_origin(&self, origin: &str) -> &GasEstimation {
+        self.origins.get(origin).unwrap_or(&self.default)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let too_high = std::iter::once(&self.default)
+            .chain(self.origins.values())
+            .any(|estimation| estimation.buffer_percent > MAX_GAS_BUFFER_PERCENT);
+        if too_high {
+            return Err(format!("Gas buffer can't exceed {}%", MAX_GAS_BUFFER_PERCENT));
+        }
+        Ok(())
+    }
+}
+
+async fn call_succeeds(client: &dyn EthClientApi, tx: &TransactionRequest, gas: u64) -> Result<(), String> {
+    let mut tx = tx.clone();
+    tx.gas = Some(gas as u128);
+    client.call(&tx, BlockTag::Latest).await.map(|_| ()).map_err(|e| e.to_string())
+}
+
+// Binary search between an estimate that fails and the block gas limit
+async fn refine(client: &dyn EthClientApi, tx: &TransactionRequest, estimate: u64, cap: u64) -> Result<u64, String> {
+    if estimate >= cap || call_succeeds(client, tx, estimate).await.is_ok() {
+        return Ok(estimate);
+    }
+    call_succeeds(client, tx, cap).await.map_err(|e| format!("Transaction fails even at the block gas limit: {}", e))?;
+
+    let (mut low, mut high) = (estimate, cap);
+    while high - low > 1 && high - low > low * REFINE_TOLERANCE_PERMILLE / 1000 {
+        let middle = low + (high - low) / 2;
+        match call_succeeds(client, tx, middle).await {
+            Ok(()) => high = middle,
+            Err(_) => low = middle,
+        }
+    }
+    Ok(high)
+}
+
+// The client's estimate, refined if configured, plus the buffer, capped at the block gas limit
+pub async fn estimate(client: &dyn EthClientApi, tx: &TransactionRequest, estimation: &GasEstimation) -> Result<u64, String> {
+    let cap = client.get_block_by_number(BlockTag::Latest, false)
+        .await
+        .map_err(|e| format!("Failed to get latest block: {}", e))?
+        .ok_or("Latest block is not available")?
+        .gas_limit
+        .to::<u64>();
+    let mut gas = client.estimate_gas(tx).await.map_err(|e| e.to_string())?;
+    if estimation.refine {
+        gas = refine(client, tx, gas, cap).await?;
+    }
+    let buffered = gas.saturating_mul(100 + estimation.buffer_percent) / 100;
+    Ok(buffered.min(cap).max(gas))
+}
+
+// Recomputes the quotes as new verified blocks arrive and emits `gas-quotes` when they change
+pub fn spawn_gas_oracle(app: AppHandle) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+        let mut last_block: Option<u64> = None;
+
+        loop {
+            interval.tick().await;
+
+            let state = app.state::<Mutex<AppState>>();
+            let mut state_guard = state.lock().await;
+            let AppState { client, gas_oracle, .. } = &mut *state_guard;
+            let Some(client) = client.as_ref() else {
+                break;
+            };
+
+            match gas_oracle.update(client).await {
+                Ok(quotes) if last_block != Some(quotes.block_number) => {
+                    last_block = Some(quotes.block_number);
+                    let _ = app.emit("gas-quotes", quotes);
+                },
+                Ok(_) => {},
+                Err(e) => tracing::warn!("Gas oracle: {}", e),
+            }
+        }
+    })
+}
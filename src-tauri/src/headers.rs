@@ -0,0 +1,238 @@
+use alloy::consensus::TxEnvelope;
+use alloy::eips::eip2718::Encodable2718;
+use alloy::primitives::B256;
+use alloy::rpc::types::Transaction;
+use alloy_trie::{HashBuilder, Nibbles};
+use helios::core::types::Block;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::VecDeque;
+
+use crate::passthrough;
+
+// Beacon chain genesis for mainnet, used to map execution timestamps back to slots
+const MAINNET_GENESIS_TIME: u64 = 1606824023;
+const SECONDS_PER_SLOT: u64 = 12;
+// Verified headers kept for historical queries, a little over a day of mainnet blocks
+pub const HEADER_STORE_CAPACITY: usize = 8_192;
+
+// Execution header fields the light client has verified against the beacon chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedHeader {
+    pub slot: u64,
+    pub block_number: u64,
+    pub block_hash: B256,
+    pub parent_hash: B256,
+    pub state_root: B256,
+    pub receipts_root: B256,
+    pub timestamp: u64,
+}
+
+impl VerifiedHeader {
+    pub fn from_block(block: &Block<Transaction>) -> Self {
+        let timestamp = block.timestamp.to::<u64>();
+        Self {
+            slot: slot_at(timestamp),
+            block_number: block.number.to::<u64>(),
+            block_hash: block.hash,
+            parent_hash: block.parent_hash,
+            state_root: block.state_root,
+            receipts_root: block.receipts_root,
+            timestamp,
+        }
+    }
+}
+
+pub fn slot_at(timestamp: u64) -> u64 {
+    timestamp.saturating_sub(MAINNET_GENESIS_TIME) / SECONDS_PER_SLOT
+}
+
+// Verified headers of the recent canonical chain, oldest first and without gaps, so blocks can
+// still be checked after they leave the light client's own window. Headers from an imported
+// snapshot are held apart until they link up with a header the light client verified itself
+#[derive(Default)]
+pub struct HeaderStore {
+    headers: VecDeque<VerifiedHeader>,
+    staged: Vec<VerifiedHeader>,
+}
+
+impl HeaderStore {
+    pub fn latest(&self) -> Option<&VerifiedHeader> {
+        self.headers.back()
+    }
+
+    pub fn by_number(&self, number: u64) -> Option<&VerifiedHeader> {
+        let oldest = self.headers.front()?.block_number;
+        self.headers.get(number.checked_sub(oldest)? as usize)
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = &VerifiedHeader> {
+        self.headers.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    pub fn by_hash(&self, hash: B256) -> Option<&VerifiedHeader> {
+        self.headers.iter().rev().find(|header| header.block_hash == hash)
+    }
+
+    // Whether the header's parent is stored, or the store is empty and it can start the chain
+    pub fn extends(&self, header: &VerifiedHeader) -> bool {
+        match header.block_number.checked_sub(1).and_then(|parent| self.by_number(parent)) {
+            Some(parent) => parent.block_hash == header.parent_hash,
+            None => self.headers.is_empty(),
+        }
+    }
+
+    // Adds the next header, replacing any stored headers at or after its height, which it returns
+    // as reorged out. A header that doesn't extend the store starts it over
+    pub fn insert(&mut self, header: VerifiedHeader) -> Vec<VerifiedHeader> {
+        let mut replaced = Vec::new();
+        while self.headers.back().is_some_and(|last| last.block_number >= header.block_number) {
+            replaced.extend(self.headers.pop_back().filter(|last| last.block_hash != header.block_hash));
+        }
+        if !self.extends(&header) {
+            self.headers.clear();
+        }
+        self.headers.push_back(header);
+        while self.headers.len() > HEADER_STORE_CAPACITY {
+            self.headers.pop_front();
+        }
+        replaced.reverse();
+        replaced
+    }
+
+    pub fn clear(&mut self) {
+        self.headers.clear();
+        self.staged.clear();
+    }
+
+    // Holds unverified headers, oldest first, until `adopt_staged` can anchor them. Only the
+    // unbroken run of parent links ending at the newest header is kept
+    pub fn stage(&mut self, mut headers: Vec<VerifiedHeader>) -> usize {
+        headers.sort_by_key(|header| header.block_number);
+        let mut linked = headers.len().min(1);
+        for pair in headers.windows(2).rev() {
+            if pair[1].block_number != pair[0].block_number + 1 || pair[1].parent_hash != pair[0].block_hash {
+                break;
+            }
+            linked += 1;
+        }
+        self.staged = headers.split_off(headers.len() - linked);
+        self.staged.len()
+    }
+
+    // Moves staged headers into the store once the oldest stored header's parent is among them.
+    // Parent hashes then vouch for every staged header back from there. Staged headers that can no
+    // longer reach the store, because it has moved past them, are dropped. Returns how many were
+    // adopted
+    pub fn adopt_staged(&mut self) -> usize {
+        let Some(oldest) = self.headers.front() else {
+            return 0;
+        };
+        let Some(newest_staged) = self.staged.last() else {
+            return 0;
+        };
+        if newest_staged.block_number + 1 < oldest.block_number {
+            self.staged.clear();
+            return 0;
+        }
+        let Some(position) = self.staged.iter().position(|header| {
+            header.block_number + 1 == oldest.block_number && header.block_hash == oldest.parent_hash
+        }) else {
+            return 0;
+        };
+
+        let room = HEADER_STORE_CAPACITY.saturating_sub(self.headers.len());
+        let mut staged = std::mem::take(&mut self.staged);
+        staged.truncate(position + 1);
+        let adopted = staged.len().min(room);
+        for header in staged.into_iter().rev().take(adopted) {
+            self.headers.push_front(header);
+        }
+        adopted
+    }
+}
+
+// Hash of an execution RPC header recomputed from its fields, None if it's missing the number
+fn header_hash(header: &alloy::rpc::types::Header) -> Option<B256> {
+    let header = alloy::consensus::Header {
+        parent_hash: header.parent_hash,
+        ommers_hash: header.uncles_hash,
+        beneficiary: header.miner,
+        state_root: header.state_root,
+        transactions_root: header.transactions_root,
+        receipts_root: header.receipts_root,
+        withdrawals_root: header.withdrawals_root,
+        logs_bloom: header.logs_bloom,
+        difficulty: header.difficulty,
+        number: header.number?,
+        gas_limit: header.gas_limit,
+        gas_used: header.gas_used,
+        timestamp: header.timestamp,
+        mix_hash: header.mix_hash.unwrap_or_default(),
+        nonce: header.nonce.unwrap_or_default(),
+        base_fee_per_gas: header.base_fee_per_gas,
+        blob_gas_used: header.blob_gas_used,
+        excess_blob_gas: header.excess_blob_gas,
+        parent_beacon_block_root: header.parent_beacon_block_root,
+        requests_root: header.requests_root,
+        extra_data: header.extra_data.clone(),
+    };
+    Some(header.hash_slow())
+}
+
+// Root of the trie keyed by each transaction's RLP-encoded index, as in the block header. The hash
+// and sender the RPC gave each transaction are checked against its signed payload too
+fn transactions_root(transactions: &[Transaction]) -> Result<B256, String> {
+    let mut leaves = Vec::with_capacity(transactions.len());
+    for (index, tx) in transactions.iter().enumerate() {
+        let envelope = TxEnvelope::try_from(tx.clone())
+            .map_err(|e| format!("Invalid transaction 0x{:x} from execution RPC: {}", tx.hash, e))?;
+        if *envelope.tx_hash() != tx.hash {
+            return Err(format!("Transaction 0x{:x} from execution RPC has the wrong hash", tx.hash));
+        }
+        if envelope.recover_signer().ok() != Some(tx.from) {
+            return Err(format!("Transaction 0x{:x} from execution RPC has the wrong sender", tx.hash));
+        }
+        leaves.push((alloy::rlp::encode(index), envelope.encoded_2718()));
+    }
+    leaves.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut builder = HashBuilder::default();
+    for (key, value) in &leaves {
+        builder.add_leaf(Nibbles::unpack(key), value);
+    }
+    Ok(builder.root())
+}
+
+// Fetches a stored block from the execution RPC. The header is rehashed and has to match the
+// verified hash, and the transactions have to rebuild its transactions root, so the whole block is
+// as good as verified. Transactions are always fetched in full since hashes alone can't be checked
+pub async fn fetch_block(rpc_url: &str, header: &VerifiedHeader, full: bool) -> Result<serde_json::Value, String> {
+    let params = [json!(format!("0x{:x}", header.block_number)), json!(true)];
+    let upstream = passthrough::forward(rpc_url, "eth_getBlockByNumber", &params).await?;
+    let mut result = upstream.get("result")
+        .filter(|result| !result.is_null())
+        .cloned()
+        .ok_or_else(|| format!("Execution RPC has no block {}", header.block_number))?;
+    let block: alloy::rpc::types::Block = serde_json::from_value(result.clone())
+        .map_err(|e| format!("Invalid block from execution RPC: {}", e))?;
+    if header_hash(&block.header) != Some(header.block_hash) {
+        return Err(format!("Block {} from execution RPC doesn't match the verified header", header.block_number));
+    }
+    let transactions = block.transactions
+        .as_transactions()
+        .ok_or_else(|| format!("Execution RPC left out the transactions of block {}", header.block_number))?;
+    if transactions_root(transactions)? != block.header.transactions_root {
+        return Err(format!("Transactions of block {} don't match the verified transactions root", header.block_number));
+    }
+
+    if !full {
+        result["transactions"] = json!(transactions.iter().map(|tx| tx.hash).collect::<Vec<_>>());
+    }
+    Ok(result)
+}
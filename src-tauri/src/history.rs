@@ -0,0 +1,538 @@
+use alloy::hex;
+use alloy::primitives::{Address, B256};
+use alloy::rpc::types::{Filter, Log, Transaction};
+use helios::core::types::{BlockTag, Transactions};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Mutex;
+
+use crate::abi::{self, AbiRegistry};
+use crate::client::EthClientApi;
+use crate::profile;
+use crate::AppState;
+
+const INDEX_INTERVAL: Duration = Duration::from_secs(12);
+
+// The light client only serves recent blocks, so a long pause resumes from near the head
+const MAX_CATCH_UP: u64 = 64;
+
+// How far back to re-index when a new block doesn't build on the last indexed one
+const REORG_DEPTH: u64 = 8;
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 500;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS tracked (
+        address TEXT PRIMARY KEY
+    );
+    CREATE TABLE IF NOT EXISTS blocks (
+        number INTEGER PRIMARY KEY,
+        hash TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS transactions (
+        hash TEXT PRIMARY KEY,
+        block_number INTEGER NOT NULL,
+        transaction_index INTEGER NOT NULL,
+        timestamp INTEGER NOT NULL,
+        from_address TEXT NOT NULL,
+        to_address TEXT,
+        value TEXT NOT NULL,
+        selector TEXT
+    );
+    CREATE TABLE IF NOT EXISTS logs (
+        transaction_hash TEXT NOT NULL,
+        log_index INTEGER NOT NULL,
+        block_number INTEGER NOT NULL,
+        address TEXT NOT NULL,
+        topics TEXT NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (transaction_hash, log_index)
+    );
+    CREATE TABLE IF NOT EXISTS involvement (
+        address TEXT NOT NULL,
+        transaction_hash TEXT NOT NULL,
+        block_number INTEGER NOT NULL,
+        transaction_index INTEGER NOT NULL,
+        PRIMARY KEY (address, transaction_hash)
+    );
+    CREATE INDEX IF NOT EXISTS involvement_by_block ON involvement (address, block_number DESC, transaction_index DESC);
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    In,
+    Out,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryFilter {
+    pub direction: Option<Direction>,
+    // Only transactions that emitted a log from this contract
+    pub contract: Option<Address>,
+    pub from_block: Option<u64>,
+    pub to_block: Option<u64>,
+    pub offset: Option<u64>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryLog {
+    pub address: String,
+    pub log_index: u64,
+    pub topics: Vec<String>,
+    pub data: String,
+    // The event, when the contract has an ABI in the registry
+    pub decoded: Option<abi::DecodedEvent>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub hash: String,
+    pub block_number: u64,
+    pub transaction_index: u64,
+    pub timestamp: u64,
+    pub from: String,
+    pub to: Option<String>,
+    pub value: String,
+    pub selector: Option<String>,
+    // Logs from this transaction that name the queried address in a topic
+    pub logs: Vec<HistoryLog>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    pub next_offset: Option<u64>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryUpdate {
+    pub block_number: u64,
+    pub addresses: Vec<String>,
+}
+
+// Everything in one block that touches a tracked address
+struct BlockActivity {
+    number: u64,
+    hash: B256,
+    timestamp: u64,
+    transactions: Vec<Transaction>,
+    logs: Vec<Log>,
+}
+
+fn hex_address(address: Address) -> String {
+    format!("0x{:x}", address)
+}
+
+fn hex_hash(hash: B256) -> String {
+    format!("0x{:x}", hash)
+}
+
+pub fn history_dir(app: &AppHandle) -> Option<PathBuf> {
+    profile::data_dir(app).map(|dir| dir.join("history"))
+}
+
+pub fn history_path(app: &AppHandle, chain_id: u64) -> Option<PathBuf> {
+    history_dir(app).map(|dir| dir.join(format!("{}.sqlite", chain_id)))
+}
+
+pub struct HistoryDb {
+    conn: Connection,
+}
+
+impl HistoryDb {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create history dir: {}", e))?;
+        }
+        let conn = Connection::open(path).map_err(|e| format!("Failed to open history database: {}", e))?;
+        conn.execute_batch(SCHEMA).map_err(|e| format!("Failed to create history database: {}", e))?;
+        Ok(Self { conn })
+    }
+
+    pub fn tracked(&self) -> Result<HashSet<Address>, String> {
+        let mut statement = self.conn
+            .prepare("SELECT address FROM tracked")
+            .map_err(|e| format!("Failed to query history database: {}", e))?;
+        let rows = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to query history database: {}", e))?;
+        let mut addresses = HashSet::new();
+        for row in rows {
+            let row = row.map_err(|e| format!("Failed to read history database: {}", e))?;
+            if let Ok(address) = row.parse() {
+                addresses.insert(address);
+            }
+        }
+        Ok(addresses)
+    }
+
+    pub fn track(&self, address: Address) -> Result<(), String> {
+        self.conn
+            .execute("INSERT OR IGNORE INTO tracked (address) VALUES (?1)", params![hex_address(address)])
+            .map(|_| ())
+            .map_err(|e| format!("Failed to write history database: {}", e))
+    }
+
+    pub fn untrack(&self, address: Address) -> Result<(), String> {
+        self.conn
+            .execute("DELETE FROM tracked WHERE address = ?1", params![hex_address(address)])
+            .map(|_| ())
+            .map_err(|e| format!("Failed to write history database: {}", e))
+    }
+
+    fn last_indexed(&self) -> Result<Option<(u64, String)>, String> {
+        self.conn
+            .query_row("SELECT number, hash FROM blocks ORDER BY number DESC LIMIT 1", [], |row| {
+                Ok((row.get::<_, i64>(0)? as u64, row.get(1)?))
+            })
+            .optional()
+            .map_err(|e| format!("Failed to query history database: {}", e))
+    }
+
+    // Drops everything from `number` on, used when the chain reorganizes under the index
+    fn rewind(&mut self, number: u64) -> Result<(), String> {
+        let number = number as i64;
+        let tx = self.conn.transaction().map_err(|e| format!("Failed to rewind history database: {}", e))?;
+        tx.execute("DELETE FROM blocks WHERE number >= ?1", params![number])
+            .and_then(|_| tx.execute("DELETE FROM transactions WHERE block_number >= ?1", params![number]))
+            .and_then(|_| tx.execute("DELETE FROM logs WHERE block_number >= ?1", params![number]))
+            .and_then(|_| tx.execute("DELETE FROM involvement WHERE block_number >= ?1", params![number]))
+            .and_then(|_| tx.commit())
+            .map_err(|e| format!("Failed to rewind history database: {}", e))
+    }
+
+    // Drops transactions older than `before`, a unix timestamp, with their logs. Indexed block
+    // hashes are only needed near the tip to spot reorgs, so older ones go too
+    pub fn prune(&mut self, before: u64) -> Result<usize, String> {
+        let before = before as i64;
+        let tx = self.conn.transaction().map_err(|e| format!("Failed to prune history database: {}", e))?;
+        let pruned = tx
+            .execute("DELETE FROM involvement WHERE transaction_hash IN (SELECT hash FROM transactions WHERE timestamp < ?1)", params![before])
+            .and_then(|_| tx.execute("DELETE FROM logs WHERE transaction_hash IN (SELECT hash FROM transactions WHERE timestamp < ?1)", params![before]))
+            .and_then(|_| tx.execute("DELETE FROM transactions WHERE timestamp < ?1", params![before]))
+            .and_then(|pruned| {
+                tx.execute("DELETE FROM blocks WHERE number < (SELECT MAX(number) FROM blocks) - ?1", params![REORG_DEPTH as i64])?;
+                tx.commit()?;
+                Ok(pruned)
+            })
+            .map_err(|e| format!("Failed to prune history database: {}", e))?;
+        if pruned > 0 {
+            self.conn.execute_batch("VACUUM").map_err(|e| format!("Failed to compact history database: {}", e))?;
+        }
+        Ok(pruned)
+    }
+
+    // Writes a block's activity atomically and returns the tracked addresses it touched
+    fn record(&mut self, activity: &BlockActivity, tracked: &HashSet<Address>) -> Result<Vec<String>, String> {
+        let tx = self.conn.transaction().map_err(|e| format!("Failed to write history database: {}", e))?;
+        let number = activity.number as i64;
+        let mut touched = HashSet::new();
+
+        let mut involve = |tx: &rusqlite::Transaction, address: Address, hash: B256, index: u64| -> rusqlite::Result<()> {
+            touched.insert(hex_address(address));
+            tx.execute(
+                "INSERT OR IGNORE INTO involvement (address, transaction_hash, block_number, transaction_index) VALUES (?1, ?2, ?3, ?4)",
+                params![hex_address(address), hex_hash(hash), number, index as i64],
+            )
+            .map(|_| ())
+        };
+
+        let result = (|| -> rusqlite::Result<()> {
+            tx.execute(
+                "INSERT OR REPLACE INTO blocks (number, hash) VALUES (?1, ?2)",
+                params![number, hex_hash(activity.hash)],
+            )?;
+
+            let mut indices = HashMap::new();
+            for transaction in &activity.transactions {
+                let index = transaction.transaction_index.unwrap_or_default();
+                indices.insert(transaction.hash, index);
+                tx.execute(
+                    "INSERT OR REPLACE INTO transactions (hash, block_number, transaction_index, timestamp, from_address, to_address, value, selector)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        hex_hash(transaction.hash),
+                        number,
+                        index as i64,
+                        activity.timestamp as i64,
+                        hex_address(transaction.from),
+                        transaction.to.map(hex_address),
+                        transaction.value.to_string(),
+                        transaction.input.get(..4).map(|selector| format!("0x{}", hex::encode(selector))),
+                    ],
+                )?;
+                for address in [Some(transaction.from), transaction.to].into_iter().flatten() {
+                    if tracked.contains(&address) {
+                        involve(&tx, address, transaction.hash, index)?;
+                    }
+                }
+            }
+
+            for log in &activity.logs {
+                let (Some(hash), Some(log_index)) = (log.transaction_hash, log.log_index) else {
+                    continue;
+                };
+                let topics: Vec<String> = log.topics().iter().map(|topic| hex_hash(*topic)).collect();
+                tx.execute(
+                    "INSERT OR REPLACE INTO logs (transaction_hash, log_index, block_number, address, topics, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        hex_hash(hash),
+                        log_index as i64,
+                        number,
+                        hex_address(log.address()),
+                        serde_json::to_string(&topics).unwrap_or_default(),
+                        format!("0x{}", hex::encode(&log.data().data)),
+                    ],
+                )?;
+                let index = indices.get(&hash).copied().or(log.transaction_index).unwrap_or_default();
+                for address in tracked.iter().filter(|address| log.topics().iter().skip(1).any(|topic| *topic == address.into_word())) {
+                    involve(&tx, *address, hash, index)?;
+                }
+            }
+            Ok(())
+        })();
+
+        result
+            .and_then(|_| tx.commit())
+            .map_err(|e| format!("Failed to write history database: {}", e))?;
+        Ok(touched.into_iter().collect())
+    }
+
+    pub fn query(&self, address: Address, filter: &HistoryFilter) -> Result<HistoryPage, String> {
+        let limit = filter.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let offset = filter.offset.unwrap_or(0);
+        let address = hex_address(address);
+        let direction = filter.direction.map(|direction| match direction {
+            Direction::In => "in",
+            Direction::Out => "out",
+        });
+
+        let mut statement = self.conn
+            .prepare(
+                "SELECT t.hash, t.block_number, t.transaction_index, t.timestamp, t.from_address, t.to_address, t.value, t.selector
+                 FROM involvement i JOIN transactions t ON t.hash = i.transaction_hash
+                 WHERE i.address = ?1
+                   AND (?2 IS NULL OR i.block_number >= ?2)
+                   AND (?3 IS NULL OR i.block_number <= ?3)
+                   AND (?4 IS NULL OR (?4 = 'out') = (t.from_address = ?1))
+                   AND (?5 IS NULL OR EXISTS (SELECT 1 FROM logs l WHERE l.transaction_hash = t.hash AND l.address = ?5))
+                 ORDER BY i.block_number DESC, i.transaction_index DESC
+                 LIMIT ?6 OFFSET ?7",
+            )
+            .map_err(|e| format!("Failed to query history database: {}", e))?;
+        let rows = statement
+            .query_map(
+                params![
+                    address,
+                    filter.from_block.map(|n| n as i64),
+                    filter.to_block.map(|n| n as i64),
+                    direction,
+                    filter.contract.map(hex_address),
+                    (limit + 1) as i64,
+                    offset as i64,
+                ],
+                |row| {
+                    Ok(HistoryEntry {
+                        hash: row.get(0)?,
+                        block_number: row.get::<_, i64>(1)? as u64,
+                        transaction_index: row.get::<_, i64>(2)? as u64,
+                        timestamp: row.get::<_, i64>(3)? as u64,
+                        from: row.get(4)?,
+                        to: row.get(5)?,
+                        value: row.get(6)?,
+                        selector: row.get(7)?,
+                        logs: Vec::new(),
+                    })
+                },
+            )
+            .map_err(|e| format!("Failed to query history database: {}", e))?;
+        let mut entries = rows
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read history database: {}", e))?;
+
+        // One extra row was requested to know whether another page exists
+        let next_offset = (entries.len() as u64 > limit).then(|| offset + limit);
+        entries.truncate(limit as usize);
+
+        let topic = format!("0x{:0>64}", address.trim_start_matches("0x"));
+        let mut statement = self.conn
+            .prepare("SELECT address, log_index, topics, data FROM logs WHERE transaction_hash = ?1 AND topics LIKE ?2 ORDER BY log_index")
+            .map_err(|e| format!("Failed to query history database: {}", e))?;
+        for entry in &mut entries {
+            let logs = statement
+                .query_map(params![entry.hash, format!("%{}%", topic)], |row| {
+                    Ok(HistoryLog {
+                        address: row.get(0)?,
+                        log_index: row.get::<_, i64>(1)? as u64,
+                        topics: serde_json::from_str(&row.get::<_, String>(2)?).unwrap_or_default(),
+                        data: row.get(3)?,
+                        decoded: None,
+                    })
+                })
+                .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+                .map_err(|e| format!("Failed to read history database: {}", e))?;
+            entry.logs = logs;
+        }
+
+        Ok(HistoryPage { entries, next_offset })
+    }
+}
+
+// Decodes the logs of contracts with a registered ABI. Only the registry is consulted, so reading
+// history never waits on Sourcify
+pub fn decode_logs(page: &mut HistoryPage, registry: &AbiRegistry, chain_id: u64) {
+    let mut abis = HashMap::new();
+    for log in page.entries.iter_mut().flat_map(|entry| entry.logs.iter_mut()) {
+        let Ok(address) = log.address.parse::<Address>() else {
+            continue;
+        };
+        let abi = abis
+            .entry(address)
+            .or_insert_with(|| registry.get(chain_id, address).ok().flatten().map(|registered| registered.abi));
+        let topics: Option<Vec<B256>> = log.topics.iter().map(|topic| topic.parse().ok()).collect();
+        let data = hex::decode(&log.data).ok();
+        if let (Some(abi), Some(topics), Some(data)) = (abi.as_ref(), topics, data) {
+            log.decoded = abi::decode_log(abi, &topics, &data);
+        }
+    }
+}
+
+// Reads one verified block and the logs that name a tracked address in an indexed topic
+async fn fetch_activity(
+    client: &dyn EthClientApi,
+    number: u64,
+    tracked: &HashSet<Address>,
+) -> Result<(B256, BlockActivity), String> {
+    let block = client.get_block_by_number(BlockTag::Number(number), true)
+        .await
+        .map_err(|e| format!("Failed to get block {}: {}", number, e))?
+        .ok_or_else(|| format!("Block {} is not available from the light client", number))?;
+    let Transactions::Full(transactions) = block.transactions else {
+        return Err(format!("Block {} was returned without transaction bodies", number));
+    };
+
+    let topics: Vec<B256> = tracked.iter().map(|address| address.into_word()).collect();
+    let mut logs = Vec::new();
+    for position in 1..=3 {
+        let filter = Filter::new().from_block(number).to_block(number);
+        let filter = match position {
+            1 => filter.topic1(topics.clone()),
+            2 => filter.topic2(topics.clone()),
+            _ => filter.topic3(topics.clone()),
+        };
+        let matched = client.get_logs(&filter)
+            .await
+            .map_err(|e| format!("Failed to get logs for block {}: {}", number, e))?;
+        logs.extend(matched);
+    }
+
+    let mut hashes: HashSet<B256> = logs.iter().filter_map(|log| log.transaction_hash).collect();
+    let transactions = transactions
+        .into_iter()
+        .filter(|tx| {
+            hashes.remove(&tx.hash)
+                || tracked.contains(&tx.from)
+                || tx.to.map_or(false, |to| tracked.contains(&to))
+        })
+        .collect();
+
+    Ok((block.parent_hash, BlockActivity {
+        number,
+        hash: block.hash,
+        timestamp: block.timestamp.to::<u64>(),
+        transactions,
+        logs,
+    }))
+}
+
+// Indexes new verified blocks for the tracked addresses and emits `history-updated` when any are touched
+pub fn spawn_history_indexer(app: AppHandle) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(INDEX_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            // A pass fetches many blocks, so it works on its own handle to the client and leaves
+            // the state free for dapp requests
+            let (client, path) = {
+                let state = app.state::<Mutex<AppState>>();
+                let state_guard = state.lock().await;
+                let Some(client) = state_guard.client.clone() else {
+                    break;
+                };
+                let Some(path) = history_path(&app, state_guard.chain_id) else {
+                    break;
+                };
+                (client, path)
+            };
+
+            let (tracked, last) = match HistoryDb::open(&path).and_then(|db| Ok((db.tracked()?, db.last_indexed()?))) {
+                Ok(indexed) => indexed,
+                Err(e) => {
+                    tracing::warn!("History indexer: {}", e);
+                    continue;
+                },
+            };
+            if tracked.is_empty() {
+                continue;
+            }
+
+            let head = match client.get_block_by_number(BlockTag::Latest, false).await {
+                Ok(Some(block)) => block.number.to::<u64>(),
+                _ => continue,
+            };
+            let earliest = head.saturating_sub(MAX_CATCH_UP - 1);
+            let mut next = last.as_ref().map_or(head, |(number, _)| (number + 1).max(earliest));
+            let mut expected_parent = last.filter(|(number, _)| number + 1 == next).map(|(_, hash)| hash);
+
+            while next <= head {
+                let (parent_hash, activity) = match fetch_activity(&*client, next, &tracked).await {
+                    Ok(fetched) => fetched,
+                    Err(e) => {
+                        tracing::warn!("History indexer: {}", e);
+                        break;
+                    },
+                };
+
+                if expected_parent.as_ref().is_some_and(|expected| *expected != hex_hash(parent_hash)) {
+                    let rewind_to = next.saturating_sub(REORG_DEPTH).max(earliest);
+                    tracing::warn!("History indexer: reorg below block {}, re-indexing from {}", next, rewind_to);
+                    if let Err(e) = HistoryDb::open(&path).and_then(|mut db| db.rewind(rewind_to)) {
+                        tracing::warn!("History indexer: {}", e);
+                        break;
+                    }
+                    next = rewind_to;
+                    expected_parent = None;
+                    continue;
+                }
+
+                match HistoryDb::open(&path).and_then(|mut db| db.record(&activity, &tracked)) {
+                    Ok(touched) if !touched.is_empty() => {
+                        let _ = app.emit("history-updated", HistoryUpdate { block_number: next, addresses: touched });
+                    },
+                    Ok(_) => {},
+                    Err(e) => {
+                        tracing::warn!("History indexer: {}", e);
+                        break;
+                    },
+                }
+                expected_parent = Some(hex_hash(activity.hash));
+                next += 1;
+            }
+        }
+    })
+}
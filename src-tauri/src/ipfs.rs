@@ -0,0 +1,319 @@
+use alloy::transports::http::reqwest;
+use std::path::PathBuf;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+
+use crate::outbound;
+use crate::profile;
+use crate::unixfs::{base32_encode, Cid, PbNode, CODEC_DAG_PB, CODEC_RAW};
+use crate::{ens, AppState};
+
+pub const DEFAULT_GATEWAYS: &[&str] = &["https://ipfs.io", "https://dweb.link"];
+
+// Upper bound on a single file assembled from blocks, so a hostile DAG can't exhaust memory
+const MAX_CONTENT_SIZE: usize = 256 * 1024 * 1024;
+
+// EIP-1577 contenthash namespaces we know how to fetch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentHash {
+    Ipfs(Cid),
+    Ipns(String),
+}
+
+impl ContentHash {
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        match bytes {
+            [0xe3, 0x01, cid @ ..] if !cid.is_empty() => Ok(ContentHash::Ipfs(Cid::from_bytes(cid)?)),
+            [0xe5, 0x01, name @ ..] if !name.is_empty() => Ok(ContentHash::Ipns(format!("b{}", base32_encode(name)))),
+            [] => Err("Empty contenthash".to_string()),
+            _ => Err("Unsupported contenthash namespace".to_string()),
+        }
+    }
+}
+
+pub enum IpfsError {
+    NotFound(String),
+    Failed(String),
+}
+
+impl From<String> for IpfsError {
+    fn from(message: String) -> Self {
+        IpfsError::Failed(message)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Scheme {
+    Ens,
+    Ipfs,
+    Ipns,
+}
+
+// Fetches blocks from untrusted gateways and only accepts bytes that hash to the requested CID
+pub struct IpfsFetcher {
+    http: reqwest::Client,
+    gateways: Vec<String>,
+    cache_dir: Option<PathBuf>,
+}
+
+impl IpfsFetcher {
+    pub fn new(gateways: Vec<String>, cache_dir: Option<PathBuf>) -> Self {
+        Self {
+            http: outbound::client(),
+            gateways,
+            cache_dir,
+        }
+    }
+
+    async fn fetch_block(&self, cid: &Cid) -> Result<Vec<u8>, IpfsError> {
+        if cid.is_inline() {
+            return Ok(cid.digest.clone());
+        }
+
+        let key = cid.to_string();
+        if let Some(cache_dir) = &self.cache_dir {
+            if let Ok(block) = tokio::fs::read(cache_dir.join(&key)).await {
+                if cid.verify(&block) {
+                    return Ok(block);
+                }
+            }
+        }
+
+        let mut errors = Vec::new();
+        for gateway in &self.gateways {
+            let url = format!("{}/ipfs/{}?format=raw", gateway.trim_end_matches('/'), key);
+            let response = self.http.get(&url)
+                .header(header::ACCEPT, "application/vnd.ipld.raw")
+                .send()
+                .await
+                .and_then(|r| r.error_for_status());
+
+            let block = match response {
+                Ok(response) => match response.bytes().await {
+                    Ok(bytes) => bytes.to_vec(),
+                    Err(e) => {
+                        errors.push(format!("{}: {}", gateway, e));
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    errors.push(format!("{}: {}", gateway, e));
+                    continue;
+                }
+            };
+
+            if !cid.verify(&block) {
+                errors.push(format!("{}: block does not match CID", gateway));
+                continue;
+            }
+
+            if let Some(cache_dir) = &self.cache_dir {
+                if tokio::fs::create_dir_all(cache_dir).await.is_ok() {
+                    let _ = tokio::fs::write(cache_dir.join(&key), &block).await;
+                }
+            }
+            return Ok(block);
+        }
+
+        Err(IpfsError::Failed(format!("No gateway returned a valid block for {}: {}", key, errors.join("; "))))
+    }
+
+    // Resolves an IPNS name to its current root CID. The name-to-CID mapping is taken from the
+    // gateway, but everything fetched under the CID is still verified
+    pub async fn resolve_ipns(&self, name: &str) -> Result<Cid, IpfsError> {
+        let mut errors = Vec::new();
+        for gateway in &self.gateways {
+            let url = format!("{}/ipns/{}", gateway.trim_end_matches('/'), name);
+            let response = match self.http.head(&url).send().await.and_then(|r| r.error_for_status()) {
+                Ok(response) => response,
+                Err(e) => {
+                    errors.push(format!("{}: {}", gateway, e));
+                    continue;
+                }
+            };
+
+            let roots = response.headers()
+                .get("x-ipfs-roots")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next().map(|s| s.trim().to_string()));
+            let path = response.headers()
+                .get("x-ipfs-path")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("/ipfs/"))
+                .and_then(|v| v.split('/').next().map(|s| s.to_string()));
+
+            match roots.or(path).map(|cid| Cid::parse(&cid)) {
+                Some(Ok(cid)) => return Ok(cid),
+                Some(Err(e)) => errors.push(format!("{}: {}", gateway, e)),
+                None => errors.push(format!("{}: no resolved path in response", gateway)),
+            }
+        }
+
+        Err(IpfsError::Failed(format!("Failed to resolve IPNS name {}: {}", name, errors.join("; "))))
+    }
+
+    // Walks a UnixFS path from the root, serving index.html for directories.
+    // Returns the file contents and the name it was found under
+    pub async fn fetch_path(&self, root: Cid, path: &str) -> Result<(Vec<u8>, String), IpfsError> {
+        let mut cid = root;
+        let mut name = String::new();
+
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            let node = self.fetch_directory(&cid).await?;
+            cid = node.link(segment)
+                .cloned()
+                .ok_or_else(|| IpfsError::NotFound(format!("{} not found", path)))?;
+            name = segment.to_string();
+        }
+
+        if cid.codec == CODEC_DAG_PB {
+            let node = PbNode::decode(&self.fetch_block(&cid).await?)?;
+            if node.is_directory() {
+                cid = node.link("index.html")
+                    .cloned()
+                    .ok_or_else(|| IpfsError::NotFound(format!("{} has no index.html", path)))?;
+                name = "index.html".to_string();
+            }
+        }
+
+        Ok((self.read_file(cid).await?, name))
+    }
+
+    async fn fetch_directory(&self, cid: &Cid) -> Result<PbNode, IpfsError> {
+        if cid.codec != CODEC_DAG_PB {
+            return Err(IpfsError::NotFound("Path traverses a non-directory".to_string()));
+        }
+        let node = PbNode::decode(&self.fetch_block(cid).await?)?;
+        if node.is_sharded_directory() {
+            return Err(IpfsError::Failed("Sharded directories are not supported".to_string()));
+        }
+        if !node.is_directory() {
+            return Err(IpfsError::NotFound("Path traverses a non-directory".to_string()));
+        }
+        Ok(node)
+    }
+
+    // Reassembles a UnixFS file with a pre-order walk: each node's data precedes its children's
+    async fn read_file(&self, root: Cid) -> Result<Vec<u8>, IpfsError> {
+        let mut content = Vec::new();
+        let mut stack = vec![root];
+
+        while let Some(cid) = stack.pop() {
+            let block = self.fetch_block(&cid).await?;
+            match cid.codec {
+                CODEC_RAW => content.extend_from_slice(&block),
+                CODEC_DAG_PB => {
+                    let node = PbNode::decode(&block)?;
+                    content.extend_from_slice(&node.data);
+                    stack.extend(node.links.into_iter().rev().map(|link| link.cid));
+                },
+                codec => return Err(IpfsError::Failed(format!("Unsupported codec 0x{:x}", codec))),
+            }
+            if content.len() > MAX_CONTENT_SIZE {
+                return Err(IpfsError::Failed("Content exceeds maximum size".to_string()));
+            }
+        }
+
+        Ok(content)
+    }
+}
+
+fn guess_content_type(name: &str, content: &[u8]) -> &'static str {
+    let extension = name.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "html" | "htm" => "text/html",
+        "js" | "mjs" => "text/javascript",
+        "css" => "text/css",
+        "json" => "application/json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "wasm" => "application/wasm",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "txt" => "text/plain",
+        _ if content.starts_with(b"<!DOCTYPE html") || content.starts_with(b"<html") => "text/html",
+        _ => "application/octet-stream",
+    }
+}
+
+// Splits `scheme://host/path` (or `scheme://localhost/host/path` on platforms that rewrite
+// custom schemes to http://scheme.localhost) into the host part and the resource path
+fn parse_scheme_uri(request: &Request<Vec<u8>>) -> Option<(String, String)> {
+    let uri = request.uri();
+    let host = uri.host().unwrap_or_default();
+    let path = uri.path().trim_start_matches('/');
+
+    if host.is_empty() || host == "localhost" || host.ends_with(".localhost") {
+        let (name, rest) = path.split_once('/').unwrap_or((path, ""));
+        (!name.is_empty()).then(|| (name.to_string(), rest.to_string()))
+    } else {
+        Some((host.to_string(), path.to_string()))
+    }
+}
+
+fn error_response(status: StatusCode, message: String) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .body(message.into_bytes())
+        .unwrap()
+}
+
+// Handles ens://, ipfs:// and ipns:// requests from the webview
+pub async fn handle_request(app: &AppHandle, request: Request<Vec<u8>>, scheme: Scheme) -> Response<Vec<u8>> {
+    let Some((host, path)) = parse_scheme_uri(&request) else {
+        return error_response(StatusCode::BAD_REQUEST, "Missing host".to_string());
+    };
+
+    let state = app.state::<Mutex<AppState>>();
+    let cache_dir = profile::cache_dir(app).map(|dir| dir.join("ipfs"));
+    let gateways = state.lock().await.ipfs_gateways.clone();
+    let fetcher = IpfsFetcher::new(gateways, cache_dir);
+
+    let content = match scheme {
+        Scheme::Ipfs => match Cid::parse(&host) {
+            Ok(cid) => ContentHash::Ipfs(cid),
+            Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+        },
+        Scheme::Ipns => ContentHash::Ipns(host),
+        Scheme::Ens => {
+            let state_guard = state.lock().await;
+            let Some(client) = state_guard.client.as_ref() else {
+                return error_response(StatusCode::SERVICE_UNAVAILABLE, "Light client not initialized".to_string());
+            };
+            let contenthash = match ens::resolve_contenthash(client, &host).await {
+                Ok(Some(bytes)) => bytes,
+                Ok(None) => return error_response(StatusCode::NOT_FOUND, format!("{} has no contenthash", host)),
+                Err(e) => return error_response(StatusCode::BAD_GATEWAY, e),
+            };
+            match ContentHash::decode(&contenthash) {
+                Ok(content) => content,
+                Err(e) => return error_response(StatusCode::UNPROCESSABLE_ENTITY, e),
+            }
+        }
+    };
+
+    let root = match content {
+        ContentHash::Ipfs(cid) => Ok(cid),
+        ContentHash::Ipns(name) => fetcher.resolve_ipns(&name).await,
+    };
+
+    match root {
+        Ok(root) => match fetcher.fetch_path(root, &path).await {
+            Ok((body, name)) => Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, guess_content_type(&name, &body))
+                .body(body)
+                .unwrap(),
+            Err(IpfsError::NotFound(e)) => error_response(StatusCode::NOT_FOUND, e),
+            Err(IpfsError::Failed(e)) => error_response(StatusCode::BAD_GATEWAY, e),
+        },
+        Err(IpfsError::NotFound(e)) => error_response(StatusCode::NOT_FOUND, e),
+        Err(IpfsError::Failed(e)) => error_response(StatusCode::BAD_GATEWAY, e),
+    }
+}
@@ -0,0 +1,105 @@
+use alloy::primitives::{Address, B256};
+use alloy::signers::local::PrivateKeySigner;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::profile;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredAccount {
+    pub address: Address,
+    pub label: Option<String>,
+    // Imported from a raw private key rather than derived from the wallet's seed, these can't be
+    // recovered from the seed phrase and are shown separately
+    pub imported: bool,
+    // Encrypted key file name inside the keystore dir
+    pub file: String,
+}
+
+pub fn keystore_dir(app: &AppHandle) -> Option<PathBuf> {
+    profile::data_dir(app).map(|dir| dir.join("keystore"))
+}
+
+// Accounts with an encrypted key on disk, indexed in keystore/accounts.json
+#[derive(Default)]
+pub struct Keystore {
+    accounts: Vec<StoredAccount>,
+    loaded: bool,
+}
+
+impl Keystore {
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    pub async fn load(&mut self, dir: &Path) {
+        if let Ok(bytes) = tokio::fs::read(dir.join("accounts.json")).await {
+            if let Ok(accounts) = serde_json::from_slice(&bytes) {
+                self.accounts = accounts;
+            }
+        }
+        self.loaded = true;
+    }
+
+    pub async fn save(&self, dir: &Path) -> Result<(), String> {
+        let bytes = serde_json::to_vec(&self.accounts).map_err(|e| format!("Failed to serialize accounts: {}", e))?;
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|e| format!("Failed to create keystore dir: {}", e))?;
+        tokio::fs::write(dir.join("accounts.json"), bytes)
+            .await
+            .map_err(|e| format!("Failed to write accounts: {}", e))
+    }
+
+    pub fn accounts(&self) -> &[StoredAccount] {
+        &self.accounts
+    }
+
+    pub fn contains(&self, address: Address) -> bool {
+        self.accounts.iter().any(|account| account.address == address)
+    }
+
+    pub fn add(&mut self, account: StoredAccount) {
+        self.accounts.push(account);
+    }
+}
+
+pub async fn load_keystore(app: &AppHandle, keystore: &mut Keystore) {
+    if let (false, Some(dir)) = (keystore.is_loaded(), keystore_dir(app)) {
+        keystore.load(&dir).await;
+    }
+}
+
+// Checks that the key is a valid secp256k1 scalar and returns its signer
+pub fn parse_private_key(private_key: &str) -> Result<PrivateKeySigner, String> {
+    let bytes = private_key.trim().parse::<B256>().map_err(|_| "Private key must be 32 bytes of hex".to_string())?;
+    PrivateKeySigner::from_bytes(&bytes).map_err(|_| "Invalid private key".to_string())
+}
+
+// Writes the key as a scrypt-encrypted v3 keystore file and returns the file name. Key
+// derivation is deliberately slow, so it runs off the async runtime
+pub async fn encrypt_key(dir: &Path, signer: &PrivateKeySigner, password: String) -> Result<String, String> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(|e| format!("Failed to create keystore dir: {}", e))?;
+    let dir = dir.to_path_buf();
+    let key = signer.to_bytes();
+    tokio::task::spawn_blocking(move || {
+        PrivateKeySigner::encrypt_keystore(&dir, &mut rand::thread_rng(), key, password, None)
+            .map(|(_, file)| file)
+            .map_err(|e| format!("Failed to encrypt key: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Key encryption task failed: {}", e))?
+}
+
+pub async fn decrypt_key(dir: &Path, file: &str, password: String) -> Result<PrivateKeySigner, String> {
+    let path = dir.join(file);
+    tokio::task::spawn_blocking(move || {
+        PrivateKeySigner::decrypt_keystore(path, password).map_err(|e| format!("Failed to decrypt key: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Key decryption task failed: {}", e))?
+}
@@ -1,14 +1,104 @@
+mod abi;
+mod accounts;
+mod approvals;
+mod auth;
+mod backup;
+mod balances;
+mod benchmark;
+mod bundle;
+mod cache;
+mod calls;
+mod ccip;
+mod chains;
+mod checkpoint;
+mod client;
+mod consensus;
+mod contract;
+mod daemon;
+mod db;
+mod decode;
+mod devmode;
+mod distribution;
+mod eip681;
+mod ens;
+mod errors;
+mod evm;
+mod filters;
+mod gas;
+mod headers;
+mod history;
+mod ipfs;
+mod keystore;
+mod logging;
+mod mempool;
+mod metrics;
+mod multicall;
+mod nft;
+mod notify;
+mod offline;
+mod outbound;
+mod passthrough;
+mod pending;
+mod policy;
+mod portfolio;
+mod prices;
+mod profile;
+mod prompts;
+mod proofs;
+mod protect;
+mod provenance;
+mod ratelimit;
+mod receipts;
+mod replace;
+mod retry;
+mod rpc_server;
+mod sandbox;
+mod settings;
+mod simulate;
+mod signatures;
+mod signer;
+mod siwe;
+mod sourcify;
+mod storage;
+mod subscriptions;
+mod sync;
+mod tokens;
+mod trace;
+mod tray;
+mod unixfs;
+mod userop;
+mod walletconnect;
+mod watch;
+mod watchdog;
+mod websocket;
+mod window;
+
 use alloy::hex;
-use alloy::transports::http::reqwest;
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use tauri::{Emitter, Manager};
+use tauri_plugin_deep_link::DeepLinkExt;
 use tokio::sync::Mutex;
-use alloy::primitives::{Address, B256};
+use alloy::primitives::{Address, B256, U256};
 use alloy::rpc::types::Transaction;
 use helios::core::types::{Block, BlockTag};
 use helios::ethereum::{
-    config::networks::Network, database::FileDB, EthereumClient, EthereumClientBuilder,
+    config::networks::Network, EthereumClient, EthereumClientBuilder,
 };
+use db::AppDB;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+const DATA_DIR: &str = "/tmp/helios";
+const DEFAULT_RECEIPT_CONCURRENCY: usize = 8;
+const DEFAULT_CHAIN_ID: u64 = 1;
+const DEFAULT_CONSENSUS_RPC: &str = "https://www.lightclientdata.org";
+const CONSENSUS_SYNC_TIMEOUT: Duration = Duration::from_secs(120);
+// How long a confirmation prompt waits on signature and Sourcify lookups before showing raw calldata
+const DESCRIBE_TIMEOUT: Duration = Duration::from_secs(5);
 
 // Helper types and enums
 enum JsonRpcResult<T> {
@@ -16,6 +106,34 @@ enum JsonRpcResult<T> {
     Error(i32, String),
 }
 
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConsensusFailover {
+    failed_rpc: String,
+    next_rpc: Option<String>,
+    reason: String,
+}
+
+// Everything needed to rebuild the client after a stop, kept around for restarts
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClientConfig {
+    rpc_url: String,
+    // WebSocket endpoint of the same execution provider. Carries upstream calls while it is reachable
+    // and the streams HTTP can't
+    ws_url: Option<String>,
+    consensus_rpcs: Vec<String>,
+    chain_id: u64,
+    ephemeral: bool,
+    checkpoint_fallbacks: Vec<String>,
+}
+
+struct LaunchedClient {
+    client: EthereumClient<AppDB>,
+    consensus_rpc: String,
+    checkpoint: Option<checkpoint::CheckpointRecord>,
+}
+
 // Helper functions
 fn json_rpc_error(code: i32, message: &str) -> serde_json::Value {
     json!({
@@ -24,11 +142,107 @@ fn json_rpc_error(code: i32, message: &str) -> serde_json::Value {
     })
 }
 
+// Pending state isn't tracked, so `pending` reads the latest block. `safe` lags `latest` by a few
+// blocks and `finalized` is the nearest verified block at or behind it
 fn parse_block_tag(value: &serde_json::Value) -> Result<BlockTag, String> {
     match value.as_str() {
-        Some("latest") => Ok(BlockTag::Latest),
-        _ => Err("Invalid params: only 'latest' block tag is currently supported".to_string())
+        Some("latest" | "pending") => Ok(BlockTag::Latest),
+        Some("finalized" | "safe") => Ok(BlockTag::Finalized),
+        Some("earliest") => Ok(BlockTag::Number(0)),
+        Some(s) if s.starts_with("0x") && s.len() < 66 => u64::from_str_radix(&s[2..], 16)
+            .map(BlockTag::Number)
+            .map_err(|_| "Invalid params: invalid block number".to_string()),
+        _ => Err("Invalid params: expected a block number or tag".to_string())
+    }
+}
+
+// Block selector for methods that take a block hash as well as a tag
+#[derive(Clone, Copy)]
+enum BlockRef {
+    Tag(BlockTag),
+    Hash(B256),
+}
+
+// A tag, a 32-byte hash, or an EIP-1898 `{ blockHash }` / `{ blockNumber }` object
+fn parse_block_ref(value: &serde_json::Value) -> Result<BlockRef, String> {
+    if let Some(hash) = value.get("blockHash") {
+        return parse_hash(hash).map(BlockRef::Hash);
+    }
+    if let Some(number) = value.get("blockNumber") {
+        return parse_block_tag(number).map(BlockRef::Tag);
+    }
+    match value.as_str() {
+        Some(s) if s.len() == 66 => parse_hash(value).map(BlockRef::Hash),
+        _ => parse_block_tag(value).map(BlockRef::Tag),
+    }
+}
+
+// Positional params a method can't do without, by name. Optional trailing params, like eth_call's
+// block, are left out and default in the handler
+fn required_params(method: &str) -> &'static [&'static str] {
+    match method {
+        "eth_getBalance" | "eth_getCode" | "eth_getTransactionCount" => &["address", "block"],
+        "eth_getStorageAt" => &["address", "slot", "block"],
+        "eth_getProof" => &["address", "storageKeys"],
+        "eth_getBlockByNumber" => &["block", "fullTransactions"],
+        "eth_getBlockByHash" => &["blockHash", "fullTransactions"],
+        "eth_getBlockTransactionCountByNumber" | "eth_getBlockReceipts" => &["block"],
+        "eth_getBlockTransactionCountByHash" => &["blockHash"],
+        "eth_getTransactionByBlockHashAndIndex" => &["blockHash", "index"],
+        "eth_getTransactionByHash" | "eth_getTransactionReceipt" | "debug_traceTransaction" => &["transactionHash"],
+        "eth_call" | "eth_estimateGas" | "eth_sendTransaction" | "eth_signTransaction" => &["transaction"],
+        "eth_sendRawTransaction" => &["signedTransaction"],
+        "eth_getLogs" | "eth_newFilter" => &["filter"],
+        "eth_getFilterChanges" | "eth_uninstallFilter" => &["filterId"],
+        "eth_simulateV1" => &["payload"],
+        "wallet_switchEthereumChain" => &["chain"],
+        "eth_subscribe" => &["subscriptionType"],
+        "eth_unsubscribe" => &["subscriptionId"],
+        _ => &[],
+    }
+}
+
+// Methods that sign, send or hand out keys, all refused in read-only mode. Every dispatcher branch
+// that signs or broadcasts has to be listed: raw transactions may go out through the private relay,
+// user operations through the bundler, and node control changes a dev node's or the sandbox's chain
+fn is_write_method(method: &str) -> bool {
+    matches!(
+        method,
+        "eth_sign"
+            | "personal_sign"
+            | "eth_signTypedData"
+            | "eth_signTypedData_v3"
+            | "eth_signTypedData_v4"
+            | "eth_signTransaction"
+            | "eth_sendTransaction"
+            | "eth_sendRawTransaction"
+            | "eth_sendUserOperation"
+            | "wallet_sendCalls"
+    ) || devmode::is_node_control(method)
+}
+
+// The method and positional params of a request, or the error code and message it's refused with.
+// By-name params, as wallet_watchAsset sends, become a single positional param. Handlers index
+// required params directly, so a short list is rejected here
+fn parse_request(request: &serde_json::Value) -> Result<(&str, Vec<serde_json::Value>), (i32, String)> {
+    if request.get("jsonrpc").and_then(|v| v.as_str()) != Some("2.0") {
+        return Err((errors::INVALID_REQUEST, "Invalid Request: only JSON-RPC 2.0 is supported".to_string()));
     }
+    let method = request.get("method")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| (errors::INVALID_REQUEST, "Invalid Request: missing method".to_string()))?;
+    let params = match request.get("params") {
+        Some(serde_json::Value::Array(params)) => params.clone(),
+        Some(object @ serde_json::Value::Object(_)) => vec![object.clone()],
+        _ => return Err((errors::INVALID_PARAMS, "Invalid params: missing or invalid params".to_string())),
+    };
+    if let Some(missing) = required_params(method).get(params.len()) {
+        return Err((
+            errors::INVALID_PARAMS,
+            format!("Invalid params: missing {} (param {}) for {}", missing, params.len(), method)
+        ));
+    }
+    Ok((method, params))
 }
 
 fn parse_address(value: &serde_json::Value) -> Result<Address, String> {
@@ -48,6 +262,87 @@ fn parse_bool(value: &serde_json::Value) -> Result<bool, String> {
         .ok_or_else(|| "Invalid params: parameter must be a boolean".to_string())
 }
 
+// With address distribution on, account queries on the app's chain go to the provider assigned to
+// the address as a proven eth_getProof instead of through the light client's own RPC. None when
+// distribution doesn't apply
+async fn distributed_account(
+    upstream: Option<&Upstream>,
+    accounts: &distribution::AccountSource,
+    address: Address,
+    block_tag: BlockTag,
+) -> Option<Result<distribution::Account, String>> {
+    if !accounts.is_distributed() {
+        return None;
+    }
+    Some(accounts.account(upstream?.client(), address, block_tag).await)
+}
+
+// Receipts of a verified block. Helios checks the whole block's receipts for every receipt it
+// returns, so the block's receipts are fetched and checked against its root once instead.
+// `concurrency` bounds the per-transaction fetches for RPCs without eth_getBlockReceipts
+async fn fetch_block_receipts(
+    client: &dyn client::EthClientApi,
+    rpc_url: &str,
+    block: BlockRef,
+    concurrency: usize,
+) -> Result<Option<Vec<serde_json::Value>>, String> {
+    let block = match block {
+        BlockRef::Tag(tag) => client.get_block_by_number(tag, false).await,
+        BlockRef::Hash(hash) => client.get_block_by_hash(hash, false).await,
+    };
+    let block = match block {
+        Ok(Some(block)) => block,
+        Ok(None) => return Ok(None),
+        Err(e) => return Err(format!("failed to get block: {}", e)),
+    };
+
+    let hashes: Vec<B256> = block.transactions.hashes().collect();
+    receipts::verified_receipts(rpc_url, &headers::VerifiedHeader::from_block(&block), &hashes, concurrency)
+        .await
+        .map(Some)
+}
+
+// A second params object can force or skip the private relay for one transaction
+fn private_override(params: &[serde_json::Value]) -> Option<bool> {
+    params.get(1)
+        .and_then(|options| options.get("private"))
+        .and_then(|private| private.as_bool())
+}
+
+// Sends a signed transaction, through the private relay when enabled, and records it in the pending tracker
+async fn broadcast_transaction(
+    app: &tauri::AppHandle,
+    state_guard: &mut AppState,
+    chain_id: u64,
+    bytes: &[u8],
+    private: Option<bool>,
+    method: &str,
+) -> Result<B256, String> {
+    let private = private.unwrap_or(state_guard.private_relay.enabled);
+    let Some(client) = state_guard.client_for(chain_id) else {
+        return Err("Light client not initialized".to_string());
+    };
+    let hash = if private {
+        protect::send_raw_transaction(&state_guard.private_relay.relay_url, bytes).await?
+    } else {
+        // Sends aren't retried, so nothing backs off here while the caller holds the lock
+        retry::with_retry(&state_guard.retry_policy, method, || client.send_raw_transaction(bytes))
+            .await
+            .map_err(|e| e.to_string())?
+    };
+
+    pending::load_tracker(app, &mut state_guard.pending).await;
+    match state_guard.pending.record(bytes, chain_id, private) {
+        Ok(()) => if let Some(path) = pending::tracker_path(app) {
+            if let Err(e) = state_guard.pending.save(&path).await {
+                tracing::warn!("Failed to save pending transactions: {}", e);
+            }
+        },
+        Err(e) => tracing::warn!("Not tracking 0x{:x}: {}", hash, e),
+    }
+    Ok(hash)
+}
+
 fn handle_response(response: &mut serde_json::Value, result: JsonRpcResult<serde_json::Value>) {
     match result {
         JsonRpcResult::Success(value) => {
@@ -62,66 +357,368 @@ fn handle_response(response: &mut serde_json::Value, result: JsonRpcResult<serde
 // Tauri setup
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let daemon = match daemon::DaemonArgs::parse(std::env::args()) {
+        Ok(daemon) => daemon,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let headless = daemon.is_some();
+    let mut context = tauri::generate_context!();
+    if headless {
+        context.config_mut().app.windows.clear();
+    }
+
     tauri::Builder::default()
         .manage(Mutex::new(AppState::default()))
-        .setup(|app| {
-            if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
+        .manage(prompts::Prompts::default())
+        .manage(auth::AuthGate::default())
+        .manage(metrics::Metrics::default())
+        .manage(cache::SharedDiskCache::default())
+        .register_asynchronous_uri_scheme_protocol("ens", |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                responder.respond(ipfs::handle_request(&app, request, ipfs::Scheme::Ens).await);
+            });
+        })
+        .register_asynchronous_uri_scheme_protocol("ipfs", |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                responder.respond(ipfs::handle_request(&app, request, ipfs::Scheme::Ipfs).await);
+            });
+        })
+        .register_asynchronous_uri_scheme_protocol("ipns", |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                responder.respond(ipfs::handle_request(&app, request, ipfs::Scheme::Ipns).await);
+            });
+        })
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_notification::init())
+        .setup(move |app| {
+            let logging = logging::init(app.handle(), cfg!(debug_assertions) || headless)?;
+            app.manage(logging);
+
+            #[cfg(desktop)]
+            app.deep_link().register_all()?;
+
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    match url.scheme() {
+                        "ethereum" => {
+                            tauri::async_runtime::spawn(eip681::handle_deep_link(handle.clone(), url.to_string()));
+                        },
+                        "wc" => {
+                            tauri::async_runtime::spawn(walletconnect::handle_deep_link(handle.clone(), url.to_string()));
+                        },
+                        _ => {},
+                    }
+                }
+            });
+
+            profile::set_active(&profile::at_launch(app.handle(), std::env::args()));
+            let handle = app.handle().clone();
+            tauri::async_runtime::block_on(async move {
+                let state = handle.state::<Mutex<AppState>>();
+                let mut state_guard = state.lock().await;
+                settings::restore(&handle, &mut state_guard).await;
+                handle.state::<auth::AuthGate>().restore(&handle).await;
+            });
+
+            signer::spawn_auto_lock(app.handle().clone());
+            storage::spawn_pruner(app.handle().clone());
+
+            #[cfg(desktop)]
+            tray::build(app.handle())?;
+
+            if let Some(args) = daemon {
+                #[cfg(target_os = "macos")]
+                app.set_activation_policy(tauri::ActivationPolicy::Accessory);
+                tauri::async_runtime::spawn(daemon::run(app.handle().clone(), args));
             }
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![start, get_block, request])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .invoke_handler(tauri::generate_handler![start, get_block, get_finalized_header, get_consensus_head, request, aggregate_calls, simulate_transaction, get_contract_metadata, register_abi, remove_abi, list_abis, get_decoded_logs, decode_calldata, set_token_lists, refresh_token_lists, search_tokens, get_token_metadata, get_token_balances, get_portfolio, set_price_feeds, get_prices, get_gas_quotes, speed_up_transaction, cancel_transaction, list_pending_transactions, set_private_relay, get_private_transaction_status, set_bundle_signer, send_bundle, set_bundler, resolve_prompt, import_private_key, unlock_wallet, lock_wallet, set_auto_lock, export_backup, import_backup, switch_account, list_sessions, revoke_session, list_walletconnect_pairings, set_policy, remove_policy, list_policies, track_history_address, untrack_history_address, get_history, watch_address, unwatch_address, list_watched_addresses, nft_verify_ownership, nft_list_owned, nft_get_metadata, ens_resolve, ens_lookup, parse_payment_uri, siwe_parse_and_verify, set_ipfs_gateways, set_retry_policy, set_local_tracing, set_unverified_passthrough, set_allow_eth_sign, set_read_only, set_auth_settings, set_notification_settings, get_setting, set_setting, get_recent_logs, set_log_level, get_rpc_stats, get_connectivity, set_dev_mode, get_dev_mode, get_sandbox, reset_sandbox, stop_sandbox, set_cache_policy, get_cache_stats, clear_cache, get_storage_usage, benchmark_rpc, start_rpc_server, stop_rpc_server, get_checkpoint_info, get_checkpoint_history, clear_data_dir, export_header_snapshot, import_header_snapshot, list_profiles, switch_profile])
+        .build(context)
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                let state = app.state::<Mutex<AppState>>();
+                tauri::async_runtime::block_on(shutdown(&state));
+            }
+        });
 }
 
 #[tauri::command]
 async fn start(
+    app: tauri::AppHandle,
     state: tauri::State<'_, Mutex<AppState>>, 
     rpc_url: String,
+    ws_rpc_url: Option<String>,
     consensus_rpc: Option<String>,
+    fallback_consensus_rpcs: Option<Vec<String>>,
     chain_id: u64,
+    receipt_concurrency: Option<usize>,
+    ephemeral: Option<bool>,
+    checkpoint_fallbacks: Option<Vec<String>>,
 ) -> Result<String, String> {
-    let mut client = {
+    {
         let state_guard = state.lock().await;
         if state_guard.client.is_some() {
             return Err("Light client is already running".to_string());
         }
-        
-        let consensus_url = consensus_rpc.unwrap_or_else(|| "https://www.lightclientdata.org".to_string());
-        
-        let network = get_network(chain_id)
-            .map_err(|e| format!("Failed to get network: {}", e))?;
-        
-        EthereumClientBuilder::new()
-            .network(network)
-            .consensus_rpc(&consensus_url)
-            .execution_rpc(&rpc_url)
-            .load_external_fallback()
-            .data_dir(PathBuf::from("/tmp/helios"))
-            .build()
-            .map_err(|e| format!("Failed to create client: {}", e))?
+    }
+
+    if let Some(ws_url) = ws_rpc_url.as_deref().filter(|ws_url| !mempool::is_ws_url(ws_url)) {
+        return Err(format!("WebSocket RPC must be a ws(s) URL: {}", ws_url));
+    }
+
+    let mut consensus_rpcs = vec![consensus_rpc.unwrap_or_else(|| DEFAULT_CONSENSUS_RPC.to_string())];
+    consensus_rpcs.extend(fallback_consensus_rpcs.unwrap_or_default());
+
+    let config = ClientConfig {
+        rpc_url,
+        ws_url: ws_rpc_url,
+        consensus_rpcs,
+        chain_id,
+        ephemeral: ephemeral.unwrap_or(false),
+        checkpoint_fallbacks: checkpoint_fallbacks.unwrap_or_default(),
     };
-    
-    client.start()
-        .await
-        .map_err(|e| format!("Failed to start client: {}", e))?;
-    
-    client.wait_synced().await;
-    
+
+    let launched = launch_client(&app, &config).await?;
+
     {
         let mut state_guard = state.lock().await;
-        state_guard.client = Some(client);
-        state_guard.rpc_url = rpc_url;
+        state_guard.receipt_concurrency = receipt_concurrency.unwrap_or(DEFAULT_RECEIPT_CONCURRENCY);
+        let switched = state_guard.chain_id != config.chain_id;
+        install_client(&app, &mut state_guard, launched, config);
+        let chain_id = state_guard.chain_id;
+        restore_filters(&app, &mut state_guard, chain_id).await;
+        if switched {
+            if let Err(e) = settings::commit(&app, &state_guard, "network.chainId").await {
+                tracing::warn!("Failed to save chain id: {}", e);
+            }
+        }
     }
 
     Ok("Light client started and synced successfully".to_string())
 }
 
+// Stores a freshly launched client and starts the background tasks that depend on it
+fn install_client(
+    app: &tauri::AppHandle,
+    state_guard: &mut AppState,
+    launched: LaunchedClient,
+    config: ClientConfig,
+) {
+    // Restarts on the same chain keep the header store, it only tracks one chain
+    if state_guard.config.as_ref().map(|c| c.chain_id) != Some(config.chain_id) {
+        state_guard.header_store.clear();
+        state_guard.finalized = None;
+    }
+    // Filter ids belong to the previous client, or to a window's client of the new chain that this
+    // one takes over from. restore_filters installs them here
+    let previous_chain = state_guard.chain_id;
+    state_guard.filters.detach(previous_chain);
+    state_guard.filters.detach(config.chain_id);
+    state_guard.client = Some(Arc::new(launched.client));
+    state_guard.chain_id = config.chain_id;
+    state_guard.rpc_url = config.rpc_url.clone();
+    state_guard.consensus_rpc = launched.consensus_rpc;
+    state_guard.checkpoint = launched.checkpoint;
+    state_guard.known_networks.insert(config.chain_id, config.clone());
+    state_guard.config = Some(config);
+    state_guard.tasks.push(sync::spawn_head_watcher(app.clone()));
+    state_guard.tasks.push(history::spawn_history_indexer(app.clone()));
+    state_guard.tasks.push(watch::spawn_address_watcher(app.clone()));
+    state_guard.tasks.push(gas::spawn_gas_oracle(app.clone()));
+    state_guard.tasks.push(pending::spawn_pending_tracker(app.clone()));
+    if state_guard.watchdog.is_none() {
+        state_guard.watchdog = Some(watchdog::spawn_watchdog(app.clone()));
+    }
+}
+
+// Shuts down the running client and its background tasks, returning the config it was started with
+async fn stop_client(state: &Mutex<AppState>) -> Option<ClientConfig> {
+    let (client, config) = {
+        let mut state_guard = state.lock().await;
+        state_guard.abort_tasks();
+        (state_guard.client.take(), state_guard.config.clone())
+    };
+
+    client?.shutdown().await;
+    config
+}
+
+// Installs the saved filters of `chain_id` on the client now serving it, under the ids dapps
+// already hold
+async fn restore_filters(app: &tauri::AppHandle, state_guard: &mut AppState, chain_id: u64) {
+    filters::load_filters(app, &mut state_guard.filters).await;
+    let AppState { client, chain_id: app_chain, tab_chains, filters, .. } = state_guard;
+    if let Some(client) = tab_chains.resolve(client.as_deref(), *app_chain, chain_id) {
+        filters.reinstall(chain_id, client).await;
+    }
+}
+
+// Drops filters nobody polled within the timeout, uninstalling them from their client. Runs
+// whenever filters are used, which is when a leak would otherwise grow
+async fn expire_filters(app: &tauri::AppHandle, state_guard: &mut AppState) {
+    filters::load_filters(app, &mut state_guard.filters).await;
+    let expired = state_guard.filters.expire(state_guard.filter_limits.timeout_secs);
+    if expired.is_empty() {
+        return;
+    }
+    filters::save_filters(app, &state_guard.filters).await;
+    for filter in expired {
+        if let (Some(client_id), Some(client)) = (filter.client_id, state_guard.client_for(filter.chain_id)) {
+            let _ = client.uninstall_filter(client_id).await;
+        }
+    }
+}
+
+// Stops the watchdog first so it can't restart the client, then shuts the windows' clients and the
+// app's client down. Dropping a client makes helios persist its latest checkpoint, so the next
+// launch resumes from it
+async fn shutdown(state: &Mutex<AppState>) {
+    let tab_clients = {
+        let mut state_guard = state.lock().await;
+        if let Some(watchdog) = state_guard.watchdog.take() {
+            watchdog.abort();
+        }
+        state_guard.tab_chains.take_all()
+    };
+    for client in tab_clients {
+        client.shutdown().await;
+    }
+    stop_client(state).await;
+}
+
+async fn relaunch_client(
+    app: &tauri::AppHandle,
+    state: &Mutex<AppState>,
+    config: ClientConfig,
+) -> Result<(), String> {
+    let launched = launch_client(app, &config).await?;
+    let mut state_guard = state.lock().await;
+    let switched = state_guard.chain_id != config.chain_id;
+    install_client(app, &mut state_guard, launched, config);
+    let chain_id = state_guard.chain_id;
+    restore_filters(app, &mut state_guard, chain_id).await;
+    if switched {
+        settings::commit(app, &state_guard, "network.chainId").await?;
+    }
+    Ok(())
+}
+
+// Restarts the running client from its saved config
+async fn restart_client(app: &tauri::AppHandle, state: &Mutex<AppState>) -> Result<(), String> {
+    match stop_client(state).await {
+        Some(config) => relaunch_client(app, state, config).await,
+        None => Err("Light client not initialized".to_string()),
+    }
+}
+
+// Tries each configured consensus endpoint in order until one syncs, emitting an event on every failover
+#[tracing::instrument(skip_all, fields(chain_id = config.chain_id))]
+async fn launch_client(
+    app: &tauri::AppHandle,
+    config: &ClientConfig,
+) -> Result<LaunchedClient, String> {
+    let network = get_network(config.chain_id)
+        .map_err(|e| format!("Failed to get network: {}", e))?;
+    websocket::configure(&config.rpc_url, config.ws_url.as_deref());
+
+    let data_dir = if config.ephemeral { None } else { Some(profile::light_client_dir()) };
+
+    // Pinned fallback services replace the list helios would otherwise load externally
+    let checkpoint = if config.checkpoint_fallbacks.is_empty() {
+        None
+    } else {
+        Some(checkpoint::fetch_from_services(&config.checkpoint_fallbacks).await?)
+    };
+
+    let mut last_error = "No consensus RPC configured".to_string();
+    for (i, consensus_url) in config.consensus_rpcs.iter().enumerate() {
+        let _ = app.emit("sync-status", sync::SyncStatus {
+            status: "syncing",
+            consensus_rpc: consensus_url.clone(),
+        });
+
+        let pinned = checkpoint.as_ref().map(|record| record.checkpoint);
+        match start_client(network.clone(), consensus_url, &config.rpc_url, data_dir.clone(), pinned).await {
+            Ok(client) => {
+                if let (Some(record), Some(data_dir)) = (&checkpoint, &data_dir) {
+                    if let Err(e) = checkpoint::record_checkpoint(data_dir, record).await {
+                        tracing::warn!("{}", e);
+                    }
+                }
+                let _ = app.emit("sync-status", sync::SyncStatus {
+                    status: "synced",
+                    consensus_rpc: consensus_url.clone(),
+                });
+                return Ok(LaunchedClient {
+                    client,
+                    consensus_rpc: consensus_url.clone(),
+                    checkpoint,
+                });
+            },
+            Err(e) => {
+                let _ = app.emit("consensus-failover", ConsensusFailover {
+                    failed_rpc: consensus_url.clone(),
+                    next_rpc: config.consensus_rpcs.get(i + 1).cloned(),
+                    reason: e.clone(),
+                });
+                last_error = e;
+            }
+        }
+    }
+
+    Err(last_error)
+}
+
+// Builds, starts and syncs a client against a single consensus endpoint, giving up if sync stalls
+async fn start_client(
+    network: Network,
+    consensus_url: &str,
+    rpc_url: &str,
+    data_dir: Option<PathBuf>,
+    checkpoint: Option<B256>,
+) -> Result<EthereumClient<AppDB>, String> {
+    let consensus_route = outbound::route(consensus_url).await?;
+    let execution_route = outbound::route(rpc_url).await?;
+    let mut builder = EthereumClientBuilder::new()
+        .network(network)
+        .consensus_rpc(&consensus_route)
+        .execution_rpc(&execution_route);
+
+    builder = match checkpoint {
+        Some(checkpoint) => builder.checkpoint(checkpoint),
+        None => builder.load_external_fallback(),
+    };
+
+    // Without a data dir the client falls back to the in-memory checkpoint store
+    if let Some(data_dir) = data_dir {
+        builder = builder.data_dir(data_dir);
+    }
+
+    let mut client = builder
+        .build()
+        .map_err(|e| format!("Failed to create client: {}", e))?;
+
+    client.start()
+        .await
+        .map_err(|e| format!("Failed to start client: {}", e))?;
+
+    if tokio::time::timeout(CONSENSUS_SYNC_TIMEOUT, client.wait_synced()).await.is_err() {
+        client.shutdown().await;
+        return Err(format!("Sync stalled using consensus RPC {}", consensus_url));
+    }
+
+    Ok(client)
+}
+
 #[tauri::command]
 async fn get_block(state: tauri::State<'_, Mutex<AppState>>) -> Result<Option<Block<Transaction>>, String> {
     let state_guard = state.lock().await;
@@ -131,60 +728,2443 @@ async fn get_block(state: tauri::State<'_, Mutex<AppState>>) -> Result<Option<Bl
                 .await
                 .map_err(|e| format!("Failed to get latest block: {}", e))
         },
-        None => {
-            Err("Light client not initialized".to_string())
-        }
-    }
-}
+        None => {
+            Err("Light client not initialized".to_string())
+        }
+    }
+}
+
+#[tauri::command]
+async fn get_finalized_header(state: tauri::State<'_, Mutex<AppState>>) -> Result<headers::VerifiedHeader, String> {
+    let state_guard = state.lock().await;
+    match state_guard.client.as_ref() {
+        Some(client) => {
+            client.get_block_by_number(BlockTag::Finalized, false)
+                .await
+                .map_err(|e| format!("Failed to get finalized block: {}", e))?
+                .map(|block| headers::VerifiedHeader::from_block(&block))
+                .ok_or_else(|| "No finalized block available yet".to_string())
+        },
+        None => {
+            Err("Light client not initialized".to_string())
+        }
+    }
+}
+
+// Beacon headers behind the verified chain, for showing where the light client's trust comes from
+#[tauri::command]
+async fn get_consensus_head(state: tauri::State<'_, Mutex<AppState>>) -> Result<consensus::ConsensusHead, String> {
+    let state_guard = state.lock().await;
+    match state_guard.client.as_ref() {
+        Some(client) => consensus::head(client, &state_guard.consensus_rpc).await,
+        None => Err("Light client not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_checkpoint_info(state: tauri::State<'_, Mutex<AppState>>) -> Result<checkpoint::CheckpointInfo, String> {
+    let state_guard = state.lock().await;
+    let ephemeral = state_guard.config.as_ref().map(|c| c.ephemeral).unwrap_or(false);
+    let mut info = checkpoint::read_checkpoint_info(&profile::light_client_dir()).await?;
+    info.running = state_guard.client.is_some();
+    info.ephemeral = ephemeral;
+    if info.running {
+        info.consensus_rpc = Some(state_guard.consensus_rpc.clone());
+        info.accepted = state_guard.checkpoint.clone();
+    }
+    Ok(info)
+}
+
+#[tauri::command]
+async fn get_checkpoint_history() -> Result<Vec<checkpoint::CheckpointRecord>, String> {
+    checkpoint::read_checkpoint_history(&profile::light_client_dir()).await
+}
+
+#[tauri::command]
+async fn clear_data_dir(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+) -> Result<String, String> {
+    let config = stop_client(&state).await;
+
+    checkpoint::clear_data_dir(&profile::light_client_dir()).await?;
+
+    match config {
+        Some(config) => {
+            relaunch_client(&app, &state, config).await?;
+            Ok("Data dir cleared and light client restarted".to_string())
+        },
+        None => Ok("Data dir cleared".to_string()),
+    }
+}
+
+// Writes the checkpoint and verified headers to a file another install can bootstrap from. The
+// saved checkpoint follows finality while the client runs, an ephemeral client has only the one
+// it started from
+#[tauri::command]
+async fn export_header_snapshot(
+    state: tauri::State<'_, Mutex<AppState>>,
+    path: PathBuf,
+) -> Result<(), String> {
+    let snapshot = {
+        let state_guard = state.lock().await;
+        let saved = checkpoint::read_checkpoint_info(&profile::light_client_dir()).await?.checkpoint;
+        let accepted = state_guard.checkpoint.as_ref().map(|record| record.checkpoint);
+        let ephemeral = state_guard.config.as_ref().is_some_and(|c| c.ephemeral);
+        let checkpoint = if ephemeral { accepted } else { saved.or(accepted) };
+        let checkpoint = checkpoint.ok_or("No checkpoint to export yet")?;
+        checkpoint::HeaderSnapshot::new(state_guard.chain_id, checkpoint, state_guard.header_store.all().cloned().collect())
+    };
+    checkpoint::write_snapshot(&path, &snapshot).await
+}
+
+// Makes a snapshot's checkpoint the one the light client starts from and restarts it if running.
+// Headers are staged for an empty header store and only join it once sync links them to a header
+// the light client verified, which needs the chain not to have moved past the light client's
+// window since the export. Pinned checkpoint services still take precedence at launch
+#[tauri::command]
+async fn import_header_snapshot(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    path: PathBuf,
+) -> Result<checkpoint::SnapshotImport, String> {
+    let snapshot = checkpoint::read_snapshot(&path).await?;
+    {
+        let state_guard = state.lock().await;
+        if snapshot.chain_id != state_guard.chain_id {
+            return Err(format!("Snapshot is for chain {}, the light client follows chain {}", snapshot.chain_id, state_guard.chain_id));
+        }
+        if state_guard.config.as_ref().is_some_and(|c| c.ephemeral) {
+            return Err("An ephemeral light client has no data dir to import a checkpoint into".to_string());
+        }
+    }
+
+    // Shutting down makes helios save its own checkpoint, so the imported one is written after
+    let config = stop_client(&state).await;
+    checkpoint::save_checkpoint(&profile::light_client_dir(), snapshot.checkpoint).await?;
+
+    let headers = {
+        let mut state_guard = state.lock().await;
+        if state_guard.header_store.is_empty() {
+            state_guard.header_store.stage(snapshot.headers)
+        } else {
+            0
+        }
+    };
+
+    let restarted = config.is_some();
+    if let Some(config) = config {
+        relaunch_client(&app, &state, config).await?;
+    }
+    Ok(checkpoint::SnapshotImport { checkpoint: snapshot.checkpoint, headers, restarted })
+}
+
+#[tauri::command]
+async fn list_profiles(app: tauri::AppHandle) -> Result<profile::ProfileList, String> {
+    Ok(profile::list(&app).await)
+}
+
+// Tears down everything belonging to the active profile, then loads `name`'s settings into a fresh
+// state, creating the profile if it's new. The light client is left stopped for the UI to start on
+// the new profile's network
+#[tauri::command]
+async fn switch_profile(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    auth: tauri::State<'_, auth::AuthGate>,
+    prompts: tauri::State<'_, prompts::Prompts>,
+    name: String,
+) -> Result<profile::ProfileList, String> {
+    profile::validate_name(&name)?;
+    if name != profile::active() {
+        // Another profile means other keys and settings, so it takes the same confirmation as signing
+        auth.authorize("Switch profile").await?;
+        shutdown(&state).await;
+        prompts.reject_all();
+        auth.forget();
+
+        let mut state_guard = state.lock().await;
+        if let Some(server) = state_guard.rpc_server.take() {
+            server.stop();
+        }
+        if let Some(stream) = state_guard.pending_stream.take() {
+            stream.abort();
+        }
+        // Dapps connected under the old profile see no accounts from now on
+        for connection in state_guard.connections.list() {
+            accounts::emit_accounts_changed(&app, &connection, &[]);
+        }
+        if state_guard.wallet.lock() {
+            let _ = app.emit("wallet-locked", json!({ "reason": "profile" }));
+        }
+
+        *state_guard = AppState::default();
+        profile::set_active(&name);
+        settings::restore(&app, &mut state_guard).await;
+        auth.restore(&app).await;
+        drop(state_guard);
+
+        profile::remember(&app, &name).await?;
+        tracing::info!("Switched to profile {}", name);
+        let _ = app.emit("profile-changed", json!({ "profile": name }));
+    }
+    Ok(profile::list(&app).await)
+}
+
+#[tauri::command]
+async fn aggregate_calls(
+    state: tauri::State<'_, Mutex<AppState>>,
+    calls: Vec<multicall::AggregateCall>,
+) -> Result<Vec<multicall::AggregateResult>, String> {
+    let state_guard = state.lock().await;
+    match state_guard.client.as_ref() {
+        Some(client) => multicall::aggregate(client, &calls, BlockTag::Latest).await,
+        None => Err("Light client not initialized".to_string())
+    }
+}
+
+fn sourcify_cache_dir(app: &tauri::AppHandle) -> Option<PathBuf> {
+    profile::cache_dir(app).map(|dir| dir.join("sourcify"))
+}
+
+fn response_cache(app: &tauri::AppHandle) -> Option<PathBuf> {
+    profile::cache_dir(app).map(|dir| dir.join("responses.sqlite"))
+}
+
+fn signature_cache(app: &tauri::AppHandle) -> Option<PathBuf> {
+    profile::cache_dir(app).map(|dir| dir.join("signatures.sqlite"))
+}
+
+fn token_cache(app: &tauri::AppHandle) -> Option<PathBuf> {
+    profile::cache_dir(app).map(|dir| dir.join("tokens.json"))
+}
+
+fn nft_cache_dir(app: &tauri::AppHandle, chain_id: u64) -> Option<PathBuf> {
+    profile::cache_dir(app).map(|dir| dir.join("nft").join(chain_id.to_string()))
+}
+
+async fn load_token_cache(app: &tauri::AppHandle, registry: &mut tokens::TokenRegistry) {
+    if let (false, Some(cache)) = (registry.is_loaded(), token_cache(app)) {
+        registry.load(&cache).await;
+    }
+}
+
+#[tauri::command]
+async fn simulate_transaction(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    tx: alloy::rpc::types::TransactionRequest,
+    block_overrides: Option<evm::BlockOverrides>,
+) -> Result<simulate::TransactionSimulation, String> {
+    let (mut simulation, chain_id) = {
+        let mut state_guard = state.lock().await;
+        let accounts = state_guard.account_source(state_guard.chain_id);
+        let AppState { client, gas_oracle, fee_speed, gas_estimation, pending, .. } = &mut *state_guard;
+        let Some(client) = client.as_deref() else {
+            return Err("Light client not initialized".to_string());
+        };
+        let chain_id = client.chain_id().await;
+        // Nonce and fees are filled the way they will be when signing. Gas stays at the simulator's
+        // cap unless given, an estimate would fail on exactly the reverting transactions the preview
+        // is meant to show
+        let tx = match tx.from {
+            Some(from) => {
+                let quotes = gas_oracle.update(client).await.ok();
+                let unestimated = alloy::rpc::types::TransactionRequest { gas: tx.gas.or(Some(evm::DEFAULT_GAS_CAP as u128)), ..tx.clone() };
+                let filled = signer::fill_transaction(
+                    client,
+                    &accounts,
+                    unestimated,
+                    pending.next_nonce(chain_id, from),
+                    quotes.as_ref().map(|quotes| quotes.tier(*fee_speed)),
+                    &gas_estimation.default,
+                ).await?;
+                alloy::rpc::types::TransactionRequest { gas: tx.gas, ..filled }
+            },
+            None => tx.clone(),
+        };
+        (simulate::simulate_transaction(client, accounts, &tx, block_overrides.as_ref()).await?, chain_id)
+    };
+
+    approvals::label_spenders(&mut simulation.approvals, sourcify_cache_dir(&app).as_deref(), chain_id).await;
+    {
+        let mut state_guard = state.lock().await;
+        load_token_cache(&app, &mut state_guard.tokens).await;
+        for approval in &mut simulation.approvals {
+            approval.token_symbol = state_guard.tokens.get(chain_id, approval.token).map(|t| t.symbol.clone());
+        }
+    }
+    if let (Some(cache), Some(data)) = (signature_cache(&app), tx.input.input()) {
+        simulation.call = decode::describe_call(&cache, data).await.ok().flatten();
+    }
+    Ok(simulation)
+}
+
+#[tauri::command]
+async fn get_contract_metadata(
+    app: tauri::AppHandle,
+    address: Address,
+    chain_id: u64,
+) -> Result<sourcify::ContractMetadata, String> {
+    sourcify::get_contract_metadata(sourcify_cache_dir(&app).as_deref(), address, chain_id).await
+}
+
+// What a transaction awaiting approval calls, for the confirmation prompt. Slow lookups give up so
+// the prompt still shows, with the calldata undecoded
+async fn describe_transaction(
+    app: &tauri::AppHandle,
+    chain_id: u64,
+    tx: &alloy::rpc::types::TransactionRequest,
+) -> Option<decode::DecodedCalldata> {
+    let (registry, signatures, data) = (abi::registry_path(app)?, signature_cache(app)?, tx.input.input()?);
+    let to = tx.to.and_then(|to| to.to().copied());
+    let sourcify = sourcify_cache_dir(app);
+    let decoded = decode::decode_calldata(&registry, sourcify.as_deref(), &signatures, chain_id, to, data);
+    match tokio::time::timeout(DESCRIBE_TIMEOUT, decoded).await {
+        Ok(Ok(call)) => call,
+        Ok(Err(e)) => {
+            tracing::warn!("Failed to decode calldata: {}", e);
+            None
+        },
+        Err(_) => {
+            tracing::warn!("Decoding calldata timed out after {}s", DESCRIBE_TIMEOUT.as_secs());
+            None
+        },
+    }
+}
+
+// What calldata sent to `to` on `chain_id` calls, for the calldata inspector
+#[tauri::command]
+async fn decode_calldata(
+    app: tauri::AppHandle,
+    to: Option<Address>,
+    data: alloy::primitives::Bytes,
+    chain_id: u64,
+) -> Result<Option<decode::DecodedCalldata>, String> {
+    let registry = abi::registry_path(&app).ok_or("No data dir")?;
+    let signatures = signature_cache(&app).ok_or("No cache dir")?;
+    decode::decode_calldata(&registry, sourcify_cache_dir(&app).as_deref(), &signatures, chain_id, to, &data).await
+}
+
+#[tauri::command]
+async fn register_abi(
+    app: tauri::AppHandle,
+    chain_id: u64,
+    address: Address,
+    abi: alloy::json_abi::JsonAbi,
+    name: Option<String>,
+) -> Result<(), String> {
+    let path = abi::registry_path(&app).ok_or("No data dir")?;
+    abi::AbiRegistry::open(&path)?.register(chain_id, address, name.as_deref(), &abi, abi::AbiSource::User)
+}
+
+#[tauri::command]
+async fn remove_abi(app: tauri::AppHandle, chain_id: u64, address: Address) -> Result<bool, String> {
+    let path = abi::registry_path(&app).ok_or("No data dir")?;
+    abi::AbiRegistry::open(&path)?.remove(chain_id, address)
+}
+
+#[tauri::command]
+async fn list_abis(app: tauri::AppHandle, chain_id: Option<u64>) -> Result<Vec<abi::RegisteredAbi>, String> {
+    let path = abi::registry_path(&app).ok_or("No data dir")?;
+    abi::AbiRegistry::open(&path)?.list(chain_id)
+}
+
+// eth_getLogs on the app's chain with each log decoded by its contract's ABI, from the registry or
+// else Sourcify. Logs of contracts without a known ABI come back undecoded
+#[tauri::command]
+async fn get_decoded_logs(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    filter: alloy::rpc::types::Filter,
+) -> Result<Vec<abi::DecodedLog>, String> {
+    let (upstream, chain_id) = {
+        let state_guard = state.lock().await;
+        let chain_id = state_guard.chain_id;
+        (state_guard.upstream(chain_id), chain_id)
+    };
+    let upstream = upstream.ok_or("Light client not initialized")?;
+    let logs = retry::with_retry(&upstream.retry_policy, "eth_getLogs", || upstream.client().get_logs(&filter))
+        .await
+        .map_err(|e| format!("Failed to get logs: {}", e))?;
+
+    let mut registry = abi::AbiRegistry::open(&abi::registry_path(&app).ok_or("No data dir")?)?;
+    let sourcify_cache = sourcify_cache_dir(&app);
+    let mut abis = std::collections::HashMap::new();
+    let mut decoded = Vec::with_capacity(logs.len());
+    for log in logs {
+        let address = log.address();
+        if !abis.contains_key(&address) {
+            let abi = registry.resolve(sourcify_cache.as_deref(), chain_id, address)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::warn!("No ABI for 0x{:x}: {}", address, e);
+                    None
+                });
+            abis.insert(address, abi);
+        }
+        let event = abis[&address].as_ref().and_then(|abi| abi::decode_log(abi, log.topics(), &log.data().data));
+        decoded.push(abi::DecodedLog { log, decoded: event });
+    }
+    Ok(decoded)
+}
+
+#[tauri::command]
+async fn set_token_lists(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    urls: Vec<String>,
+) -> Result<(), String> {
+    let mut state_guard = state.lock().await;
+    state_guard.token_lists = urls;
+    settings::commit(&app, &state_guard, "rpc.tokenLists").await
+}
+
+// Fetches every configured list and merges the valid entries into the local token cache
+#[tauri::command]
+async fn refresh_token_lists(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+) -> Result<usize, String> {
+    let urls = state.lock().await.token_lists.clone();
+
+    let mut fetched = Vec::new();
+    let mut errors = Vec::new();
+    for url in &urls {
+        match tokens::fetch_list(url).await {
+            Ok(list) => fetched.extend(list),
+            Err(e) => errors.push(e),
+        }
+    }
+    if fetched.is_empty() && !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+
+    let count = fetched.len();
+    let mut state_guard = state.lock().await;
+    load_token_cache(&app, &mut state_guard.tokens).await;
+    state_guard.tokens.extend(fetched);
+    if let Some(cache) = token_cache(&app) {
+        state_guard.tokens.save(&cache).await?;
+    }
+    Ok(count)
+}
+
+#[tauri::command]
+async fn search_tokens(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    query: String,
+    chain_id: Option<u64>,
+) -> Result<Vec<tokens::TokenInfo>, String> {
+    let mut state_guard = state.lock().await;
+    load_token_cache(&app, &mut state_guard.tokens).await;
+    Ok(state_guard.tokens.search(&query, chain_id))
+}
+
+// Token lists first, then the contract itself through verified calls
+#[tauri::command]
+async fn get_token_metadata(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    address: Address,
+    chain_id: u64,
+) -> Result<tokens::TokenInfo, String> {
+    let mut state_guard = state.lock().await;
+    load_token_cache(&app, &mut state_guard.tokens).await;
+    if let Some(token) = state_guard.tokens.get(chain_id, address) {
+        return Ok(token.clone());
+    }
+
+    let token = match state_guard.client.as_ref() {
+        Some(client) if client.chain_id().await == chain_id => tokens::fetch_onchain(client, address, chain_id).await?,
+        _ => return Err(format!("Unknown token 0x{:x} on chain {}", address, chain_id)),
+    };
+    state_guard.tokens.extend([token.clone()]);
+    if let Some(cache) = token_cache(&app) {
+        let _ = state_guard.tokens.save(&cache).await;
+    }
+    Ok(token)
+}
+
+// Explicit token sets report every balance, the token-list fallback only non-zero ones
+#[tauri::command]
+async fn get_token_balances(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    address: Address,
+    tokens: Option<Vec<Address>>,
+) -> Result<Vec<balances::TokenBalance>, String> {
+    let mut state_guard = state.lock().await;
+    load_token_cache(&app, &mut state_guard.tokens).await;
+    let Some(client) = state_guard.client.as_ref() else {
+        return Err("Light client not initialized".to_string());
+    };
+    let chain_id = client.chain_id().await;
+
+    let (token_infos, include_zero) = match tokens {
+        Some(addresses) => {
+            let mut infos = Vec::with_capacity(addresses.len());
+            for token in addresses {
+                match state_guard.tokens.get(chain_id, token) {
+                    Some(info) => infos.push(info.clone()),
+                    None => infos.push(tokens::fetch_onchain(client, token, chain_id).await?),
+                }
+            }
+            (infos, true)
+        },
+        None => (state_guard.tokens.for_chain(chain_id), false),
+    };
+
+    balances::token_balances(client, address, token_infos, include_zero).await
+}
+
+// Queries the app's chain and every chain a window started, concurrently
+#[tauri::command]
+async fn get_portfolio(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    address: Address,
+) -> Result<portfolio::Portfolio, String> {
+    let mut state_guard = state.lock().await;
+    load_token_cache(&app, &mut state_guard.tokens).await;
+    if state_guard.client.is_none() {
+        return Err("Light client not initialized".to_string());
+    }
+    Ok(collect_portfolio(&state_guard, address).await)
+}
+
+// One entry per running chain, each read through that chain's account source with the token-list
+// entries for that chain
+async fn collect_portfolio(state_guard: &AppState, address: Address) -> portfolio::Portfolio {
+    let chains = futures::future::join_all(state_guard.running_clients().map(|(chain_id, client)| {
+        let accounts = state_guard.account_source(chain_id);
+        let tokens = state_guard.tokens.for_chain(chain_id);
+        async move { portfolio::chain_portfolio(client, &accounts, address, tokens).await }
+    }))
+    .await;
+    portfolio::Portfolio { address, chains }
+}
+
+#[tauri::command]
+async fn set_price_feeds(
+    state: tauri::State<'_, Mutex<AppState>>,
+    feeds: Vec<prices::PriceFeed>,
+) -> Result<(), String> {
+    let mut state_guard = state.lock().await;
+    state_guard.price_feeds = feeds;
+    state_guard.price_cache.clear();
+    Ok(())
+}
+
+// USD prices straight from Chainlink aggregators through verified calls
+#[tauri::command]
+async fn get_prices(
+    state: tauri::State<'_, Mutex<AppState>>,
+    symbols: Vec<String>,
+) -> Result<Vec<prices::Price>, String> {
+    let mut state_guard = state.lock().await;
+    let AppState { client, price_feeds, price_cache, .. } = &mut *state_guard;
+    match client.as_ref() {
+        Some(client) => prices::get_prices(client, price_feeds, price_cache, &symbols).await,
+        None => Err("Light client not initialized".to_string())
+    }
+}
+
+// Served from the background oracle, only recomputed here when a new head arrived since its last run
+#[tauri::command]
+async fn get_gas_quotes(state: tauri::State<'_, Mutex<AppState>>) -> Result<gas::GasQuotes, String> {
+    let mut state_guard = state.lock().await;
+    let AppState { client, gas_oracle, .. } = &mut *state_guard;
+    match client.as_ref() {
+        Some(client) => gas_oracle.update(client).await,
+        None => Err("Light client not initialized".to_string())
+    }
+}
+
+// Replacements come back unsigned for the approval UI to sign and broadcast
+#[tauri::command]
+async fn speed_up_transaction(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    gate: tauri::State<'_, auth::AuthGate>,
+    hash: B256,
+    new_fees: Option<replace::FeeOverride>,
+) -> Result<replace::Replacement, String> {
+    let replacement = {
+        let mut state_guard = state.lock().await;
+        state_guard.check_writable()?;
+        let accounts = state_guard.account_source(state_guard.chain_id);
+        let AppState { client, rpc_url, gas_oracle, .. } = &mut *state_guard;
+        let Some(client) = client.as_ref() else {
+            return Err("Light client not initialized".to_string());
+        };
+        let quotes = gas_oracle.update(client).await.ok();
+        replace::speed_up(client, &accounts, rpc_url, hash, new_fees, quotes.as_ref()).await?
+    };
+    send_replacement(&app, &state, &gate, replacement).await
+}
+
+#[tauri::command]
+async fn cancel_transaction(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    gate: tauri::State<'_, auth::AuthGate>,
+    hash: B256,
+) -> Result<replace::Replacement, String> {
+    let replacement = {
+        let mut state_guard = state.lock().await;
+        state_guard.check_writable()?;
+        let accounts = state_guard.account_source(state_guard.chain_id);
+        let AppState { client, rpc_url, gas_oracle, .. } = &mut *state_guard;
+        let Some(client) = client.as_ref() else {
+            return Err("Light client not initialized".to_string());
+        };
+        let quotes = gas_oracle.update(client).await.ok();
+        replace::cancel(client, &accounts, rpc_url, hash, quotes.as_ref()).await?
+    };
+    send_replacement(&app, &state, &gate, replacement).await
+}
+
+// Signs and broadcasts a replacement the way the original went out, publicly or through the
+// private relay. The pending tracker files it as another attempt at the same nonce
+async fn send_replacement(
+    app: &tauri::AppHandle,
+    state: &Mutex<AppState>,
+    gate: &auth::AuthGate,
+    mut replacement: replace::Replacement,
+) -> Result<replace::Replacement, String> {
+    let from = replacement.transaction.from.ok_or("Replacement has no sender")?;
+    let reason = match replacement.kind {
+        replace::ReplacementKind::SpeedUp => "Speed up a transaction",
+        replace::ReplacementKind::Cancel => "Cancel a transaction",
+    };
+    let authorization = gate.authorize(reason).await?;
+
+    let mut state_guard = state.lock().await;
+    state_guard.check_writable()?;
+    state_guard.wallet.touch();
+    let signer = state_guard.wallet.signer(from).ok_or_else(|| format!("0x{:x} is locked", from))?;
+    let raw = signer::sign_transaction(signer, replacement.transaction.clone(), &authorization).await?;
+
+    pending::load_tracker(app, &mut state_guard.pending).await;
+    let private = state_guard.pending.is_private(replacement.original_hash);
+    let chain_id = state_guard.chain_id;
+    let hash = broadcast_transaction(app, &mut state_guard, chain_id, &raw, Some(private), "eth_sendRawTransaction").await?;
+    replacement.hash = Some(hash);
+    Ok(replacement)
+}
+
+#[tauri::command]
+async fn list_pending_transactions(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    include_finished: Option<bool>,
+) -> Result<Vec<pending::PendingTransaction>, String> {
+    let mut state_guard = state.lock().await;
+    pending::load_tracker(&app, &mut state_guard.pending).await;
+    Ok(state_guard.pending.list(include_finished.unwrap_or(false)))
+}
+
+#[tauri::command]
+async fn set_private_relay(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    enabled: bool,
+    relay_url: Option<String>,
+    status_url: Option<String>,
+) -> Result<(), String> {
+    let mut state_guard = state.lock().await;
+    state_guard.private_relay = protect::RelayConfig {
+        enabled,
+        relay_url: relay_url.unwrap_or_else(|| protect::DEFAULT_RELAY_URL.to_string()),
+        status_url: status_url.unwrap_or_else(|| protect::DEFAULT_STATUS_URL.to_string()),
+    };
+    settings::commit(&app, &state_guard, "privacy.privateRelay").await
+}
+
+#[tauri::command]
+async fn get_private_transaction_status(
+    state: tauri::State<'_, Mutex<AppState>>,
+    hash: B256,
+) -> Result<protect::PrivateTxStatus, String> {
+    let status_url = state.lock().await.private_relay.status_url.clone();
+    protect::get_status(&status_url, hash).await
+}
+
+// Without a key a fresh random identity is generated, its address is returned either way
+#[tauri::command]
+async fn set_bundle_signer(
+    state: tauri::State<'_, Mutex<AppState>>,
+    private_key: Option<String>,
+) -> Result<Address, String> {
+    let signer = match private_key {
+        Some(key) => key.trim().parse::<alloy::signers::local::PrivateKeySigner>()
+            .map_err(|e| format!("Invalid private key: {}", e))?,
+        None => alloy::signers::local::PrivateKeySigner::random(),
+    };
+    let address = signer.address();
+    let mut state_guard = state.lock().await;
+    state_guard.check_writable()?;
+    state_guard.bundle_signer = Some(signer);
+    Ok(address)
+}
+
+#[tauri::command]
+async fn send_bundle(
+    state: tauri::State<'_, Mutex<AppState>>,
+    transactions: Vec<bundle::BundleTransaction>,
+    block: Option<u64>,
+    max_block: Option<u64>,
+    relay_url: Option<String>,
+) -> Result<bundle::BundleResult, String> {
+    let state_guard = state.lock().await;
+    state_guard.check_writable()?;
+    let Some(client) = state_guard.client.as_ref() else {
+        return Err("Light client not initialized".to_string());
+    };
+    let signer = state_guard.bundle_signer.as_ref().ok_or("No bundle signing key configured")?;
+    let head = client.get_block_by_number(BlockTag::Latest, false)
+        .await
+        .map_err(|e| format!("Failed to get latest block: {}", e))?
+        .ok_or("Latest block is not available")?
+        .number
+        .to::<u64>();
+    let (block, max_block) = bundle::inclusion_range(head, block, max_block)?;
+    let relay_url = relay_url.unwrap_or_else(|| bundle::DEFAULT_BUNDLE_RELAY.to_string());
+    bundle::send_bundle(&relay_url, signer, &transactions, block, max_block).await
+}
+
+#[tauri::command]
+async fn set_bundler(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    chain_id: u64,
+    url: Option<String>,
+) -> Result<(), String> {
+    let mut state_guard = state.lock().await;
+    match url {
+        Some(url) => {
+            url::Url::parse(&url).map_err(|e| format!("Invalid bundler URL: {}", e))?;
+            state_guard.bundlers.insert(chain_id, url);
+        },
+        None => {
+            state_guard.bundlers.remove(&chain_id);
+        },
+    }
+    settings::commit(&app, &state_guard, "rpc.bundlers").await
+}
+
+// Answers an `approval-request` event raised by a signing method
+#[tauri::command]
+async fn resolve_prompt(
+    prompts: tauri::State<'_, prompts::Prompts>,
+    id: u64,
+    approved: bool,
+) -> Result<(), String> {
+    if prompts.resolve(id, approved) {
+        Ok(())
+    } else {
+        Err(format!("No pending prompt with id {}", id))
+    }
+}
+
+// Encrypts a raw private key into the keystore and unlocks it for signing
+#[tauri::command]
+async fn import_private_key(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    private_key: String,
+    password: String,
+    label: Option<String>,
+) -> Result<keystore::StoredAccount, String> {
+    if password.is_empty() {
+        return Err("A password is required to encrypt the key".to_string());
+    }
+    let signer = keystore::parse_private_key(&private_key)?;
+    let address = signer.address();
+    let dir = keystore::keystore_dir(&app).ok_or("App data dir unavailable")?;
+
+    {
+        let mut state_guard = state.lock().await;
+        state_guard.check_writable()?;
+        keystore::load_keystore(&app, &mut state_guard.keystore).await;
+        if state_guard.keystore.contains(address) {
+            return Err(format!("0x{:x} is already in the keystore", address));
+        }
+    }
+
+    // Encrypting takes a while, so it happens without holding the state lock
+    let file = keystore::encrypt_key(&dir, &signer, password).await?;
+    let account = keystore::StoredAccount { address, label, imported: true, file };
+
+    let mut state_guard = state.lock().await;
+    state_guard.keystore.add(account.clone());
+    state_guard.keystore.save(&dir).await?;
+    state_guard.wallet.add(signer);
+    Ok(account)
+}
+
+// Decrypts every keystore account the password opens, accounts encrypted under another password stay locked
+#[tauri::command]
+async fn unlock_wallet(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    password: String,
+) -> Result<Vec<Address>, String> {
+    let dir = keystore::keystore_dir(&app).ok_or("App data dir unavailable")?;
+    let stored = {
+        let mut state_guard = state.lock().await;
+        state_guard.check_writable()?;
+        keystore::load_keystore(&app, &mut state_guard.keystore).await;
+        state_guard.keystore.accounts().to_vec()
+    };
+    if stored.is_empty() {
+        return Err("No accounts in the keystore".to_string());
+    }
+
+    let mut signers = Vec::new();
+    for account in &stored {
+        if let Ok(signer) = keystore::decrypt_key(&dir, &account.file, password.clone()).await {
+            signers.push(signer);
+        }
+    }
+    if signers.is_empty() {
+        return Err("Incorrect password".to_string());
+    }
+
+    let mut state_guard = state.lock().await;
+    for signer in signers {
+        state_guard.wallet.add(signer);
+    }
+    let unlocked = state_guard.wallet.addresses();
+    let _ = app.emit("wallet-unlocked", &unlocked);
+    Ok(unlocked)
+}
+
+#[tauri::command]
+async fn lock_wallet(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+) -> Result<(), String> {
+    let mut state_guard = state.lock().await;
+    if state_guard.wallet.lock() {
+        let _ = app.emit("wallet-locked", json!({ "reason": "user" }));
+    }
+    Ok(())
+}
+
+// Idle minutes before the wallet locks itself, None turns auto-lock off. Turning it off or
+// raising the timeout needs the user to authenticate
+#[tauri::command]
+async fn set_auto_lock(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    gate: tauri::State<'_, auth::AuthGate>,
+    minutes: Option<u64>,
+) -> Result<(), String> {
+    let next = signer::WalletSettings { auto_lock_minutes: minutes };
+    let auto_lock = next.auto_lock()?;
+    if next.loosens(&settings::Settings::from_state(&*state.lock().await).wallet) {
+        gate.reauthorize("Extend auto-lock").await?;
+    }
+    let mut state_guard = state.lock().await;
+    state_guard.wallet.set_auto_lock(auto_lock);
+    state_guard.wallet.touch();
+    settings::commit(&app, &state_guard, "wallet.autoLockMinutes").await
+}
+
+// Writes keystore accounts, address book, settings, networks and permissions to one file
+// encrypted under `password`
+#[tauri::command]
+async fn export_backup(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    path: PathBuf,
+    password: String,
+) -> Result<(), String> {
+    if password.is_empty() {
+        return Err("A password is required to encrypt the backup".to_string());
+    }
+    let backup = {
+        let mut state_guard = state.lock().await;
+        state_guard.check_writable()?;
+        backup::collect(&app, &mut state_guard).await?
+    };
+    backup::write(path, &backup, password).await
+}
+
+// Merges a backup into this install, imported accounts stay locked until unlocked with their own password
+#[tauri::command]
+async fn import_backup(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    gate: tauri::State<'_, auth::AuthGate>,
+    path: PathBuf,
+    password: String,
+) -> Result<backup::ImportSummary, String> {
+    state.lock().await.check_writable()?;
+    let backup = backup::read(path, password).await?;
+    let current = settings::Settings::from_state(&*state.lock().await);
+    if let Some(reason) = backup.settings.weakens(&current) {
+        gate.reauthorize(reason).await?;
+    }
+    let mut state_guard = state.lock().await;
+    backup::merge(&app, &mut state_guard, backup).await
+}
+
+// Changes which unlocked account an origin sees first, notifying only that origin's webviews
+#[tauri::command]
+async fn switch_account(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    origin: String,
+    address: Address,
+) -> Result<Vec<Address>, String> {
+    let mut state_guard = state.lock().await;
+    if state_guard.wallet.signer(address).is_none() {
+        return Err(format!("0x{:x} is not an unlocked account", address));
+    }
+    let connection = state_guard.connections.set_active(&origin, address)?;
+    accounts::emit_accounts_changed(&app, connection, &connection.accounts);
+    Ok(connection.accounts.clone())
+}
+
+#[tauri::command]
+async fn list_sessions(state: tauri::State<'_, Mutex<AppState>>) -> Result<Vec<accounts::Connection>, String> {
+    Ok(state.lock().await.connections.list())
+}
+
+// Disconnects an origin, its webviews see no accounts from now on
+#[tauri::command]
+async fn revoke_session(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    origin: String,
+) -> Result<bool, String> {
+    let revoked = state.lock().await.connections.revoke(&origin);
+    if let Some(connection) = &revoked {
+        accounts::emit_accounts_changed(&app, connection, &[]);
+    }
+    Ok(revoked.is_some())
+}
+
+#[tauri::command]
+async fn set_policy(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    gate: tauri::State<'_, auth::AuthGate>,
+    account: Address,
+    policy: policy::Policy,
+) -> Result<(), String> {
+    // An edit can loosen a limit as easily as tighten it, so it always asks
+    gate.reauthorize("Change a transaction policy").await?;
+    let mut state_guard = state.lock().await;
+    policy::load_policies(&app, &mut state_guard.policies).await;
+    state_guard.policies.set(account, policy);
+    let path = policy::policy_path(&app).ok_or("App data dir unavailable")?;
+    state_guard.policies.save(&path).await
+}
+
+#[tauri::command]
+async fn remove_policy(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    gate: tauri::State<'_, auth::AuthGate>,
+    account: Address,
+) -> Result<bool, String> {
+    gate.reauthorize("Remove a transaction policy").await?;
+    let mut state_guard = state.lock().await;
+    policy::load_policies(&app, &mut state_guard.policies).await;
+    let removed = state_guard.policies.remove(account);
+    if removed {
+        let path = policy::policy_path(&app).ok_or("App data dir unavailable")?;
+        state_guard.policies.save(&path).await?;
+    }
+    Ok(removed)
+}
+
+#[tauri::command]
+async fn list_policies(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+) -> Result<HashMap<Address, policy::Policy>, String> {
+    let mut state_guard = state.lock().await;
+    policy::load_policies(&app, &mut state_guard.policies).await;
+    Ok(state_guard.policies.list())
+}
+
+#[tauri::command]
+async fn list_walletconnect_pairings(state: tauri::State<'_, Mutex<AppState>>) -> Result<Vec<walletconnect::Pairing>, String> {
+    Ok(state.lock().await.walletconnect.list())
+}
+
+// History lives in one database per chain, so these need a configured client
+async fn history_db(app: &tauri::AppHandle, state: &tauri::State<'_, Mutex<AppState>>) -> Result<history::HistoryDb, String> {
+    let chain_id = state.lock().await.config.as_ref().map(|config| config.chain_id);
+    let path = chain_id
+        .and_then(|chain_id| history::history_path(app, chain_id))
+        .ok_or("Light client not initialized")?;
+    history::HistoryDb::open(&path)
+}
+
+// Indexing starts from the current head, earlier history isn't backfilled
+#[tauri::command]
+async fn track_history_address(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    address: Address,
+) -> Result<(), String> {
+    history_db(&app, &state).await?.track(address)
+}
+
+#[tauri::command]
+async fn untrack_history_address(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    address: Address,
+) -> Result<(), String> {
+    history_db(&app, &state).await?.untrack(address)
+}
+
+#[tauri::command]
+async fn get_history(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    address: Address,
+    filter: Option<history::HistoryFilter>,
+) -> Result<history::HistoryPage, String> {
+    let mut page = history_db(&app, &state).await?.query(address, &filter.unwrap_or_default())?;
+    let chain_id = state.lock().await.chain_id;
+    if let Some(registry) = abi::registry_path(&app).and_then(|path| abi::AbiRegistry::open(&path).ok()) {
+        history::decode_logs(&mut page, &registry, chain_id);
+    }
+    Ok(page)
+}
+
+// Watched addresses are also tracked by the history indexer when a client is configured
+#[tauri::command]
+async fn watch_address(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    watched: watch::WatchedAddress,
+) -> Result<(), String> {
+    let address = watched.address;
+    {
+        let mut state_guard = state.lock().await;
+        watch::load_watch_list(&app, &mut state_guard.watched).await;
+        state_guard.watched.upsert(watched);
+        if let Some(path) = watch::watch_list_path(&app) {
+            state_guard.watched.save(&path).await?;
+        }
+    }
+    if let Ok(db) = history_db(&app, &state).await {
+        db.track(address)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn unwatch_address(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    address: Address,
+) -> Result<bool, String> {
+    let mut state_guard = state.lock().await;
+    watch::load_watch_list(&app, &mut state_guard.watched).await;
+    let removed = state_guard.watched.remove(address);
+    if let (true, Some(path)) = (removed, watch::watch_list_path(&app)) {
+        state_guard.watched.save(&path).await?;
+    }
+    Ok(removed)
+}
+
+#[tauri::command]
+async fn list_watched_addresses(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+) -> Result<Vec<watch::WatchedAddress>, String> {
+    let mut state_guard = state.lock().await;
+    watch::load_watch_list(&app, &mut state_guard.watched).await;
+    Ok(state_guard.watched.list().to_vec())
+}
+
+#[tauri::command]
+async fn nft_verify_ownership(
+    state: tauri::State<'_, Mutex<AppState>>,
+    owner: Address,
+    contract: Address,
+    token_id: U256,
+) -> Result<nft::NftOwnership, String> {
+    let state_guard = state.lock().await;
+    match state_guard.client.as_ref() {
+        Some(client) => nft::verify_ownership(client, owner, contract, token_id).await,
+        None => Err("Light client not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+async fn nft_list_owned(
+    state: tauri::State<'_, Mutex<AppState>>,
+    owner: Address,
+    contract: Address,
+) -> Result<nft::OwnedNfts, String> {
+    let state_guard = state.lock().await;
+    match state_guard.client.as_ref() {
+        Some(client) => nft::list_owned(client, owner, contract).await,
+        None => Err("Light client not initialized".to_string())
+    }
+}
+
+// tokenURI is read through a verified call, IPFS metadata is checked against its CID
+#[tauri::command]
+async fn nft_get_metadata(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    contract: Address,
+    token_id: U256,
+) -> Result<nft::NftMetadata, String> {
+    let state_guard = state.lock().await;
+    let Some(client) = state_guard.client.as_ref() else {
+        return Err("Light client not initialized".to_string());
+    };
+    let cache_dir = nft_cache_dir(&app, client.chain_id().await);
+    let fetcher = ipfs::IpfsFetcher::new(
+        state_guard.ipfs_gateways.clone(),
+        profile::cache_dir(&app).map(|dir| dir.join("ipfs")),
+    );
+    nft::get_metadata(client, &fetcher, cache_dir.as_deref(), contract, token_id).await
+}
+
+#[tauri::command]
+async fn ens_resolve(state: tauri::State<'_, Mutex<AppState>>, name: String) -> Result<Option<Address>, String> {
+    let state_guard = state.lock().await;
+    match state_guard.client.as_ref() {
+        Some(client) => ens::resolve_address(client, &name).await,
+        None => Err("Light client not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+async fn ens_lookup(state: tauri::State<'_, Mutex<AppState>>, address: Address) -> Result<Option<String>, String> {
+    let state_guard = state.lock().await;
+    match state_guard.client.as_ref() {
+        Some(client) => ens::lookup_address(client, address).await,
+        None => Err("Light client not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+async fn parse_payment_uri(app: tauri::AppHandle, uri: String) -> Result<eip681::PaymentRequest, String> {
+    eip681::resolve(&app, &uri).await
+}
+
+// Parses and checks a Sign-In with Ethereum message, then hands it to the dedicated approval UI
+#[tauri::command]
+async fn siwe_parse_and_verify(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    message: String,
+    origin: String,
+) -> Result<siwe::SiweVerification, String> {
+    let parsed = siwe::parse(&message)?;
+    let chain_id = state.lock().await.config.as_ref().map(|config| config.chain_id);
+    let verification = siwe::verify(parsed, &origin, chain_id);
+    let _ = app.emit("siwe-request", verification.clone());
+    Ok(verification)
+}
+
+#[tauri::command]
+async fn set_ipfs_gateways(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    gateways: Vec<String>,
+) -> Result<(), String> {
+    if gateways.is_empty() {
+        return Err("At least one IPFS gateway is required".to_string());
+    }
+    let mut state_guard = state.lock().await;
+    state_guard.ipfs_gateways = gateways;
+    settings::commit(&app, &state_guard, "rpc.ipfsGateways").await
+}
+
+#[tauri::command]
+async fn set_retry_policy(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    policy: retry::RetryPolicy,
+) -> Result<(), String> {
+    policy.validate()?;
+    let mut state_guard = state.lock().await;
+    state_guard.retry_policy = policy;
+    settings::commit(&app, &state_guard, "rpc.retry").await
+}
+
+// Local re-execution is slow and fetches a lot of state, so tracing is opt-in
+#[tauri::command]
+async fn set_local_tracing(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    enabled: bool,
+) -> Result<(), String> {
+    let mut state_guard = state.lock().await;
+    state_guard.local_tracing = enabled;
+    settings::commit(&app, &state_guard, "features.localTracing").await
+}
+
+// eth_sign lets a site get an arbitrary hash signed blind, so it needs an explicit opt-in
+#[tauri::command]
+async fn set_allow_eth_sign(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    gate: tauri::State<'_, auth::AuthGate>,
+    enabled: bool,
+) -> Result<(), String> {
+    if enabled && !state.lock().await.allow_eth_sign {
+        gate.reauthorize("Allow eth_sign").await?;
+    }
+    let mut state_guard = state.lock().await;
+    state_guard.allow_eth_sign = enabled;
+    settings::commit(&app, &state_guard, "features.allowEthSign").await
+}
+
+// Refuses signing, sending and key management from dapps and the UI alike. Turning it on locks
+// the wallet, so no unlocked key stays in memory. Turning it off needs the user to authenticate
+#[tauri::command]
+async fn set_read_only(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    gate: tauri::State<'_, auth::AuthGate>,
+    enabled: bool,
+) -> Result<(), String> {
+    if !enabled && state.lock().await.read_only {
+        gate.reauthorize("Turn off read-only mode").await?;
+    }
+    let mut state_guard = state.lock().await;
+    state_guard.read_only = enabled;
+    if enabled {
+        state_guard.bundle_signer = None;
+        if state_guard.wallet.lock() {
+            let _ = app.emit("wallet-locked", json!({ "reason": "readOnly" }));
+        }
+    }
+    settings::commit(&app, &state_guard, "features.readOnly").await
+}
+
+// Forwards allowlisted trace/debug methods to the execution RPC without verification
+#[tauri::command]
+async fn set_unverified_passthrough(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    gate: tauri::State<'_, auth::AuthGate>,
+    enabled: bool,
+) -> Result<(), String> {
+    if enabled && !state.lock().await.unverified_passthrough {
+        gate.reauthorize("Allow unverified passthrough").await?;
+    }
+    let mut state_guard = state.lock().await;
+    state_guard.unverified_passthrough = enabled;
+    settings::commit(&app, &state_guard, "features.unverifiedPassthrough").await
+}
+
+// Turning authentication off or lengthening the grace period has to be confirmed by the user, or
+// anything able to invoke commands could quietly remove the gate before signing
+#[tauri::command]
+async fn set_auth_settings(
+    app: tauri::AppHandle,
+    gate: tauri::State<'_, auth::AuthGate>,
+    settings: auth::AuthSettings,
+) -> Result<(), String> {
+    if settings.weakens(&gate.settings()) {
+        gate.reauthorize("Weaken signing authentication").await?;
+    }
+    gate.configure(settings);
+    gate.save(&app).await
+}
+
+#[tauri::command]
+async fn set_notification_settings(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    settings: notify::NotificationSettings,
+) -> Result<(), String> {
+    let mut state_guard = state.lock().await;
+    state_guard.notifications = settings;
+    settings::commit(&app, &state_guard, "notifications").await
+}
+
+// Reads one setting by its dotted key, or all of them without one
+#[tauri::command]
+async fn get_setting(
+    state: tauri::State<'_, Mutex<AppState>>,
+    key: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let state_guard = state.lock().await;
+    settings::Settings::from_state(&state_guard).get(key.as_deref().unwrap_or_default())
+}
+
+#[tauri::command]
+async fn set_setting(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    gate: tauri::State<'_, auth::AuthGate>,
+    key: String,
+    value: serde_json::Value,
+) -> Result<(), String> {
+    let weakened = {
+        let current = settings::Settings::from_state(&*state.lock().await);
+        current.with(&key, value.clone())?.weakens(&current)
+    };
+    if let Some(reason) = weakened {
+        gate.reauthorize(reason).await?;
+    }
+    // Rebuilt from the live state, so changes made while the prompt was up aren't reverted
+    let mut state_guard = state.lock().await;
+    let settings = settings::Settings::from_state(&state_guard).with(&key, value)?;
+    settings.apply(&mut state_guard);
+    settings::commit(&app, &state_guard, &key).await
+}
+
+// Latest log entries for the diagnostics panel
+#[tauri::command]
+async fn get_recent_logs(
+    app: tauri::AppHandle,
+    filter: Option<logging::LogFilter>,
+) -> Result<Vec<logging::LogEntry>, String> {
+    logging::recent(&app, filter.unwrap_or_default()).await
+}
+
+// Turns logging up or down without a restart, e.g. `dispatcher` or `helios` to `debug`
+#[tauri::command]
+async fn set_log_level(
+    logging: tauri::State<'_, logging::Logging>,
+    target: Option<String>,
+    level: String,
+) -> Result<(), String> {
+    logging.set_level(target.as_deref(), &level)
+}
+
+#[tauri::command]
+async fn set_cache_policy(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    policy: cache::CachePolicy,
+) -> Result<(), String> {
+    policy.validate()?;
+    let mut state_guard = state.lock().await;
+    if let Some(path) = response_cache(&app) {
+        let max_bytes = policy.responses.max_bytes;
+        app.state::<cache::SharedDiskCache>().with(path, move |db| db.evict(max_bytes)).await?;
+    }
+    state_guard.cache_policy = policy;
+    settings::commit(&app, &state_guard, "rpc.cache").await
+}
+
+// Hit rates and sizes of the response caches, for the storage settings panel
+#[tauri::command]
+async fn get_cache_stats(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+) -> Result<cache::CacheStats, String> {
+    let state_guard = state.lock().await;
+    let (entries, bytes) = match response_cache(&app) {
+        Some(path) => app.state::<cache::SharedDiskCache>().with(path, |db| db.usage()).await?,
+        None => (0, 0),
+    };
+    Ok(cache::CacheStats {
+        responses: cache::CategoryStats {
+            counts: state_guard.disk_cache_counts,
+            entries,
+            bytes: Some(bytes),
+        },
+        reads: cache::CategoryStats {
+            counts: state_guard.response_cache.counts,
+            entries: state_guard.response_cache.entries() as u64,
+            bytes: None,
+        },
+    })
+}
+
+// Empties one of cache::CATEGORIES. The file-backed ones are removed and rebuilt on next use
+#[tauri::command]
+async fn clear_cache(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    category: String,
+) -> Result<(), String> {
+    let mut state_guard = state.lock().await;
+    let cache_dir = profile::cache_dir(&app).ok_or("No cache dir")?;
+    let path = match category.as_str() {
+        "responses" => {
+            if let Some(path) = response_cache(&app) {
+                app.state::<cache::SharedDiskCache>().with(path, |db| db.clear()).await?;
+            }
+            state_guard.disk_cache_counts = cache::HitCounts::default();
+            return Ok(());
+        },
+        "reads" => {
+            state_guard.response_cache.clear();
+            state_guard.response_cache.counts = cache::HitCounts::default();
+            return Ok(());
+        },
+        "prices" => {
+            state_guard.price_cache.clear();
+            return Ok(());
+        },
+        "signatures" => cache_dir.join("signatures.sqlite"),
+        "sourcify" | "nft" | "ipfs" => cache_dir.join(&category),
+        _ => return Err(format!("Unknown cache {}, expected one of {}", category, cache::CATEGORIES.join(", "))),
+    };
+    let removed = if path.is_dir() {
+        tokio::fs::remove_dir_all(&path).await
+    } else {
+        tokio::fs::remove_file(&path).await
+    };
+    match removed {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Failed to clear {} cache: {}", category, e)),
+        _ => Ok(()),
+    }
+}
+
+// Disk used by each kind of data against the storage limit, for the storage settings panel
+#[tauri::command]
+async fn get_storage_usage(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+) -> Result<storage::StorageUsage, String> {
+    let settings = state.lock().await.storage.clone();
+    storage::storage_usage(&app, settings).await
+}
+
+// Points every dapp at a local development node instead of the light client, or back with None.
+// Windows get `chainChanged` for the chain they now see and the UI gets `dev-mode`
+#[tauri::command]
+async fn set_dev_mode(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    url: Option<String>,
+) -> Result<Option<devmode::DevNode>, String> {
+    let node = match url {
+        Some(url) => Some(devmode::connect(&url).await?),
+        None => None,
+    };
+    let mut state_guard = state.lock().await;
+    state_guard.dev_node = node.clone();
+    match &node {
+        Some(node) => {
+            tracing::warn!("Dev mode on, dapps are served unverified by {}", node.url);
+            let _ = app.emit("chainChanged", format!("0x{:x}", node.chain_id));
+        },
+        None => {
+            tracing::info!("Dev mode off");
+            let _ = app.emit("chainChanged", format!("0x{:x}", state_guard.chain_id));
+            for (caller, chain_id) in state_guard.tab_chains.selections() {
+                if let Some(webview) = &caller.webview {
+                    let _ = app.emit_to(tauri::EventTarget::webview(webview), "chainChanged", format!("0x{:x}", chain_id));
+                }
+            }
+        },
+    }
+    let _ = app.emit("dev-mode", &node);
+    Ok(node)
+}
+
+#[tauri::command]
+async fn get_dev_mode(state: tauri::State<'_, Mutex<AppState>>) -> Result<Option<devmode::DevNode>, String> {
+    Ok(state.lock().await.dev_node.clone())
+}
+
+#[tauri::command]
+async fn get_sandbox(state: tauri::State<'_, Mutex<AppState>>) -> Result<Option<sandbox::SandboxStatus>, String> {
+    Ok(state.lock().await.sandbox.as_ref().map(sandbox::Sandbox::status))
+}
+
+// Throws away every sandbox transaction and forks again at the latest verified block
+#[tauri::command]
+async fn reset_sandbox(app: tauri::AppHandle, state: tauri::State<'_, Mutex<AppState>>) -> Result<sandbox::SandboxStatus, String> {
+    let mut state_guard = state.lock().await;
+    let client = state_guard.client.as_deref().ok_or("Light client not initialized")?;
+    let forked = sandbox::Sandbox::fork(client, state_guard.account_source(state_guard.chain_id)).await?;
+    let status = forked.status();
+    tracing::info!("Sandbox reset, forked chain 0x{:x} at block {}", status.parent_chain, status.fork_block);
+    state_guard.sandbox = Some(forked);
+    let _ = app.emit("sandbox", &status);
+    Ok(status)
+}
+
+// Drops the sandbox and moves the windows on it back to the app's chain
+#[tauri::command]
+async fn stop_sandbox(app: tauri::AppHandle, state: tauri::State<'_, Mutex<AppState>>) -> Result<bool, String> {
+    let mut state_guard = state.lock().await;
+    let stopped = state_guard.sandbox.take().is_some();
+    for caller in state_guard.tab_chains.release(sandbox::SANDBOX_CHAIN_ID) {
+        if let Some(webview) = caller.webview.as_deref() {
+            let _ = app.emit_to(tauri::EventTarget::webview(webview), "chainChanged", format!("0x{:x}", state_guard.chain_id));
+        }
+    }
+    let _ = app.emit("sandbox", None::<sandbox::SandboxStatus>);
+    Ok(stopped)
+}
+
+// Whether the upstream was reachable on the last request, for windows opened after the last
+// `connectivity` event
+#[tauri::command]
+async fn get_connectivity(state: tauri::State<'_, Mutex<AppState>>) -> Result<bool, String> {
+    Ok(state.lock().await.connectivity.is_online())
+}
+
+// Request counts, errors and latency per origin and method, plus upstream traffic, for the network activity panel
+#[tauri::command]
+async fn get_rpc_stats(metrics: tauri::State<'_, metrics::Metrics>) -> Result<metrics::RpcStats, String> {
+    Ok(metrics.snapshot())
+}
+
+// Times a fixed verified workload against the current execution RPC, for comparing providers
+#[tauri::command]
+async fn benchmark_rpc(
+    app: tauri::AppHandle,
+    profile: Option<benchmark::BenchmarkProfile>,
+) -> Result<benchmark::BenchmarkReport, String> {
+    benchmark::run(&app, profile.unwrap_or_default()).await
+}
+
+// Serves verified JSON-RPC on localhost for wallets and scripts outside the app
+#[tauri::command]
+async fn start_rpc_server(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    port: Option<u16>,
+) -> Result<String, String> {
+    let mut state_guard = state.lock().await;
+    if let Some(server) = &state_guard.rpc_server {
+        return Err(format!("RPC server is already running on {}", server.addr));
+    }
+    let server = rpc_server::serve(app.clone(), port.unwrap_or(rpc_server::DEFAULT_RPC_PORT)).await?;
+    let addr = format!("http://{}", server.addr);
+    state_guard.rpc_server = Some(server);
+    Ok(addr)
+}
+
+#[tauri::command]
+async fn stop_rpc_server(state: tauri::State<'_, Mutex<AppState>>) -> Result<bool, String> {
+    let server = state.lock().await.rpc_server.take();
+    Ok(server.map(rpc_server::RpcServer::stop).is_some())
+}
+
+#[tauri::command]
+async fn request(app: tauri::AppHandle, webview: tauri::Webview, state: tauri::State<'_, Mutex<AppState>>, request: serde_json::Value) -> Result<serde_json::Value, String> {
+    handle_request(app, accounts::Caller::from_webview(&webview), state, request).await
+}
+
+// Dispatches one JSON-RPC request, shared by the webview command and the local RPC server
+#[tracing::instrument(name = "rpc", skip_all, fields(origin = %caller.origin, method = tracing::field::Empty))]
+async fn handle_request(
+    app: tauri::AppHandle,
+    caller: accounts::Caller,
+    state: tauri::State<'_, Mutex<AppState>>,
+    request: serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    let started = std::time::Instant::now();
+    let method = request.get("method").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    let origin = caller.origin.clone();
+    let params = request.get("params").cloned().unwrap_or(json!([]));
+    let tab_caller = caller.clone();
+    // A dev node's and the sandbox's answers are neither verified nor about a real chain, so they
+    // skip the caches
+    let uncached = {
+        let state_guard = state.lock().await;
+        state_guard.dev_node.is_some() || state_guard.tab_chains.selected(&tab_caller) == Some(sandbox::SANDBOX_CHAIN_ID)
+    };
+    let mut result = dispatch_request(app.clone(), caller, state, request).await;
+    if let Ok(response) = &mut result {
+        if !uncached {
+            answer_offline(&app, &tab_caller, &method, &params, response).await;
+        }
+        // Early answers that didn't tag themselves, like connecting to a dev node, are the app's own
+        provenance::tag(response, provenance::Provenance::Computed);
+    }
+    let failed = result.as_ref().map_or(true, |response| response.get("error").is_some());
+    app.state::<metrics::Metrics>().record(&origin, &method, started.elapsed(), failed);
+    result
+}
+
+// Immutable lookups answered before, in this session or an earlier one, come straight from disk
+async fn cached_answer(
+    app: &tauri::AppHandle,
+    state: &Mutex<AppState>,
+    chain_id: u64,
+    method: &str,
+    params: &serde_json::Value,
+    request: &serde_json::Value,
+) -> Option<serde_json::Value> {
+    if !cache::is_immutable(method, params) || request.get("jsonrpc").and_then(|v| v.as_str()) != Some("2.0") {
+        return None;
+    }
+    let path = response_cache(app)?;
+    let limits = state.lock().await.cache_policy.responses.clone();
+    let (method_owned, params_owned) = (method.to_string(), params.clone());
+    let cached = app.state::<cache::SharedDiskCache>()
+        .with(path, move |db| db.get(chain_id, &method_owned, &params_owned, &limits))
+        .await;
+    let result = match cached {
+        Ok(result) => {
+            state.lock().await.disk_cache_counts.record(result.is_some());
+            result?
+        },
+        Err(e) => {
+            tracing::warn!("{}", e);
+            return None;
+        }
+    };
+    let mut response = json!({"jsonrpc": "2.0", "result": result});
+    if let Some(id) = request.get("id") {
+        response.as_object_mut().unwrap().insert("id".to_string(), id.clone());
+    }
+    // Only verified answers are ever stored
+    provenance::tag(&mut response, provenance::Provenance::Verified);
+    Some(response)
+}
+
+// Stores the answer to an immutable lookup on disk once it can't change any more. How far the chain
+// has finalized comes from the head watcher, which only follows the app's chain, so other chains'
+// transactions aren't stored
+async fn store_immutable(
+    app: &tauri::AppHandle,
+    state_guard: &AppState,
+    chain_id: u64,
+    method: &str,
+    params: &serde_json::Value,
+    result: &serde_json::Value,
+) {
+    if !cache::is_immutable(method, params) {
+        return;
+    }
+    let finalized = state_guard.finalized.filter(|_| chain_id == state_guard.chain_id);
+    if !cache::is_settled(method, result, finalized) {
+        return;
+    }
+    if let Some(path) = response_cache(app) {
+        let limits = state_guard.cache_policy.responses.clone();
+        let (method, params, result) = (method.to_string(), params.clone(), result.clone());
+        let stored = app.state::<cache::SharedDiskCache>()
+            .with(path, move |db| db.put(chain_id, &method, &params, &result, &limits))
+            .await;
+        if let Err(e) = stored {
+            tracing::warn!("{}", e);
+        }
+    }
+}
+
+// Remembers verified read answers, and while the upstream can't be reached answers reads from them
+// marked `stale` instead of failing
+async fn answer_offline(
+    app: &tauri::AppHandle,
+    caller: &accounts::Caller,
+    method: &str,
+    params: &serde_json::Value,
+    response: &mut serde_json::Value,
+) {
+    let state = app.state::<Mutex<AppState>>();
+    let mut state_guard = state.lock().await;
+    let chain_id = state_guard.tab_chains.selected(caller).unwrap_or(state_guard.chain_id);
+    // The header store only follows the app's chain
+    let last_verified = state_guard.header_store.latest()
+        .filter(|_| chain_id == state_guard.chain_id)
+        .map(|header| header.block_number);
+    let AppState { response_cache, connectivity, cache_policy, .. } = &mut *state_guard;
+
+    match response.get("error") {
+        Some(error) if offline::is_connectivity_error(error) => {
+            connectivity.set(app, false, last_verified);
+            response_cache.serve_stale(chain_id, method, params, response, &cache_policy.reads);
+        },
+        Some(_) => {},
+        // Only reads that normally reach the upstream say anything about connectivity
+        None if offline::ResponseCache::is_cacheable(method, response) => {
+            connectivity.set(app, true, last_verified);
+            response_cache.insert(chain_id, method, params, response["result"].clone(), last_verified, &cache_policy.reads);
+        },
+        None => {},
+    }
+    if provenance::is_verified(response) && response.get("stale").is_none() {
+        store_immutable(app, &state_guard, chain_id, method, params, &response["result"]).await;
+    }
+}
+
+// Answers from the execution RPC as-is, with `unverified` set so the caller can tell
+async fn forward_unverified(
+    response: &mut serde_json::Value,
+    rpc_url: &str,
+    method: &str,
+    params: &[serde_json::Value],
+) {
+    match passthrough::forward(rpc_url, method, params).await {
+        Ok(upstream) => {
+            let object = response.as_object_mut().unwrap();
+            match upstream.get("error") {
+                Some(error) => object.insert("error".to_string(), error.clone()),
+                None => object.insert("result".to_string(), upstream.get("result").cloned().unwrap_or(json!(null))),
+            };
+            object.insert("unverified".to_string(), json!(true));
+            provenance::tag(response, provenance::Provenance::Passthrough);
+        },
+        Err(e) => handle_response(response, JsonRpcResult::Error(
+            errors::INTERNAL_ERROR,
+            format!("Internal error: {}", e)
+        ))
+    }
+}
+
+async fn dispatch_request(
+    app: tauri::AppHandle,
+    caller: accounts::Caller,
+    state: tauri::State<'_, Mutex<AppState>>,
+    request: serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    let mut response = json!({"jsonrpc": "2.0"});
+
+    if let Some(id) = request.get("id") {
+        response.as_object_mut().unwrap().insert("id".to_string(), id.clone());
+    }
+
+    let (method, params) = match parse_request(&request) {
+        Ok(parsed) => parsed,
+        Err((code, message)) => {
+            handle_response(&mut response, JsonRpcResult::Error(code, message));
+            return Ok(response);
+        }
+    };
+    let params = &params;
+    tracing::Span::current().record("method", method);
+    tracing::debug!(request = %request, "Handling request");
+
+    if is_write_method(method) && state.lock().await.read_only {
+        handle_response(&mut response, JsonRpcResult::Error(
+            errors::READ_ONLY,
+            format!("{} is unavailable while read-only mode is on", method)
+        ));
+        return Ok(response);
+    }
+
+    let origin = caller.origin.clone();
+    let chain_id = {
+        let mut state_guard = state.lock().await;
+        // Only dapp windows are limited, the local RPC server and benchmarks are the user's own tools
+        if caller.webview.is_some() && !state_guard.rate_limiter.check(&caller) {
+            handle_response(&mut response, JsonRpcResult::Error(
+                errors::LIMIT_EXCEEDED,
+                format!("Rate limit of {} requests per second exceeded", ratelimit::REQUESTS_PER_SECOND)
+            ));
+            return Ok(response);
+        }
+        // A window that moved to another dapp loses the filters it installed for the previous one
+        if let Some(webview) = caller.webview.as_deref() {
+            if state_guard.connections.seen(&origin, Some(webview)) {
+                filters::load_filters(&app, &mut state_guard.filters).await;
+                let stale = state_guard.filters.release_navigated(webview, &origin);
+                if !stale.is_empty() {
+                    filters::save_filters(&app, &state_guard.filters).await;
+                }
+                for filter in stale {
+                    if let (Some(client_id), Some(client)) = (filter.client_id, state_guard.client_for(filter.chain_id)) {
+                        let _ = client.uninstall_filter(client_id).await;
+                    }
+                }
+                state_guard.subscriptions.release_navigated(webview, &origin);
+                state_guard.tab_chains.release_navigated(webview, &origin);
+            }
+        }
+        // The chain this window switched to, or the app's
+        state_guard.tab_chains.selected(&caller).unwrap_or(state_guard.chain_id)
+    };
+
+    let dev_node = state.lock().await.dev_node.clone();
+    let on_sandbox = chain_id == sandbox::SANDBOX_CHAIN_ID;
+    if devmode::node_control(method, dev_node.is_some(), on_sandbox) == Some(devmode::NodeControl::Refused) {
+        let message = if on_sandbox {
+            format!("{} is not available in the sandbox", method)
+        } else {
+            format!("{} controls a development node and needs dev mode or the sandbox", method)
+        };
+        handle_response(&mut response, JsonRpcResult::Error(errors::UNSUPPORTED_METHOD, message));
+        return Ok(response);
+    }
+    if let Some(node) = &dev_node {
+        // Accounts come from the node instead of the wallet, a dapp still only sees them once the
+        // user approves the connection
+        if method == "eth_accounts" {
+            let accounts = state.lock().await.connections.accounts(&origin);
+            handle_response(&mut response, JsonRpcResult::Success(json!(accounts)));
+            return Ok(response);
+        }
+        if method == "eth_requestAccounts" {
+            let connected = state.lock().await.connections.accounts(&origin);
+            if !connected.is_empty() {
+                handle_response(&mut response, JsonRpcResult::Success(json!(connected)));
+                return Ok(response);
+            }
+            let accounts = match devmode::node_accounts(&node.url).await {
+                Ok(accounts) if !accounts.is_empty() => accounts,
+                Ok(_) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::RESOURCE_UNAVAILABLE,
+                        "The development node has no unlocked accounts".to_string()
+                    ));
+                    return Ok(response);
+                },
+                Err(e) => {
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INTERNAL_ERROR, e));
+                    return Ok(response);
+                },
+            };
+            if !prompts::ask(&app, method, json!({ "origin": origin, "accounts": accounts, "devNode": node.url })).await {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::USER_REJECTED,
+                    "User rejected the request".to_string()
+                ));
+                return Ok(response);
+            }
+            state.lock().await.connections.connect(&origin, caller.webview.as_deref(), accounts.clone(), Some(node.chain_id));
+            handle_response(&mut response, JsonRpcResult::Success(json!(accounts)));
+            return Ok(response);
+        }
+        if devmode::is_forwarded(method) {
+            forward_unverified(&mut response, &node.url, method, params).await;
+            return Ok(response);
+        }
+        if method == "wallet_switchEthereumChain" {
+            let target = params[0].get("chainId")
+                .and_then(|id| id.as_str())
+                .and_then(|id| u64::from_str_radix(id.trim_start_matches("0x"), 16).ok());
+            let result = match target {
+                Some(target) if target == node.chain_id => JsonRpcResult::Success(json!(null)),
+                _ => JsonRpcResult::Error(
+                    errors::UNRECOGNIZED_CHAIN,
+                    format!("Dev mode only serves the local node's chain 0x{:x}", node.chain_id)
+                ),
+            };
+            handle_response(&mut response, result);
+            return Ok(response);
+        }
+    }
+
+    // Windows on the sandbox chain are served by the local fork. Sending mines the transaction there
+    // at once, after the usual account checks and confirmation
+    if on_sandbox {
+        let mut sandbox_tx = None;
+        if method == "eth_sendTransaction" {
+            let tx: alloy::rpc::types::TransactionRequest = match serde_json::from_value(params[0].clone()) {
+                Ok(t) => t,
+                Err(e) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INVALID_PARAMS,
+                        format!("Invalid params: invalid transaction request: {}", e)
+                    ));
+                    return Ok(response);
+                }
+            };
+            let Some(from) = tx.from else {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::INVALID_PARAMS,
+                    "Invalid params: missing from address".to_string()
+                ));
+                return Ok(response);
+            };
+            {
+                let state_guard = state.lock().await;
+                if state_guard.wallet.is_locked() {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::WALLET_LOCKED,
+                        "Wallet is locked".to_string()
+                    ));
+                    return Ok(response);
+                }
+                if !state_guard.connections.is_exposed(&origin, from) {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::UNAUTHORIZED,
+                        format!("Unauthorized: 0x{:x} is not an account connected to {}", from, origin)
+                    ));
+                    return Ok(response);
+                }
+            }
+            if !prompts::ask(&app, method, json!({ "transaction": tx, "sandbox": true })).await {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::USER_REJECTED,
+                    "User rejected the request".to_string()
+                ));
+                return Ok(response);
+            }
+            sandbox_tx = Some(tx);
+        }
+
+        if sandbox::is_served(method) || sandbox_tx.is_some() {
+            let mut state_guard = state.lock().await;
+            let exposed = state_guard.connections.accounts(&origin);
+            let AppState { client, chain_id: app_chain, tab_chains, sandbox, .. } = &mut *state_guard;
+            let Some(sandbox) = sandbox.as_mut() else {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::DISCONNECTED,
+                    "Sandbox was stopped".to_string()
+                ));
+                return Ok(response);
+            };
+            let Some(client) = tab_chains.resolve(client.as_deref(), *app_chain, sandbox.parent_chain) else {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::DISCONNECTED,
+                    format!("Light client for the sandbox's chain 0x{:x} isn't running", sandbox.parent_chain)
+                ));
+                return Ok(response);
+            };
+            let result = match (sandbox.fund(client, &exposed), &sandbox_tx) {
+                (Err(e), _) => Err(sandbox::SandboxError::Failed(e)),
+                (Ok(()), Some(tx)) => sandbox.send_transaction(client, tx).map(|hash| json!(hash)),
+                (Ok(()), None) => sandbox.request(client, method, params).await,
+            };
+            match result {
+                Ok(result) => {
+                    handle_response(&mut response, JsonRpcResult::Success(result));
+                    response.as_object_mut().unwrap().insert("sandbox".to_string(), json!(true));
+                    // Executed locally on top of verified state, nothing it returns is on chain
+                    provenance::tag(&mut response, provenance::Provenance::Computed);
+                },
+                Err(sandbox::SandboxError::InvalidParams(e)) => handle_response(&mut response, JsonRpcResult::Error(
+                    errors::INVALID_PARAMS,
+                    format!("Invalid params: {}", e)
+                )),
+                Err(sandbox::SandboxError::LimitExceeded(e)) => handle_response(&mut response, JsonRpcResult::Error(errors::LIMIT_EXCEEDED, e)),
+                Err(sandbox::SandboxError::Failed(e)) => handle_response(&mut response, JsonRpcResult::Error(errors::INTERNAL_ERROR, e)),
+            }
+            return Ok(response);
+        }
+        if sandbox::is_refused(method) {
+            handle_response(&mut response, JsonRpcResult::Error(
+                errors::UNSUPPORTED_METHOD,
+                format!("{} is not available in the sandbox", method)
+            ));
+            return Ok(response);
+        }
+        if method.starts_with("eth_signTypedData") {
+            if let Some(reason) = sandbox::typed_data_refusal(method, params) {
+                handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, reason));
+                return Ok(response);
+            }
+        }
+    }
+
+    // Immutable lookups answered before skip the network, once the caller is past the checks above
+    if dev_node.is_none() && chain_id != sandbox::SANDBOX_CHAIN_ID {
+        if let Some(cached) = cached_answer(&app, &state, chain_id, method, params, &request).await {
+            return Ok(cached);
+        }
+    }
+
+    // Opted-in trace/debug methods bypass verification and are tagged so the caller knows.
+    // Local tracing takes precedence for debug_traceTransaction
+    if passthrough::is_allowed(method) {
+        let state_guard = state.lock().await;
+        let local = method == "debug_traceTransaction" && state_guard.local_tracing;
+        if state_guard.unverified_passthrough && !local {
+            if state_guard.client_for(chain_id).is_none() {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::DISCONNECTED,
+                    "Light client not initialized".to_string()
+                ));
+                return Ok(response);
+            }
+            forward_unverified(&mut response, state_guard.rpc_url_for(chain_id), method, params).await;
+            return Ok(response);
+        }
+    }
+
+    // Blocks past the light client's window that are still in the header store are fetched from the
+    // execution RPC and checked against the stored header. The store follows the app's chain only
+    if let Some(param) = params.first().filter(|_| matches!(method, "eth_getBlockByNumber" | "eth_getBlockByHash")) {
+        let state_guard = state.lock().await;
+        let stored = match method {
+            _ if chain_id != state_guard.chain_id => None,
+            "eth_getBlockByNumber" => window::requested_number(param).and_then(|number| state_guard.header_store.by_number(number)),
+            _ => parse_hash(param).ok().and_then(|hash| state_guard.header_store.by_hash(hash)),
+        };
+        if let (Some(header), Some(client)) = (stored, state_guard.client_for(chain_id)) {
+            let in_window = window::Window::current(client)
+                .await
+                .is_ok_and(|verifiable| verifiable.contains(header.block_number));
+            if !in_window {
+                let full_tx = params.get(1).and_then(|full| full.as_bool()).unwrap_or(false);
+                match headers::fetch_block(state_guard.rpc_url_for(chain_id), header, full_tx).await {
+                    Ok(block) => {
+                        handle_response(&mut response, JsonRpcResult::Success(block));
+                        // Checked against the stored header, which the light client verified
+                        provenance::tag(&mut response, provenance::Provenance::Verified);
+                    },
+                    Err(e) => handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INTERNAL_ERROR,
+                        format!("Internal error: {}", e)
+                    ))
+                }
+                return Ok(response);
+            }
+        }
+    }
+
+    // A block the light client can't prove, by number, logs range or hash, is either refused with
+    // the verifiable range or, when the user allows it, answered by the execution RPC and tagged unverified
+    if let Some(requested) = window::requested_block(method, params) {
+        let state_guard = state.lock().await;
+        if let Some(client) = state_guard.client_for(chain_id) {
+            if let Ok(verifiable) = window::Window::current(client).await {
+                if let Err(error) = verifiable.place(client, requested).await {
+                    match state_guard.out_of_window {
+                        window::OutOfWindow::Error => {
+                            response.as_object_mut().unwrap().insert("error".to_string(), error);
+                        },
+                        window::OutOfWindow::Unverified => {
+                            forward_unverified(&mut response, state_guard.rpc_url_for(chain_id), method, params).await;
+                        },
+                    }
+                    return Ok(response);
+                }
+            }
+        }
+    }
+
+    // With proof export on, the block is pinned before the handler runs so the answer and the
+    // exported proof come from the same block
+    let mut pinned = None;
+    let pinned_params;
+    let params = match window::block_param_index(method).filter(|_| proofs::is_exportable(method)) {
+        Some(index) => {
+            let state_guard = state.lock().await;
+            match state_guard.client_for(chain_id).filter(|_| state_guard.export_proofs) {
+                Some(client) => {
+                    let tag = match params.get(index).map(parse_block_tag).unwrap_or(Ok(BlockTag::Latest)) {
+                        Ok(tag) => tag,
+                        Err(e) => {
+                            handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
+                            return Ok(response);
+                        }
+                    };
+                    match proofs::pin(client, tag).await {
+                        Ok(number) => {
+                            let mut with_number = params.clone();
+                            if with_number.len() <= index {
+                                with_number.resize(index + 1, json!(null));
+                            }
+                            with_number[index] = json!(format!("0x{:x}", number));
+                            pinned = Some(number);
+                            pinned_params = with_number;
+                            &pinned_params
+                        },
+                        // The handler reports the failure
+                        Err(_) => params,
+                    }
+                },
+                None => params,
+            }
+        },
+        None => params,
+    };
+
+    if userop::is_bundler_method(method) {
+        if let Err(e) = userop::validate_params(method, params) {
+            handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, format!("Invalid params: {}", e)));
+            return Ok(response);
+        }
+        let state_guard = state.lock().await;
+        if state_guard.client_for(chain_id).is_none() {
+            handle_response(&mut response, JsonRpcResult::Error(
+                errors::DISCONNECTED,
+                "Light client not initialized".to_string()
+            ));
+            return Ok(response);
+        }
+        let Some(bundler) = state_guard.bundlers.get(&chain_id) else {
+            handle_response(&mut response, JsonRpcResult::Error(
+                errors::RESOURCE_UNAVAILABLE,
+                format!("No bundler configured for chain {}", chain_id)
+            ));
+            return Ok(response);
+        };
+        match passthrough::forward(bundler, method, params).await {
+            Ok(upstream) => {
+                let object = response.as_object_mut().unwrap();
+                match upstream.get("error") {
+                    Some(error) => object.insert("error".to_string(), error.clone()),
+                    None => object.insert("result".to_string(), upstream.get("result").cloned().unwrap_or(json!(null))),
+                };
+                provenance::tag(&mut response, provenance::Provenance::Passthrough);
+            },
+            Err(e) => handle_response(&mut response, JsonRpcResult::Error(
+                errors::INTERNAL_ERROR,
+                format!("Internal error: {}", e)
+            ))
+        }
+        return Ok(response);
+    }
+
+    match method {
+        "eth_accounts" => {
+            // Like other wallets, a locked wallet exposes no accounts
+            let state_guard = state.lock().await;
+            let accounts = if state_guard.wallet.is_locked() {
+                Vec::new()
+            } else {
+                state_guard.connections.accounts(&origin)
+            };
+            handle_response(&mut response, JsonRpcResult::Success(json!(accounts)));
+        },
+
+        "eth_requestAccounts" => {
+            let (connected, unlocked) = {
+                let state_guard = state.lock().await;
+                (state_guard.connections.accounts(&origin), state_guard.wallet.addresses())
+            };
+            if !connected.is_empty() && !unlocked.is_empty() {
+                handle_response(&mut response, JsonRpcResult::Success(json!(connected)));
+                return Ok(response);
+            }
+            if unlocked.is_empty() {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::WALLET_LOCKED,
+                    "Wallet is locked".to_string()
+                ));
+                return Ok(response);
+            }
+
+            if !prompts::ask(&app, method, json!({ "origin": origin, "accounts": unlocked })).await {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::USER_REJECTED,
+                    "User rejected the request".to_string()
+                ));
+                return Ok(response);
+            }
+            let mut state_guard = state.lock().await;
+            let connected_chain = state_guard.client_for(chain_id).map(|_| chain_id);
+            state_guard.connections.connect(&origin, caller.webview.as_deref(), unlocked.clone(), connected_chain);
+            handle_response(&mut response, JsonRpcResult::Success(json!(unlocked)));
+        },
+
+        "wallet_getCapabilities" => {
+            let address = match params.first().and_then(|v| v.as_str()).and_then(|s| s.parse::<Address>().ok()) {
+                Some(address) => address,
+                None => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INVALID_PARAMS,
+                        "Invalid params: expected address".to_string()
+                    ));
+                    return Ok(response);
+                }
+            };
+            let state_guard = state.lock().await;
+            if !state_guard.connections.is_exposed(&origin, address) {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::UNAUTHORIZED,
+                    format!("Unauthorized: 0x{:x} is not an account connected to {}", address, origin)
+                ));
+                return Ok(response);
+            }
+            // Only accounts with code can execute a 4337 batch, keystore EOAs get sequential calls
+            let smart_account = match state_guard.client_for(chain_id) {
+                Some(client) => client.get_code(address, BlockTag::Latest).await.is_ok_and(|code| !code.is_empty()),
+                None => false,
+            };
+            let active_chain = state_guard.client_for(chain_id).map(|_| chain_id);
+            handle_response(&mut response, JsonRpcResult::Success(
+                calls::capabilities(active_chain, &state_guard.bundlers, smart_account)
+            ));
+        },
+
+        // Keystore accounts are EOAs, so batches run as consecutive-nonce transactions approved
+        // together. They are not atomic, and wallet_getCapabilities reports as much for EOAs
+        "wallet_sendCalls" => {
+            let batch: calls::SendCallsParams = match params.first().map(|p| serde_json::from_value(p.clone())) {
+                Some(Ok(batch)) => batch,
+                Some(Err(e)) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INVALID_PARAMS,
+                        format!("Invalid params: {}", e)
+                    ));
+                    return Ok(response);
+                },
+                None => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INVALID_PARAMS,
+                        "Invalid params: missing calls".to_string()
+                    ));
+                    return Ok(response);
+                }
+            };
+            if batch.calls.is_empty() {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::INVALID_PARAMS,
+                    "Invalid params: calls must not be empty".to_string()
+                ));
+                return Ok(response);
+            }
+            let from = batch.from;
+
+            let filled = {
+                let mut state_guard = state.lock().await;
+                pending::load_tracker(&app, &mut state_guard.pending).await;
+                let accounts = state_guard.account_source(chain_id);
+                let AppState { client, chain_id: app_chain, tab_chains, gas_oracle, fee_speed, gas_estimation, pending, wallet, connections, .. } = &mut *state_guard;
+                let Some(client) = tab_chains.resolve(client.as_deref(), *app_chain, chain_id) else {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::DISCONNECTED,
+                        "Light client not initialized".to_string()
+                    ));
+                    return Ok(response);
+                };
+                if wallet.is_locked() {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::WALLET_LOCKED,
+                        "Wallet is locked".to_string()
+                    ));
+                    return Ok(response);
+                }
+                if !connections.is_exposed(&origin, from) || wallet.signer(from).is_none() {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::UNAUTHORIZED,
+                        format!("Unauthorized: 0x{:x} is not an account connected to {}", from, origin)
+                    ));
+                    return Ok(response);
+                }
+                // The gas oracle follows the app's chain, windows on other chains get the client's fees
+                let quotes = if chain_id == *app_chain { gas_oracle.update(client).await.ok() } else { None };
+                let mut next_nonce = pending.next_nonce(client.chain_id().await, from);
+                let mut filled = Vec::with_capacity(batch.calls.len());
+                let mut failed = None;
+                for tx in batch.transactions() {
+                    match signer::fill_transaction(client, &accounts, tx, next_nonce, quotes.as_ref().map(|quotes| quotes.tier(*fee_speed)), gas_estimation.for_origin(&origin)).await {
+                        Ok(tx) => {
+                            next_nonce = tx.nonce.map(|nonce| nonce + 1);
+                            filled.push(tx);
+                        },
+                        Err(e) => {
+                            failed = Some(e);
+                            break;
+                        }
+                    }
+                }
+                match failed {
+                    Some(e) => Err(e),
+                    None => Ok(filled),
+                }
+            };
+            let filled = match filled {
+                Ok(filled) => filled,
+                Err(e) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INTERNAL_ERROR,
+                        format!("Internal error: {}", e)
+                    ));
+                    return Ok(response);
+                }
+            };
+
+            let verdict = {
+                let mut state_guard = state.lock().await;
+                policy::load_policies(&app, &mut state_guard.policies).await;
+                state_guard.policies.evaluate_batch(&filled)
+            };
+            let policy_warning = match verdict {
+                Ok(policy::Verdict::Allow) => None,
+                Ok(policy::Verdict::Confirm(reason)) => Some(reason),
+                Err(reason) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::UNAUTHORIZED,
+                        format!("Blocked by policy: {}", reason)
+                    ));
+                    return Ok(response);
+                }
+            };
+
+            let confirmed = policy_warning.is_some();
+            let calls: Vec<_> = stream::iter(&filled).then(|tx| describe_transaction(&app, chain_id, tx)).collect().await;
+            if !prompts::ask(&app, method, json!({ "transactions": filled, "calls": calls })).await {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::USER_REJECTED,
+                    "User rejected the request".to_string()
+                ));
+                return Ok(response);
+            }
+            if let Some(warning) = policy_warning {
+                if !prompts::ask(&app, method, json!({ "transactions": filled, "policyWarning": warning })).await {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::USER_REJECTED,
+                        "User rejected the request".to_string()
+                    ));
+                    return Ok(response);
+                }
+            }
+
+            let authorization = match app.state::<auth::AuthGate>().authorize("Sign a batch of transactions").await {
+                Ok(authorization) => authorization,
+                Err(e) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::USER_REJECTED,
+                        format!("User rejected the request: {}", e)
+                    ));
+                    return Ok(response);
+                }
+            };
+
+            // Later calls may depend on earlier ones, so a failure stops the batch and the
+            // transactions already sent stay tracked under the batch id
+            let mut state_guard = state.lock().await;
+            // Checked again under the lock that records the spend, so another send or a policy edit
+            // while the prompts were open can't get past it
+            if let Err(reason) = policy::enforce(state_guard.policies.evaluate_batch(&filled), confirmed) {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::UNAUTHORIZED,
+                    format!("Blocked by policy: {}", reason)
+                ));
+                return Ok(response);
+            }
+            state_guard.wallet.touch();
+            let mut hashes = Vec::with_capacity(filled.len());
+            let mut failed = None;
+            for tx in filled {
+                let signed = match state_guard.wallet.signer(from) {
+                    Some(_) if !state_guard.connections.is_exposed(&origin, from) => Err(format!("{} was disconnected", origin)),
+                    Some(signer) => signer::sign_transaction(signer, tx.clone(), &authorization).await,
+                    None => Err(format!("0x{:x} was locked while waiting for approval", from)),
+                };
+                let sent = match signed {
+                    Ok(raw) => broadcast_transaction(&app, &mut state_guard, chain_id, &raw, None, "eth_sendRawTransaction").await,
+                    Err(e) => Err(e),
+                };
+                match sent {
+                    Ok(hash) => {
+                        state_guard.policies.record_spend(&tx);
+                        hashes.push(hash);
+                    },
+                    Err(e) => {
+                        failed = Some(e);
+                        break;
+                    }
+                }
+            }
+            if let Some(path) = policy::policy_path(&app) {
+                if let Err(e) = state_guard.policies.save(&path).await {
+                    tracing::warn!("Failed to save policies: {}", e);
+                }
+            }
+
+            match failed {
+                Some(e) if hashes.is_empty() => handle_response(&mut response, JsonRpcResult::Error(
+                    errors::INTERNAL_ERROR,
+                    format!("Internal error: {}", e)
+                )),
+                failed => {
+                    if let Some(e) = failed {
+                        tracing::warn!("Batch stopped after {} of its calls: {}", hashes.len(), e);
+                    }
+                    let id = state_guard.call_batches.insert(hashes);
+                    handle_response(&mut response, JsonRpcResult::Success(json!(id)));
+                },
+            }
+        },
 
-#[tauri::command]
-async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::Value) -> Result<serde_json::Value, String> {
-    println!("Request: {}", serde_json::to_string_pretty(&request).unwrap());
-    let mut response = json!({"jsonrpc": "2.0"});
+        "wallet_getCallsStatus" => {
+            let Some(id) = params.first().and_then(|v| v.as_str()) else {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::INVALID_PARAMS,
+                    "Invalid params: expected batch id".to_string()
+                ));
+                return Ok(response);
+            };
+            let state_guard = state.lock().await;
+            let Some(client) = state_guard.client_for(chain_id) else {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::DISCONNECTED,
+                    "Light client not initialized".to_string()
+                ));
+                return Ok(response);
+            };
+            match state_guard.call_batches.status(client, id).await {
+                Ok(status) => handle_response(&mut response, JsonRpcResult::Success(status)),
+                Err(e) => handle_response(&mut response, JsonRpcResult::Error(
+                    errors::INVALID_PARAMS,
+                    format!("Invalid params: {}", e)
+                ))
+            }
+        },
 
-    if let Some(id) = request.get("id") {
-        response.as_object_mut().unwrap().insert("id".to_string(), id.clone());
-    }
+        // EIP-747: the asset is checked on-chain before the user is asked to add it
+        "wallet_watchAsset" => {
+            let asset: tokens::WatchAssetParams = match params.first().map(|p| serde_json::from_value(p.clone())) {
+                Some(Ok(asset)) => asset,
+                Some(Err(e)) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INVALID_PARAMS,
+                        format!("Invalid params: {}", e)
+                    ));
+                    return Ok(response);
+                },
+                None => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INVALID_PARAMS,
+                        "Invalid params: missing asset".to_string()
+                    ));
+                    return Ok(response);
+                }
+            };
 
-    // Validate JSON-RPC version
-    if request.get("jsonrpc").and_then(|v| v.as_str()) != Some("2.0") {
-        handle_response(&mut response, JsonRpcResult::Error(
-            -32600,
-            "Invalid Request: only JSON-RPC 2.0 is supported".to_string()
-        ));
-        return Ok(response);
-    }
+            let verified = {
+                let state_guard = state.lock().await;
+                let owner = state_guard.connections.accounts(&origin).first().copied();
+                match state_guard.client_for(chain_id) {
+                    Some(client) => {
+                        let chain_id = client.chain_id().await;
+                        Some(tokens::verify_watch_asset(client, &asset, owner, chain_id).await)
+                    },
+                    None => None,
+                }
+            };
+            let token = match verified {
+                Some(Ok(token)) => token,
+                Some(Err(e)) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INVALID_PARAMS,
+                        format!("Invalid params: {}", e)
+                    ));
+                    return Ok(response);
+                },
+                None => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::DISCONNECTED,
+                        "Light client not initialized".to_string()
+                    ));
+                    return Ok(response);
+                }
+            };
 
-    // Get method
-    let method = match request.get("method").and_then(|v| v.as_str()) {
-        Some(m) => m,
-        None => {
-            handle_response(&mut response, JsonRpcResult::Error(
-                -32600,
-                "Invalid Request: missing method".to_string()
-            ));
-            return Ok(response);
-        }
-    };
+            let payload = json!({ "origin": origin, "asset": token, "tokenId": asset.options.token_id });
+            if !prompts::ask(&app, method, payload).await {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::USER_REJECTED,
+                    "User rejected the request".to_string()
+                ));
+                return Ok(response);
+            }
 
-    // Get params
-    let params = match request.get("params").and_then(|v| v.as_array()) {
-        Some(p) => p,
-        None => {
-            handle_response(&mut response, JsonRpcResult::Error(
-                -32602,
-                "Invalid params: missing or invalid params".to_string()
-            ));
-            return Ok(response);
-        }
-    };
+            let mut state_guard = state.lock().await;
+            load_token_cache(&app, &mut state_guard.tokens).await;
+            state_guard.tokens.extend([token]);
+            if let Some(cache) = token_cache(&app) {
+                if let Err(e) = state_guard.tokens.save(&cache).await {
+                    tracing::warn!("Failed to save watched asset: {}", e);
+                }
+            }
+            handle_response(&mut response, JsonRpcResult::Success(json!(true)));
+        },
 
-    match method {
         "eth_getBlockByNumber" => {
             let block_tag = match parse_block_tag(&params[0]) {
                 Ok(tag) => tag,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
@@ -192,31 +3172,32 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
             let full_tx = match parse_bool(&params[1]) {
                 Ok(b) => b,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
 
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.get_block_by_number(block_tag, full_tx).await {
+            let upstream = state.lock().await.upstream(chain_id);
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    match retry::with_retry(&upstream.retry_policy, method, || client.get_block_by_number(block_tag, full_tx)).await {
                         Ok(block) => match serde_json::to_value(block) {
                             Ok(block_value) => handle_response(&mut response, JsonRpcResult::Success(block_value)),
                             Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                                -32603,
+                                errors::INTERNAL_ERROR,
                                 format!("Internal error: failed to serialize block: {}", e)
                             ))
                         },
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: failed to get block: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                 }
@@ -227,7 +3208,7 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
             let address = match parse_address(&params[0]) {
                 Ok(addr) => addr,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
@@ -235,30 +3216,43 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
             let block_tag = match parse_block_tag(&params[1]) {
                 Ok(tag) => tag,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
             
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.get_balance(address, block_tag).await {
-                        Ok(balance) => handle_response(&mut response, JsonRpcResult::Success(
-                            json!(format!("0x{:x}", balance))
-                        )),
-                        Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
-                            format!("Internal error: {}", e)
-                        ))
+            let (upstream, accounts) = {
+                let state_guard = state.lock().await;
+                (state_guard.upstream(chain_id), state_guard.account_source(chain_id))
+            };
+            match distributed_account(upstream.as_ref(), &accounts, address, block_tag).await {
+                Some(Ok(account)) => handle_response(&mut response, JsonRpcResult::Success(
+                    json!(format!("0x{:x}", account.balance))
+                )),
+                Some(Err(e)) => handle_response(&mut response, JsonRpcResult::Error(
+                    errors::INTERNAL_ERROR,
+                    format!("Internal error: {}", e)
+                )),
+                None => match &upstream {
+                    Some(upstream) => {
+                        let client = upstream.client();
+                        match retry::with_retry(&upstream.retry_policy, method, || client.get_balance(address, block_tag)).await {
+                            Ok(balance) => handle_response(&mut response, JsonRpcResult::Success(
+                                json!(format!("0x{:x}", balance))
+                            )),
+                            Err(e) => handle_response(&mut response, JsonRpcResult::Error(
+                                errors::INTERNAL_ERROR,
+                                format!("Internal error: {}", e)
+                            ))
+                        }
+                    },
+                    None => {
+                        handle_response(&mut response, JsonRpcResult::Error(
+                            errors::DISCONNECTED,
+                            "Light client not initialized".to_string()
+                        ));
+                        return Ok(response);
                     }
-                },
-                None => {
-                    handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
-                        "Light client not initialized".to_string()
-                    ));
-                    return Ok(response);
                 }
             }
         },
@@ -267,34 +3261,35 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
             let address = match parse_address(&params[0]) {
                 Ok(addr) => addr,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
             let block_tag = match parse_block_tag(&params[1]) {
                 Ok(tag) => tag,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
             
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.get_code(address, block_tag).await {
+            let upstream = state.lock().await.upstream(chain_id);
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    match retry::with_retry(&upstream.retry_policy, method, || client.get_code(address, block_tag)).await {
                         Ok(code) => handle_response(&mut response, JsonRpcResult::Success(
                             json!(format!("0x{}", hex::encode(code)))
                         )),
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -306,41 +3301,42 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
             let address = match parse_address(&params[0]) {
                 Ok(addr) => addr,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
             let slot = match parse_hash(&params[1]) {
                 Ok(h) => h,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
             let block_tag = match parse_block_tag(&params[2]) {
                 Ok(tag) => tag,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
             
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.get_storage_at(address, slot, block_tag).await {
+            let upstream = state.lock().await.upstream(chain_id);
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    match retry::with_retry(&upstream.retry_policy, method, || client.get_storage_at(address, slot, block_tag)).await {
                         Ok(value) => handle_response(&mut response, JsonRpcResult::Success(
                             json!(format!("0x{:x}", value))
                         )),
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -352,37 +3348,50 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
             let address = match parse_address(&params[0]) {
                 Ok(addr) => addr,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
             let block_tag = match parse_block_tag(&params[1]) {
                 Ok(tag) => tag,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
             
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.get_nonce(address, block_tag).await {
-                        Ok(nonce) => handle_response(&mut response, JsonRpcResult::Success(
-                            json!(format!("0x{:x}", nonce))
-                        )),
-                        Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
-                            format!("Internal error: {}", e)
-                        ))
+            let (upstream, accounts) = {
+                let state_guard = state.lock().await;
+                (state_guard.upstream(chain_id), state_guard.account_source(chain_id))
+            };
+            match distributed_account(upstream.as_ref(), &accounts, address, block_tag).await {
+                Some(Ok(account)) => handle_response(&mut response, JsonRpcResult::Success(
+                    json!(format!("0x{:x}", account.nonce))
+                )),
+                Some(Err(e)) => handle_response(&mut response, JsonRpcResult::Error(
+                    errors::INTERNAL_ERROR,
+                    format!("Internal error: {}", e)
+                )),
+                None => match &upstream {
+                    Some(upstream) => {
+                        let client = upstream.client();
+                        match retry::with_retry(&upstream.retry_policy, method, || client.get_nonce(address, block_tag)).await {
+                            Ok(nonce) => handle_response(&mut response, JsonRpcResult::Success(
+                                json!(format!("0x{:x}", nonce))
+                            )),
+                            Err(e) => handle_response(&mut response, JsonRpcResult::Error(
+                                errors::INTERNAL_ERROR,
+                                format!("Internal error: {}", e)
+                            ))
+                        }
+                    },
+                    None => {
+                        handle_response(&mut response, JsonRpcResult::Error(
+                            errors::DISCONNECTED,
+                            "Light client not initialized".to_string()
+                        ));
+                        return Ok(response);
                     }
-                },
-                None => {
-                    handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
-                        "Light client not initialized".to_string()
-                    ));
-                    return Ok(response);
                 }
             }
         },
@@ -391,27 +3400,28 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
             let hash = match parse_hash(&params[0]) {
                 Ok(h) => h,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
             
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.get_block_transaction_count_by_hash(hash).await {
+            let upstream = state.lock().await.upstream(chain_id);
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    match retry::with_retry(&upstream.retry_policy, method, || client.get_block_transaction_count_by_hash(hash)).await {
                         Ok(count) => handle_response(&mut response, JsonRpcResult::Success(
                             json!(format!("0x{:x}", count.unwrap_or(0)))
                         )),
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -423,27 +3433,28 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
             let block_tag = match parse_block_tag(&params[0]) {
                 Ok(tag) => tag,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
             
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.get_block_transaction_count_by_number(block_tag).await {
+            let upstream = state.lock().await.upstream(chain_id);
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    match retry::with_retry(&upstream.retry_policy, method, || client.get_block_transaction_count_by_number(block_tag)).await {
                         Ok(count) => handle_response(&mut response, JsonRpcResult::Success(
                             json!(format!("0x{:x}", count.unwrap_or(0)))
                         )),
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -455,38 +3466,39 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
             let hash = match parse_hash(&params[0]) {
                 Ok(h) => h,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
             let full_tx = match parse_bool(&params[1]) {
                 Ok(b) => b,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
             
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.get_block_by_hash(hash, full_tx).await {
+            let upstream = state.lock().await.upstream(chain_id);
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    match retry::with_retry(&upstream.retry_policy, method, || client.get_block_by_hash(hash, full_tx)).await {
                         Ok(block) => match serde_json::to_value(block) {
                             Ok(block_value) => handle_response(&mut response, JsonRpcResult::Success(block_value)),
                             Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                                -32603,
+                                errors::INTERNAL_ERROR,
                                 format!("Internal error: failed to serialize block: {}", e)
                             ))
                         },
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -495,22 +3507,23 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
         },
 
         "eth_gasPrice" => {
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.get_gas_price().await {
+            let upstream = state.lock().await.upstream(chain_id);
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    match retry::with_retry(&upstream.retry_policy, method, || client.get_gas_price()).await {
                         Ok(price) => handle_response(&mut response, JsonRpcResult::Success(
                             json!(format!("0x{:x}", price))
                         )),
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -518,23 +3531,160 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
             }
         },
 
+        // Dapps probe the chain on page load, so these answer from the window's chain or settings
+        // before the client starts
         "eth_chainId" => {
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    let chain_id = client.chain_id().await;
-                    handle_response(&mut response, JsonRpcResult::Success(
-                        json!(format!("0x{:x}", chain_id))
-                    ));
-                },
-                None => {
+            handle_response(&mut response, JsonRpcResult::Success(
+                json!(format!("0x{:x}", chain_id))
+            ));
+        },
+
+        "net_version" => {
+            handle_response(&mut response, JsonRpcResult::Success(json!(chain_id.to_string())));
+        },
+
+        // Moves only the calling window, once the user agrees. A chain the app isn't running on gets a
+        // client of its own, started with the settings that chain last ran with
+        "wallet_switchEthereumChain" => {
+            let Some(target) = params[0].get("chainId")
+                .and_then(|id| id.as_str())
+                .and_then(|id| u64::from_str_radix(id.trim_start_matches("0x"), 16).ok())
+            else {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::INVALID_PARAMS,
+                    "Invalid params: expected { chainId }".to_string()
+                ));
+                return Ok(response);
+            };
+
+            // A switch changes what every later request from the window means, so the user confirms it
+            if target != chain_id {
+                let payload = json!({
+                    "origin": origin,
+                    "from": format!("0x{:x}", chain_id),
+                    "chainId": format!("0x{:x}", target),
+                    "sandbox": target == sandbox::SANDBOX_CHAIN_ID,
+                });
+                if !prompts::ask(&app, method, payload).await {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
-                        "Light client not initialized".to_string()
+                        errors::USER_REJECTED,
+                        "User rejected the request".to_string()
                     ));
                     return Ok(response);
                 }
             }
+
+            // The sandbox forks the app's chain the first time a window asks for it
+            if target == sandbox::SANDBOX_CHAIN_ID {
+                let mut state_guard = state.lock().await;
+                if state_guard.sandbox.is_none() {
+                    let forked = match state_guard.client.as_deref() {
+                        Some(client) => sandbox::Sandbox::fork(client, state_guard.account_source(state_guard.chain_id)).await,
+                        None => Err("Light client not initialized".to_string()),
+                    };
+                    match forked {
+                        Ok(forked) => {
+                            tracing::info!("Sandbox forked chain 0x{:x} at block {}", forked.parent_chain, forked.status().fork_block);
+                            let _ = app.emit("sandbox", forked.status());
+                            state_guard.sandbox = Some(forked);
+                        },
+                        Err(e) => {
+                            handle_response(&mut response, JsonRpcResult::Error(
+                                errors::RESOURCE_UNAVAILABLE,
+                                format!("Failed to start the sandbox: {}", e)
+                            ));
+                            return Ok(response);
+                        }
+                    }
+                }
+            }
+
+            let config = {
+                let state_guard = state.lock().await;
+                match state_guard.client_for(target) {
+                    Some(_) => None,
+                    None if target == sandbox::SANDBOX_CHAIN_ID => None,
+                    None => match state_guard.known_networks.get(&target) {
+                        Some(config) => Some(config.clone()),
+                        None => {
+                            handle_response(&mut response, JsonRpcResult::Error(
+                                errors::UNRECOGNIZED_CHAIN,
+                                format!("Unrecognized chain 0x{:x}, start it once from the app first", target)
+                            ));
+                            return Ok(response);
+                        }
+                    },
+                }
+            };
+            if let Some(config) = config {
+                let launched = match launch_client(&app, &config).await {
+                    Ok(launched) => launched,
+                    Err(e) => {
+                        handle_response(&mut response, JsonRpcResult::Error(
+                            errors::RESOURCE_UNAVAILABLE,
+                            format!("Failed to start chain 0x{:x}: {}", target, e)
+                        ));
+                        return Ok(response);
+                    }
+                };
+                let surplus = {
+                    let mut state_guard = state.lock().await;
+                    let surplus = state_guard.tab_chains.insert_client(target, Arc::new(launched.client), config.rpc_url);
+                    if surplus.is_none() {
+                        restore_filters(&app, &mut state_guard, target).await;
+                    }
+                    surplus
+                };
+                if let Some(surplus) = surplus {
+                    surplus.shutdown().await;
+                }
+            }
+
+            let unused = {
+                let mut state_guard = state.lock().await;
+                state_guard.tab_chains.select(&caller, target);
+                let app_chain = state_guard.chain_id;
+                let unused = state_guard.tab_chains.take_unused(app_chain);
+                // The app's own client already carries the filters of its chain
+                for (unused_chain, _) in unused.iter().filter(|(unused_chain, _)| *unused_chain != app_chain) {
+                    state_guard.filters.detach(*unused_chain);
+                }
+                unused
+            };
+            for (_, client) in unused {
+                client.shutdown().await;
+            }
+            if target != chain_id {
+                if let Some(webview) = caller.webview.as_deref() {
+                    let _ = app.emit_to(tauri::EventTarget::webview(webview), "chainChanged", format!("0x{:x}", target));
+                }
+            }
+            handle_response(&mut response, JsonRpcResult::Success(serde_json::Value::Null));
+        },
+
+        // Legacy connection checks. The light client doesn't mine or join the devp2p network, so
+        // it counts the execution RPC it reads through as its one peer
+        "net_listening" => {
+            let running = state.lock().await.client_for(chain_id).is_some();
+            handle_response(&mut response, JsonRpcResult::Success(json!(running)));
+        },
+
+        "net_peerCount" => {
+            let peers = u64::from(state.lock().await.client_for(chain_id).is_some());
+            handle_response(&mut response, JsonRpcResult::Success(json!(format!("0x{:x}", peers))));
+        },
+
+        "eth_mining" => {
+            handle_response(&mut response, JsonRpcResult::Success(json!(false)));
+        },
+
+        "eth_hashrate" => {
+            handle_response(&mut response, JsonRpcResult::Success(json!("0x0")));
+        },
+
+        // eth/65, the last version MetaMask reported before the method was dropped
+        "eth_protocolVersion" => {
+            handle_response(&mut response, JsonRpcResult::Success(json!("0x41")));
         },
 
         "eth_sendRawTransaction" => {
@@ -542,7 +3692,7 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
                 Some(s) => s,
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32602,
+                        errors::INVALID_PARAMS,
                         "Invalid params: expected hex string".to_string()
                     ));
                     return Ok(response);
@@ -553,66 +3703,313 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
                 Ok(b) => b,
                 Err(e) => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32602,
+                        errors::INVALID_PARAMS,
                         format!("Invalid params: {}", e)
                     ));
                     return Ok(response);
                 }
             };
             
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.send_raw_transaction(&bytes).await {
-                        Ok(hash) => handle_response(&mut response, JsonRpcResult::Success(
-                            json!(format!("0x{:x}", hash))
-                        )),
-                        Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
-                            format!("Internal error: {}", e)
-                        ))
-                    }
-                },
-                None => {
+            let mut state_guard = state.lock().await;
+            if state_guard.client_for(chain_id).is_none() {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::DISCONNECTED,
+                    "Light client not initialized".to_string()
+                ));
+                return Ok(response);
+            }
+
+            match broadcast_transaction(&app, &mut state_guard, chain_id, &bytes, private_override(params), method).await {
+                Ok(hash) => handle_response(&mut response, JsonRpcResult::Success(
+                    json!(format!("0x{:x}", hash))
+                )),
+                Err(e) => handle_response(&mut response, JsonRpcResult::Error(
+                    errors::TRANSACTION_REJECTED,
+                    format!("Transaction rejected: {}", e)
+                ))
+            }
+        },
+
+        // Blind signing of an arbitrary 32-byte hash, which can authorize anything including
+        // transactions, so it stays off unless the user opts in
+        "eth_sign" => {
+            if !state.lock().await.allow_eth_sign {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::UNSUPPORTED_METHOD,
+                    "eth_sign is disabled, enable \"allow eth_sign\" in settings to use it".to_string()
+                ));
+                return Ok(response);
+            }
+
+            let (address, data) = match (
+                params.first().and_then(|v| v.as_str()).and_then(|s| s.parse::<Address>().ok()),
+                params.get(1).and_then(|v| v.as_str()).and_then(|s| s.parse::<B256>().ok()),
+            ) {
+                (Some(address), Some(data)) => (address, data),
+                _ => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INVALID_PARAMS,
+                        "Invalid params: expected address and 32-byte hex data".to_string()
+                    ));
+                    return Ok(response);
+                }
+            };
+
+            let (locked, authorized) = {
+                let state_guard = state.lock().await;
+                (
+                    state_guard.wallet.is_locked(),
+                    state_guard.connections.is_exposed(&origin, address) && state_guard.wallet.signer(address).is_some(),
+                )
+            };
+            if locked {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::WALLET_LOCKED,
+                    "Wallet is locked".to_string()
+                ));
+                return Ok(response);
+            }
+            if !authorized {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::UNAUTHORIZED,
+                    format!("Unauthorized: 0x{:x} is not an account connected to {}", address, origin)
+                ));
+                return Ok(response);
+            }
+
+            let payload = json!({
+                "address": format!("0x{:x}", address),
+                "data": data,
+                "warning": "This site is asking you to sign an opaque hash. It cannot be decoded or verified and may authorize a transaction that transfers your assets. Only approve if you fully trust the site and know exactly what the hash is.",
+            });
+            if !prompts::ask(&app, method, payload).await {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::USER_REJECTED,
+                    "User rejected the request".to_string()
+                ));
+                return Ok(response);
+            }
+
+            let authorization = match app.state::<auth::AuthGate>().authorize("Sign a message hash").await {
+                Ok(authorization) => authorization,
+                Err(e) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::USER_REJECTED,
+                        format!("User rejected the request: {}", e)
+                    ));
+                    return Ok(response);
+                }
+            };
+
+            let mut state_guard = state.lock().await;
+            state_guard.wallet.touch();
+            // The session may have been revoked or the wallet locked while the prompt was open
+            let signed = match state_guard.wallet.signer(address) {
+                Some(_) if !state_guard.connections.is_exposed(&origin, address) => Err(format!("{} was disconnected", origin)),
+                Some(signer) => signer::sign_hash(signer, data, &authorization),
+                None => Err(format!("0x{:x} was locked while waiting for approval", address)),
+            };
+            match signed {
+                Ok(signature) => handle_response(&mut response, JsonRpcResult::Success(json!(signature))),
+                Err(e) => handle_response(&mut response, JsonRpcResult::Error(
+                    errors::INTERNAL_ERROR,
+                    format!("Internal error: {}", e)
+                ))
+            }
+        },
+
+        // Both fill the request from verified state and wait for the user's approval before signing,
+        // eth_signTransaction then returns the signed transaction instead of broadcasting it
+        "eth_sendTransaction" | "eth_signTransaction" => {
+            let tx: alloy::rpc::types::TransactionRequest = match serde_json::from_value(params[0].clone()) {
+                Ok(t) => t,
+                Err(e) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INVALID_PARAMS,
+                        format!("Invalid params: invalid transaction request: {}", e)
+                    ));
+                    return Ok(response);
+                }
+            };
+            let Some(from) = tx.from else {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::INVALID_PARAMS,
+                    "Invalid params: missing from address".to_string()
+                ));
+                return Ok(response);
+            };
+
+            let filled = {
+                let mut state_guard = state.lock().await;
+                pending::load_tracker(&app, &mut state_guard.pending).await;
+                let accounts = state_guard.account_source(chain_id);
+                let AppState { client, chain_id: app_chain, tab_chains, gas_oracle, fee_speed, gas_estimation, pending, wallet, connections, .. } = &mut *state_guard;
+                let Some(client) = tab_chains.resolve(client.as_deref(), *app_chain, chain_id) else {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
+                };
+                if wallet.is_locked() {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::WALLET_LOCKED,
+                        "Wallet is locked".to_string()
+                    ));
+                    return Ok(response);
+                }
+                if !connections.is_exposed(&origin, from) || wallet.signer(from).is_none() {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::UNAUTHORIZED,
+                        format!("Unauthorized: 0x{:x} is not an account connected to {}", from, origin)
+                    ));
+                    return Ok(response);
+                }
+                // The gas oracle follows the app's chain, windows on other chains get the client's fees
+                let quotes = if chain_id == *app_chain { gas_oracle.update(client).await.ok() } else { None };
+                let next_nonce = pending.next_nonce(client.chain_id().await, from);
+                signer::fill_transaction(client, &accounts, tx, next_nonce, quotes.as_ref().map(|quotes| quotes.tier(*fee_speed)), gas_estimation.for_origin(&origin)).await
+            };
+            let filled = match filled {
+                Ok(tx) => tx,
+                Err(e) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INTERNAL_ERROR,
+                        format!("Internal error: {}", e)
+                    ));
+                    return Ok(response);
+                }
+            };
+
+            let verdict = {
+                let mut state_guard = state.lock().await;
+                policy::load_policies(&app, &mut state_guard.policies).await;
+                state_guard.policies.evaluate(&filled)
+            };
+            let policy_warning = match verdict {
+                Ok(policy::Verdict::Allow) => None,
+                Ok(policy::Verdict::Confirm(reason)) => Some(reason),
+                Err(reason) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::UNAUTHORIZED,
+                        format!("Blocked by policy: {}", reason)
+                    ));
+                    return Ok(response);
+                }
+            };
+
+            let confirmed = policy_warning.is_some();
+            let call = describe_transaction(&app, chain_id, &filled).await;
+            if !prompts::ask(&app, method, json!({ "transaction": filled, "call": call })).await {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::USER_REJECTED,
+                    "User rejected the request".to_string()
+                ));
+                return Ok(response);
+            }
+            // Transactions over the policy's threshold are confirmed a second time
+            if let Some(warning) = policy_warning {
+                if !prompts::ask(&app, method, json!({ "transaction": filled, "policyWarning": warning })).await {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::USER_REJECTED,
+                        "User rejected the request".to_string()
+                    ));
+                    return Ok(response);
+                }
+            }
+
+            let authorization = match app.state::<auth::AuthGate>().authorize("Sign a transaction").await {
+                Ok(authorization) => authorization,
+                Err(e) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::USER_REJECTED,
+                        format!("User rejected the request: {}", e)
+                    ));
+                    return Ok(response);
+                }
+            };
+
+            let mut state_guard = state.lock().await;
+            // Checked again under the lock that records the spend, so another send or a policy edit
+            // while the prompts were open can't get past it
+            if let Err(reason) = policy::enforce(state_guard.policies.evaluate(&filled), confirmed) {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::UNAUTHORIZED,
+                    format!("Blocked by policy: {}", reason)
+                ));
+                return Ok(response);
+            }
+            state_guard.wallet.touch();
+            let signed = match state_guard.wallet.signer(from) {
+                Some(_) if !state_guard.connections.is_exposed(&origin, from) => Err(format!("{} was disconnected", origin)),
+                Some(signer) => signer::sign_transaction(signer, filled.clone(), &authorization).await,
+                None => Err(format!("0x{:x} was locked while waiting for approval", from)),
+            };
+            let raw = match signed {
+                Ok(raw) => raw,
+                Err(e) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INTERNAL_ERROR,
+                        format!("Internal error: {}", e)
+                    ));
+                    return Ok(response);
+                }
+            };
+
+            // A signed transaction can be broadcast by anyone, so it counts against the limit either way
+            state_guard.policies.record_spend(&filled);
+            if let Some(path) = policy::policy_path(&app) {
+                if let Err(e) = state_guard.policies.save(&path).await {
+                    tracing::warn!("Failed to save policies: {}", e);
                 }
             }
+
+            if method == "eth_signTransaction" {
+                handle_response(&mut response, JsonRpcResult::Success(json!(raw)));
+                return Ok(response);
+            }
+            match broadcast_transaction(&app, &mut state_guard, chain_id, &raw, private_override(params), method).await {
+                Ok(hash) => handle_response(&mut response, JsonRpcResult::Success(
+                    json!(format!("0x{:x}", hash))
+                )),
+                Err(e) => handle_response(&mut response, JsonRpcResult::Error(
+                    errors::TRANSACTION_REJECTED,
+                    format!("Transaction rejected: {}", e)
+                ))
+            }
         },
 
         "eth_getTransactionReceipt" => {
             let tx_hash = match parse_hash(&params[0]) {
                 Ok(h) => h,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
             
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.get_transaction_receipt(tx_hash).await {
+            let upstream = state.lock().await.upstream(chain_id);
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    match retry::with_retry(&upstream.retry_policy, method, || client.get_transaction_receipt(tx_hash)).await {
                         Ok(Some(receipt)) => match serde_json::to_value(receipt) {
                             Ok(receipt_value) => handle_response(&mut response, JsonRpcResult::Success(receipt_value)),
                             Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                                -32603,
+                                errors::INTERNAL_ERROR,
                                 format!("Internal error: failed to serialize receipt: {}", e)
                             ))
                         },
                         Ok(None) => handle_response(&mut response, JsonRpcResult::Success(json!(null))),
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -624,19 +4021,20 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
             let tx_hash = match parse_hash(&params[0]) {
                 Ok(h) => h,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
             
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
+            let upstream = state.lock().await.upstream(chain_id);
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
                     match client.get_transaction_by_hash(tx_hash).await {
                         Some(tx) => match serde_json::to_value(tx) {
                             Ok(tx_value) => handle_response(&mut response, JsonRpcResult::Success(tx_value)),
                             Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                                -32603,
+                                errors::INTERNAL_ERROR,
                                 format!("Internal error: failed to serialize transaction: {}", e)
                             ))
                         },
@@ -645,7 +4043,7 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -658,33 +4056,42 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
                 Ok(f) => f,
                 Err(e) => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32602,
+                        errors::INVALID_PARAMS,
                         format!("Invalid params: {}", e)
                     ));
                     return Ok(response);
                 }
             };
             
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.get_logs(&filter).await {
+            let upstream = {
+                let state_guard = state.lock().await;
+                // Logs are verified against receipts from the light client's own RPC, so they can't be
+                // moved to another provider, only hidden among decoys
+                if state_guard.distribution.enabled && chain_id == state_guard.chain_id {
+                    distribution::send_log_decoys(&state_guard.rpc_url, state_guard.distribution.decoys, &params[0]);
+                }
+                state_guard.upstream(chain_id)
+            };
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    match retry::with_retry(&upstream.retry_policy, method, || client.get_logs(&filter)).await {
                         Ok(logs) => match serde_json::to_value(logs) {
                             Ok(logs_value) => handle_response(&mut response, JsonRpcResult::Success(logs_value)),
                             Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                                -32603,
+                                errors::INTERNAL_ERROR,
                                 format!("Internal error: failed to serialize logs: {}", e)
                             ))
                         },
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -692,122 +4099,133 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
             }
         },
 
-        "eth_newFilter" => {
-            let filter = match serde_json::from_value(params[0].clone()) {
-                Ok(f) => f,
-                Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(
-                        -32602,
-                        format!("Invalid params: {}", e)
-                    ));
-                    return Ok(response);
-                }
-            };
-            
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.new_filter(&filter).await {
-                        Ok(filter_id) => handle_response(&mut response, JsonRpcResult::Success(
-                            json!(format!("0x{:x}", filter_id))
-                        )),
-                        Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
-                            format!("Internal error: {}", e)
-                        ))
+        // Dapps get ids from the filter store rather than the client's, so the filter can be
+        // installed again after a restart without the dapp noticing
+        "eth_newFilter" | "eth_newBlockFilter" | "eth_newPendingTransactionFilter" => {
+            let kind = match method {
+                "eth_newFilter" => match serde_json::from_value(params[0].clone()) {
+                    Ok(filter) => filters::FilterKind::Logs { filter },
+                    Err(e) => {
+                        handle_response(&mut response, JsonRpcResult::Error(
+                            errors::INVALID_PARAMS,
+                            format!("Invalid params: {}", e)
+                        ));
+                        return Ok(response);
                     }
                 },
-                None => {
-                    handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
-                        "Light client not initialized".to_string()
-                    ));
-                }
-            }
-            return Ok(response)
-        },
+                "eth_newBlockFilter" => filters::FilterKind::Blocks,
+                _ => filters::FilterKind::PendingTransactions,
+            };
 
-        "eth_newBlockFilter" => {
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.new_block_filter().await {
-                        Ok(filter_id) => handle_response(&mut response, JsonRpcResult::Success(
-                            json!(format!("0x{:x}", filter_id))
-                        )),
-                        Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
-                            format!("Internal error: {}", e)
-                        ))
-                    }
-                },
-                None => {
-                    handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
-                        "Light client not initialized".to_string()
-                    ));
-                    return Ok(response);
-                }
+            let mut state_guard = state.lock().await;
+            expire_filters(&app, &mut state_guard).await;
+            let max_per_origin = state_guard.filter_limits.max_per_origin;
+            let limit_exceeded = format!("{} already has {} filters installed, uninstall some first", origin, max_per_origin);
+            if state_guard.filters.count_for_origin(&origin) >= max_per_origin {
+                handle_response(&mut response, JsonRpcResult::Error(errors::LIMIT_EXCEEDED, limit_exceeded));
+                return Ok(response);
             }
-        },
-
-        "eth_newPendingTransactionFilter" => {
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.new_pending_transaction_filter().await {
-                        Ok(filter_id) => handle_response(&mut response, JsonRpcResult::Success(
-                            json!(format!("0x{:x}", filter_id))
-                        )),
+            let upstream = state_guard.upstream(chain_id);
+            drop(state_guard);
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    match retry::with_retry(&upstream.retry_policy, method, || kind.install(client)).await {
+                        Ok(client_id) => {
+                            let mut state_guard = state.lock().await;
+                            // The lock was released for the install, so the origin may have filled
+                            // its quota meanwhile
+                            if state_guard.filters.count_for_origin(&origin) >= max_per_origin {
+                                drop(state_guard);
+                                let _ = client.uninstall_filter(client_id).await;
+                                handle_response(&mut response, JsonRpcResult::Error(errors::LIMIT_EXCEEDED, limit_exceeded));
+                                return Ok(response);
+                            }
+                            let filter_id = state_guard.filters.add(chain_id, caller.clone(), kind, client_id);
+                            filters::save_filters(&app, &state_guard.filters).await;
+                            handle_response(&mut response, JsonRpcResult::Success(
+                                json!(format!("0x{:x}", filter_id))
+                            ))
+                        },
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
-                    return Ok(response);
                 }
             }
+            return Ok(response)
         },
 
         "eth_getFilterChanges" => {
-            let filter_id = match params[0].as_str()
-                .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok()) {
+            let filter_id = match params[0].as_str().and_then(|s| s.parse::<alloy::primitives::U256>().ok()) {
                 Some(id) => id,
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32602,
+                        errors::INVALID_PARAMS,
                         "Invalid params: invalid filter id".to_string()
                     ));
                     return Ok(response);
                 }
             };
             
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.get_filter_changes(alloy::primitives::U256::from(filter_id)).await {
+            let mut state_guard = state.lock().await;
+            expire_filters(&app, &mut state_guard).await;
+            let Some(filter) = state_guard.filters.get_mut(filter_id, chain_id, &caller) else {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::RESOURCE_NOT_FOUND,
+                    "Filter not found".to_string()
+                ));
+                return Ok(response);
+            };
+            filter.touch();
+            let (kind, client_id) = (filter.kind.clone(), filter.client_id);
+            let upstream = state_guard.upstream(chain_id);
+            drop(state_guard);
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    // Reinstalling failed when the client started, so the filter reports changes
+                    // from this poll on
+                    let client_id = match client_id {
+                        Some(client_id) => Ok(client_id),
+                        None => match kind.install(client).await {
+                            Ok(client_id) => {
+                                if let Some(filter) = state.lock().await.filters.get_mut(filter_id, chain_id, &caller) {
+                                    filter.client_id = Some(client_id);
+                                }
+                                Ok(client_id)
+                            },
+                            Err(e) => Err(e),
+                        },
+                    };
+                    let changes = match client_id {
+                        Ok(client_id) => retry::with_retry(&upstream.retry_policy, method, || client.get_filter_changes(client_id)).await,
+                        Err(e) => Err(e),
+                    };
+                    match changes {
                         Ok(logs) => match serde_json::to_value(logs) {
                             Ok(logs_value) => handle_response(&mut response, JsonRpcResult::Success(logs_value)),
                             Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                                -32603,
+                                errors::INTERNAL_ERROR,
                                 format!("Internal error: failed to serialize logs: {}", e)
                             ))
                         },
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                 }
@@ -816,60 +4234,115 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
         },
 
         "eth_uninstallFilter" => {
-            let filter_id = match params[0].as_str()
-                .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok()) {
+            let filter_id = match params[0].as_str().and_then(|s| s.parse::<alloy::primitives::U256>().ok()) {
                 Some(id) => id,
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32602,
+                        errors::INVALID_PARAMS,
                         "Invalid params: invalid filter id".to_string()
                     ));
                     return Ok(response);
                 }
             };
             
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.uninstall_filter(alloy::primitives::U256::from(filter_id)).await {
-                        Ok(success) => handle_response(&mut response, JsonRpcResult::Success(json!(success))),
-                        Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
-                            format!("Internal error: {}", e)
-                        ))
-                    }
-                },
-                None => {
+            let mut state_guard = state.lock().await;
+            expire_filters(&app, &mut state_guard).await;
+            if state_guard.filters.get_mut(filter_id, chain_id, &caller).is_none() {
+                handle_response(&mut response, JsonRpcResult::Success(json!(false)));
+                return Ok(response);
+            }
+            let removed = state_guard.filters.remove(filter_id);
+            filters::save_filters(&app, &state_guard.filters).await;
+            let upstream = state_guard.upstream(chain_id);
+            drop(state_guard);
+            // The dapp's filter is gone either way, the client's copy only needs cleaning up
+            if let (Some(client_id), Some(upstream)) = (removed.and_then(|filter| filter.client_id), &upstream) {
+                if let Err(e) = retry::with_retry(&upstream.retry_policy, method, || upstream.client().uninstall_filter(client_id)).await {
+                    tracing::warn!("Failed to uninstall filter from the client: {}", e);
+                }
+            }
+            handle_response(&mut response, JsonRpcResult::Success(json!(true)));
+            return Ok(response)
+        },
+
+        // Notifications are pushed to the subscribing webview as `eth_subscription` events, callers
+        // of the local RPC server have no channel to receive them on
+        "eth_subscribe" => {
+            if caller.webview.is_none() {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::METHOD_NOT_SUPPORTED,
+                    "Subscriptions are only available to dapp windows".to_string()
+                ));
+                return Ok(response);
+            }
+            let kind = match subscriptions::SubscriptionKind::parse(params) {
+                Ok(kind) => kind,
+                Err(e) => {
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
+                    return Ok(response);
+                }
+            };
+            let mut state_guard = state.lock().await;
+            if kind == subscriptions::SubscriptionKind::NewPendingTransactions {
+                let has_ws = state_guard.config.as_ref().is_some_and(|config| config.ws_url.is_some());
+                if chain_id != state_guard.chain_id || !has_ws {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
-                        "Light client not initialized".to_string()
+                        errors::RESOURCE_UNAVAILABLE,
+                        "newPendingTransactions needs a WebSocket execution RPC for this chain".to_string()
                     ));
+                    return Ok(response);
                 }
+                response.as_object_mut().unwrap().insert("unverified".to_string(), json!(true));
             }
-            return Ok(response)
+            // The head watcher only follows the app's chain
+            if matches!(kind, subscriptions::SubscriptionKind::Logs { .. }) && chain_id != state_guard.chain_id {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::RESOURCE_UNAVAILABLE,
+                    "logs subscriptions are only available on the app's chain".to_string()
+                ));
+                return Ok(response);
+            }
+            let id = state_guard.subscriptions.add(caller.clone(), chain_id, kind.clone());
+            if kind == subscriptions::SubscriptionKind::NewPendingTransactions && state_guard.pending_stream.is_none() {
+                state_guard.pending_stream = Some(mempool::spawn_pending_stream(app.clone()));
+            }
+            handle_response(&mut response, JsonRpcResult::Success(json!(format!("0x{:x}", id))));
+            // Pending transactions come straight from the execution RPC's mempool
+            if kind == subscriptions::SubscriptionKind::NewPendingTransactions {
+                provenance::tag(&mut response, provenance::Provenance::Passthrough);
+            }
+        },
+
+        "eth_unsubscribe" => {
+            let removed = match params[0].as_str().and_then(|s| s.parse::<alloy::primitives::U256>().ok()) {
+                Some(id) => state.lock().await.subscriptions.remove(id, &caller),
+                None => false,
+            };
+            handle_response(&mut response, JsonRpcResult::Success(json!(removed)));
         },
 
         "eth_syncing" => {
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.syncing().await {
+            let upstream = state.lock().await.upstream(chain_id);
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    match retry::with_retry(&upstream.retry_policy, method, || client.syncing()).await {
                         Ok(sync_state) => match serde_json::to_value(sync_state) {
                             Ok(sync_value) => handle_response(&mut response, JsonRpcResult::Success(sync_value)),
                             Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                                -32603,
+                                errors::INTERNAL_ERROR,
                                 format!("Internal error: failed to serialize sync state: {}", e)
                             ))
                         },
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -878,22 +4351,23 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
         },
 
         "eth_coinbase" => {
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.get_coinbase().await {
+            let upstream = state.lock().await.upstream(chain_id);
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    match retry::with_retry(&upstream.retry_policy, method, || client.get_coinbase()).await {
                         Ok(address) => handle_response(&mut response, JsonRpcResult::Success(
                             json!(format!("0x{:x}", address))
                         )),
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -906,36 +4380,65 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
                 Ok(t) => t,
                 Err(e) => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32602,
+                        errors::INVALID_PARAMS,
                         format!("Invalid params: invalid transaction request: {}", e)
                     ));
                     return Ok(response);
                 }
             };
-            let block_tag = match parse_block_tag(&params[1]) {
+            let block_tag = match params.get(1).map(parse_block_tag).unwrap_or(Ok(BlockTag::Latest)) {
                 Ok(tag) => tag,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
-            
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.call(&tx, block_tag).await {
+            let state_overrides: Option<evm::StateOverride> = match params.get(2).filter(|v| !v.is_null()).cloned().map(serde_json::from_value).transpose() {
+                Ok(overrides) => overrides,
+                Err(e) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INVALID_PARAMS,
+                        format!("Invalid params: invalid state override: {}", e)
+                    ));
+                    return Ok(response);
+                }
+            };
+            let block_overrides: Option<evm::BlockOverrides> = match params.get(3).filter(|v| !v.is_null()).cloned().map(serde_json::from_value).transpose() {
+                Ok(overrides) => overrides,
+                Err(e) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INVALID_PARAMS,
+                        format!("Invalid params: invalid block override: {}", e)
+                    ));
+                    return Ok(response);
+                }
+            };
+
+            let (upstream, account_source) = {
+                let state_guard = state.lock().await;
+                (state_guard.upstream(chain_id), state_guard.account_source(chain_id))
+            };
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    let result = if state_overrides.is_some() || block_overrides.is_some() {
+                        simulate::call_with_overrides(client, account_source, &tx, block_tag, state_overrides.as_ref(), block_overrides.as_ref()).await
+                    } else {
+                        retry::with_retry(&upstream.retry_policy, method, || ccip::call(client, &tx, block_tag)).await
+                    };
+                    match result {
                         Ok(data) => handle_response(&mut response, JsonRpcResult::Success(
                             json!(format!("0x{}", hex::encode(data)))
                         )),
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -948,29 +4451,33 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
                 Ok(t) => t,
                 Err(e) => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32602,
+                        errors::INVALID_PARAMS,
                         format!("Invalid params: invalid transaction request: {}", e)
                     ));
                     return Ok(response);
                 }
             };
             
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.estimate_gas(&tx).await {
+            let (upstream, estimation) = {
+                let state_guard = state.lock().await;
+                (state_guard.upstream(chain_id), state_guard.gas_estimation.for_origin(&origin).clone())
+            };
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    match retry::with_retry(&upstream.retry_policy, method, || gas::estimate(client, &tx, &estimation)).await {
                         Ok(gas) => handle_response(&mut response, JsonRpcResult::Success(
                             json!(format!("0x{:x}", gas))
                         )),
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -982,7 +4489,7 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
             let block_hash = match parse_hash(&params[0]) {
                 Ok(h) => h,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
@@ -991,21 +4498,22 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
                 Some(i) => i,
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32602,
+                        errors::INVALID_PARAMS,
                         "Invalid params: invalid index format".to_string()
                     ));
                     return Ok(response);
                 }
             };
             
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
+            let upstream = state.lock().await.upstream(chain_id);
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
                     match client.get_transaction_by_block_hash_and_index(block_hash, index).await {
                         Some(tx) => match serde_json::to_value(tx) {
                             Ok(tx_value) => handle_response(&mut response, JsonRpcResult::Success(tx_value)),
                             Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                                -32603,
+                                errors::INTERNAL_ERROR,
                                 format!("Internal error: failed to serialize transaction: {}", e)
                             ))
                         },
@@ -1014,7 +4522,7 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -1022,23 +4530,45 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
             }
         },
 
+        // Derived from tips in recent verified blocks, the execution RPC's suggestion is only used
+        // before any block with transactions has been seen
         "eth_maxPriorityFeePerGas" => {
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(client) => {
-                    match client.get_priority_fee().await {
+            let (upstream, tracked) = {
+                let mut state_guard = state.lock().await;
+                let upstream = state_guard.upstream(chain_id);
+                let AppState { chain_id: app_chain, gas_oracle, priority_fee, .. } = &mut *state_guard;
+                // The oracle only tracks the app's chain
+                let tracked = match &upstream {
+                    Some(upstream) if chain_id == *app_chain => {
+                        if let Err(e) = gas_oracle.update(upstream.client()).await {
+                            tracing::warn!("Gas oracle: {}", e);
+                        }
+                        gas_oracle.priority_fee(priority_fee)
+                    },
+                    _ => None,
+                };
+                (upstream, tracked)
+            };
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    let fee = match tracked {
+                        Some(fee) => Ok(fee),
+                        None => retry::with_retry(&upstream.retry_policy, method, || client.get_priority_fee()).await.map_err(|e| e.to_string()),
+                    };
+                    match fee {
                         Ok(fee) => handle_response(&mut response, JsonRpcResult::Success(
                             json!(format!("0x{:x}", fee))
                         )),
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -1047,35 +4577,124 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
         },
 
         "eth_getBlockReceipts" => {
-            let block_tag = match parse_block_tag(&params[0]) {
-                Ok(tag) => tag,
+            let block = match parse_block_ref(&params[0]) {
+                Ok(block) => block,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
             
+            let (upstream, concurrency) = {
+                let state_guard = state.lock().await;
+                (state_guard.upstream(chain_id), state_guard.receipt_concurrency)
+            };
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    match retry::with_retry(&upstream.retry_policy, method, || fetch_block_receipts(client, &upstream.rpc_url, block, concurrency)).await {
+                        Ok(Some(receipts)) => handle_response(&mut response, JsonRpcResult::Success(json!(receipts))),
+                        Ok(None) => handle_response(&mut response, JsonRpcResult::Success(json!(null))),
+                        Err(e) => handle_response(&mut response, JsonRpcResult::Error(
+                            errors::INTERNAL_ERROR,
+                            format!("Internal error: {}", e)
+                        ))
+                    }
+                },
+                None => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::DISCONNECTED,
+                        "Light client not initialized".to_string()
+                    ));
+                    return Ok(response);
+                }
+            }
+        },
+
+        "eth_simulateV1" => {
+            let payload: simulate::SimulatePayload = match serde_json::from_value(params[0].clone()) {
+                Ok(p) => p,
+                Err(e) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INVALID_PARAMS,
+                        format!("Invalid params: invalid simulation payload: {}", e)
+                    ));
+                    return Ok(response);
+                }
+            };
+            let block_tag = match params.get(1).map(parse_block_tag).unwrap_or(Ok(BlockTag::Latest)) {
+                Ok(tag) => tag,
+                Err(e) => {
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
+                    return Ok(response);
+                }
+            };
+
+            let (upstream, account_source) = {
+                let state_guard = state.lock().await;
+                (state_guard.upstream(chain_id), state_guard.account_source(chain_id))
+            };
+            match &upstream {
+                Some(upstream) => {
+                    let client = upstream.client();
+                    match simulate::simulate_v1(client, account_source, payload, block_tag).await {
+                        Ok(blocks) => handle_response(&mut response, JsonRpcResult::Success(json!(blocks))),
+                        Err(e) => handle_response(&mut response, JsonRpcResult::Error(
+                            errors::INTERNAL_ERROR,
+                            format!("Internal error: {}", e)
+                        ))
+                    }
+                },
+                None => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::DISCONNECTED,
+                        "Light client not initialized".to_string()
+                    ));
+                    return Ok(response);
+                }
+            }
+        },
+
+        "debug_traceTransaction" => {
+            let tx_hash = match parse_hash(&params[0]) {
+                Ok(h) => h,
+                Err(e) => {
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
+                    return Ok(response);
+                }
+            };
+            let options: trace::TraceOptions = match params.get(1).map(|p| serde_json::from_value(p.clone())).transpose() {
+                Ok(options) => options.unwrap_or_default(),
+                Err(e) => {
+                    handle_response(&mut response, JsonRpcResult::Error(
+                        errors::INVALID_PARAMS,
+                        format!("Invalid params: invalid trace options: {}", e)
+                    ));
+                    return Ok(response);
+                }
+            };
+
             let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
+            if !state_guard.local_tracing {
+                handle_response(&mut response, JsonRpcResult::Error(
+                    errors::METHOD_NOT_SUPPORTED,
+                    "Method not supported: debug_traceTransaction requires local tracing to be enabled".to_string()
+                ));
+                return Ok(response);
+            }
+            match state_guard.client_for(chain_id) {
                 Some(client) => {
-                    match client.get_block_receipts(block_tag).await {
-                        Ok(Some(receipts)) => match serde_json::to_value(receipts) {
-                            Ok(receipts_value) => handle_response(&mut response, JsonRpcResult::Success(receipts_value)),
-                            Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                                -32603,
-                                format!("Internal error: failed to serialize receipts: {}", e)
-                            ))
-                        },
-                        Ok(None) => handle_response(&mut response, JsonRpcResult::Success(json!(null))),
+                    match trace::trace_transaction(client, state_guard.account_source(chain_id), tx_hash, options).await {
+                        Ok(trace) => handle_response(&mut response, JsonRpcResult::Success(trace)),
                         Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                            -32603,
+                            errors::INTERNAL_ERROR,
                             format!("Internal error: {}", e)
                         ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -1087,7 +4706,7 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
             let address = match parse_address(&params[0]) {
                 Ok(addr) => addr,
                 Err(e) => {
-                    handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                    handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                     return Ok(response);
                 }
             };
@@ -1099,7 +4718,7 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
                         match parse_hash(key) {
                             Ok(hash) => result.push(hash),
                             Err(e) => {
-                                handle_response(&mut response, JsonRpcResult::Error(-32602, e));
+                                handle_response(&mut response, JsonRpcResult::Error(errors::INVALID_PARAMS, e));
                                 return Ok(response);
                             }
                         }
@@ -1108,19 +4727,19 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32602,
+                        errors::INVALID_PARAMS,
                         "Invalid params: storage keys must be an array".to_string()
                     ));
                     return Ok(response);
                 }
             };
 
-            let block_param = "latest"; // Or parse from params[2] if needed
+            let block_param = params.get(2).cloned().unwrap_or(json!("latest"));
 
-            let state_guard = state.lock().await;
-            match state_guard.client.as_ref() {
-                Some(_) => {
-                    let client = reqwest::Client::new();
+            let upstream = state.lock().await.upstream(chain_id);
+            match &upstream {
+                Some(upstream) => {
+                    let client = outbound::client();
                     
                     let payload = serde_json::json!({
                         "jsonrpc": "2.0",
@@ -1133,29 +4752,30 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
                         "id": 1
                     });
 
-                    match client
-                        .post(&state_guard.rpc_url)
-                        .json(&payload)
-                        .send()
-                        .await {
+                    let http = &client;
+                    let rpc_url = upstream.rpc_url.as_str();
+                    let payload = &payload;
+                    match retry::with_retry(&upstream.retry_policy, method, || async move {
+                        http.post(rpc_url).json(payload).send().await?.error_for_status()
+                    }).await {
                             Ok(http_response) => {
                                 match http_response.json::<serde_json::Value>().await {
                                     Ok(proof) => handle_response(&mut response, JsonRpcResult::Success(proof)),
                                     Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                                        -32603,
+                                        errors::INTERNAL_ERROR,
                                         format!("Failed to parse response: {}", e)
                                     ))
                                 }
                             },
                             Err(e) => handle_response(&mut response, JsonRpcResult::Error(
-                                -32603,
+                                errors::INTERNAL_ERROR,
                                 format!("Failed to send request: {}", e)
                             ))
                     }
                 },
                 None => {
                     handle_response(&mut response, JsonRpcResult::Error(
-                        -32000,
+                        errors::DISCONNECTED,
                         "Light client not initialized".to_string()
                     ));
                     return Ok(response);
@@ -1165,32 +4785,373 @@ async fn request(state: tauri::State<'_, Mutex<AppState>>, request: serde_json::
 
         _u => {
             handle_response(&mut response, JsonRpcResult::Error(
-                -32601,
+                errors::METHOD_NOT_FOUND,
                 format!("Method not found: {} is not supported", method)
             ));
         }
     }
 
+    if let (Some(number), Some(result)) = (pinned, response.get("result").cloned()) {
+        let state_guard = state.lock().await;
+        if let Some(client) = state_guard.client_for(chain_id) {
+            let exported = proofs::export(client, state_guard.rpc_url_for(chain_id), method, params, number, &result).await;
+            let object = response.as_object_mut().unwrap();
+            match exported {
+                Ok(proof) => object.insert("proof".to_string(), json!(proof)),
+                Err(e) => object.insert("proofError".to_string(), json!(e)),
+            };
+        }
+    }
+    let proven = response.get("proof").is_some();
+    provenance::tag(&mut response, provenance::classify(method, proven));
+
     Ok(response)
 }
 
 struct AppState {
-    client: Option<EthereumClient<FileDB>>,
+    // Shared so background tasks can query it without holding the state lock
+    client: Option<Arc<dyn client::EthClientApi>>,
+    rpc_url: String,
+    consensus_rpc: String,
+    config: Option<ClientConfig>,
+    // Chain of the running client, or the one it last ran on
+    chain_id: u64,
+    checkpoint: Option<checkpoint::CheckpointRecord>,
+    receipt_concurrency: usize,
+    retry_policy: retry::RetryPolicy,
+    ipfs_gateways: Vec<String>,
+    local_tracing: bool,
+    unverified_passthrough: bool,
+    export_proofs: bool,
+    // Signing, sending and key management are refused
+    read_only: bool,
+    // Local node that replaces the light client for dapps while dev mode is on
+    dev_node: Option<devmode::DevNode>,
+    // In-process fork windows on the sandbox chain transact against
+    sandbox: Option<sandbox::Sandbox>,
+    out_of_window: window::OutOfWindow,
+    allow_eth_sign: bool,
+    token_lists: Vec<String>,
+    tokens: tokens::TokenRegistry,
+    price_feeds: Vec<prices::PriceFeed>,
+    price_cache: prices::PriceCache,
+    response_cache: offline::ResponseCache,
+    cache_policy: cache::CachePolicy,
+    disk_cache_counts: cache::HitCounts,
+    connectivity: offline::Connectivity,
+    watched: watch::WatchList,
+    gas_oracle: gas::GasOracle,
+    header_store: headers::HeaderStore,
+    // Latest finalized block of the app's chain, as the head watcher last saw it
+    finalized: Option<u64>,
+    fee_speed: gas::FeeSpeed,
+    gas_estimation: gas::EstimationPolicy,
+    priority_fee: gas::PriorityFeeSettings,
+    pending: pending::PendingTracker,
+    private_relay: protect::RelayConfig,
+    proxy: Option<String>,
+    distribution: distribution::DistributionSettings,
+    storage: storage::StorageSettings,
+    // Searcher identity for bundle relays, never holds funds and is kept in memory only
+    bundle_signer: Option<alloy::signers::local::PrivateKeySigner>,
+    // ERC-4337 bundler endpoint per chain id
+    bundlers: HashMap<u64, String>,
+    wallet: signer::Wallet,
+    keystore: keystore::Keystore,
+    connections: accounts::Connections,
+    tab_chains: chains::TabChains,
+    filters: filters::FilterStore,
+    filter_limits: filters::FilterLimits,
+    http_settings: outbound::HttpSettings,
+    subscriptions: subscriptions::Subscriptions,
+    pending_stream: Option<tauri::async_runtime::JoinHandle<()>>,
+    rate_limiter: ratelimit::RateLimiter,
+    policies: policy::PolicyStore,
+    call_batches: calls::CallBatches,
+    rpc_server: Option<rpc_server::RpcServer>,
+    // Settings each chain was last started with, for switching networks from the tray
+    known_networks: HashMap<u64, ClientConfig>,
+    notifications: notify::NotificationSettings,
+    walletconnect: walletconnect::Pairings,
+    tasks: Vec<tauri::async_runtime::JoinHandle<()>>,
+    watchdog: Option<tauri::async_runtime::JoinHandle<()>>,
+}
+
+impl AppState {
+    fn abort_tasks(&mut self) {
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+    }
+
+    // Guard for commands that sign or manage keys
+    fn check_writable(&self) -> Result<(), String> {
+        if self.read_only {
+            return Err("Unavailable while read-only mode is on".to_string());
+        }
+        Ok(())
+    }
+
+    // Distribution only covers the app's chain
+    fn account_source(&self, chain_id: u64) -> distribution::AccountSource {
+        if chain_id == self.chain_id {
+            distribution::AccountSource::new(&self.distribution, &self.rpc_url)
+        } else {
+            distribution::AccountSource::direct()
+        }
+    }
+
+    // The app's client, then the windows' clients of other chains
+    fn running_clients(&self) -> impl Iterator<Item = (u64, &dyn client::EthClientApi)> {
+        let app_chain = self.chain_id;
+        self.client.as_deref()
+            .map(|client| (app_chain, client))
+            .into_iter()
+            .chain(self.tab_chains.clients().filter(move |(chain_id, _)| *chain_id != app_chain))
+    }
+
+    fn client_for(&self, chain_id: u64) -> Option<&dyn client::EthClientApi> {
+        self.tab_chains.resolve(self.client.as_deref(), self.chain_id, chain_id)
+    }
+
+    fn rpc_url_for(&self, chain_id: u64) -> &str {
+        if chain_id == self.chain_id {
+            &self.rpc_url
+        } else {
+            self.tab_chains.rpc_url(chain_id).unwrap_or_default()
+        }
+    }
+
+    // Copies out what a call to `chain_id`'s upstream needs, so the state lock can be released
+    // before it goes out
+    fn upstream(&self, chain_id: u64) -> Option<Upstream> {
+        let client = match &self.client {
+            Some(client) if chain_id == self.chain_id => client.clone(),
+            _ => self.tab_chains.handle(chain_id)?,
+        };
+        Some(Upstream {
+            client,
+            retry_policy: self.retry_policy.clone(),
+            rpc_url: self.rpc_url_for(chain_id).to_string(),
+        })
+    }
+}
+
+// Client serving a chain with the retry policy and RPC URL in force when it was looked up. Retries
+// back off for seconds, so they run on this copy rather than under the state lock
+struct Upstream {
+    client: Arc<dyn client::EthClientApi>,
+    retry_policy: retry::RetryPolicy,
     rpc_url: String,
 }
 
+impl Upstream {
+    fn client(&self) -> &dyn client::EthClientApi {
+        self.client.as_ref()
+    }
+}
+
 impl Default for AppState {
     fn default() -> Self {
         Self { 
             client: None,
             rpc_url: String::new(),
+            consensus_rpc: String::new(),
+            config: None,
+            chain_id: DEFAULT_CHAIN_ID,
+            checkpoint: None,
+            receipt_concurrency: DEFAULT_RECEIPT_CONCURRENCY,
+            retry_policy: retry::RetryPolicy::default(),
+            ipfs_gateways: ipfs::DEFAULT_GATEWAYS.iter().map(|g| g.to_string()).collect(),
+            local_tracing: false,
+            unverified_passthrough: false,
+            export_proofs: false,
+            read_only: false,
+            dev_node: None,
+            sandbox: None,
+            out_of_window: window::OutOfWindow::default(),
+            allow_eth_sign: false,
+            token_lists: tokens::DEFAULT_TOKEN_LISTS.iter().map(|l| l.to_string()).collect(),
+            tokens: tokens::TokenRegistry::default(),
+            price_feeds: prices::default_feeds(),
+            price_cache: prices::PriceCache::default(),
+            response_cache: offline::ResponseCache::default(),
+            cache_policy: cache::CachePolicy::default(),
+            disk_cache_counts: cache::HitCounts::default(),
+            connectivity: offline::Connectivity::default(),
+            watched: watch::WatchList::default(),
+            gas_oracle: gas::GasOracle::default(),
+            header_store: headers::HeaderStore::default(),
+            finalized: None,
+            fee_speed: gas::FeeSpeed::default(),
+            gas_estimation: gas::EstimationPolicy::default(),
+            priority_fee: gas::PriorityFeeSettings::default(),
+            pending: pending::PendingTracker::default(),
+            private_relay: protect::RelayConfig::default(),
+            proxy: None,
+            distribution: distribution::DistributionSettings::default(),
+            storage: storage::StorageSettings::default(),
+            bundle_signer: None,
+            bundlers: HashMap::new(),
+            wallet: signer::Wallet::default(),
+            keystore: keystore::Keystore::default(),
+            connections: accounts::Connections::default(),
+            tab_chains: chains::TabChains::default(),
+            filters: filters::FilterStore::default(),
+            filter_limits: filters::FilterLimits::default(),
+            http_settings: outbound::HttpSettings::default(),
+            subscriptions: subscriptions::Subscriptions::default(),
+            pending_stream: None,
+            rate_limiter: ratelimit::RateLimiter::default(),
+            policies: policy::PolicyStore::default(),
+            call_batches: calls::CallBatches::default(),
+            rpc_server: None,
+            known_networks: HashMap::new(),
+            notifications: notify::NotificationSettings::default(),
+            walletconnect: walletconnect::Pairings::default(),
+            tasks: Vec::new(),
+            watchdog: None,
         }
     }
 }
 
+fn network_name(chain_id: u64) -> String {
+    match chain_id {
+        1 => "Mainnet".to_string(),
+        _ => format!("Chain {}", chain_id),
+    }
+}
+
 fn get_network(chain_id: u64) -> Result<Network, String> {
     match chain_id {
         1 => Ok(Network::MAINNET),
         _ => Err(format!("Unsupported chain ID: {}. Supported chains are: Mainnet (1), Base (8453), Optimism (10)", chain_id))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{mock_block, MockClient};
+
+    fn error_code(request: serde_json::Value) -> Option<i32> {
+        parse_request(&request).err().map(|(code, _)| code)
+    }
+
+    #[test]
+    fn malformed_requests_are_invalid_requests() {
+        assert_eq!(error_code(json!({ "method": "eth_chainId", "params": [] })), Some(errors::INVALID_REQUEST));
+        assert_eq!(error_code(json!({ "jsonrpc": "1.0", "method": "eth_chainId", "params": [] })), Some(errors::INVALID_REQUEST));
+        assert_eq!(error_code(json!({ "jsonrpc": "2.0", "params": [] })), Some(errors::INVALID_REQUEST));
+        assert_eq!(error_code(json!({ "jsonrpc": "2.0", "method": 1, "params": [] })), Some(errors::INVALID_REQUEST));
+    }
+
+    #[test]
+    fn missing_params_are_invalid_params() {
+        assert_eq!(error_code(json!({ "jsonrpc": "2.0", "method": "eth_chainId" })), Some(errors::INVALID_PARAMS));
+        assert_eq!(error_code(json!({ "jsonrpc": "2.0", "method": "eth_chainId", "params": "0x1" })), Some(errors::INVALID_PARAMS));
+        let short = json!({ "jsonrpc": "2.0", "method": "eth_getBalance", "params": ["0x0000000000000000000000000000000000000001"] });
+        let (code, message) = parse_request(&short).unwrap_err();
+        assert_eq!(code, errors::INVALID_PARAMS);
+        assert!(message.contains("missing block (param 1)"));
+    }
+
+    #[test]
+    fn by_name_params_become_one_positional_param() {
+        let request = json!({ "jsonrpc": "2.0", "method": "wallet_watchAsset", "params": { "type": "ERC20" } });
+        let (method, params) = parse_request(&request).unwrap();
+        assert_eq!(method, "wallet_watchAsset");
+        assert_eq!(params, vec![json!({ "type": "ERC20" })]);
+
+        let request = json!({ "jsonrpc": "2.0", "method": "eth_blockNumber", "params": [] });
+        assert_eq!(parse_request(&request).unwrap(), ("eth_blockNumber", Vec::new()));
+    }
+
+    #[test]
+    fn every_signing_and_broadcast_method_is_a_write() {
+        for method in [
+            "eth_sign",
+            "personal_sign",
+            "eth_signTypedData_v4",
+            "eth_signTransaction",
+            "eth_sendTransaction",
+            "eth_sendRawTransaction",
+            "eth_sendUserOperation",
+            "wallet_sendCalls",
+            "anvil_setBalance",
+            "hardhat_impersonateAccount",
+            "evm_mine",
+        ] {
+            assert!(is_write_method(method), "{} should be refused in read-only mode", method);
+        }
+        for method in ["eth_call", "eth_getBalance", "eth_estimateUserOperationGas", "eth_accounts", "wallet_switchEthereumChain"] {
+            assert!(!is_write_method(method), "{} should be allowed in read-only mode", method);
+        }
+    }
+
+    #[test]
+    fn node_control_goes_to_the_dev_node_or_the_sandbox() {
+        use devmode::NodeControl;
+        assert_eq!(devmode::node_control("eth_call", false, false), None);
+        assert_eq!(devmode::node_control("anvil_setBalance", true, false), Some(NodeControl::DevNode));
+        assert_eq!(devmode::node_control("evm_mine", true, true), Some(NodeControl::DevNode));
+        assert_eq!(devmode::node_control("evm_snapshot", false, true), Some(NodeControl::Sandbox));
+        assert_eq!(devmode::node_control("anvil_setBalance", false, true), Some(NodeControl::Refused));
+        assert_eq!(devmode::node_control("evm_mine", false, false), Some(NodeControl::Refused));
+        assert!(sandbox::is_served("evm_revert"));
+        assert!(!sandbox::is_served("hardhat_reset"));
+    }
+
+    #[test]
+    fn block_params_are_parsed_or_refused_as_invalid_params() {
+        assert!(matches!(parse_block_tag(&json!("pending")), Ok(BlockTag::Latest)));
+        assert!(matches!(parse_block_tag(&json!("safe")), Ok(BlockTag::Finalized)));
+        assert!(matches!(parse_block_tag(&json!("0x10")), Ok(BlockTag::Number(16))));
+        for invalid in [json!("0xzz"), json!(16), json!("newest")] {
+            assert!(parse_block_tag(&invalid).unwrap_err().starts_with("Invalid params"));
+        }
+
+        let hash = B256::repeat_byte(0xab);
+        assert!(matches!(parse_block_ref(&json!({ "blockHash": hash })), Ok(BlockRef::Hash(h)) if h == hash));
+        assert!(matches!(parse_block_ref(&json!(hash)), Ok(BlockRef::Hash(h)) if h == hash));
+        assert!(matches!(parse_block_ref(&json!({ "blockNumber": "0x1" })), Ok(BlockRef::Tag(BlockTag::Number(1)))));
+        assert!(parse_address(&json!("0x1234")).unwrap_err().starts_with("Invalid params"));
+    }
+
+    #[test]
+    fn errors_carry_their_code() {
+        let mut response = json!({ "jsonrpc": "2.0", "id": 1 });
+        handle_response(&mut response, JsonRpcResult::Error(errors::USER_REJECTED, "User rejected the request".to_string()));
+        assert_eq!(response["error"], json!({ "code": 4001, "message": "User rejected the request" }));
+        assert!(response.get("result").is_none());
+    }
+
+    #[tokio::test]
+    async fn block_receipts_of_an_unknown_block_are_null() {
+        let client = MockClient { chain_id: 1, blocks: vec![mock_block(1, 1_700_000_000)], ..Default::default() };
+        let unknown = fetch_block_receipts(&client, "", BlockRef::Tag(BlockTag::Number(2)), 4).await.unwrap();
+        assert!(unknown.is_none());
+        let empty = fetch_block_receipts(&client, "", BlockRef::Tag(BlockTag::Number(1)), 4).await.unwrap();
+        assert_eq!(empty, Some(Vec::new()));
+    }
+
+    #[tokio::test]
+    async fn portfolio_covers_every_running_chain() {
+        let owner = Address::repeat_byte(0x42);
+        let account = client::MockAccount { balance: U256::from(7), ..Default::default() };
+        let mainnet = MockClient {
+            chain_id: 1,
+            blocks: vec![mock_block(1, 1_700_000_000)],
+            accounts: HashMap::from([(owner, account)]),
+            ..Default::default()
+        };
+        let optimism = MockClient { chain_id: 10, blocks: vec![mock_block(1, 1_700_000_000)], ..Default::default() };
+        let mut state = AppState { client: Some(Arc::new(mainnet)), chain_id: 1, ..Default::default() };
+        assert!(state.tab_chains.insert_client(10, Arc::new(optimism), String::new()).is_none());
+
+        let portfolio = collect_portfolio(&state, owner).await;
+        let chains: Vec<(u64, U256)> = portfolio.chains.iter().map(|chain| (chain.chain_id, chain.native_balance)).collect();
+        assert_eq!(chains, vec![(1, U256::from(7)), (10, U256::ZERO)]);
+        assert!(portfolio.chains.iter().all(|chain| chain.error.is_none()));
+    }
+}
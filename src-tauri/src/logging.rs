@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tracing::Level;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+const LOG_FILE_PREFIX: &str = "mana";
+const LOG_FILE_SUFFIX: &str = "log";
+// Days of logs kept, one file per day
+const MAX_LOG_FILES: usize = 7;
+const DEFAULT_FILTER: &str = "info";
+const DEFAULT_LOG_LIMIT: usize = 200;
+
+// Keeps the file writer flushing until the app exits, and the filter swappable at runtime
+pub struct Logging {
+    dir: PathBuf,
+    filter: reload::Handle<EnvFilter, Registry>,
+    levels: std::sync::Mutex<Levels>,
+    _guard: WorkerGuard,
+}
+
+// The default directive plus per-target levels set from the UI
+#[derive(Clone)]
+struct Levels {
+    default: String,
+    targets: BTreeMap<String, LevelFilter>,
+}
+
+impl Levels {
+    fn filter(&self) -> Result<EnvFilter, String> {
+        let mut directives = vec![self.default.clone()];
+        directives.extend(self.targets.iter().map(|(target, level)| format!("{}={}", target, level)));
+        EnvFilter::try_new(directives.join(",")).map_err(|e| format!("Invalid log filter: {}", e))
+    }
+}
+
+// Friendly names for the parts users are asked to turn up when reporting issues. The dispatcher
+// is matched by its span, so everything a request does is included
+fn resolve_target(target: &str) -> &str {
+    match target {
+        "dispatcher" | "rpc" => "[rpc]",
+        "helios" => "helios",
+        "upstream" => "[upstream]",
+        target => target,
+    }
+}
+
+// One line of a log file, as written by the JSON formatter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    #[serde(default)]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+    // Enclosing spans, outermost first, e.g. the dispatcher's method and origin
+    #[serde(default)]
+    pub spans: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogFilter {
+    // Least severe level to include, e.g. "warn" also returns errors
+    pub level: Option<String>,
+    // Module path prefix, e.g. "app_lib::rpc_server"
+    pub target: Option<String>,
+    // Case-insensitive text anywhere in the entry
+    pub contains: Option<String>,
+    pub limit: Option<usize>,
+}
+
+// Structured logs to daily-rotated JSON files under the app log dir, plus the console in debug
+// builds and headless mode. `RUST_LOG` overrides the default filter. Records from crates using
+// `log`, like tauri, are forwarded as tracing events
+pub fn init(app: &AppHandle, console: bool) -> Result<Logging, String> {
+    let dir = app.path().app_log_dir().map_err(|e| format!("Log dir unavailable: {}", e))?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(&dir)
+        .map_err(|e| format!("Failed to open log file: {}", e))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let levels = Levels {
+        default: std::env::var(EnvFilter::DEFAULT_ENV).unwrap_or_else(|_| DEFAULT_FILTER.to_string()),
+        targets: BTreeMap::new(),
+    };
+    let filter = levels.filter().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let (filter, handle) = reload::Layer::new(filter);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().json().with_span_list(true).with_current_span(false).with_writer(writer))
+        .with(console.then(fmt::layer))
+        .try_init()
+        .map_err(|e| format!("Failed to install logger: {}", e))?;
+
+    Ok(Logging {
+        dir,
+        filter: handle,
+        levels: std::sync::Mutex::new(levels),
+        _guard: guard,
+    })
+}
+
+impl Logging {
+    // Changes the level for `target`, or the default level without one, taking effect immediately.
+    // `reset` drops a target's override
+    pub fn set_level(&self, target: Option<&str>, level: &str) -> Result<(), String> {
+        let mut levels = self.levels.lock().unwrap();
+        let mut updated = levels.clone();
+        match (target.filter(|target| !target.is_empty()), level) {
+            (Some(target), "reset") => {
+                updated.targets.remove(resolve_target(target));
+            },
+            (Some(target), level) => {
+                let level = level.parse::<LevelFilter>().map_err(|_| format!("Invalid log level {}", level))?;
+                updated.targets.insert(resolve_target(target).to_string(), level);
+            },
+            (None, "reset") => updated.default = DEFAULT_FILTER.to_string(),
+            (None, level) => {
+                let level = level.parse::<LevelFilter>().map_err(|_| format!("Invalid log level {}", level))?;
+                updated.default = level.to_string();
+            },
+        }
+
+        let filter = updated.filter()?;
+        self.filter.reload(filter).map_err(|e| format!("Failed to update log filter: {}", e))?;
+        *levels = updated;
+        Ok(())
+    }
+}
+
+impl LogFilter {
+    fn matches(&self, entry: &LogEntry, line: &str, level: Option<Level>) -> bool {
+        if let Some(level) = level {
+            match entry.level.parse::<Level>() {
+                Ok(entry_level) if entry_level <= level => {},
+                _ => return false,
+            }
+        }
+        if let Some(target) = &self.target {
+            if !entry.target.starts_with(target.as_str()) {
+                return false;
+            }
+        }
+        match &self.contains {
+            Some(text) => line.to_lowercase().contains(&text.to_lowercase()),
+            None => true,
+        }
+    }
+}
+
+// Log files, newest first. Rotated names end in the date, so they sort by age
+fn log_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(|name| name.starts_with(LOG_FILE_PREFIX))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort_unstable_by(|a, b| b.cmp(a));
+    files
+}
+
+// Deletes rotated log files, oldest first, until `bytes` are freed. Today's file is kept
+pub fn remove_oldest(dir: &Path, bytes: u64) -> u64 {
+    let mut freed = 0;
+    for path in log_files(dir).into_iter().skip(1).rev() {
+        if freed >= bytes {
+            break;
+        }
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or_default();
+        if std::fs::remove_file(&path).is_ok() {
+            freed += size;
+        }
+    }
+    freed
+}
+
+fn read_recent(dir: &Path, filter: &LogFilter) -> Result<Vec<LogEntry>, String> {
+    let level = filter.level
+        .as_deref()
+        .map(|level| level.parse::<Level>().map_err(|_| format!("Invalid log level {}", level)))
+        .transpose()?;
+    let limit = filter.limit.unwrap_or(DEFAULT_LOG_LIMIT);
+
+    let mut entries = Vec::new();
+    for path in log_files(dir) {
+        if entries.len() == limit {
+            break;
+        }
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        for line in contents.lines().rev() {
+            if entries.len() == limit {
+                break;
+            }
+            if let Ok(entry) = serde_json::from_str::<LogEntry>(line) {
+                if filter.matches(&entry, line, level) {
+                    entries.push(entry);
+                }
+            }
+        }
+    }
+    // Oldest first, like the file
+    entries.reverse();
+    Ok(entries)
+}
+
+// The most recent entries matching `filter`, for the diagnostics panel
+pub async fn recent(app: &AppHandle, filter: LogFilter) -> Result<Vec<LogEntry>, String> {
+    let dir = app.state::<Logging>().dir.clone();
+    tokio::task::spawn_blocking(move || read_recent(&dir, &filter))
+        .await
+        .map_err(|e| format!("Log read task failed: {}", e))?
+}
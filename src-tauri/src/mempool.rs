@@ -0,0 +1,94 @@
+use futures::{SinkExt, StreamExt};
+use serde_json::json;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::outbound;
+use crate::subscriptions::{self, SubscriptionKind};
+use crate::AppState;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+pub fn is_ws_url(url: &str) -> bool {
+    url::Url::parse(url).is_ok_and(|parsed| matches!(parsed.scheme(), "ws" | "wss"))
+}
+
+// The WebSocket endpoint and chain the stream should follow, None once it has nobody to serve
+async fn target(app: &AppHandle) -> Option<(String, u64)> {
+    let state = app.state::<Mutex<AppState>>();
+    let mut state_guard = state.lock().await;
+    let chain_id = state_guard.chain_id;
+    let ws_url = state_guard.config.as_ref().and_then(|config| config.ws_url.clone());
+    let subscribed = !state_guard.subscriptions.matching(chain_id, &SubscriptionKind::NewPendingTransactions).is_empty();
+    match ws_url {
+        Some(ws_url) if subscribed => Some((ws_url, chain_id)),
+        _ => {
+            state_guard.pending_stream = None;
+            None
+        }
+    }
+}
+
+// Forwards the execution RPC's pending transaction hashes until the connection drops, the app
+// moves to another endpoint, or the last subscriber leaves
+async fn stream(app: &AppHandle, ws_url: &str, chain_id: u64) -> Result<(), String> {
+    let mut socket = outbound::connect_ws(ws_url).await?;
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_subscribe",
+        "params": ["newPendingTransactions"],
+    });
+    socket.send(Message::Text(request.to_string()))
+        .await
+        .map_err(|e| format!("Failed to subscribe: {}", e))?;
+
+    while let Some(message) = socket.next().await {
+        let text = match message.map_err(|e| e.to_string())? {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            // Pings are answered by tungstenite
+            _ => continue,
+        };
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(&text) else {
+            continue;
+        };
+        if let Some(error) = value.get("error") {
+            return Err(format!("Execution RPC refused the subscription: {}", error));
+        }
+        let Some(hash) = value.pointer("/params/result") else {
+            continue;
+        };
+
+        let state = app.state::<Mutex<AppState>>();
+        let state_guard = state.lock().await;
+        let current = state_guard.config.as_ref().and_then(|config| config.ws_url.as_deref());
+        if current != Some(ws_url) || state_guard.chain_id != chain_id {
+            return Ok(());
+        }
+        let subscribers = state_guard.subscriptions.matching(chain_id, &SubscriptionKind::NewPendingTransactions);
+        if subscribers.is_empty() {
+            return Ok(());
+        }
+        for subscription in &subscribers {
+            subscriptions::notify_unverified(app, subscription, hash);
+        }
+    }
+    Err("Connection closed".to_string())
+}
+
+// Mempool contents can't be proven, so the stream is relayed from the execution RPC as-is and
+// every notification is tagged `unverified`. Reconnects while anyone is subscribed
+pub fn spawn_pending_stream(app: AppHandle) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        while let Some((ws_url, chain_id)) = target(&app).await {
+            if let Err(e) = stream(&app, &ws_url, chain_id).await {
+                tracing::warn!("Pending transaction stream: {}", e);
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    })
+}
@@ -0,0 +1,146 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Upper bounds of the latency histogram buckets, anything slower lands in a final overflow bucket
+pub const LATENCY_BUCKETS_MS: &[u64] = &[5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+// Method names come from pages, so past this many origin/method pairs new ones share one entry
+const MAX_ENTRIES: usize = 2_000;
+const OVERFLOW_KEY: &str = "(other)";
+
+// Upstream HTTP traffic per host. passthrough::forward has no app handle, so these live in a static
+static UPSTREAM: Mutex<BTreeMap<String, UpstreamStats>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallStats {
+    pub count: u64,
+    pub errors: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+    // One count per LATENCY_BUCKETS_MS bound plus the overflow bucket
+    pub latency_buckets: Vec<u64>,
+}
+
+impl Default for CallStats {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            errors: 0,
+            total_ms: 0,
+            max_ms: 0,
+            latency_buckets: vec![0; LATENCY_BUCKETS_MS.len() + 1],
+        }
+    }
+}
+
+impl CallStats {
+    fn record(&mut self, elapsed: Duration, error: bool) {
+        let ms = elapsed.as_millis() as u64;
+        self.count += 1;
+        if error {
+            self.errors += 1;
+        }
+        self.total_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| ms <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.latency_buckets[bucket] += 1;
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamStats {
+    pub requests: u64,
+    pub errors: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallEntry {
+    pub origin: String,
+    pub method: String,
+    #[serde(flatten)]
+    pub stats: CallStats,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcStats {
+    // When collection started, in unix seconds
+    pub since: u64,
+    pub latency_buckets_ms: &'static [u64],
+    pub calls: Vec<CallEntry>,
+    pub upstream: BTreeMap<String, UpstreamStats>,
+}
+
+// Dispatcher counters keyed by origin and method, kept since app start
+pub struct Metrics {
+    since: u64,
+    calls: Mutex<BTreeMap<(String, String), CallStats>>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            since: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            calls: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl Metrics {
+    pub fn record(&self, origin: &str, method: &str, elapsed: Duration, error: bool) {
+        let mut calls = self.calls.lock().unwrap();
+        let mut key = (origin.to_string(), method.to_string());
+        if calls.len() >= MAX_ENTRIES && !calls.contains_key(&key) {
+            key = (OVERFLOW_KEY.to_string(), OVERFLOW_KEY.to_string());
+        }
+        calls.entry(key).or_default().record(elapsed, error);
+    }
+
+    pub fn snapshot(&self) -> RpcStats {
+        let calls = self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((origin, method), stats)| CallEntry {
+                origin: origin.clone(),
+                method: method.clone(),
+                stats: stats.clone(),
+            })
+            .collect();
+        RpcStats {
+            since: self.since,
+            latency_buckets_ms: LATENCY_BUCKETS_MS,
+            calls,
+            upstream: UPSTREAM.lock().unwrap().clone(),
+        }
+    }
+}
+
+// Counts one upstream request against the URL's host
+pub fn record_upstream(url: &str, bytes_sent: usize, bytes_received: usize, error: bool) {
+    let host = url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| OVERFLOW_KEY.to_string());
+    let mut upstream = UPSTREAM.lock().unwrap();
+    let stats = upstream.entry(host).or_default();
+    stats.requests += 1;
+    if error {
+        stats.errors += 1;
+    }
+    stats.bytes_sent += bytes_sent as u64;
+    stats.bytes_received += bytes_received as u64;
+}
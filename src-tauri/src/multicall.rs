@@ -0,0 +1,88 @@
+use alloy::primitives::{address, Address, Bytes};
+use alloy::rpc::types::TransactionRequest;
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use helios::core::types::BlockTag;
+use serde::{Deserialize, Serialize};
+
+use crate::client::EthClientApi;
+
+// Multicall3 is deployed at the same address on every major chain
+pub const MULTICALL3_ADDRESS: Address = address!("cA11bde05977b3631167028862bE2a173976CA11");
+
+sol! {
+    struct Call3 {
+        address target;
+        bool allowFailure;
+        bytes callData;
+    }
+
+    struct Call3Result {
+        bool success;
+        bytes returnData;
+    }
+
+    function aggregate3(Call3[] calldata calls) external payable returns (Call3Result[] memory returnData);
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateCall {
+    pub target: Address,
+    pub call_data: Bytes,
+    #[serde(default = "default_allow_failure")]
+    pub allow_failure: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateResult {
+    pub success: bool,
+    pub return_data: Bytes,
+}
+
+fn default_allow_failure() -> bool {
+    true
+}
+
+// Packs the calls into a single Multicall3 `aggregate3` eth_call and unpacks the results in order
+pub async fn aggregate(
+    client: &dyn EthClientApi,
+    calls: &[AggregateCall],
+    block_tag: BlockTag,
+) -> Result<Vec<AggregateResult>, String> {
+    if calls.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let calldata = aggregate3Call {
+        calls: calls
+            .iter()
+            .map(|call| Call3 {
+                target: call.target,
+                allowFailure: call.allow_failure,
+                callData: call.call_data.clone(),
+            })
+            .collect(),
+    }
+    .abi_encode();
+
+    let tx = TransactionRequest::default()
+        .to(MULTICALL3_ADDRESS)
+        .input(Bytes::from(calldata).into());
+
+    let output = client.call(&tx, block_tag)
+        .await
+        .map_err(|e| format!("Multicall3 call failed: {}", e))?;
+
+    let decoded = aggregate3Call::abi_decode_returns(&output, true)
+        .map_err(|e| format!("Failed to decode Multicall3 response: {}", e))?;
+
+    Ok(decoded.returnData
+        .into_iter()
+        .map(|result| AggregateResult {
+            success: result.success,
+            return_data: result.returnData,
+        })
+        .collect())
+}
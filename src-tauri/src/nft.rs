@@ -0,0 +1,278 @@
+use alloy::primitives::{Address, U256};
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use base64::Engine;
+use helios::core::types::BlockTag;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::client::EthClientApi;
+use crate::contract;
+use crate::ipfs::{IpfsError, IpfsFetcher};
+use crate::multicall::{self, AggregateCall};
+use crate::outbound;
+use crate::unixfs::Cid;
+
+const ERC721_INTERFACE: [u8; 4] = [0x80, 0xac, 0x58, 0xcd];
+const ERC721_ENUMERABLE_INTERFACE: [u8; 4] = [0x78, 0x0e, 0x9d, 0x63];
+const ERC1155_INTERFACE: [u8; 4] = [0xd9, 0xb6, 0x7a, 0x26];
+
+// Enumeration is one call per token, so very large collections are truncated
+const MAX_ENUMERATED_TOKENS: usize = 200;
+const MAX_METADATA_SIZE: usize = 1024 * 1024;
+
+sol! {
+    function supportsInterface(bytes4 interfaceId) external view returns (bool);
+
+    interface IERC721 {
+        function ownerOf(uint256 tokenId) external view returns (address);
+        function balanceOf(address owner) external view returns (uint256);
+        function tokenURI(uint256 tokenId) external view returns (string);
+        function tokenOfOwnerByIndex(address owner, uint256 index) external view returns (uint256);
+    }
+
+    interface IERC1155 {
+        function balanceOf(address account, uint256 id) external view returns (uint256);
+        function uri(uint256 id) external view returns (string);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NftStandard {
+    Erc721,
+    Erc1155,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NftOwnership {
+    pub contract: Address,
+    pub token_id: U256,
+    pub standard: NftStandard,
+    pub owned: bool,
+    pub balance: U256,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OwnedNfts {
+    pub contract: Address,
+    pub standard: NftStandard,
+    pub balance: U256,
+    // Only filled for ERC721Enumerable collections, other contracts can't be listed on-chain
+    pub token_ids: Vec<U256>,
+    pub enumerable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NftMetadata {
+    pub contract: Address,
+    pub token_id: U256,
+    pub standard: NftStandard,
+    pub token_uri: String,
+    pub metadata: serde_json::Value,
+    pub image: Option<String>,
+}
+
+async fn supports_interface(client: &dyn EthClientApi, contract: Address, interface: [u8; 4]) -> bool {
+    let calldata = supportsInterfaceCall { interfaceId: interface.into() }.abi_encode();
+    contract::call(client, contract, calldata)
+        .await
+        .ok()
+        .and_then(|out| supportsInterfaceCall::abi_decode_returns(&out, true).ok())
+        .map(|r| r._0)
+        .unwrap_or(false)
+}
+
+pub async fn detect_standard(client: &dyn EthClientApi, contract: Address) -> Result<NftStandard, String> {
+    if supports_interface(client, contract, ERC721_INTERFACE).await {
+        Ok(NftStandard::Erc721)
+    } else if supports_interface(client, contract, ERC1155_INTERFACE).await {
+        Ok(NftStandard::Erc1155)
+    } else {
+        Err(format!("0x{:x} is not an ERC-721 or ERC-1155 contract", contract))
+    }
+}
+
+// Checks ownership of a single token with verified calls: `ownerOf` for ERC-721, `balanceOf` for ERC-1155
+pub async fn verify_ownership(
+    client: &dyn EthClientApi,
+    owner: Address,
+    contract: Address,
+    token_id: U256,
+) -> Result<NftOwnership, String> {
+    let standard = detect_standard(client, contract).await?;
+    let balance = match standard {
+        NftStandard::Erc721 => {
+            let out = contract::call(client, contract, IERC721::ownerOfCall { tokenId: token_id }.abi_encode()).await?;
+            let holder = IERC721::ownerOfCall::abi_decode_returns(&out, true)
+                .map_err(|e| format!("Invalid ownerOf response: {}", e))?
+                ._0;
+            if holder == owner { U256::from(1) } else { U256::ZERO }
+        },
+        NftStandard::Erc1155 => {
+            let out = contract::call(client, contract, IERC1155::balanceOfCall { account: owner, id: token_id }.abi_encode()).await?;
+            IERC1155::balanceOfCall::abi_decode_returns(&out, true)
+                .map_err(|e| format!("Invalid balanceOf response: {}", e))?
+                ._0
+        },
+    };
+
+    Ok(NftOwnership {
+        contract,
+        token_id,
+        standard,
+        owned: !balance.is_zero(),
+        balance,
+    })
+}
+
+// Lists what `owner` holds in an ERC-721 collection. Token ids come from ERC721Enumerable when
+// the contract supports it, otherwise only the balance is known
+pub async fn list_owned(client: &dyn EthClientApi, owner: Address, contract: Address) -> Result<OwnedNfts, String> {
+    let standard = detect_standard(client, contract).await?;
+    if standard == NftStandard::Erc1155 {
+        return Err(format!("ERC-1155 contract 0x{:x} can't be enumerated without token ids", contract));
+    }
+
+    let out = contract::call(client, contract, IERC721::balanceOfCall { owner }.abi_encode()).await?;
+    let balance = IERC721::balanceOfCall::abi_decode_returns(&out, true)
+        .map_err(|e| format!("Invalid balanceOf response: {}", e))?
+        ._0;
+
+    let enumerable = supports_interface(client, contract, ERC721_ENUMERABLE_INTERFACE).await;
+    let mut token_ids = Vec::new();
+    if enumerable && !balance.is_zero() {
+        let count = balance.saturating_to::<usize>().min(MAX_ENUMERATED_TOKENS);
+        let calls: Vec<AggregateCall> = (0..count)
+            .map(|index| AggregateCall {
+                target: contract,
+                call_data: IERC721::tokenOfOwnerByIndexCall { owner, index: U256::from(index) }.abi_encode().into(),
+                allow_failure: true,
+            })
+            .collect();
+        for result in multicall::aggregate(client, &calls, BlockTag::Latest).await? {
+            if let (true, Ok(decoded)) = (result.success, IERC721::tokenOfOwnerByIndexCall::abi_decode_returns(&result.return_data, true)) {
+                token_ids.push(decoded._0);
+            }
+        }
+    }
+
+    Ok(OwnedNfts {
+        contract,
+        standard,
+        balance,
+        token_ids,
+        enumerable,
+    })
+}
+
+// Resolves ipfs:// (through the CID-verifying fetcher), http(s):// and data: URIs
+async fn fetch_uri(uri: &str, ipfs: &IpfsFetcher) -> Result<Vec<u8>, String> {
+    if let Some(rest) = uri.strip_prefix("ipfs://") {
+        let rest = rest.strip_prefix("ipfs/").unwrap_or(rest);
+        let (cid, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let cid = Cid::parse(cid)?;
+        return match ipfs.fetch_path(cid, path).await {
+            Ok((content, _)) => Ok(content),
+            Err(IpfsError::NotFound(e)) | Err(IpfsError::Failed(e)) => Err(e),
+        };
+    }
+
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (header, payload) = data.split_once(',').ok_or("Malformed data URI")?;
+        return if header.ends_with(";base64") {
+            base64::engine::general_purpose::STANDARD
+                .decode(payload)
+                .map_err(|e| format!("Invalid base64 data URI: {}", e))
+        } else {
+            Ok(percent_encoding::percent_decode_str(payload).collect())
+        };
+    }
+
+    if uri.starts_with("https://") || uri.starts_with("http://") {
+        let bytes = outbound::client()
+            .get(uri)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Failed to fetch {}: {}", uri, e))?
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", uri, e))?;
+        if bytes.len() > MAX_METADATA_SIZE {
+            return Err("Metadata exceeds maximum size".to_string());
+        }
+        return Ok(bytes.to_vec());
+    }
+
+    Err(format!("Unsupported metadata URI: {}", uri))
+}
+
+fn cache_path(cache_dir: &Path, contract: Address, token_id: U256) -> PathBuf {
+    cache_dir.join(format!("0x{:x}", contract)).join(format!("{}.json", token_id))
+}
+
+// Reads tokenURI/uri through a verified call, then fetches and caches the JSON it points to
+pub async fn get_metadata(
+    client: &dyn EthClientApi,
+    ipfs: &IpfsFetcher,
+    cache_dir: Option<&Path>,
+    contract: Address,
+    token_id: U256,
+) -> Result<NftMetadata, String> {
+    let path = cache_dir.map(|dir| cache_path(dir, contract, token_id));
+    if let Some(path) = &path {
+        if let Ok(bytes) = tokio::fs::read(path).await {
+            if let Ok(metadata) = serde_json::from_slice::<NftMetadata>(&bytes) {
+                return Ok(metadata);
+            }
+        }
+    }
+
+    let standard = detect_standard(client, contract).await?;
+    let token_uri = match standard {
+        NftStandard::Erc721 => {
+            let out = contract::call(client, contract, IERC721::tokenURICall { tokenId: token_id }.abi_encode()).await?;
+            IERC721::tokenURICall::abi_decode_returns(&out, true)
+                .map_err(|e| format!("Invalid tokenURI response: {}", e))?
+                ._0
+        },
+        NftStandard::Erc1155 => {
+            let out = contract::call(client, contract, IERC1155::uriCall { id: token_id }.abi_encode()).await?;
+            let uri = IERC1155::uriCall::abi_decode_returns(&out, true)
+                .map_err(|e| format!("Invalid uri response: {}", e))?
+                ._0;
+            // ERC-1155 substitutes the id as 64 lowercase hex characters
+            uri.replace("{id}", &format!("{:064x}", token_id))
+        },
+    };
+
+    let body = fetch_uri(&token_uri, ipfs).await?;
+    let metadata: serde_json::Value = serde_json::from_slice(&body)
+        .map_err(|e| format!("Invalid metadata JSON at {}: {}", token_uri, e))?;
+    let image = metadata.get("image")
+        .or_else(|| metadata.get("image_url"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    let metadata = NftMetadata {
+        contract,
+        token_id,
+        standard,
+        token_uri,
+        metadata,
+        image,
+    };
+
+    if let Some(path) = &path {
+        if let (Some(parent), Ok(bytes)) = (path.parent(), serde_json::to_vec(&metadata)) {
+            if tokio::fs::create_dir_all(parent).await.is_ok() {
+                let _ = tokio::fs::write(path, bytes).await;
+            }
+        }
+    }
+    Ok(metadata)
+}
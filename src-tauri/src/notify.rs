@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri_plugin_notification::NotificationExt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    // ETH arriving at a watched address
+    IncomingTransfer,
+    // Any other balance or nonce change on a watched address
+    WatchedActivity,
+    TransactionConfirmed,
+    TransactionFailed,
+}
+
+// Per-category toggles for native notifications, all on by default
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NotificationSettings {
+    pub incoming_transfer: bool,
+    pub watched_activity: bool,
+    pub transaction_confirmed: bool,
+    pub transaction_failed: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            incoming_transfer: true,
+            watched_activity: true,
+            transaction_confirmed: true,
+            transaction_failed: true,
+        }
+    }
+}
+
+impl NotificationSettings {
+    fn enabled(&self, category: Category) -> bool {
+        match category {
+            Category::IncomingTransfer => self.incoming_transfer,
+            Category::WatchedActivity => self.watched_activity,
+            Category::TransactionConfirmed => self.transaction_confirmed,
+            Category::TransactionFailed => self.transaction_failed,
+        }
+    }
+}
+
+pub fn show(app: &AppHandle, settings: &NotificationSettings, category: Category, title: &str, body: String) {
+    if !settings.enabled(category) {
+        return;
+    }
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        tracing::warn!("Failed to show notification: {}", e);
+    }
+}
@@ -0,0 +1,154 @@
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+
+use crate::cache::{HitCounts, ReadCacheLimits};
+use crate::{errors, retry};
+
+// Failures that mean the upstream couldn't be reached at all, rather than that it refused the request
+const CONNECTIVITY_ERROR_PATTERNS: &[&str] = &[
+    "error sending request",
+    "failed to send request",
+    "connection refused",
+    "dns error",
+    "network is unreachable",
+    "failed to connect",
+    "failed to reach proxy",
+];
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+pub fn is_connectivity_error(error: &serde_json::Value) -> bool {
+    let message = error.get("message").and_then(|m| m.as_str()).unwrap_or_default().to_lowercase();
+    error.get("code").and_then(|c| c.as_i64()) == Some(errors::INTERNAL_ERROR as i64)
+        && (retry::is_transient(&message) || CONNECTIVITY_ERROR_PATTERNS.iter().any(|pattern| message.contains(pattern)))
+}
+
+#[derive(Debug, Clone)]
+struct CachedAnswer {
+    result: serde_json::Value,
+    // Latest verified block when the answer was given
+    block_number: Option<u64>,
+    cached_at: u64,
+}
+
+// Last verified answer to each read request, so reads can still be answered while the execution
+// RPC is unreachable
+#[derive(Default)]
+pub struct ResponseCache {
+    answers: HashMap<String, CachedAnswer>,
+    order: VecDeque<String>,
+    pub counts: HitCounts,
+}
+
+fn key(chain_id: u64, method: &str, params: &serde_json::Value) -> String {
+    format!("{}:{}:{}", chain_id, method, params)
+}
+
+impl ResponseCache {
+    // Only verified answers to read methods are kept
+    pub fn is_cacheable(method: &str, response: &serde_json::Value) -> bool {
+        retry::is_idempotent(method) && response.get("result").is_some() && response.get("unverified").is_none()
+    }
+
+    pub fn insert(
+        &mut self,
+        chain_id: u64,
+        method: &str,
+        params: &serde_json::Value,
+        result: serde_json::Value,
+        block_number: Option<u64>,
+        limits: &ReadCacheLimits,
+    ) {
+        let key = key(chain_id, method, params);
+        let answer = CachedAnswer { result, block_number, cached_at: now() };
+        if self.answers.insert(key.clone(), answer).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > limits.max_entries {
+            if let Some(oldest) = self.order.pop_front() {
+                self.answers.remove(&oldest);
+            }
+        }
+    }
+
+    // Replaces a failed response with the cached answer, marked stale with the block it was verified at
+    pub fn serve_stale(
+        &mut self,
+        chain_id: u64,
+        method: &str,
+        params: &serde_json::Value,
+        response: &mut serde_json::Value,
+        limits: &ReadCacheLimits,
+    ) -> bool {
+        let answer = self.answers
+            .get(&key(chain_id, method, params))
+            .filter(|answer| limits.max_age_secs == 0 || now().saturating_sub(answer.cached_at) <= limits.max_age_secs)
+            .cloned();
+        self.counts.record(answer.is_some());
+        let Some(answer) = answer else {
+            return false;
+        };
+        let object = response.as_object_mut().unwrap();
+        object.remove("error");
+        object.insert("result".to_string(), answer.result);
+        object.insert("stale".to_string(), json!(true));
+        object.insert("lastVerifiedBlock".to_string(), json!(answer.block_number));
+        object.insert("cachedAt".to_string(), json!(answer.cached_at));
+        true
+    }
+
+    pub fn entries(&self) -> usize {
+        self.answers.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.answers.clear();
+        self.order.clear();
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConnectivityChanged {
+    online: bool,
+    last_verified_block: Option<u64>,
+}
+
+// Whether the upstream was reachable on the last request, `connectivity` is emitted when it flips
+// so the UI can show an offline banner
+pub struct Connectivity {
+    online: bool,
+}
+
+impl Default for Connectivity {
+    fn default() -> Self {
+        Self { online: true }
+    }
+}
+
+impl Connectivity {
+    pub fn is_online(&self) -> bool {
+        self.online
+    }
+
+    pub fn set(&mut self, app: &AppHandle, online: bool, last_verified_block: Option<u64>) {
+        if self.online == online {
+            return;
+        }
+        self.online = online;
+        if online {
+            tracing::info!("Upstream reachable again");
+        } else {
+            tracing::warn!("Upstream unreachable, serving cached answers");
+        }
+        let _ = app.emit("connectivity", ConnectivityChanged { online, last_verified_block });
+    }
+}
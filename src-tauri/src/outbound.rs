@@ -0,0 +1,258 @@
+use alloy::transports::http::reqwest;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, Method, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_socks::tcp::Socks5Stream;
+use tokio_tungstenite::{client_async_tls, connect_async, MaybeTlsStream, WebSocketStream};
+
+// Built from the current settings and shared by every handler, so bursts of requests reuse pooled
+// connections. Lives in a static because passthrough::forward has no app handle
+static CLIENT: RwLock<Option<reqwest::Client>> = RwLock::new(None);
+static PROXY: RwLock<Option<url::Url>> = RwLock::new(None);
+// Loopback relays by upstream URL, started the first time the light client needs one
+static RELAYS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+// A node on this machine is reached directly even when a proxy is set
+const NO_PROXY: &str = "localhost,127.0.0.1,::1";
+
+pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+pub fn validate_proxy(proxy: &str) -> Result<url::Url, String> {
+    let parsed = url::Url::parse(proxy).map_err(|e| format!("Invalid proxy {}: {}", proxy, e))?;
+    if !matches!(parsed.scheme(), "http" | "socks5" | "socks5h") {
+        return Err(format!("Proxy must be an http, socks5 or socks5h URL: {}", proxy));
+    }
+    if parsed.host_str().is_none() || parsed.port_or_known_default().is_none() {
+        return Err(format!("Proxy needs a host and port: {}", proxy));
+    }
+    Ok(parsed)
+}
+
+// How the shared HTTP client keeps connections. reqwest doesn't cap open connections, only how
+// many idle ones it keeps per host for reuse
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HttpSettings {
+    pub max_idle_per_host: usize,
+    // Idle pooled connections are closed after this long
+    pub idle_timeout_secs: u64,
+    // TCP keep-alive probe interval, 0 turns it off
+    pub tcp_keepalive_secs: u64,
+    // Negotiate HTTP/2 with servers that offer it, otherwise stick to HTTP/1.1
+    pub http2: bool,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            max_idle_per_host: 32,
+            idle_timeout_secs: 90,
+            tcp_keepalive_secs: 60,
+            http2: true,
+        }
+    }
+}
+
+impl HttpSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.idle_timeout_secs == 0 {
+            return Err("Idle connection timeout must be at least one second".to_string());
+        }
+        Ok(())
+    }
+
+    fn build(&self, proxy: Option<&url::Url>) -> Result<reqwest::Client, String> {
+        let mut builder = reqwest::Client::builder()
+            .pool_max_idle_per_host(self.max_idle_per_host)
+            .pool_idle_timeout(Duration::from_secs(self.idle_timeout_secs))
+            .tcp_keepalive((self.tcp_keepalive_secs > 0).then(|| Duration::from_secs(self.tcp_keepalive_secs)));
+        if !self.http2 {
+            builder = builder.http1_only();
+        }
+        builder = match proxy {
+            Some(proxy) => {
+                let proxy = reqwest::Proxy::all(proxy.as_str())
+                    .map_err(|e| format!("Invalid proxy: {}", e))?
+                    .no_proxy(reqwest::NoProxy::from_string(NO_PROXY));
+                builder.proxy(proxy)
+            },
+            None => builder,
+        };
+        builder.build().map_err(|e| format!("Failed to build HTTP client: {}", e))
+    }
+}
+
+// Replaces the shared client, connections pooled by the old one close once in-flight requests finish
+pub fn configure(settings: &HttpSettings, proxy: Option<&str>) {
+    let proxy = proxy.and_then(|proxy| validate_proxy(proxy).map_err(|e| tracing::warn!("{}", e)).ok());
+    match settings.build(proxy.as_ref()) {
+        Ok(client) => *CLIENT.write().unwrap() = Some(client),
+        Err(e) => tracing::warn!("{}", e),
+    }
+    *PROXY.write().unwrap() = proxy;
+}
+
+// helios builds its own HTTP clients with no way to hand them a proxy. While one is configured the
+// light client is pointed at a relay on localhost instead, which sends every request on through
+// the shared client. Takes effect the next time the light client starts
+pub async fn route(upstream: &str) -> Result<String, String> {
+    let host = url::Url::parse(upstream)
+        .map_err(|e| format!("Invalid URL {}: {}", upstream, e))?
+        .host_str()
+        .map(str::to_string)
+        .unwrap_or_default();
+    if PROXY.read().unwrap().is_none() || is_local(&host) {
+        return Ok(upstream.to_string());
+    }
+    if let Some(relay) = RELAYS.lock().unwrap().get(upstream) {
+        return Ok(relay.clone());
+    }
+
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .map_err(|e| format!("Failed to start relay for {}: {}", upstream, e))?;
+    let addr = listener.local_addr().map_err(|e| format!("Failed to read relay address: {}", e))?;
+    let router = axum::Router::new()
+        .fallback(relay)
+        .with_state(Arc::new(upstream.trim_end_matches('/').to_string()));
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, router).await {
+            tracing::error!("Relay stopped: {}", e);
+        }
+    });
+
+    let relay = format!("http://{}", addr);
+    // Two starts racing for the same upstream keep whichever relay registered first
+    Ok(RELAYS.lock().unwrap().entry(upstream.to_string()).or_insert(relay).clone())
+}
+
+// Replays one request against the upstream, keeping the path so consensus REST routes still resolve
+async fn relay(State(upstream): State<Arc<String>>, method: Method, uri: Uri, headers: HeaderMap, body: Bytes) -> Response {
+    let target = match uri.path_and_query().map(|path| path.as_str()) {
+        None | Some("/") => upstream.to_string(),
+        Some(path) => format!("{}{}", upstream, path),
+    };
+    let Ok(method) = reqwest::Method::from_bytes(method.as_str().as_bytes()) else {
+        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+    };
+    let mut request = client().request(method, &target).body(body);
+    for name in [header::ACCEPT, header::CONTENT_TYPE] {
+        if let Some(value) = headers.get(&name).and_then(|value| value.to_str().ok()) {
+            request = request.header(name.as_str(), value);
+        }
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => return (StatusCode::BAD_GATEWAY, format!("Relay failed: {}", e)).into_response(),
+    };
+    let status = StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let bytes = match response.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => return (StatusCode::BAD_GATEWAY, format!("Relay failed: {}", e)).into_response(),
+    };
+    match content_type {
+        Some(content_type) => (status, [(header::CONTENT_TYPE, content_type)], bytes).into_response(),
+        None => (status, bytes).into_response(),
+    }
+}
+
+pub fn client() -> reqwest::Client {
+    if let Some(client) = CLIENT.read().unwrap().as_ref() {
+        return client.clone();
+    }
+    let client = HttpSettings::default().build(None).unwrap_or_default();
+    CLIENT.write().unwrap().get_or_insert(client).clone()
+}
+
+fn is_local(host: &str) -> bool {
+    NO_PROXY.split(',').any(|local| local == host.trim_start_matches('[').trim_end_matches(']'))
+}
+
+// Basic credentials from the proxy URL, percent-decoded
+fn credentials(proxy: &url::Url) -> Option<(String, String)> {
+    if proxy.username().is_empty() {
+        return None;
+    }
+    let decode = |part: &str| percent_encoding::percent_decode_str(part).decode_utf8_lossy().into_owned();
+    Some((decode(proxy.username()), decode(proxy.password().unwrap_or_default())))
+}
+
+// Opens a tunnel to host:port through an HTTP proxy with CONNECT
+async fn http_connect(proxy: &url::Url, host: &str, port: u16) -> Result<TcpStream, String> {
+    let proxy_host = proxy.host_str().unwrap_or_default();
+    let proxy_port = proxy.port_or_known_default().unwrap_or(80);
+    let mut stream = TcpStream::connect((proxy_host, proxy_port))
+        .await
+        .map_err(|e| format!("Failed to reach proxy: {}", e))?;
+
+    let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+    if let Some((user, password)) = credentials(proxy) {
+        let token = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, password));
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", token));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write to proxy: {}", e))?;
+
+    // Read byte by byte so nothing past the proxy's headers is consumed
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() > 8 * 1024 {
+            return Err("Proxy sent an oversized response".to_string());
+        }
+        let byte = stream.read_u8().await.map_err(|e| format!("Failed to read from proxy: {}", e))?;
+        head.push(byte);
+    }
+    let status = String::from_utf8_lossy(&head);
+    let status = status.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some("200") => Ok(stream),
+        _ => Err(format!("Proxy refused the tunnel: {}", status)),
+    }
+}
+
+// Connects a WebSocket, through the configured proxy unless the endpoint is on this machine
+pub async fn connect_ws(ws_url: &str) -> Result<WsStream, String> {
+    let proxy = PROXY.read().unwrap().clone();
+    let target = url::Url::parse(ws_url).map_err(|e| format!("Invalid WebSocket URL {}: {}", ws_url, e))?;
+    let host = target.host_str().ok_or_else(|| format!("WebSocket URL has no host: {}", ws_url))?;
+    let port = target.port_or_known_default().unwrap_or(443);
+
+    let Some(proxy) = proxy.filter(|_| !is_local(host)) else {
+        return connect_async(ws_url)
+            .await
+            .map(|(socket, _)| socket)
+            .map_err(|e| format!("Failed to connect to {}: {}", ws_url, e));
+    };
+
+    let stream = if proxy.scheme() == "http" {
+        http_connect(&proxy, host, port).await?
+    } else {
+        // socks5 and socks5h both let the proxy resolve the name, so lookups don't leak locally
+        let proxy_addr = (proxy.host_str().unwrap_or_default(), proxy.port_or_known_default().unwrap_or(1080));
+        let connected = match credentials(&proxy) {
+            Some((user, password)) => Socks5Stream::connect_with_password(proxy_addr, (host, port), &user, &password).await,
+            None => Socks5Stream::connect(proxy_addr, (host, port)).await,
+        };
+        connected.map_err(|e| format!("Proxy failed to connect to {}: {}", ws_url, e))?.into_inner()
+    };
+    client_async_tls(ws_url, stream)
+        .await
+        .map(|(socket, _)| socket)
+        .map_err(|e| format!("Failed to connect to {}: {}", ws_url, e))
+}
@@ -0,0 +1,68 @@
+use alloy::transports::http::reqwest;
+use serde_json::json;
+
+use crate::metrics;
+use crate::outbound;
+use crate::websocket;
+
+// Trace and debug methods that can't be verified against the light client but are safe to
+// forward: none of them change chain state
+const ALLOWED_METHODS: &[&str] = &[
+    "trace_block",
+    "trace_call",
+    "trace_callMany",
+    "trace_filter",
+    "trace_get",
+    "trace_rawTransaction",
+    "trace_replayBlockTransactions",
+    "trace_replayTransaction",
+    "trace_transaction",
+    "debug_traceBlockByHash",
+    "debug_traceBlockByNumber",
+    "debug_traceCall",
+    "debug_traceTransaction",
+];
+
+pub fn is_allowed(method: &str) -> bool {
+    ALLOWED_METHODS.contains(&method)
+}
+
+// Sends the request to the execution RPC as-is and returns its JSON-RPC response body. Goes over
+// the endpoint's WebSocket when one is configured and reachable, over HTTP otherwise
+pub async fn forward(
+    rpc_url: &str,
+    method: &str,
+    params: &[serde_json::Value],
+) -> Result<serde_json::Value, String> {
+    if let Some(response) = websocket::request(rpc_url, method, params).await {
+        return Ok(response);
+    }
+
+    let payload = json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": 1
+    });
+    let body = serde_json::to_vec(&payload).map_err(|e| format!("Failed to serialize request: {}", e))?;
+    let sent = body.len();
+
+    let result = async {
+        outbound::client()
+            .post(rpc_url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Failed to send request: {}", e))?
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to read response: {}", e))
+    }
+    .await;
+    let received = result.as_ref().map(|bytes| bytes.len()).unwrap_or_default();
+    metrics::record_upstream(rpc_url, sent, received, result.is_err());
+
+    serde_json::from_slice(&result?).map_err(|e| format!("Failed to parse response: {}", e))
+}
@@ -0,0 +1,300 @@
+use alloy::consensus::TxEnvelope;
+use alloy::eips::eip2718::Decodable2718;
+use alloy::primitives::{Address, B256};
+use helios::core::types::BlockTag;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Mutex;
+
+use crate::notify;
+use crate::passthrough;
+use crate::profile;
+use crate::protect;
+use crate::AppState;
+
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(12);
+
+// A transaction the RPC has forgotten for this long is considered dropped
+const DROP_TIMEOUT_SECS: u64 = 30 * 60;
+
+// Finished entries kept around for the activity view
+const MAX_FINISHED: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PendingStatus {
+    Pending,
+    Confirmed,
+    Failed,
+    // The nonce was used by a transaction the wallet didn't broadcast
+    Replaced,
+    Dropped,
+}
+
+// One broadcast of a nonce; speed-ups and cancels add further attempts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attempt {
+    pub hash: B256,
+    pub gas_price: Option<u128>,
+    pub max_fee_per_gas: Option<u128>,
+    pub max_priority_fee_per_gas: Option<u128>,
+    pub submitted_at: u64,
+    // Sent through the private relay, so absence from the public mempool means nothing
+    #[serde(default)]
+    pub private: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingTransaction {
+    pub chain_id: u64,
+    pub from: Address,
+    pub nonce: u64,
+    pub attempts: Vec<Attempt>,
+    pub status: PendingStatus,
+    // Attempt that made it into a block
+    pub included_hash: Option<B256>,
+    pub block_number: Option<u64>,
+    pub updated_at: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+pub fn tracker_path(app: &AppHandle) -> Option<PathBuf> {
+    profile::data_dir(app).map(|dir| dir.join("pending.json"))
+}
+
+// Transactions the wallet has broadcast, persisted so they survive restarts
+#[derive(Default)]
+pub struct PendingTracker {
+    entries: Vec<PendingTransaction>,
+    loaded: bool,
+}
+
+impl PendingTracker {
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    pub async fn load(&mut self, path: &Path) {
+        if let Ok(bytes) = tokio::fs::read(path).await {
+            if let Ok(entries) = serde_json::from_slice(&bytes) {
+                self.entries = entries;
+            }
+        }
+        self.loaded = true;
+    }
+
+    pub async fn save(&self, path: &Path) -> Result<(), String> {
+        let bytes = serde_json::to_vec(&self.entries).map_err(|e| format!("Failed to serialize pending transactions: {}", e))?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Failed to create data dir: {}", e))?;
+        }
+        tokio::fs::write(path, bytes)
+            .await
+            .map_err(|e| format!("Failed to write pending transactions: {}", e))
+    }
+
+    pub fn list(&self, include_finished: bool) -> Vec<PendingTransaction> {
+        self.entries
+            .iter()
+            .filter(|entry| include_finished || entry.status == PendingStatus::Pending)
+            .cloned()
+            .collect()
+    }
+
+    // Whether a broadcast went through the private relay, so its replacement can go the same way
+    pub fn is_private(&self, hash: B256) -> bool {
+        self.entries
+            .iter()
+            .flat_map(|entry| &entry.attempts)
+            .any(|attempt| attempt.hash == hash && attempt.private)
+    }
+
+    // Nonce after the sender's highest transaction that is still waiting for inclusion
+    pub fn next_nonce(&self, chain_id: u64, from: Address) -> Option<u64> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.chain_id == chain_id && entry.from == from && entry.status == PendingStatus::Pending)
+            .map(|entry| entry.nonce + 1)
+            .max()
+    }
+
+    // Records a raw transaction after a successful broadcast, grouping attempts by sender and nonce
+    pub fn record(&mut self, raw: &[u8], chain_id: u64, private: bool) -> Result<(), String> {
+        let envelope = TxEnvelope::decode_2718(&mut &raw[..]).map_err(|e| format!("Invalid transaction: {}", e))?;
+        let from = envelope.recover_signer().map_err(|e| format!("Invalid signature: {}", e))?;
+        let (nonce, gas_price, max_fee_per_gas, max_priority_fee_per_gas) = match &envelope {
+            TxEnvelope::Legacy(tx) => (tx.tx().nonce, Some(tx.tx().gas_price), None, None),
+            TxEnvelope::Eip2930(tx) => (tx.tx().nonce, Some(tx.tx().gas_price), None, None),
+            TxEnvelope::Eip1559(tx) => (tx.tx().nonce, None, Some(tx.tx().max_fee_per_gas), Some(tx.tx().max_priority_fee_per_gas)),
+            TxEnvelope::Eip4844(tx) => {
+                let tx = tx.tx().tx();
+                (tx.nonce, None, Some(tx.max_fee_per_gas), Some(tx.max_priority_fee_per_gas))
+            },
+            _ => return Err("Unsupported transaction type".to_string()),
+        };
+
+        let attempt = Attempt {
+            hash: *envelope.tx_hash(),
+            gas_price,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            submitted_at: now(),
+            private,
+        };
+        let existing = self.entries
+            .iter_mut()
+            .find(|entry| entry.chain_id == chain_id && entry.from == from && entry.nonce == nonce);
+        match existing {
+            Some(entry) => {
+                if !entry.attempts.iter().any(|a| a.hash == attempt.hash) {
+                    entry.attempts.push(attempt);
+                }
+                entry.status = PendingStatus::Pending;
+                entry.updated_at = now();
+            },
+            None => self.entries.push(PendingTransaction {
+                chain_id,
+                from,
+                nonce,
+                attempts: vec![attempt],
+                status: PendingStatus::Pending,
+                included_hash: None,
+                block_number: None,
+                updated_at: now(),
+            }),
+        }
+        Ok(())
+    }
+
+    fn prune(&mut self) {
+        let finished = self.entries.iter().filter(|entry| entry.status != PendingStatus::Pending).count();
+        let mut excess = finished.saturating_sub(MAX_FINISHED);
+        self.entries.sort_by_key(|entry| entry.updated_at);
+        self.entries.retain(|entry| {
+            if excess > 0 && entry.status != PendingStatus::Pending {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+    }
+}
+
+pub async fn load_tracker(app: &AppHandle, tracker: &mut PendingTracker) {
+    if let (false, Some(path)) = (tracker.is_loaded(), tracker_path(app)) {
+        tracker.load(&path).await;
+    }
+}
+
+// Checks pending entries against verified receipts and nonces, emitting `pending-transaction-status` on changes
+pub fn spawn_pending_tracker(app: AppHandle) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(RECONCILE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let state = app.state::<Mutex<AppState>>();
+            let mut state_guard = state.lock().await;
+            load_tracker(&app, &mut state_guard.pending).await;
+            let accounts = state_guard.account_source(state_guard.chain_id);
+            let AppState { client, rpc_url, pending, private_relay, notifications, .. } = &mut *state_guard;
+            let Some(client) = client.as_ref() else {
+                break;
+            };
+            let chain_id = client.chain_id().await;
+
+            let mut changed = Vec::new();
+            for entry in pending.entries.iter_mut() {
+                if entry.chain_id != chain_id || entry.status != PendingStatus::Pending {
+                    continue;
+                }
+
+                let mut included = None;
+                for attempt in &entry.attempts {
+                    if let Ok(Some(receipt)) = client.get_transaction_receipt(attempt.hash).await {
+                        included = Some(receipt);
+                        break;
+                    }
+                }
+
+                if let Some(receipt) = included {
+                    entry.status = if receipt.status() { PendingStatus::Confirmed } else { PendingStatus::Failed };
+                    entry.included_hash = Some(receipt.transaction_hash);
+                    entry.block_number = receipt.block_number;
+                } else {
+                    let nonce = match accounts.account(client.as_ref(), entry.from, BlockTag::Latest).await {
+                        Ok(account) => account.nonce,
+                        Err(_) => continue,
+                    };
+                    if nonce > entry.nonce {
+                        entry.status = PendingStatus::Replaced;
+                    } else {
+                        let last_submitted = entry.attempts.iter().map(|a| a.submitted_at).max().unwrap_or_default();
+                        if now().saturating_sub(last_submitted) < DROP_TIMEOUT_SECS {
+                            continue;
+                        }
+                        let mut known = false;
+                        for attempt in &entry.attempts {
+                            known = if attempt.private {
+                                protect::get_status(&private_relay.status_url, attempt.hash)
+                                    .await
+                                    .map_or(true, |status| !status.status.is_final_failure())
+                            } else {
+                                passthrough::forward(rpc_url, "eth_getTransactionByHash", &[json!(attempt.hash)])
+                                    .await
+                                    .map_or(true, |r| !r.get("result").map_or(true, |v| v.is_null()))
+                            };
+                            if known {
+                                break;
+                            }
+                        }
+                        if known {
+                            continue;
+                        }
+                        entry.status = PendingStatus::Dropped;
+                    }
+                }
+                entry.updated_at = now();
+                changed.push(entry.clone());
+            }
+
+            if changed.is_empty() {
+                continue;
+            }
+            pending.prune();
+            if let Some(path) = tracker_path(&app) {
+                if let Err(e) = pending.save(&path).await {
+                    tracing::warn!("Pending tracker: {}", e);
+                }
+            }
+            for entry in changed {
+                let category = match entry.status {
+                    PendingStatus::Confirmed => Some((notify::Category::TransactionConfirmed, "Transaction confirmed", "confirmed")),
+                    PendingStatus::Failed => Some((notify::Category::TransactionFailed, "Transaction failed", "failed")),
+                    _ => None,
+                };
+                if let Some((category, title, outcome)) = category {
+                    let block = entry.block_number.map(|n| format!(" in block {}", n)).unwrap_or_default();
+                    let body = format!("Nonce {} from 0x{:x} {}{}", entry.nonce, entry.from, outcome, block);
+                    notify::show(&app, notifications, category, title, body);
+                }
+                let _ = app.emit("pending-transaction-status", entry);
+            }
+        }
+    })
+}
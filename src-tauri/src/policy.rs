@@ -0,0 +1,212 @@
+use alloy::primitives::utils::format_ether;
+use alloy::primitives::{Address, TxKind, U256};
+use alloy::rpc::types::TransactionRequest;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+use crate::profile;
+
+// Rules checked before an account signs a transaction, empty lists and None limits don't restrict
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Policy {
+    // Most wei the account may send per UTC day
+    pub daily_limit: Option<U256>,
+    // When set, only these destinations are allowed
+    pub allowlist: Vec<Address>,
+    pub denylist: Vec<Address>,
+    pub allowed_chains: Vec<u64>,
+    // Values above this need a second confirmation
+    pub confirm_above: Option<U256>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DailySpend {
+    day: u64,
+    spent: U256,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct PolicyFile {
+    policies: HashMap<Address, Policy>,
+    spent: HashMap<Address, DailySpend>,
+}
+
+pub enum Verdict {
+    Allow,
+    // Allowed once the user confirms again, with the reason to show
+    Confirm(String),
+}
+
+fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86400)
+        .unwrap_or_default()
+}
+
+pub fn policy_path(app: &AppHandle) -> Option<PathBuf> {
+    profile::data_dir(app).map(|dir| dir.join("policies.json"))
+}
+
+// Per-account policies and what each account has spent today, persisted together so limits
+// survive restarts
+#[derive(Default)]
+pub struct PolicyStore {
+    file: PolicyFile,
+    loaded: bool,
+}
+
+impl PolicyStore {
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    pub async fn load(&mut self, path: &Path) {
+        if let Ok(bytes) = tokio::fs::read(path).await {
+            if let Ok(file) = serde_json::from_slice(&bytes) {
+                self.file = file;
+            }
+        }
+        self.loaded = true;
+    }
+
+    pub async fn save(&self, path: &Path) -> Result<(), String> {
+        let bytes = serde_json::to_vec(&self.file).map_err(|e| format!("Failed to serialize policies: {}", e))?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Failed to create data dir: {}", e))?;
+        }
+        tokio::fs::write(path, bytes)
+            .await
+            .map_err(|e| format!("Failed to write policies: {}", e))
+    }
+
+    pub fn list(&self) -> HashMap<Address, Policy> {
+        self.file.policies.clone()
+    }
+
+    pub fn set(&mut self, account: Address, policy: Policy) {
+        self.file.policies.insert(account, policy);
+    }
+
+    pub fn remove(&mut self, account: Address) -> bool {
+        self.file.policies.remove(&account).is_some()
+    }
+
+    fn spent_today(&self, account: Address) -> U256 {
+        match self.file.spent.get(&account) {
+            Some(spend) if spend.day == today() => spend.spent,
+            _ => U256::ZERO,
+        }
+    }
+
+    // Denied transactions come back as Err with the rule they broke
+    pub fn evaluate(&self, tx: &TransactionRequest) -> Result<Verdict, String> {
+        self.evaluate_with(tx, U256::ZERO)
+    }
+
+    // Evaluates a batch as a whole, each transaction's value counting toward the daily limit of the next
+    pub fn evaluate_batch(&self, txs: &[TransactionRequest]) -> Result<Verdict, String> {
+        let mut pending: HashMap<Address, U256> = HashMap::new();
+        let mut confirm = Vec::new();
+        for tx in txs {
+            let from = tx.from.unwrap_or_default();
+            let queued = pending.get(&from).copied().unwrap_or_default();
+            if let Verdict::Confirm(reason) = self.evaluate_with(tx, queued)? {
+                confirm.push(reason);
+            }
+            *pending.entry(from).or_default() += tx.value.unwrap_or_default();
+        }
+        if confirm.is_empty() {
+            Ok(Verdict::Allow)
+        } else {
+            Ok(Verdict::Confirm(confirm.join("; ")))
+        }
+    }
+
+    // `queued` is value already approved in the same batch but not yet recorded
+    fn evaluate_with(&self, tx: &TransactionRequest, queued: U256) -> Result<Verdict, String> {
+        let Some(from) = tx.from else {
+            return Err("missing from address".to_string());
+        };
+        let Some(policy) = self.file.policies.get(&from) else {
+            return Ok(Verdict::Allow);
+        };
+
+        if let Some(chain_id) = tx.chain_id {
+            if !policy.allowed_chains.is_empty() && !policy.allowed_chains.contains(&chain_id) {
+                return Err(format!("chain {} is not allowed for this account", chain_id));
+            }
+        }
+
+        match tx.to {
+            Some(TxKind::Call(to)) => {
+                if policy.denylist.contains(&to) {
+                    return Err(format!("0x{:x} is on the denylist", to));
+                }
+                if !policy.allowlist.is_empty() && !policy.allowlist.contains(&to) {
+                    return Err(format!("0x{:x} is not on the allowlist", to));
+                }
+            },
+            _ => if !policy.allowlist.is_empty() {
+                return Err("contract creation is not on the allowlist".to_string());
+            },
+        }
+
+        let value = tx.value.unwrap_or_default();
+        if let Some(limit) = policy.daily_limit {
+            let spent = self.spent_today(from).saturating_add(queued);
+            if spent.saturating_add(value) > limit {
+                return Err(format!(
+                    "daily limit of {} ETH would be exceeded, {} ETH already sent today",
+                    format_ether(limit),
+                    format_ether(spent)
+                ));
+            }
+        }
+        match policy.confirm_above {
+            Some(threshold) if value > threshold => Ok(Verdict::Confirm(format!(
+                "Sends {} ETH, above the {} ETH confirmation threshold",
+                format_ether(value),
+                format_ether(threshold)
+            ))),
+            _ => Ok(Verdict::Allow),
+        }
+    }
+
+    // Counts a signed transaction's value against the sender's daily limit
+    pub fn record_spend(&mut self, tx: &TransactionRequest) {
+        let (Some(from), Some(value)) = (tx.from, tx.value) else {
+            return;
+        };
+        let day = today();
+        let spend = self.file.spent.entry(from).or_default();
+        if spend.day != day {
+            *spend = DailySpend { day, spent: U256::ZERO };
+        }
+        spend.spent = spend.spent.saturating_add(value);
+    }
+}
+
+// Whether a transaction may be signed on a verdict reached under the signing lock. `confirmed` is
+// whether the user already gave the second confirmation an earlier verdict asked for
+pub fn enforce(verdict: Result<Verdict, String>, confirmed: bool) -> Result<(), String> {
+    match verdict {
+        Ok(Verdict::Allow) => Ok(()),
+        Ok(Verdict::Confirm(_)) if confirmed => Ok(()),
+        Ok(Verdict::Confirm(reason)) => Err(format!("{}, which wasn't confirmed", reason)),
+        Err(reason) => Err(reason),
+    }
+}
+
+pub async fn load_policies(app: &AppHandle, store: &mut PolicyStore) {
+    if let (false, Some(path)) = (store.is_loaded(), policy_path(app)) {
+        store.load(&path).await;
+    }
+}
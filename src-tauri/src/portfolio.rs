@@ -0,0 +1,61 @@
+use alloy::primitives::utils::format_ether;
+use alloy::primitives::{Address, U256};
+use helios::core::types::BlockTag;
+use serde::Serialize;
+
+use crate::client::EthClientApi;
+use crate::balances::{self, TokenBalance};
+use crate::distribution::AccountSource;
+use crate::tokens::TokenInfo;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainPortfolio {
+    pub chain_id: u64,
+    pub native_balance: U256,
+    pub native_formatted: String,
+    pub tokens: Vec<TokenBalance>,
+    // A chain that fails to answer is reported here instead of failing the whole portfolio
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Portfolio {
+    pub address: Address,
+    pub chains: Vec<ChainPortfolio>,
+}
+
+// Native and token balances for one chain, read concurrently against verified latest state
+pub async fn chain_portfolio(
+    client: &dyn EthClientApi,
+    accounts: &AccountSource,
+    owner: Address,
+    tokens: Vec<TokenInfo>,
+) -> ChainPortfolio {
+    let chain_id = client.chain_id().await;
+    let native = async {
+        accounts.account(client, owner, BlockTag::Latest)
+            .await
+            .map(|account| account.balance)
+            .map_err(|e| format!("Failed to get balance: {}", e))
+    };
+    let tokens = balances::token_balances(client, owner, tokens, false);
+
+    match futures::try_join!(native, tokens) {
+        Ok((native_balance, tokens)) => ChainPortfolio {
+            chain_id,
+            native_balance,
+            native_formatted: format_ether(native_balance),
+            tokens,
+            error: None,
+        },
+        Err(e) => ChainPortfolio {
+            chain_id,
+            native_balance: U256::ZERO,
+            native_formatted: format_ether(U256::ZERO),
+            tokens: Vec::new(),
+            error: Some(e),
+        },
+    }
+}
@@ -0,0 +1,155 @@
+use alloy::primitives::utils::format_units;
+use alloy::primitives::{address, Address, I256};
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use helios::core::types::BlockTag;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::client::EthClientApi;
+use crate::multicall::{self, AggregateCall};
+
+// Answers are reused for this long before the feed is read again
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+sol! {
+    function latestRoundData() external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound);
+    function decimals() external view returns (uint8);
+}
+
+// A Chainlink aggregator proxy quoting `symbol` in USD
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceFeed {
+    pub symbol: String,
+    pub chain_id: u64,
+    pub address: Address,
+    // Maximum seconds between updates before the answer is reported as stale
+    pub heartbeat: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Price {
+    pub symbol: String,
+    pub feed: Address,
+    pub answer: I256,
+    pub decimals: u8,
+    // Answer with decimals applied, e.g. "3120.55"
+    pub price: String,
+    pub round_id: u128,
+    pub updated_at: u64,
+    pub stale: bool,
+}
+
+// Mainnet USD feeds
+pub fn default_feeds() -> Vec<PriceFeed> {
+    [
+        ("ETH", address!("5f4eC3Df9cbd43714FE2740f5E3616155c5b8419"), 3600),
+        ("BTC", address!("F4030086522a5bEEa4988F8cA5B36dbC97BeE88c"), 3600),
+        ("LINK", address!("2c1d072e956AFFC0D435Cb7AC38EF18d24d9127c"), 3600),
+        ("USDC", address!("8fFfFfd4AfB6115b954Bd326cbe7B4BA576818f6"), 86400),
+        ("USDT", address!("3E7d1eAB13ad0104d2750B8863b489D65364e32D"), 86400),
+        ("DAI", address!("Aed0c38402a5d19df6E4c03F4E2DceD6e29c1ee9"), 3600),
+    ]
+    .into_iter()
+    .map(|(symbol, address, heartbeat)| PriceFeed {
+        symbol: symbol.to_string(),
+        chain_id: 1,
+        address,
+        heartbeat,
+    })
+    .collect()
+}
+
+#[derive(Default)]
+pub struct PriceCache {
+    entries: HashMap<(u64, Address), (Instant, Price)>,
+}
+
+impl PriceCache {
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// Reads every requested feed in one verified Multicall3 call, reusing cached answers that are still fresh
+pub async fn get_prices(
+    client: &dyn EthClientApi,
+    feeds: &[PriceFeed],
+    cache: &mut PriceCache,
+    symbols: &[String],
+) -> Result<Vec<Price>, String> {
+    let chain_id = client.chain_id().await;
+    let mut requested = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        let feed = feeds
+            .iter()
+            .find(|feed| feed.chain_id == chain_id && feed.symbol.eq_ignore_ascii_case(symbol))
+            .ok_or_else(|| format!("No price feed for {} on chain {}", symbol, chain_id))?;
+        requested.push(feed);
+    }
+
+    let missing: Vec<&PriceFeed> = requested
+        .iter()
+        .filter(|feed| {
+            cache.entries
+                .get(&(chain_id, feed.address))
+                .map_or(true, |(fetched, _)| fetched.elapsed() >= CACHE_TTL)
+        })
+        .copied()
+        .collect();
+
+    if !missing.is_empty() {
+        let calls: Vec<AggregateCall> = missing
+            .iter()
+            .flat_map(|feed| {
+                [latestRoundDataCall {}.abi_encode(), decimalsCall {}.abi_encode()].map(|call_data| AggregateCall {
+                    target: feed.address,
+                    call_data: call_data.into(),
+                    allow_failure: true,
+                })
+            })
+            .collect();
+        let results = multicall::aggregate(client, &calls, BlockTag::Latest).await?;
+
+        for (feed, pair) in missing.iter().zip(results.chunks(2)) {
+            let (round, decimals) = (&pair[0], &pair[1]);
+            let round = latestRoundDataCall::abi_decode_returns(&round.return_data, true)
+                .ok()
+                .filter(|_| round.success)
+                .ok_or_else(|| format!("Price feed 0x{:x} for {} failed", feed.address, feed.symbol))?;
+            let decimals = decimalsCall::abi_decode_returns(&decimals.return_data, true)
+                .ok()
+                .filter(|_| decimals.success)
+                .ok_or_else(|| format!("Price feed 0x{:x} for {} failed", feed.address, feed.symbol))?
+                ._0;
+
+            let updated_at = round.updatedAt.saturating_to::<u64>();
+            let price = Price {
+                symbol: feed.symbol.clone(),
+                feed: feed.address,
+                answer: round.answer,
+                decimals,
+                price: format_units(round.answer, decimals).unwrap_or_default(),
+                round_id: round.roundId.to::<u128>(),
+                updated_at,
+                stale: round.answer <= I256::ZERO || now().saturating_sub(updated_at) > feed.heartbeat,
+            };
+            cache.entries.insert((chain_id, feed.address), (Instant::now(), price));
+        }
+    }
+
+    Ok(requested
+        .iter()
+        .filter_map(|feed| cache.entries.get(&(chain_id, feed.address)).map(|(_, price)| price.clone()))
+        .collect())
+}
@@ -0,0 +1,111 @@
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tauri::{AppHandle, Manager};
+
+pub const DEFAULT_PROFILE: &str = "default";
+// Named profiles live in this subdirectory of the app's data and cache dirs
+pub const PROFILES_DIR: &str = "profiles";
+// Profile to open on the next launch, kept at the top of the app data dir
+const ACTIVE_PROFILE_FILE: &str = "active-profile";
+const MAX_NAME_LEN: usize = 32;
+
+// Profile whose data the app is using. Path helpers are called from places that have the app
+// handle but may already hold the state lock, so like the HTTP client this lives in a static
+static ACTIVE: RwLock<Option<String>> = RwLock::new(None);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileList {
+    pub active: String,
+    pub profiles: Vec<String>,
+}
+
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(format!("Profile names must be 1 to {} characters", MAX_NAME_LEN));
+    }
+    if !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_') {
+        return Err(format!("Invalid profile name {}, use lowercase letters, digits, - and _", name));
+    }
+    Ok(())
+}
+
+pub fn active() -> String {
+    ACTIVE.read().unwrap().clone().unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+pub fn set_active(name: &str) {
+    *ACTIVE.write().unwrap() = (name != DEFAULT_PROFILE).then(|| name.to_string());
+}
+
+// The default profile keeps using the top of each dir, so data from before profiles existed
+// stays where it was
+fn scoped(root: PathBuf) -> PathBuf {
+    match ACTIVE.read().unwrap().as_deref() {
+        Some(name) => root.join(PROFILES_DIR).join(name),
+        None => root,
+    }
+}
+
+pub fn data_dir(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(scoped)
+}
+
+pub fn cache_dir(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_cache_dir().ok().map(scoped)
+}
+
+// Where helios keeps the checkpoint, next to rather than inside the default profile's so
+// clearing one never removes another
+pub fn light_client_dir() -> PathBuf {
+    match ACTIVE.read().unwrap().as_deref() {
+        Some(name) => PathBuf::from(format!("{}-{}", crate::DATA_DIR, name)),
+        None => PathBuf::from(crate::DATA_DIR),
+    }
+}
+
+// `--profile <name>` on the command line, otherwise the profile last switched to
+pub fn at_launch(app: &AppHandle, args: impl IntoIterator<Item = String>) -> String {
+    let args: Vec<String> = args.into_iter().collect();
+    let requested = args.iter().position(|arg| arg == "--profile").and_then(|i| args.get(i + 1)).cloned();
+    if let Some(name) = requested {
+        match validate_name(&name) {
+            Ok(()) => return name,
+            Err(e) => tracing::warn!("Ignoring --profile: {}", e),
+        }
+    }
+    app.path()
+        .app_data_dir()
+        .ok()
+        .and_then(|dir| std::fs::read_to_string(dir.join(ACTIVE_PROFILE_FILE)).ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| validate_name(name).is_ok())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+pub async fn remember(app: &AppHandle, name: &str) -> Result<(), String> {
+    let dir = app.path().app_data_dir().map_err(|e| format!("No data dir: {}", e))?;
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Failed to create data dir: {}", e))?;
+    tokio::fs::write(dir.join(ACTIVE_PROFILE_FILE), name)
+        .await
+        .map_err(|e| format!("Failed to save active profile: {}", e))
+}
+
+// The default profile and every named one that has stored data
+pub async fn list(app: &AppHandle) -> ProfileList {
+    let mut profiles = vec![DEFAULT_PROFILE.to_string()];
+    if let Ok(dir) = app.path().app_data_dir() {
+        if let Ok(mut entries) = tokio::fs::read_dir(dir.join(PROFILES_DIR)).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                if let Some(name) = entry.file_name().to_str().filter(|name| validate_name(name).is_ok()) {
+                    profiles.push(name.to_string());
+                }
+            }
+        }
+    }
+    profiles[1..].sort();
+    ProfileList { active: active(), profiles }
+}
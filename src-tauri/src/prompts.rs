@@ -0,0 +1,61 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::oneshot;
+
+// Unanswered prompts count as rejected after this long
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Prompt {
+    pub id: u64,
+    pub method: String,
+    pub payload: serde_json::Value,
+}
+
+// Requests waiting on the user, kept outside AppState so answering never contends with the
+// lock held by the request that is waiting
+#[derive(Default)]
+pub struct Prompts {
+    next_id: AtomicU64,
+    waiting: Mutex<HashMap<u64, oneshot::Sender<bool>>>,
+}
+
+impl Prompts {
+    pub fn resolve(&self, id: u64, approved: bool) -> bool {
+        let sender = self.waiting.lock().unwrap().remove(&id);
+        match sender {
+            Some(sender) => sender.send(approved).is_ok(),
+            None => false,
+        }
+    }
+
+    // Declines every prompt still waiting, they were raised for state that's going away
+    pub fn reject_all(&self) {
+        for (_, sender) in self.waiting.lock().unwrap().drain() {
+            let _ = sender.send(false);
+        }
+    }
+}
+
+// Emits `approval-request` and waits for the approval UI to answer through `resolve_prompt`
+pub async fn ask(app: &AppHandle, method: &str, payload: serde_json::Value) -> bool {
+    let prompts = app.state::<Prompts>();
+    let id = prompts.next_id.fetch_add(1, Ordering::Relaxed);
+    let (sender, receiver) = oneshot::channel();
+    prompts.waiting.lock().unwrap().insert(id, sender);
+
+    let prompt = Prompt { id, method: method.to_string(), payload };
+    if app.emit("approval-request", prompt).is_err() {
+        prompts.waiting.lock().unwrap().remove(&id);
+        return false;
+    }
+
+    let approved = matches!(tokio::time::timeout(PROMPT_TIMEOUT, receiver).await, Ok(Ok(true)));
+    prompts.waiting.lock().unwrap().remove(&id);
+    approved
+}
@@ -0,0 +1,92 @@
+use alloy::primitives::{keccak256, Address, Bytes, B256, U256};
+use helios::core::types::BlockTag;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::client::EthClientApi;
+use crate::passthrough;
+
+// Methods whose answers can carry the proof they were checked against
+pub fn is_exportable(method: &str) -> bool {
+    matches!(method, "eth_getBalance" | "eth_getStorageAt" | "eth_getProof")
+}
+
+// EIP-1186 proof for an answer plus the verified block it proves against, so external tools can
+// recheck it without trusting this app or the execution RPC
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedProof {
+    pub block_number: u64,
+    pub block_hash: B256,
+    pub state_root: B256,
+    pub proof: serde_json::Value,
+}
+
+fn field<T: std::str::FromStr>(value: &serde_json::Value, pointer: &str) -> Option<T> {
+    value.pointer(pointer)?.as_str()?.parse().ok()
+}
+
+// Resolves a tag to the verified block it currently means, so the answer and its proof are taken
+// at the same height
+pub async fn pin(client: &dyn EthClientApi, tag: BlockTag) -> Result<u64, String> {
+    client.get_block_by_number(tag, false)
+        .await
+        .map_err(|e| format!("Failed to get block: {}", e))?
+        .map(|block| block.number.to::<u64>())
+        .ok_or_else(|| "Block not available from the light client".to_string())
+}
+
+// Fetches the execution RPC's proof at `number` and checks that it is rooted in the verified state
+// root and proves the value the light client answered with
+pub async fn export(
+    client: &dyn EthClientApi,
+    rpc_url: &str,
+    method: &str,
+    params: &[serde_json::Value],
+    number: u64,
+    result: &serde_json::Value,
+) -> Result<ExportedProof, String> {
+    let block = client.get_block_by_number(BlockTag::Number(number), false)
+        .await
+        .map_err(|e| format!("Failed to get block: {}", e))?
+        .ok_or("Block not available from the light client")?;
+
+    let address: Address = params.first()
+        .and_then(|address| address.as_str())
+        .and_then(|address| address.parse().ok())
+        .ok_or("Invalid address")?;
+    let keys = match method {
+        "eth_getStorageAt" => json!([params.get(1)]),
+        "eth_getProof" => params.get(1).cloned().unwrap_or(json!([])),
+        _ => json!([]),
+    };
+    let upstream = passthrough::forward(rpc_url, "eth_getProof", &[json!(address), keys, json!(format!("0x{:x}", number))]).await?;
+    let proof = upstream.get("result")
+        .filter(|proof| !proof.is_null())
+        .cloned()
+        .ok_or("Execution RPC returned no proof")?;
+
+    // The first node of an account proof is the trie root itself
+    let root = proof.pointer("/accountProof/0")
+        .and_then(|node| node.as_str())
+        .and_then(|node| node.parse::<Bytes>().ok())
+        .map(keccak256);
+    if root != Some(block.state_root) {
+        return Err("Proof from execution RPC isn't rooted in the verified state root".to_string());
+    }
+    let proven = match method {
+        "eth_getBalance" => field::<U256>(&proof, "/balance") == field::<U256>(result, ""),
+        "eth_getStorageAt" => field::<U256>(&proof, "/storageProof/0/value") == field::<U256>(result, ""),
+        _ => true,
+    };
+    if !proven {
+        return Err("Proof from execution RPC doesn't match the verified value".to_string());
+    }
+
+    Ok(ExportedProof {
+        block_number: number,
+        block_hash: block.hash,
+        state_root: block.state_root,
+        proof,
+    })
+}
@@ -0,0 +1,81 @@
+use alloy::hex;
+use alloy::primitives::B256;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::outbound;
+use crate::passthrough;
+
+pub const DEFAULT_RELAY_URL: &str = "https://rpc.flashbots.net";
+pub const DEFAULT_STATUS_URL: &str = "https://protect.flashbots.net/tx";
+
+// Private submission keeps transactions out of the public mempool until they are included
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayConfig {
+    pub enabled: bool,
+    pub relay_url: String,
+    pub status_url: String,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            relay_url: DEFAULT_RELAY_URL.to_string(),
+            status_url: DEFAULT_STATUS_URL.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum RelayStatus {
+    Pending,
+    Included,
+    Failed,
+    Cancelled,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivateTxStatus {
+    pub hash: B256,
+    pub status: RelayStatus,
+    pub max_block_number: Option<u64>,
+    #[serde(default)]
+    pub seen_in_mempool: bool,
+}
+
+impl RelayStatus {
+    // Failed and cancelled transactions will never be included by the relay
+    pub fn is_final_failure(&self) -> bool {
+        matches!(self, RelayStatus::Failed | RelayStatus::Cancelled)
+    }
+}
+
+// Submits a signed transaction to the private relay instead of the execution RPC
+pub async fn send_raw_transaction(relay_url: &str, raw: &[u8]) -> Result<B256, String> {
+    let response = passthrough::forward(relay_url, "eth_sendRawTransaction", &[json!(format!("0x{}", hex::encode(raw)))]).await?;
+    if let Some(error) = response.get("error") {
+        let message = error.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error");
+        return Err(format!("Private relay rejected transaction: {}", message));
+    }
+    response.get("result")
+        .and_then(|result| result.as_str())
+        .and_then(|hash| hash.parse().ok())
+        .ok_or_else(|| "Private relay returned no transaction hash".to_string())
+}
+
+pub async fn get_status(status_url: &str, hash: B256) -> Result<PrivateTxStatus, String> {
+    outbound::client()
+        .get(format!("{}/0x{:x}", status_url.trim_end_matches('/'), hash))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Private relay status request failed: {}", e))?
+        .json::<PrivateTxStatus>()
+        .await
+        .map_err(|e| format!("Invalid private relay status: {}", e))
+}
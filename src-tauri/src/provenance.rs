@@ -0,0 +1,83 @@
+use serde::Serialize;
+use serde_json::json;
+
+// How a result was obtained, added to every successful response as `verification` so the UI can
+// show how far to trust each value. Whatever produced the answer sets it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provenance {
+    // Proof-checked by helios against a verified header
+    Verified,
+    // Derived locally, from verified state or the app's own data
+    Computed,
+    // Taken from an upstream service without verification
+    Passthrough,
+}
+
+// Answers of the dispatcher's own handlers, every other path tags its answers where it produces
+// them. Only an eth_getProof answer whose proof was checked against the verified state root counts
+pub fn classify(method: &str, proven: bool) -> Provenance {
+    match method {
+        "eth_getBalance"
+        | "eth_getCode"
+        | "eth_getStorageAt"
+        | "eth_getTransactionCount"
+        | "eth_getBlockByNumber"
+        | "eth_getBlockByHash"
+        | "eth_getBlockTransactionCountByNumber"
+        | "eth_getBlockTransactionCountByHash"
+        | "eth_getBlockReceipts"
+        | "eth_getTransactionByHash"
+        | "eth_getTransactionByBlockHashAndIndex"
+        | "eth_getTransactionReceipt"
+        | "eth_getLogs"
+        | "eth_getFilterChanges"
+        | "eth_coinbase" => Provenance::Verified,
+        "eth_getProof" if proven => Provenance::Verified,
+        "eth_getProof" => Provenance::Passthrough,
+        // The transaction hash is the execution RPC's or relay's acknowledgement
+        "eth_sendRawTransaction" | "eth_sendTransaction" => Provenance::Passthrough,
+        _ => Provenance::Computed,
+    }
+}
+
+// Sets `verification` on a successful response that doesn't carry one yet
+pub fn tag(response: &mut serde_json::Value, provenance: Provenance) {
+    if response.get("result").is_none() {
+        return;
+    }
+    if let Some(object) = response.as_object_mut() {
+        object.entry("verification").or_insert(json!(provenance));
+    }
+}
+
+pub fn is_verified(response: &serde_json::Value) -> bool {
+    response.get("verification") == Some(&json!(Provenance::Verified))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_first_producer_to_tag_an_answer_decides() {
+        let mut response = json!({ "jsonrpc": "2.0", "id": 1, "result": "0x1" });
+        tag(&mut response, classify("eth_getBalance", false));
+        assert!(is_verified(&response));
+
+        let mut fallback = json!({ "jsonrpc": "2.0", "id": 1, "result": "0x1", "unverified": true });
+        tag(&mut fallback, Provenance::Passthrough);
+        tag(&mut fallback, classify("eth_getBalance", false));
+        assert_eq!(fallback["verification"], json!("passthrough"));
+
+        let mut failed = json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": -32603, "message": "down" } });
+        tag(&mut failed, Provenance::Verified);
+        assert!(failed.get("verification").is_none());
+    }
+
+    #[test]
+    fn only_checked_proofs_are_verified() {
+        assert_eq!(classify("eth_getProof", true), Provenance::Verified);
+        assert_eq!(classify("eth_getProof", false), Provenance::Passthrough);
+    }
+}
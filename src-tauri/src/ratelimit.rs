@@ -0,0 +1,34 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::accounts::Caller;
+
+// Requests a single dapp window may make per second, well above what a busy page polls at
+pub const REQUESTS_PER_SECOND: u32 = 100;
+
+const WINDOW: Duration = Duration::from_secs(1);
+
+struct Usage {
+    started: Instant,
+    count: u32,
+}
+
+// Fixed one-second windows per caller, so a runaway page can't starve the other windows sharing
+// the light client
+#[derive(Default)]
+pub struct RateLimiter {
+    by_caller: HashMap<Caller, Usage>,
+}
+
+impl RateLimiter {
+    // Counts one request, false when the caller is over its limit for the current second
+    pub fn check(&mut self, caller: &Caller) -> bool {
+        let now = Instant::now();
+        self.by_caller.retain(|_, usage| now.duration_since(usage.started) < WINDOW);
+        let usage = self.by_caller
+            .entry(caller.clone())
+            .or_insert(Usage { started: now, count: 0 });
+        usage.count += 1;
+        usage.count <= REQUESTS_PER_SECOND
+    }
+}
@@ -0,0 +1,277 @@
+use alloy::primitives::{Address, Bloom, Bytes, B256, U64};
+use alloy::rlp::{Encodable, Header};
+use alloy::rpc::types::Filter;
+use alloy_trie::{HashBuilder, Nibbles};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::client::EthClientApi;
+use crate::headers::VerifiedHeader;
+use crate::passthrough;
+
+// The consensus fields of a receipt, enough to rebuild its trie leaf
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Receipt {
+    #[serde(rename = "type", default)]
+    tx_type: U64,
+    status: U64,
+    cumulative_gas_used: U64,
+    logs_bloom: Bloom,
+    logs: Vec<Log>,
+}
+
+#[derive(Deserialize)]
+struct Log {
+    address: Address,
+    topics: Vec<B256>,
+    data: Bytes,
+}
+
+fn list(payload: Vec<u8>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 9);
+    Header { list: true, payload_length: payload.len() }.encode(&mut out);
+    out.extend(payload);
+    out
+}
+
+fn encode_log(log: &Log) -> Vec<u8> {
+    let mut payload = Vec::new();
+    log.address.encode(&mut payload);
+    log.topics.encode(&mut payload);
+    log.data.encode(&mut payload);
+    list(payload)
+}
+
+// EIP-2718 encoding, the type byte followed by the RLP receipt, or the bare RLP for legacy ones
+fn encode_receipt(receipt: &Receipt) -> Vec<u8> {
+    let mut payload = Vec::new();
+    (receipt.status.to::<u64>() == 1).encode(&mut payload);
+    receipt.cumulative_gas_used.to::<u64>().encode(&mut payload);
+    receipt.logs_bloom.encode(&mut payload);
+    payload.extend(list(receipt.logs.iter().flat_map(encode_log).collect()));
+
+    let tx_type = receipt.tx_type.to::<u8>();
+    let mut out = Vec::new();
+    if tx_type != 0 {
+        out.push(tx_type);
+    }
+    out.extend(list(payload));
+    out
+}
+
+// Root of the trie keyed by each receipt's RLP-encoded index, as in the block header
+fn receipts_root(receipts: &[Receipt]) -> B256 {
+    let mut leaves: Vec<(Vec<u8>, Vec<u8>)> = receipts
+        .iter()
+        .enumerate()
+        .map(|(index, receipt)| (alloy::rlp::encode(index), encode_receipt(receipt)))
+        .collect();
+    leaves.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut builder = HashBuilder::default();
+    for (key, value) in &leaves {
+        builder.add_leaf(Nibbles::unpack(key), value);
+    }
+    builder.root()
+}
+
+pub fn matches(filter: &Filter, log: &serde_json::Value) -> bool {
+    let Ok(log) = serde_json::from_value::<Log>(log.clone()) else {
+        return false;
+    };
+    filter.address.matches(&log.address)
+        && filter.topics.iter().enumerate().all(|(i, topic)| {
+            topic.is_empty() || log.topics.get(i).is_some_and(|value| topic.matches(value))
+        })
+}
+
+// Puts each receipt in the verified block's order and fills the fields the receipts trie doesn't
+// cover from the block. Once the receipts hash to the root their positions are fixed, so this is one
+// pass over the block rather than a lookup per transaction
+fn index_receipts(
+    mut raw: Vec<serde_json::Value>,
+    header: &VerifiedHeader,
+    hashes: &[B256],
+) -> Result<Vec<serde_json::Value>, String> {
+    if hashes.len() != raw.len() {
+        return Err(format!("Execution RPC returned {} receipts for {} transactions", raw.len(), hashes.len()));
+    }
+    if let Some((_, hash)) = raw.iter().zip(hashes).find(|(receipt, _)| receipt.is_null()) {
+        return Err(format!("Execution RPC has no receipt for transaction 0x{:x}", hash));
+    }
+    let receipts: Vec<Receipt> = raw.iter()
+        .map(|receipt| serde_json::from_value(receipt.clone()))
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Invalid receipt from execution RPC: {}", e))?;
+    if receipts_root(&receipts) != header.receipts_root {
+        return Err(format!("Receipts for block {} don't match the verified receipts root", header.block_number));
+    }
+
+    let block_fields = [
+        ("blockHash", json!(header.block_hash)),
+        ("blockNumber", json!(format!("0x{:x}", header.block_number))),
+    ];
+    let mut log_index = 0;
+    for (index, (receipt, hash)) in raw.iter_mut().zip(hashes).enumerate() {
+        let Some(fields) = receipt.as_object_mut() else { continue };
+        let tx_fields = [
+            ("transactionHash", json!(hash)),
+            ("transactionIndex", json!(format!("0x{:x}", index))),
+        ];
+        for (key, value) in block_fields.iter().chain(&tx_fields) {
+            fields.insert(key.to_string(), value.clone());
+        }
+        let logs = fields.get_mut("logs").and_then(|logs| logs.as_array_mut()).into_iter().flatten();
+        for log in logs.filter_map(|log| log.as_object_mut()) {
+            for (key, value) in block_fields.iter().chain(&tx_fields) {
+                log.insert(key.to_string(), value.clone());
+            }
+            log.insert("logIndex".to_string(), json!(format!("0x{:x}", log_index)));
+            log.insert("removed".to_string(), json!(false));
+            log_index += 1;
+        }
+    }
+    Ok(raw)
+}
+
+// None when the RPC answers with an error or without the receipts, as RPCs that don't serve
+// eth_getBlockReceipts do
+async fn block_receipts(rpc_url: &str, block_hash: B256) -> Result<Option<Vec<serde_json::Value>>, String> {
+    let upstream = passthrough::forward(rpc_url, "eth_getBlockReceipts", &[json!(block_hash)]).await?;
+    if let Some(error) = upstream.get("error") {
+        tracing::debug!("eth_getBlockReceipts refused for 0x{:x}: {}", block_hash, error);
+    }
+    Ok(upstream.get("result").and_then(|receipts| receipts.as_array()).cloned())
+}
+
+// One eth_getTransactionReceipt per transaction, `concurrency` at a time, kept in block order. A
+// receipt the RPC doesn't have comes back null
+async fn transaction_receipts(
+    rpc_url: &str,
+    hashes: &[B256],
+    concurrency: usize,
+) -> Result<Vec<serde_json::Value>, String> {
+    stream::iter(hashes)
+        .map(|hash| async move {
+            let upstream = passthrough::forward(rpc_url, "eth_getTransactionReceipt", &[json!(hash)]).await?;
+            match upstream.get("error") {
+                Some(error) => Err(format!("Failed to get receipt for transaction 0x{:x}: {}", hash, error)),
+                None => Ok(upstream.get("result").cloned().unwrap_or_default()),
+            }
+        })
+        .buffered(concurrency.max(1))
+        .try_collect()
+        .await
+}
+
+// Receipts of a verified block in block order, checked against the header's receipts root once.
+// Fetched from the execution RPC in one eth_getBlockReceipts call, or receipt by receipt from RPCs
+// that refuse it
+pub async fn verified_receipts(
+    rpc_url: &str,
+    header: &VerifiedHeader,
+    hashes: &[B256],
+    concurrency: usize,
+) -> Result<Vec<serde_json::Value>, String> {
+    if hashes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let raw = match block_receipts(rpc_url, header.block_hash).await? {
+        Some(raw) => raw,
+        None => transaction_receipts(rpc_url, hashes, concurrency).await?,
+    };
+    index_receipts(raw, header, hashes)
+}
+
+// Logs of a verified block, taken from its verified receipts
+pub async fn verified_logs(
+    client: &dyn EthClientApi,
+    rpc_url: &str,
+    header: &VerifiedHeader,
+    concurrency: usize,
+) -> Result<Vec<serde_json::Value>, String> {
+    let block = client.get_block_by_hash(header.block_hash, false)
+        .await
+        .map_err(|e| format!("Failed to get block: {}", e))?
+        .ok_or("Block not available from the light client")?;
+    let hashes: Vec<B256> = block.transactions.hashes().collect();
+    let receipts = verified_receipts(rpc_url, header, &hashes, concurrency).await?;
+    Ok(receipts
+        .into_iter()
+        .flat_map(|mut receipt| match receipt.get_mut("logs").map(serde_json::Value::take) {
+            Some(serde_json::Value::Array(logs)) => logs,
+            _ => Vec::new(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(receipts_root: B256) -> VerifiedHeader {
+        VerifiedHeader {
+            slot: 0,
+            block_number: 7,
+            block_hash: B256::repeat_byte(0xbb),
+            parent_hash: B256::ZERO,
+            state_root: B256::ZERO,
+            receipts_root,
+            timestamp: 0,
+        }
+    }
+
+    fn receipt(cumulative_gas_used: u64, logs: usize) -> serde_json::Value {
+        let log = json!({ "address": Address::repeat_byte(0x11), "topics": [], "data": "0x" });
+        json!({
+            "type": "0x2",
+            "status": "0x1",
+            "cumulativeGasUsed": format!("0x{:x}", cumulative_gas_used),
+            "logsBloom": Bloom::ZERO,
+            "logs": vec![log; logs],
+            "transactionHash": B256::repeat_byte(0xee),
+        })
+    }
+
+    fn root(raw: &[serde_json::Value]) -> B256 {
+        let receipts: Vec<Receipt> = raw.iter().map(|r| serde_json::from_value(r.clone()).unwrap()).collect();
+        receipts_root(&receipts)
+    }
+
+    #[test]
+    fn receipts_take_block_fields_and_number_logs_across_the_block() {
+        let raw = vec![receipt(21_000, 1), receipt(63_000, 2)];
+        let hashes = [B256::repeat_byte(0x01), B256::repeat_byte(0x02)];
+        let indexed = index_receipts(raw.clone(), &header(root(&raw)), &hashes).unwrap();
+        assert_eq!(indexed[1]["transactionHash"], json!(hashes[1]));
+        assert_eq!(indexed[1]["transactionIndex"], json!("0x1"));
+        assert_eq!(indexed[0]["blockHash"], json!(B256::repeat_byte(0xbb)));
+        let log_indexes: Vec<_> = indexed.iter()
+            .flat_map(|receipt| receipt["logs"].as_array().unwrap().clone())
+            .map(|log| log["logIndex"].clone())
+            .collect();
+        assert_eq!(log_indexes, vec![json!("0x0"), json!("0x1"), json!("0x2")]);
+    }
+
+    #[test]
+    fn receipts_must_match_the_root_and_the_transaction_count() {
+        let raw = vec![receipt(21_000, 0), receipt(42_000, 0)];
+        let hashes = [B256::repeat_byte(0x01), B256::repeat_byte(0x02)];
+        let swapped = vec![raw[1].clone(), raw[0].clone()];
+        assert!(index_receipts(swapped, &header(root(&raw)), &hashes).unwrap_err().contains("receipts root"));
+        assert!(index_receipts(raw.clone(), &header(root(&raw)), &hashes[..1]).unwrap_err().contains("2 receipts for 1"));
+    }
+
+    #[test]
+    fn receipts_fail_on_a_missing_or_altered_receipt() {
+        let raw = vec![receipt(21_000, 1), receipt(42_000, 0)];
+        let hashes = [B256::repeat_byte(0x01), B256::repeat_byte(0x02)];
+        let missing = vec![raw[0].clone(), serde_json::Value::Null];
+        assert!(index_receipts(missing, &header(root(&raw)), &hashes).unwrap_err().contains("no receipt for transaction 0x0202"));
+        let mut altered = raw.clone();
+        altered[1]["status"] = json!("0x0");
+        assert!(index_receipts(altered, &header(root(&raw)), &hashes).unwrap_err().contains("receipts root"));
+    }
+}
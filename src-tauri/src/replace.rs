@@ -0,0 +1,195 @@
+use alloy::primitives::{Bytes, TxKind, B256, U256};
+use alloy::rpc::types::{Transaction, TransactionInput, TransactionRequest};
+use helios::core::types::BlockTag;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::client::EthClientApi;
+use crate::distribution::AccountSource;
+use crate::gas::GasQuotes;
+use crate::passthrough;
+
+// Nodes reject replacements that don't raise every fee field by at least this much
+const MIN_BUMP_PERCENT: u128 = 10;
+const TRANSFER_GAS: u128 = 21_000;
+const BLOB_TX_TYPE: u8 = 3;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeOverride {
+    pub gas_price: Option<u128>,
+    pub max_fee_per_gas: Option<u128>,
+    pub max_priority_fee_per_gas: Option<u128>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReplacementKind {
+    SpeedUp,
+    Cancel,
+}
+
+// Replacement for a pending transaction, reusing its nonce
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Replacement {
+    pub original_hash: B256,
+    pub kind: ReplacementKind,
+    pub transaction: TransactionRequest,
+    // Set once the replacement is signed and broadcast
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<B256>,
+}
+
+fn bump(fee: u128) -> u128 {
+    fee + (fee * MIN_BUMP_PERCENT).div_ceil(100)
+}
+
+fn check_minimum(name: &str, value: u128, minimum: u128) -> Result<u128, String> {
+    if value < minimum {
+        return Err(format!("{} must be at least {} to replace the pending transaction", name, minimum));
+    }
+    Ok(value)
+}
+
+// The light client only knows included transactions, so pending ones come from the execution RPC.
+// The verified nonce then confirms the slot hasn't been used yet
+async fn pending_transaction(client: &dyn EthClientApi, accounts: &AccountSource, rpc_url: &str, hash: B256) -> Result<Transaction, String> {
+    if let Some(tx) = client.get_transaction_by_hash(hash).await {
+        if let Some(number) = tx.block_number {
+            return Err(format!("Transaction 0x{:x} was already included in block {}", hash, number));
+        }
+    }
+
+    let response = passthrough::forward(rpc_url, "eth_getTransactionByHash", &[json!(hash)]).await?;
+    let result = response.get("result").cloned().unwrap_or_default();
+    if result.is_null() {
+        return Err(format!("Transaction 0x{:x} not found", hash));
+    }
+    let tx: Transaction = serde_json::from_value(result).map_err(|e| format!("Invalid transaction: {}", e))?;
+    if tx.hash != hash {
+        return Err(format!("RPC returned a different transaction for 0x{:x}", hash));
+    }
+    if tx.block_number.is_some() {
+        return Err(format!("Transaction 0x{:x} is no longer pending", hash));
+    }
+    if tx.transaction_type == Some(BLOB_TX_TYPE) {
+        return Err("Replacing blob transactions isn't supported".to_string());
+    }
+
+    let nonce = accounts.account(client, tx.from, BlockTag::Latest)
+        .await
+        .map_err(|e| format!("Failed to get nonce: {}", e))?
+        .nonce;
+    if nonce > tx.nonce {
+        return Err(format!("Nonce {} of 0x{:x} is already used", tx.nonce, tx.from));
+    }
+    Ok(tx)
+}
+
+// Applies fees that clear the replacement bump: the caller's if given, otherwise the larger of
+// the minimum bump and the current fast quote
+fn apply_fees(
+    request: &mut TransactionRequest,
+    original: &Transaction,
+    fees: Option<FeeOverride>,
+    quotes: Option<&GasQuotes>,
+) -> Result<(), String> {
+    let fast = quotes.map(|quotes| &quotes.fast);
+
+    match (original.max_fee_per_gas, original.max_priority_fee_per_gas) {
+        (Some(max_fee), Some(priority_fee)) => {
+            let min_priority_fee = bump(priority_fee);
+            let min_max_fee = bump(max_fee);
+            let (max_fee, priority_fee) = match fees {
+                Some(fees) => (
+                    check_minimum("maxFeePerGas", fees.max_fee_per_gas.unwrap_or(min_max_fee), min_max_fee)?,
+                    check_minimum("maxPriorityFeePerGas", fees.max_priority_fee_per_gas.unwrap_or(min_priority_fee), min_priority_fee)?,
+                ),
+                None => {
+                    let priority_fee = fast
+                        .map(|tier| tier.max_priority_fee_per_gas.saturating_to::<u128>())
+                        .unwrap_or_default()
+                        .max(min_priority_fee);
+                    let max_fee = fast
+                        .map(|tier| tier.max_fee_per_gas.saturating_to::<u128>())
+                        .unwrap_or_default()
+                        .max(min_max_fee)
+                        .max(priority_fee);
+                    (max_fee, priority_fee)
+                },
+            };
+            if priority_fee > max_fee {
+                return Err("maxPriorityFeePerGas can't exceed maxFeePerGas".to_string());
+            }
+            request.gas_price = None;
+            request.max_fee_per_gas = Some(max_fee);
+            request.max_priority_fee_per_gas = Some(priority_fee);
+        },
+        _ => {
+            let min_gas_price = bump(original.gas_price.unwrap_or_default());
+            let gas_price = match fees.and_then(|fees| fees.gas_price) {
+                Some(gas_price) => check_minimum("gasPrice", gas_price, min_gas_price)?,
+                None => fast
+                    .map(|tier| tier.max_fee_per_gas.saturating_to::<u128>())
+                    .unwrap_or_default()
+                    .max(min_gas_price),
+            };
+            request.gas_price = Some(gas_price);
+            request.max_fee_per_gas = None;
+            request.max_priority_fee_per_gas = None;
+        },
+    }
+    Ok(())
+}
+
+// Same transaction with higher fees
+pub async fn speed_up(
+    client: &dyn EthClientApi,
+    accounts: &AccountSource,
+    rpc_url: &str,
+    hash: B256,
+    fees: Option<FeeOverride>,
+    quotes: Option<&GasQuotes>,
+) -> Result<Replacement, String> {
+    let original = pending_transaction(client, accounts, rpc_url, hash).await?;
+    let mut transaction = original.clone().into_request();
+    apply_fees(&mut transaction, &original, fees, quotes)?;
+
+    Ok(Replacement {
+        original_hash: hash,
+        kind: ReplacementKind::SpeedUp,
+        transaction,
+        hash: None,
+    })
+}
+
+// Zero-value self-transfer at the same nonce, so the original can no longer be included
+pub async fn cancel(
+    client: &dyn EthClientApi,
+    accounts: &AccountSource,
+    rpc_url: &str,
+    hash: B256,
+    quotes: Option<&GasQuotes>,
+) -> Result<Replacement, String> {
+    let original = pending_transaction(client, accounts, rpc_url, hash).await?;
+    let mut transaction = TransactionRequest {
+        from: Some(original.from),
+        to: Some(TxKind::Call(original.from)),
+        value: Some(U256::ZERO),
+        input: TransactionInput::new(Bytes::new()),
+        nonce: Some(original.nonce),
+        gas: Some(TRANSFER_GAS),
+        chain_id: original.chain_id,
+        transaction_type: original.transaction_type,
+        ..Default::default()
+    };
+    apply_fees(&mut transaction, &original, None, quotes)?;
+
+    Ok(Replacement {
+        original_hash: hash,
+        kind: ReplacementKind::Cancel,
+        transaction,
+        hash: None,
+    })
+}
@@ -0,0 +1,121 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+
+// Only methods without side effects are retried, everything else gets a single attempt
+const IDEMPOTENT_METHODS: &[&str] = &[
+    "eth_getBlockByNumber",
+    "eth_getBlockByHash",
+    "eth_getBalance",
+    "eth_getCode",
+    "eth_getStorageAt",
+    "eth_getTransactionCount",
+    "eth_getBlockTransactionCountByHash",
+    "eth_getBlockTransactionCountByNumber",
+    "eth_gasPrice",
+    "eth_maxPriorityFeePerGas",
+    "eth_getTransactionReceipt",
+    "eth_getLogs",
+    "eth_syncing",
+    "eth_coinbase",
+    "eth_call",
+    "eth_estimateGas",
+    "eth_getBlockReceipts",
+    "eth_getProof",
+];
+
+// Backoff sleeps on the request that hit the error, so these bound how long a request can wait
+// out a struggling upstream
+const MAX_ATTEMPTS: u32 = 10;
+const MAX_DELAY_MS: u64 = 60_000;
+
+const TRANSIENT_ERROR_PATTERNS: &[&str] = &[
+    "429",
+    "too many requests",
+    "rate limit",
+    "server error",
+    "bad gateway",
+    "service unavailable",
+    "gateway timeout",
+    "timed out",
+    "connection reset",
+    "connection closed",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 200,
+            max_delay_ms: 5_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 {
+            return Err("Retry policy needs at least one attempt".to_string());
+        }
+        if self.max_attempts > MAX_ATTEMPTS {
+            return Err(format!("Retry policy allows at most {} attempts", MAX_ATTEMPTS));
+        }
+        if self.max_delay_ms > MAX_DELAY_MS {
+            return Err(format!("Retry max delay can't exceed {} ms", MAX_DELAY_MS));
+        }
+        if self.base_delay_ms > self.max_delay_ms {
+            return Err("Retry base delay can't exceed the max delay".to_string());
+        }
+        Ok(())
+    }
+
+    // Exponential backoff capped at max_delay_ms, with full jitter
+    fn delay(&self, attempt: u32) -> Duration {
+        let ceiling = self.base_delay_ms
+            .saturating_mul(1u64 << attempt.min(16))
+            .min(self.max_delay_ms);
+        Duration::from_millis(rand::thread_rng().gen_range(0..=ceiling))
+    }
+}
+
+pub fn is_idempotent(method: &str) -> bool {
+    IDEMPOTENT_METHODS.contains(&method)
+}
+
+pub fn is_transient(message: &str) -> bool {
+    let message = message.to_lowercase();
+    TRANSIENT_ERROR_PATTERNS.iter().any(|pattern| message.contains(pattern))
+}
+
+// The span ties upstream calls and their retries to the dispatcher span of the request
+#[tracing::instrument(name = "upstream", level = "debug", skip(policy, op))]
+pub async fn with_retry<T, E, F, Fut>(policy: &RetryPolicy, method: &str, mut op: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Display,
+{
+    let max_attempts = if is_idempotent(method) { policy.max_attempts.max(1) } else { 1 };
+    let mut attempt = 0;
+
+    loop {
+        match op().await {
+            Err(e) if attempt + 1 < max_attempts && is_transient(&e.to_string()) => {
+                tracing::warn!("{} failed with transient error, retrying: {}", method, e);
+                tokio::time::sleep(policy.delay(attempt)).await;
+                attempt += 1;
+            },
+            result => return result,
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use alloy::hex;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+
+use crate::accounts::Caller;
+use crate::{errors, AppState};
+
+pub const DEFAULT_RPC_PORT: u16 = 8545;
+
+// Origin used for connections from clients that don't send an Origin header, like scripts
+const LOCAL_ORIGIN: &str = "local-rpc";
+
+// Issued on the first request from an origin and sent back on every later one
+const SESSION_HEADER: &str = "x-session-token";
+// Oldest sessions are forgotten past this, their clients start a new one
+const MAX_SESSIONS: usize = 256;
+
+// Any local process can claim any Origin, so a claimed origin only ever gets the approvals granted
+// to the session it was first presented with. Without a token a client starts a new session, and
+// with it a new set of approvals, rather than borrowing the real site's
+#[derive(Default)]
+struct Sessions {
+    by_token: HashMap<String, Session>,
+    next_id: u64,
+}
+
+struct Session {
+    id: u64,
+    origin: String,
+}
+
+impl Sessions {
+    fn start(&mut self, origin: &str) -> (String, String) {
+        if self.by_token.len() >= MAX_SESSIONS {
+            let oldest = self.by_token.iter().min_by_key(|(_, session)| session.id).map(|(token, _)| token.clone());
+            if let Some(token) = oldest {
+                self.by_token.remove(&token);
+            }
+        }
+        let token = hex::encode(rand::random::<[u8; 32]>());
+        let session = Session { id: self.next_id, origin: origin.to_string() };
+        self.next_id += 1;
+        let caller = session.caller();
+        self.by_token.insert(token.clone(), session);
+        (token, caller)
+    }
+
+    // The caller origin for a token, as long as it's presented by the origin it was issued to
+    fn resume(&self, token: &str, origin: &str) -> Option<String> {
+        self.by_token.get(token).filter(|session| session.origin == origin).map(Session::caller)
+    }
+}
+
+impl Session {
+    // The token is a secret and stays out of origins, which are logged and shown to the user
+    fn caller(&self) -> String {
+        format!("{} (RPC session {})", self.origin, self.id)
+    }
+}
+
+#[derive(Clone)]
+struct ServerState {
+    app: AppHandle,
+    sessions: Arc<std::sync::Mutex<Sessions>>,
+}
+
+// Verified JSON-RPC over HTTP on localhost, answering through the same dispatcher as the webviews
+pub struct RpcServer {
+    pub addr: SocketAddr,
+    handle: JoinHandle<()>,
+}
+
+impl RpcServer {
+    pub fn stop(self) {
+        self.handle.abort();
+    }
+}
+
+pub async fn serve(app: AppHandle, port: u16) -> Result<RpcServer, String> {
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+        .await
+        .map_err(|e| format!("Failed to bind RPC server to port {}: {}", port, e))?;
+    let addr = listener.local_addr().map_err(|e| format!("Failed to read RPC server address: {}", e))?;
+    let state = ServerState { app, sessions: Arc::default() };
+    let router = Router::new().route("/", post(handle)).with_state(state);
+
+    let handle = tauri::async_runtime::spawn(async move {
+        if let Err(e) = axum::serve(listener, router).await {
+            tracing::error!("RPC server stopped: {}", e);
+        }
+    });
+    tracing::info!("RPC server listening on http://{}", addr);
+    Ok(RpcServer { addr, handle })
+}
+
+fn error(id: serde_json::Value, code: i32, message: &str) -> serde_json::Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+// Clients that send no Origin header aren't browsers and are all the user's own tools, so they share
+// one origin. Browser origins are tied to a session token
+fn caller_origin(sessions: &std::sync::Mutex<Sessions>, headers: &HeaderMap) -> Result<(String, Option<String>), String> {
+    let Some(origin) = headers.get(header::ORIGIN).and_then(|origin| origin.to_str().ok()) else {
+        return Ok((LOCAL_ORIGIN.to_string(), None));
+    };
+    let mut sessions = sessions.lock().unwrap();
+    match headers.get(SESSION_HEADER).and_then(|token| token.to_str().ok()) {
+        Some(token) => sessions
+            .resume(token, origin)
+            .map(|caller| (caller, None))
+            .ok_or_else(|| format!("Unauthorized: session token isn't valid for {}", origin)),
+        None => {
+            let (token, caller) = sessions.start(origin);
+            Ok((caller, Some(token)))
+        },
+    }
+}
+
+// Takes the raw body so malformed JSON gets a JSON-RPC parse error rather than axum's rejection
+async fn handle(State(state): State<ServerState>, headers: HeaderMap, body: Bytes) -> Response {
+    let (origin, token) = match caller_origin(&state.sessions, &headers) {
+        Ok(caller) => caller,
+        Err(e) => return Json(error(json!(null), errors::UNAUTHORIZED, &e)).into_response(),
+    };
+    let body = answer(&state.app, &origin, &body).await;
+    match token {
+        Some(token) => ([(SESSION_HEADER, token)], body).into_response(),
+        None => body.into_response(),
+    }
+}
+
+async fn answer(app: &AppHandle, origin: &str, body: &[u8]) -> Json<serde_json::Value> {
+    let Ok(body) = serde_json::from_slice::<serde_json::Value>(body) else {
+        return Json(error(json!(null), errors::PARSE_ERROR, "Parse error"));
+    };
+
+    // JSON-RPC batches are answered in order
+    match body {
+        serde_json::Value::Array(requests) if requests.is_empty() => {
+            Json(error(json!(null), errors::INVALID_REQUEST, "Invalid Request: empty batch"))
+        },
+        serde_json::Value::Array(requests) => {
+            let mut responses = Vec::with_capacity(requests.len());
+            for request in requests {
+                responses.push(dispatch(app, origin, request).await);
+            }
+            Json(json!(responses))
+        },
+        request => Json(dispatch(app, origin, request).await),
+    }
+}
+
+async fn dispatch(app: &AppHandle, origin: &str, request: serde_json::Value) -> serde_json::Value {
+    let caller = Caller { origin: origin.to_string(), webview: None };
+    let id = request.get("id").cloned().unwrap_or(json!(null));
+    match crate::handle_request(app.clone(), caller, app.state::<Mutex<AppState>>(), request).await {
+        Ok(response) => response,
+        Err(e) => error(id, errors::INTERNAL_ERROR, &e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(origin: Option<&str>, token: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(origin) = origin {
+            headers.insert(header::ORIGIN, origin.parse().unwrap());
+        }
+        if let Some(token) = token {
+            headers.insert(SESSION_HEADER, token.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn scripts_share_the_local_origin() {
+        let sessions = std::sync::Mutex::new(Sessions::default());
+        assert_eq!(caller_origin(&sessions, &headers(None, None)).unwrap(), (LOCAL_ORIGIN.to_string(), None));
+    }
+
+    #[test]
+    fn claimed_origin_without_token_gets_a_new_session() {
+        let sessions = std::sync::Mutex::new(Sessions::default());
+        let (first, token) = caller_origin(&sessions, &headers(Some("https://app.example"), None)).unwrap();
+        let (second, _) = caller_origin(&sessions, &headers(Some("https://app.example"), None)).unwrap();
+        assert_ne!(first, second);
+
+        let (resumed, issued) = caller_origin(&sessions, &headers(Some("https://app.example"), token.as_deref())).unwrap();
+        assert_eq!(resumed, first);
+        assert!(issued.is_none());
+    }
+
+    #[test]
+    fn token_is_bound_to_its_origin() {
+        let sessions = std::sync::Mutex::new(Sessions::default());
+        let (_, token) = caller_origin(&sessions, &headers(Some("https://app.example"), None)).unwrap();
+        assert!(caller_origin(&sessions, &headers(Some("https://evil.example"), token.as_deref())).is_err());
+        assert!(caller_origin(&sessions, &headers(Some("https://app.example"), Some("forged"))).is_err());
+    }
+
+    #[test]
+    fn oldest_session_is_forgotten() {
+        let mut sessions = Sessions::default();
+        let (first, _) = sessions.start("https://app.example");
+        for _ in 0..MAX_SESSIONS {
+            sessions.start("https://app.example");
+        }
+        assert_eq!(sessions.by_token.len(), MAX_SESSIONS);
+        assert!(sessions.resume(&first, "https://app.example").is_none());
+    }
+}
@@ -0,0 +1,900 @@
+use alloy::primitives::{keccak256, Address, Bytes, Log, TxKind, B256, U256};
+use alloy::rpc::types::{Filter, TransactionRequest};
+use alloy::sol_types::decode_revert_reason;
+use helios::core::types::BlockTag;
+use revm::db::{CacheDB, EmptyDB};
+use revm::primitives::{BlockEnv, ExecutionResult, Output};
+use revm::Database;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::client::EthClientApi;
+use crate::devmode;
+use crate::distribution::AccountSource;
+use crate::evm::{self, VerifiedDb, VerifiedState};
+
+// "SAND" in ASCII, so the sandbox never collides with a real chain a dapp knows
+pub const SANDBOX_CHAIN_ID: u64 = 0x5341_4e44;
+// 100 test ether, what every account a dapp can see starts with
+const SANDBOX_BALANCE: U256 = U256::from_limbs([0x6bc7_5e2d_6310_0000, 0x5, 0, 0]);
+// Gas margin over the measured use, since a later call may cost a little more
+const ESTIMATE_MARGIN_PERCENT: u64 = 20;
+// Every snapshot holds a full copy of the sandbox state, so only this many are kept at once
+pub const MAX_SNAPSHOTS: usize = 32;
+
+// Answered from the local EVM. Everything else stays with the app (accounts, signing, chain
+// switching) or is refused, as it would need a real network
+const SERVED: &[&str] = &[
+    "eth_chainId",
+    "net_version",
+    "eth_blockNumber",
+    "eth_getBlockByNumber",
+    "eth_getBlockByHash",
+    "eth_getBalance",
+    "eth_getTransactionCount",
+    "eth_getCode",
+    "eth_getStorageAt",
+    "eth_call",
+    "eth_estimateGas",
+    "eth_gasPrice",
+    "eth_maxPriorityFeePerGas",
+    "eth_getTransactionByHash",
+    "eth_getTransactionReceipt",
+    "eth_getLogs",
+];
+
+// Node control it implements is listed with the rest of the split in devmode::node_control
+pub fn is_served(method: &str) -> bool {
+    SERVED.contains(&method) || devmode::node_control(method, false, true) == Some(devmode::NodeControl::Sandbox)
+}
+
+// Chain methods the sandbox can't answer, like eth_sendRawTransaction or subscriptions. Accounts
+// keep their usual handling. eth_sign is refused too: it signs with the wallet's real keys and an
+// opaque hash can't be tied to the sandbox. Node control is settled before this, by devmode::node_control
+pub fn is_refused(method: &str) -> bool {
+    ["eth_", "net_", "debug_", "trace_", "txpool_"].iter().any(|namespace| method.starts_with(namespace))
+        && !matches!(method, "eth_accounts" | "eth_requestAccounts" | "eth_sendTransaction")
+        && !method.starts_with("eth_signTypedData")
+}
+
+// Why typed data can't be signed from the sandbox, if it can't. The real keys sign it, so its
+// domain has to name the sandbox chain or the signature would be good on a real one. Legacy
+// eth_signTypedData has no domain at all
+pub fn typed_data_refusal(method: &str, params: &[Value]) -> Option<String> {
+    if method == "eth_signTypedData" {
+        return Some("eth_signTypedData has no domain to bind it to the sandbox".to_string());
+    }
+    let typed_data = match params.get(1) {
+        Some(Value::String(encoded)) => serde_json::from_str(encoded).ok(),
+        Some(value) => Some(value.clone()),
+        None => None,
+    };
+    let chain_id = typed_data.as_ref().and_then(|data| data.get("domain")?.get("chainId").cloned());
+    let chain_id = match chain_id {
+        Some(Value::String(decimal)) if !decimal.starts_with("0x") => decimal.parse().ok(),
+        chain_id => quantity(chain_id.as_ref()).ok(),
+    };
+    match chain_id {
+        Some(SANDBOX_CHAIN_ID) => None,
+        Some(chain_id) => Some(format!(
+            "Typed data is for chain {}, only the sandbox chain {} can be signed from the sandbox", chain_id, SANDBOX_CHAIN_ID
+        )),
+        None => Some("Typed data without a domain chainId can't be signed from the sandbox".to_string()),
+    }
+}
+
+#[derive(Debug)]
+pub enum SandboxError {
+    InvalidParams(String),
+    LimitExceeded(String),
+    Failed(String),
+}
+
+impl From<String> for SandboxError {
+    fn from(message: String) -> Self {
+        SandboxError::Failed(message)
+    }
+}
+
+// A block the sandbox mined. Every transaction is mined into a block of its own as it's sent
+#[derive(Clone)]
+struct SandboxBlock {
+    number: u64,
+    hash: B256,
+    parent_hash: B256,
+    timestamp: u64,
+    transactions: Vec<B256>,
+    gas_used: u64,
+}
+
+#[derive(Clone)]
+struct SandboxTransaction {
+    hash: B256,
+    block: u64,
+    from: Address,
+    to: Option<Address>,
+    nonce: u64,
+    value: U256,
+    gas: u64,
+    input: Bytes,
+    gas_used: u64,
+    success: bool,
+    contract_address: Option<Address>,
+    logs: Vec<Log>,
+}
+
+// Everything evm_revert rolls back
+struct Snapshot {
+    state: CacheDB<EmptyDB>,
+    blocks: Vec<SandboxBlock>,
+    transactions: HashMap<B256, SandboxTransaction>,
+    funded: HashSet<Address>,
+    time_offset: u64,
+    next_timestamp: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SandboxStatus {
+    pub chain_id: u64,
+    pub parent_chain: u64,
+    pub fork_block: u64,
+    pub block_number: u64,
+    pub transactions: usize,
+    pub snapshots: usize,
+    pub time_offset: u64,
+}
+
+// A fork of the chain the light client follows, executed in process. State is pulled lazily
+// through the light client at the fork block, so everything the sandbox didn't change itself
+// is verified, and whatever a transaction changes lives only here
+pub struct Sandbox {
+    pub parent_chain: u64,
+    // Where balances and nonces not yet loaded are read from, as for the parent chain's own reads
+    accounts: AccountSource,
+    fork_block: u64,
+    fork_hash: B256,
+    fork_timestamp: u64,
+    // Fields of the fork block that carry over to sandbox blocks
+    base_env: BlockEnv,
+    // Accounts, code and storage loaded or written so far. Only the caches are kept between
+    // requests, the light client is attached again for each one
+    state: CacheDB<EmptyDB>,
+    blocks: Vec<SandboxBlock>,
+    transactions: HashMap<B256, SandboxTransaction>,
+    funded: HashSet<Address>,
+    // Seconds evm_increaseTime moved the clock forward
+    time_offset: u64,
+    // Timestamp evm_setNextBlockTimestamp asked of the next mined block
+    next_timestamp: Option<u64>,
+    // Taken with evm_snapshot, by id and at most MAX_SNAPSHOTS. Reverting to one drops it and every
+    // later one, as Hardhat does
+    snapshots: BTreeMap<u64, Snapshot>,
+    next_snapshot_id: u64,
+}
+
+impl Sandbox {
+    // Forks at the light client's latest verified block
+    pub async fn fork(client: &dyn EthClientApi, accounts: AccountSource) -> Result<Self, String> {
+        let block = client.get_block_by_number(BlockTag::Latest, false)
+            .await
+            .map_err(|e| format!("Failed to get latest block: {}", e))?
+            .ok_or("Latest block not found")?;
+        let mut base_env = evm::block_env(&block);
+        // Gas is free in the sandbox
+        base_env.basefee = U256::ZERO;
+        Ok(Self {
+            parent_chain: client.chain_id().await,
+            accounts,
+            fork_block: block.number.to::<u64>(),
+            fork_hash: block.hash,
+            fork_timestamp: block.timestamp.to::<u64>(),
+            base_env,
+            state: CacheDB::new(EmptyDB::default()),
+            blocks: Vec::new(),
+            transactions: HashMap::new(),
+            funded: HashSet::new(),
+            time_offset: 0,
+            next_timestamp: None,
+            snapshots: BTreeMap::new(),
+            next_snapshot_id: 1,
+        })
+    }
+
+    pub fn status(&self) -> SandboxStatus {
+        SandboxStatus {
+            chain_id: SANDBOX_CHAIN_ID,
+            parent_chain: self.parent_chain,
+            fork_block: self.fork_block,
+            block_number: self.head(),
+            transactions: self.transactions.len(),
+            snapshots: self.snapshots.len(),
+            time_offset: self.time_offset,
+        }
+    }
+
+    fn head(&self) -> u64 {
+        self.blocks.last().map_or(self.fork_block, |block| block.number)
+    }
+
+    fn head_hash(&self) -> B256 {
+        self.blocks.last().map_or(self.fork_hash, |block| block.hash)
+    }
+
+    fn head_timestamp(&self) -> u64 {
+        self.blocks.last().map_or(self.fork_timestamp, |block| block.timestamp)
+    }
+
+    // Environment of the block the next transaction is mined into. The clock runs from the wall
+    // clock plus any evm_increaseTime, unless evm_setNextBlockTimestamp pinned the next block
+    fn next_block_env(&self) -> Result<BlockEnv, SandboxError> {
+        let timestamp = match self.next_timestamp {
+            Some(timestamp) => timestamp,
+            None => clock(self.time_offset)
+                .zip(self.head_timestamp().checked_add(1))
+                .map(|(now, next)| now.max(next))
+                .ok_or_else(|| SandboxError::InvalidParams("Sandbox clock is past the largest timestamp".to_string()))?,
+        };
+        Ok(BlockEnv {
+            number: U256::from(self.head() + 1),
+            timestamp: U256::from(timestamp),
+            ..self.base_env.clone()
+        })
+    }
+
+    fn mine(&mut self, env: &BlockEnv, transactions: Vec<B256>, gas_used: u64) {
+        let number = env.number.to::<u64>();
+        let timestamp = env.timestamp.to::<u64>();
+        let parent_hash = self.head_hash();
+        self.blocks.push(SandboxBlock {
+            number,
+            hash: keccak256([parent_hash.as_slice(), &number.to_be_bytes(), &timestamp.to_be_bytes()].concat()),
+            parent_hash,
+            timestamp,
+            transactions,
+            gas_used,
+        });
+        self.next_timestamp = None;
+    }
+
+    fn snapshot(&mut self) -> Result<u64, SandboxError> {
+        if self.snapshots.len() >= MAX_SNAPSHOTS {
+            return Err(SandboxError::LimitExceeded(format!(
+                "The sandbox keeps at most {} snapshots, revert to an earlier one first", MAX_SNAPSHOTS
+            )));
+        }
+        let id = self.next_snapshot_id;
+        self.next_snapshot_id += 1;
+        self.snapshots.insert(id, Snapshot {
+            state: self.state.clone(),
+            blocks: self.blocks.clone(),
+            transactions: self.transactions.clone(),
+            funded: self.funded.clone(),
+            time_offset: self.time_offset,
+            next_timestamp: self.next_timestamp,
+        });
+        Ok(id)
+    }
+
+    // Returns whether the snapshot existed
+    fn revert(&mut self, id: u64) -> bool {
+        let Some(snapshot) = self.snapshots.remove(&id) else {
+            return false;
+        };
+        self.snapshots.retain(|later, _| *later < id);
+        self.state = snapshot.state;
+        self.blocks = snapshot.blocks;
+        self.transactions = snapshot.transactions;
+        self.funded = snapshot.funded;
+        self.time_offset = snapshot.time_offset;
+        self.next_timestamp = snapshot.next_timestamp;
+        true
+    }
+
+    // Runs `f` on the sandbox state with the light client behind it for anything not loaded yet
+    fn with_db<T>(&mut self, client: &dyn EthClientApi, f: impl FnOnce(&mut VerifiedDb<'_>) -> T) -> T {
+        let cached = std::mem::replace(&mut self.state, CacheDB::new(EmptyDB::default()));
+        let mut db = CacheDB {
+            accounts: cached.accounts,
+            contracts: cached.contracts,
+            logs: Vec::new(),
+            block_hashes: cached.block_hashes,
+            db: VerifiedState::new(client, self.accounts.clone(), BlockTag::Number(self.fork_block)),
+        };
+        let result = tokio::task::block_in_place(|| f(&mut db));
+        self.state.accounts = db.accounts;
+        self.state.contracts = db.contracts;
+        self.state.block_hashes = db.block_hashes;
+        result
+    }
+
+    // Tops up accounts the first time the sandbox sees them, so dapps can be tried without funds
+    pub fn fund(&mut self, client: &dyn EthClientApi, addresses: &[Address]) -> Result<(), String> {
+        let unfunded: Vec<Address> = addresses.iter().filter(|address| !self.funded.contains(address)).copied().collect();
+        if unfunded.is_empty() {
+            return Ok(());
+        }
+        self.with_db(client, |db| {
+            for address in &unfunded {
+                let mut info = db.basic(*address)?.unwrap_or_default();
+                info.balance = info.balance.max(SANDBOX_BALANCE);
+                db.insert_account_info(*address, info);
+            }
+            Ok::<_, String>(())
+        })?;
+        self.funded.extend(unfunded);
+        Ok(())
+    }
+
+    // Executes and mines a transaction at once. No signature is needed, the sandbox takes the
+    // sender the app already checked
+    pub fn send_transaction(&mut self, client: &dyn EthClientApi, tx: &TransactionRequest) -> Result<B256, SandboxError> {
+        let from = tx.from.ok_or_else(|| SandboxError::InvalidParams("missing from address".to_string()))?;
+        let env = self.next_block_env()?;
+        let gas_limit = env.gas_limit.to::<u64>().min(evm::DEFAULT_GAS_CAP);
+        let tx_env = evm::tx_env(tx, gas_limit, false);
+        let gas = tx_env.gas_limit;
+        let (nonce, result) = self.with_db(client, |db| {
+            let nonce = db.basic(from)?.map_or(0, |info| info.nonce);
+            let result = evm::transact(db, &env, tx_env, SANDBOX_CHAIN_ID)?;
+            Ok::<_, String>((nonce, result))
+        })?;
+
+        let number = env.number.to::<u64>();
+        // The timestamp keeps a transaction sent again after evm_revert from reusing the old hash
+        let hash = keccak256([from.as_slice(), &nonce.to_be_bytes(), &env.timestamp.to_be_bytes::<32>()].concat());
+        let gas_used = result.gas_used();
+        let (success, contract_address, logs) = match result {
+            ExecutionResult::Success { output: Output::Create(_, address), logs, .. } => (true, address, logs),
+            ExecutionResult::Success { logs, .. } => (true, None, logs),
+            _ => (false, None, Vec::new()),
+        };
+
+        self.mine(&env, vec![hash], gas_used);
+        self.transactions.insert(hash, SandboxTransaction {
+            hash,
+            block: number,
+            from,
+            to: tx.to.and_then(|to| match to {
+                TxKind::Call(to) => Some(to),
+                TxKind::Create => None,
+            }),
+            nonce,
+            value: tx.value.unwrap_or_default(),
+            gas,
+            input: tx.input.input().cloned().unwrap_or_default(),
+            gas_used,
+            success,
+            contract_address,
+            logs,
+        });
+        Ok(hash)
+    }
+
+    // Answers a read from sandbox state. Reads see the latest sandbox state whatever block they
+    // name, only blocks and logs from before the fork come from the parent chain
+    pub async fn request(&mut self, client: &dyn EthClientApi, method: &str, params: &[Value]) -> Result<Value, SandboxError> {
+        match method {
+            "eth_chainId" => Ok(json!(format!("0x{:x}", SANDBOX_CHAIN_ID))),
+            "net_version" => Ok(json!(SANDBOX_CHAIN_ID.to_string())),
+            "eth_blockNumber" => Ok(json!(format!("0x{:x}", self.head()))),
+            "eth_gasPrice" | "eth_maxPriorityFeePerGas" => Ok(json!("0x0")),
+
+            "eth_getBlockByNumber" => {
+                let number = self.block_number(params.first())?;
+                let full = params.get(1).and_then(Value::as_bool).unwrap_or(false);
+                if number <= self.fork_block {
+                    return Ok(parent_block(client.get_block_by_number(BlockTag::Number(number), full).await)?);
+                }
+                Ok(self.blocks.iter().find(|block| block.number == number).map_or(Value::Null, |block| self.block_json(block, full)))
+            },
+            "eth_getBlockByHash" => {
+                let hash: B256 = param(params, 0)?;
+                let full = params.get(1).and_then(Value::as_bool).unwrap_or(false);
+                match self.blocks.iter().find(|block| block.hash == hash) {
+                    Some(block) => Ok(self.block_json(block, full)),
+                    None => Ok(parent_block(client.get_block_by_hash(hash, full).await)?),
+                }
+            },
+
+            "eth_getBalance" => {
+                let address: Address = param(params, 0)?;
+                let info = self.with_db(client, |db| db.basic(address))?;
+                Ok(json!(info.map(|info| info.balance).unwrap_or_default()))
+            },
+            "eth_getTransactionCount" => {
+                let address: Address = param(params, 0)?;
+                let info = self.with_db(client, |db| db.basic(address))?;
+                Ok(json!(format!("0x{:x}", info.map_or(0, |info| info.nonce))))
+            },
+            "eth_getCode" => {
+                let address: Address = param(params, 0)?;
+                let info = self.with_db(client, |db| db.basic(address))?;
+                let code = info.and_then(|info| info.code).map(|code| code.original_bytes()).unwrap_or_default();
+                Ok(json!(code))
+            },
+            "eth_getStorageAt" => {
+                let address: Address = param(params, 0)?;
+                let slot: U256 = param(params, 1)?;
+                let value = self.with_db(client, |db| db.storage(address, slot))?;
+                Ok(json!(B256::from(value.to_be_bytes())))
+            },
+
+            "eth_call" => {
+                let tx: TransactionRequest = param(params, 0)?;
+                match self.execute(client, &tx)? {
+                    ExecutionResult::Success { output, .. } => Ok(json!(output.into_data())),
+                    ExecutionResult::Revert { output, .. } => Err(revert_message(&output).into()),
+                    ExecutionResult::Halt { reason, .. } => Err(format!("execution halted: {:?}", reason).into()),
+                }
+            },
+            "eth_estimateGas" => {
+                let tx: TransactionRequest = param(params, 0)?;
+                match self.execute(client, &tx)? {
+                    ExecutionResult::Success { gas_used, gas_refunded, .. } => {
+                        let gas = (gas_used + gas_refunded) * (100 + ESTIMATE_MARGIN_PERCENT) / 100;
+                        Ok(json!(format!("0x{:x}", gas)))
+                    },
+                    ExecutionResult::Revert { output, .. } => Err(revert_message(&output).into()),
+                    ExecutionResult::Halt { reason, .. } => Err(format!("execution halted: {:?}", reason).into()),
+                }
+            },
+
+            "eth_getTransactionByHash" => {
+                let hash: B256 = param(params, 0)?;
+                Ok(self.transactions.get(&hash).map_or(Value::Null, |tx| self.transaction_json(tx)))
+            },
+            "eth_getTransactionReceipt" => {
+                let hash: B256 = param(params, 0)?;
+                Ok(self.transactions.get(&hash).map_or(Value::Null, |tx| self.receipt_json(tx)))
+            },
+
+            "eth_getLogs" => {
+                let filter: Filter = param(params, 0)?;
+                Ok(self.logs(client, &filter).await?)
+            },
+
+            "evm_snapshot" => Ok(json!(format!("0x{:x}", self.snapshot()?))),
+            "evm_revert" => {
+                let id = quantity(params.first())?;
+                Ok(json!(self.revert(id)))
+            },
+            // Answers the total offset in seconds, like Hardhat and Anvil
+            "evm_increaseTime" => {
+                let seconds = quantity(params.first())?;
+                let offset = self.time_offset
+                    .checked_add(seconds)
+                    .filter(|offset| clock(*offset).is_some())
+                    .ok_or_else(|| SandboxError::InvalidParams(format!("Increasing the time by {} seconds overflows the clock", seconds)))?;
+                self.time_offset = offset;
+                Ok(json!(self.time_offset))
+            },
+            "evm_setNextBlockTimestamp" => {
+                self.next_timestamp = Some(self.after_head(quantity(params.first())?)?);
+                Ok(Value::Null)
+            },
+            // An empty block, at the given timestamp if there is one
+            "evm_mine" => {
+                if let Some(timestamp) = params.first().filter(|param| !param.is_null()) {
+                    self.next_timestamp = Some(self.after_head(quantity(Some(timestamp))?)?);
+                }
+                let env = self.next_block_env()?;
+                self.mine(&env, Vec::new(), 0);
+                Ok(json!("0x0"))
+            },
+
+            _ => Err(format!("{} is not available in the sandbox", method).into()),
+        }
+    }
+
+    // A timestamp asked of the next block, which has to come after the latest one
+    fn after_head(&self, timestamp: u64) -> Result<u64, SandboxError> {
+        if timestamp <= self.head_timestamp() {
+            return Err(SandboxError::InvalidParams(format!(
+                "Timestamp {} is not after the latest block's {}", timestamp, self.head_timestamp()
+            )));
+        }
+        Ok(timestamp)
+    }
+
+    // Runs a call on top of the latest sandbox state without keeping its changes
+    fn execute(&mut self, client: &dyn EthClientApi, tx: &TransactionRequest) -> Result<ExecutionResult, SandboxError> {
+        let env = self.next_block_env()?;
+        let gas_limit = env.gas_limit.to::<u64>().min(evm::DEFAULT_GAS_CAP);
+        let tx_env = evm::tx_env(tx, gas_limit, false);
+        let outcome = self.with_db(client, |db| evm::execute(db, &env, tx_env, SANDBOX_CHAIN_ID))?;
+        Ok(outcome.result)
+    }
+
+    fn block_number(&self, tag: Option<&Value>) -> Result<u64, SandboxError> {
+        match tag.and_then(Value::as_str).unwrap_or("latest") {
+            "latest" | "pending" | "safe" | "finalized" => Ok(self.head()),
+            "earliest" => Ok(0),
+            number => u64::from_str_radix(number.trim_start_matches("0x"), 16)
+                .map_err(|_| SandboxError::InvalidParams(format!("Invalid block number {}", number))),
+        }
+    }
+
+    async fn logs(&self, client: &dyn EthClientApi, filter: &Filter) -> Result<Value, String> {
+        let (from, to) = match filter.get_block_hash() {
+            Some(hash) => match self.blocks.iter().find(|block| block.hash == hash) {
+                Some(block) => (block.number, block.number),
+                None => {
+                    let logs = client.get_logs(filter).await.map_err(|e| format!("Failed to get logs: {}", e))?;
+                    return serde_json::to_value(logs).map_err(|e| e.to_string());
+                },
+            },
+            None => (
+                filter.get_from_block().unwrap_or(self.head()),
+                filter.get_to_block().unwrap_or(self.head()),
+            ),
+        };
+
+        let mut logs = Vec::new();
+        if from <= self.fork_block {
+            let parent_filter = filter.clone().from_block(from).to_block(to.min(self.fork_block));
+            let parent_logs = client.get_logs(&parent_filter).await.map_err(|e| format!("Failed to get logs: {}", e))?;
+            logs.extend(parent_logs.into_iter().filter_map(|log| serde_json::to_value(log).ok()));
+        }
+        for block in self.blocks.iter().filter(|block| (from..=to).contains(&block.number)) {
+            for tx in block.transactions.iter().filter_map(|hash| self.transactions.get(hash)) {
+                let matching = self.logs_json(tx).into_iter().zip(&tx.logs).filter(|(_, log)| {
+                    filter.address.matches(&log.address)
+                        && filter.topics.iter().enumerate().all(|(i, topics)| {
+                            topics.is_empty() || log.topics().get(i).is_some_and(|topic| topics.matches(topic))
+                        })
+                });
+                logs.extend(matching.map(|(json, _)| json));
+            }
+        }
+        Ok(json!(logs))
+    }
+
+    fn block_json(&self, block: &SandboxBlock, full: bool) -> Value {
+        let transactions: Vec<Value> = block.transactions
+            .iter()
+            .map(|hash| match self.transactions.get(hash).filter(|_| full) {
+                Some(tx) => self.transaction_json(tx),
+                None => json!(hash),
+            })
+            .collect();
+        json!({
+            "number": format!("0x{:x}", block.number),
+            "hash": block.hash,
+            "parentHash": block.parent_hash,
+            "timestamp": format!("0x{:x}", block.timestamp),
+            "gasLimit": format!("0x{:x}", self.base_env.gas_limit),
+            "gasUsed": format!("0x{:x}", block.gas_used),
+            "baseFeePerGas": "0x0",
+            "miner": self.base_env.coinbase,
+            "difficulty": "0x0",
+            "mixHash": self.base_env.prevrandao.unwrap_or_default(),
+            "nonce": "0x0000000000000000",
+            "sha3Uncles": B256::ZERO,
+            "logsBloom": Bytes::from(vec![0u8; 256]),
+            "transactionsRoot": B256::ZERO,
+            "stateRoot": B256::ZERO,
+            "receiptsRoot": B256::ZERO,
+            "extraData": "0x",
+            "size": "0x0",
+            "uncles": [],
+            "transactions": transactions,
+        })
+    }
+
+    fn block_hash(&self, number: u64) -> B256 {
+        self.blocks.iter().find(|block| block.number == number).map(|block| block.hash).unwrap_or_default()
+    }
+
+    fn transaction_json(&self, tx: &SandboxTransaction) -> Value {
+        json!({
+            "hash": tx.hash,
+            "type": "0x2",
+            "chainId": format!("0x{:x}", SANDBOX_CHAIN_ID),
+            "nonce": format!("0x{:x}", tx.nonce),
+            "blockHash": self.block_hash(tx.block),
+            "blockNumber": format!("0x{:x}", tx.block),
+            "transactionIndex": "0x0",
+            "from": tx.from,
+            "to": tx.to,
+            "value": tx.value,
+            "gas": format!("0x{:x}", tx.gas),
+            "gasPrice": "0x0",
+            "maxFeePerGas": "0x0",
+            "maxPriorityFeePerGas": "0x0",
+            "input": tx.input,
+            "accessList": [],
+            "v": "0x0",
+            "r": "0x0",
+            "s": "0x0",
+        })
+    }
+
+    fn logs_json(&self, tx: &SandboxTransaction) -> Vec<Value> {
+        let block_hash = self.block_hash(tx.block);
+        tx.logs
+            .iter()
+            .enumerate()
+            .map(|(i, log)| json!({
+                "address": log.address,
+                "topics": log.topics(),
+                "data": log.data.data,
+                "blockNumber": format!("0x{:x}", tx.block),
+                "blockHash": block_hash,
+                "transactionHash": tx.hash,
+                "transactionIndex": "0x0",
+                "logIndex": format!("0x{:x}", i),
+                "removed": false,
+            }))
+            .collect()
+    }
+
+    fn receipt_json(&self, tx: &SandboxTransaction) -> Value {
+        json!({
+            "transactionHash": tx.hash,
+            "transactionIndex": "0x0",
+            "blockHash": self.block_hash(tx.block),
+            "blockNumber": format!("0x{:x}", tx.block),
+            "from": tx.from,
+            "to": tx.to,
+            "cumulativeGasUsed": format!("0x{:x}", tx.gas_used),
+            "gasUsed": format!("0x{:x}", tx.gas_used),
+            "effectiveGasPrice": "0x0",
+            "contractAddress": tx.contract_address,
+            "logs": self.logs_json(tx),
+            "logsBloom": Bytes::from(vec![0u8; 256]),
+            "type": "0x2",
+            "status": if tx.success { "0x1" } else { "0x0" },
+        })
+    }
+}
+
+fn param<T: serde::de::DeserializeOwned>(params: &[Value], index: usize) -> Result<T, SandboxError> {
+    let value = params.get(index).cloned().ok_or_else(|| SandboxError::InvalidParams(format!("missing param {}", index)))?;
+    serde_json::from_value(value).map_err(|e| SandboxError::InvalidParams(format!("invalid param {}: {}", index, e)))
+}
+
+// A number given as a JSON number or a hex quantity, as node control methods accept both
+fn quantity(value: Option<&Value>) -> Result<u64, SandboxError> {
+    match value {
+        Some(Value::Number(number)) => number.as_u64()
+            .ok_or_else(|| SandboxError::InvalidParams(format!("Invalid number {}", number))),
+        Some(Value::String(hex)) => u64::from_str_radix(hex.trim_start_matches("0x"), 16)
+            .map_err(|_| SandboxError::InvalidParams(format!("Invalid quantity {}", hex))),
+        _ => Err(SandboxError::InvalidParams("missing quantity".to_string())),
+    }
+}
+
+// The wall clock moved forward by `offset` seconds, None if that's past the largest timestamp
+fn clock(offset: u64) -> Option<u64> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    now.checked_add(offset)
+}
+
+fn parent_block<T: Serialize, E: std::fmt::Display>(block: Result<Option<T>, E>) -> Result<Value, String> {
+    let block = block.map_err(|e| format!("Failed to get block: {}", e))?;
+    serde_json::to_value(block).map_err(|e| format!("Failed to serialize block: {}", e))
+}
+
+fn revert_message(output: &Bytes) -> String {
+    match decode_revert_reason(output) {
+        Some(reason) => format!("execution reverted: {}", reason),
+        None => "execution reverted".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{mock_block, MockClient};
+
+    const FORK_BLOCK: u64 = 100;
+    const FORK_TIMESTAMP: u64 = 1_700_000_000;
+    const ONE_ETHER: U256 = U256::from_limbs([1_000_000_000_000_000_000, 0, 0, 0]);
+
+    fn client() -> MockClient {
+        MockClient {
+            chain_id: 1,
+            blocks: vec![mock_block(FORK_BLOCK - 1, FORK_TIMESTAMP - 12), mock_block(FORK_BLOCK, FORK_TIMESTAMP)],
+            ..Default::default()
+        }
+    }
+
+    fn transfer(from: Address, to: Address, value: U256) -> TransactionRequest {
+        TransactionRequest {
+            from: Some(from),
+            to: Some(TxKind::Call(to)),
+            value: Some(value),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn refuses_chain_methods_it_cannot_serve() {
+        assert!(is_refused("eth_sendRawTransaction"));
+        assert!(is_refused("eth_subscribe"));
+        assert!(is_refused("debug_traceTransaction"));
+        assert!(is_refused("eth_sign"));
+        assert!(!is_refused("eth_accounts"));
+        assert!(!is_refused("eth_requestAccounts"));
+        assert!(!is_refused("eth_sendTransaction"));
+        assert!(!is_refused("eth_signTypedData_v4"));
+        assert!(!is_refused("wallet_switchEthereumChain"));
+        assert!(!is_refused("personal_sign"));
+    }
+
+    #[test]
+    fn typed_data_must_name_the_sandbox_chain() {
+        let account = json!("0x0000000000000000000000000000000000000001");
+        let typed_data = |chain_id: Value| json!({ "domain": { "name": "Test", "chainId": chain_id }, "message": {} });
+
+        let sandbox = [account.clone(), typed_data(json!(SANDBOX_CHAIN_ID))];
+        assert_eq!(typed_data_refusal("eth_signTypedData_v4", &sandbox), None);
+        let encoded = [account.clone(), json!(typed_data(json!(format!("0x{:x}", SANDBOX_CHAIN_ID))).to_string())];
+        assert_eq!(typed_data_refusal("eth_signTypedData_v4", &encoded), None);
+        let decimal = [account.clone(), typed_data(json!(SANDBOX_CHAIN_ID.to_string()))];
+        assert_eq!(typed_data_refusal("eth_signTypedData_v3", &decimal), None);
+
+        let mainnet = [account.clone(), typed_data(json!(1))];
+        assert!(typed_data_refusal("eth_signTypedData_v4", &mainnet).is_some());
+        let unbound = [account.clone(), json!({ "domain": { "name": "Test" }, "message": {} })];
+        assert!(typed_data_refusal("eth_signTypedData_v4", &unbound).is_some());
+        assert!(typed_data_refusal("eth_signTypedData", &[json!([]), account]).is_some());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn mines_a_transfer_from_a_funded_account() {
+        let client = client();
+        let from = Address::repeat_byte(0x11);
+        let to = Address::repeat_byte(0x22);
+        let mut sandbox = Sandbox::fork(&client, AccountSource::direct()).await.unwrap();
+        sandbox.fund(&client, &[from]).unwrap();
+
+        let hash = sandbox.send_transaction(&client, &transfer(from, to, ONE_ETHER)).unwrap();
+        let status = sandbox.status();
+        assert_eq!(status.parent_chain, 1);
+        assert_eq!(status.fork_block, FORK_BLOCK);
+        assert_eq!(status.block_number, FORK_BLOCK + 1);
+        assert_eq!(status.transactions, 1);
+
+        let tx = &sandbox.transactions[&hash];
+        let block_hash = sandbox.block_hash(FORK_BLOCK + 1);
+        assert_ne!(block_hash, B256::ZERO);
+
+        let tx_json = sandbox.transaction_json(tx);
+        assert_eq!(tx_json["hash"], json!(hash));
+        assert_eq!(tx_json["chainId"], json!(format!("0x{:x}", SANDBOX_CHAIN_ID)));
+        assert_eq!(tx_json["from"], json!(from));
+        assert_eq!(tx_json["to"], json!(to));
+        assert_eq!(tx_json["value"], json!(ONE_ETHER));
+        assert_eq!(tx_json["nonce"], json!("0x0"));
+        assert_eq!(tx_json["blockNumber"], json!(format!("0x{:x}", FORK_BLOCK + 1)));
+        assert_eq!(tx_json["blockHash"], json!(block_hash));
+
+        let receipt = sandbox.receipt_json(tx);
+        assert_eq!(receipt["transactionHash"], json!(hash));
+        assert_eq!(receipt["blockHash"], json!(block_hash));
+        assert_eq!(receipt["status"], json!("0x1"));
+        assert_eq!(receipt["gasUsed"], json!("0x5208"));
+        assert_eq!(receipt["contractAddress"], Value::Null);
+        assert_eq!(receipt["logs"], json!([]));
+
+        let balance = sandbox.request(&client, "eth_getBalance", &[json!(to), json!("latest")]).await.unwrap();
+        assert_eq!(balance, json!(ONE_ETHER));
+        let nonce = sandbox.request(&client, "eth_getTransactionCount", &[json!(from), json!("latest")]).await.unwrap();
+        assert_eq!(nonce, json!("0x1"));
+        let receipt = sandbox.request(&client, "eth_getTransactionReceipt", &[json!(hash)]).await.unwrap();
+        assert_eq!(receipt["status"], json!("0x1"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn refuses_a_transfer_from_an_unfunded_account() {
+        let client = client();
+        let mut sandbox = Sandbox::fork(&client, AccountSource::direct()).await.unwrap();
+        let tx = transfer(Address::repeat_byte(0x11), Address::repeat_byte(0x22), ONE_ETHER);
+        assert!(sandbox.send_transaction(&client, &tx).is_err());
+        assert_eq!(sandbox.status().block_number, FORK_BLOCK);
+    }
+
+    async fn mine(sandbox: &mut Sandbox, client: &MockClient) {
+        sandbox.request(client, "evm_mine", &[]).await.unwrap();
+    }
+
+    async fn snapshot(sandbox: &mut Sandbox, client: &MockClient) -> Value {
+        sandbox.request(client, "evm_snapshot", &[]).await.unwrap()
+    }
+
+    async fn revert(sandbox: &mut Sandbox, client: &MockClient, id: &Value) -> bool {
+        sandbox.request(client, "evm_revert", &[id.clone()]).await.unwrap() == json!(true)
+    }
+
+    #[tokio::test]
+    async fn reverting_drops_the_snapshot_and_every_later_one() {
+        let client = client();
+        let mut sandbox = Sandbox::fork(&client, AccountSource::direct()).await.unwrap();
+
+        let first = snapshot(&mut sandbox, &client).await;
+        mine(&mut sandbox, &client).await;
+        let second = snapshot(&mut sandbox, &client).await;
+        mine(&mut sandbox, &client).await;
+        let third = snapshot(&mut sandbox, &client).await;
+        mine(&mut sandbox, &client).await;
+        assert_eq!((first.clone(), second.clone(), third.clone()), (json!("0x1"), json!("0x2"), json!("0x3")));
+        assert_eq!(sandbox.status().block_number, FORK_BLOCK + 3);
+
+        assert!(revert(&mut sandbox, &client, &second).await);
+        assert_eq!(sandbox.status().block_number, FORK_BLOCK + 1);
+        assert_eq!(sandbox.status().snapshots, 1);
+        // Taken after the one reverted to, so gone with it
+        assert!(!revert(&mut sandbox, &client, &third).await);
+        // A snapshot is used up by reverting to it
+        assert!(!revert(&mut sandbox, &client, &second).await);
+
+        // Ids keep counting up after a revert, so a new snapshot can't be mistaken for a dropped one
+        mine(&mut sandbox, &client).await;
+        assert_eq!(snapshot(&mut sandbox, &client).await, json!("0x4"));
+
+        assert!(revert(&mut sandbox, &client, &first).await);
+        assert_eq!(sandbox.status().block_number, FORK_BLOCK);
+        assert_eq!(sandbox.status().snapshots, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reverting_restores_state_and_transactions() {
+        let client = client();
+        let from = Address::repeat_byte(0x11);
+        let to = Address::repeat_byte(0x22);
+        let mut sandbox = Sandbox::fork(&client, AccountSource::direct()).await.unwrap();
+        sandbox.fund(&client, &[from]).unwrap();
+
+        let id = snapshot(&mut sandbox, &client).await;
+        let hash = sandbox.send_transaction(&client, &transfer(from, to, ONE_ETHER)).unwrap();
+        assert!(revert(&mut sandbox, &client, &id).await);
+
+        let receipt = sandbox.request(&client, "eth_getTransactionReceipt", &[json!(hash)]).await.unwrap();
+        assert_eq!(receipt, Value::Null);
+        let balance = sandbox.request(&client, "eth_getBalance", &[json!(to), json!("latest")]).await.unwrap();
+        assert_eq!(balance, json!(U256::ZERO));
+        let nonce = sandbox.request(&client, "eth_getTransactionCount", &[json!(from), json!("latest")]).await.unwrap();
+        assert_eq!(nonce, json!("0x0"));
+    }
+
+    #[tokio::test]
+    async fn keeps_a_bounded_number_of_snapshots() {
+        let client = client();
+        let mut sandbox = Sandbox::fork(&client, AccountSource::direct()).await.unwrap();
+        for _ in 0..MAX_SNAPSHOTS {
+            snapshot(&mut sandbox, &client).await;
+        }
+        let refused = sandbox.request(&client, "evm_snapshot", &[]).await;
+        assert!(matches!(refused, Err(SandboxError::LimitExceeded(_))));
+
+        assert!(revert(&mut sandbox, &client, &json!(format!("0x{:x}", MAX_SNAPSHOTS))).await);
+        snapshot(&mut sandbox, &client).await;
+    }
+
+    #[tokio::test]
+    async fn moves_the_clock_without_overflowing() {
+        let client = client();
+        let mut sandbox = Sandbox::fork(&client, AccountSource::direct()).await.unwrap();
+
+        let offset = sandbox.request(&client, "evm_increaseTime", &[json!(3600)]).await.unwrap();
+        assert_eq!(offset, json!(3600));
+        let overflow = sandbox.request(&client, "evm_increaseTime", &[json!(u64::MAX)]).await;
+        assert!(matches!(overflow, Err(SandboxError::InvalidParams(_))));
+        assert_eq!(sandbox.status().time_offset, 3600);
+
+        let past = sandbox.request(&client, "evm_setNextBlockTimestamp", &[json!(FORK_TIMESTAMP)]).await;
+        assert!(matches!(past, Err(SandboxError::InvalidParams(_))));
+
+        sandbox.request(&client, "evm_setNextBlockTimestamp", &[json!(u64::MAX)]).await.unwrap();
+        mine(&mut sandbox, &client).await;
+        let block = sandbox.request(&client, "eth_getBlockByNumber", &[json!("latest"), json!(false)]).await.unwrap();
+        assert_eq!(block["timestamp"], json!(format!("0x{:x}", u64::MAX)));
+        // Nothing can come after the largest timestamp
+        let next = sandbox.request(&client, "evm_mine", &[]).await;
+        assert!(matches!(next, Err(SandboxError::InvalidParams(_))));
+    }
+}
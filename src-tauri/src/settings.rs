@@ -0,0 +1,363 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, EventTarget};
+
+use crate::cache::CachePolicy;
+use crate::distribution::DistributionSettings;
+use crate::filters::FilterLimits;
+use crate::gas::{EstimationPolicy, FeeSpeed, PriorityFeeSettings};
+use crate::notify::NotificationSettings;
+use crate::outbound::{self, HttpSettings};
+use crate::protect::RelayConfig;
+use crate::retry::RetryPolicy;
+use crate::signer::WalletSettings;
+use crate::storage::StorageSettings;
+use crate::walletconnect::WalletConnectSettings;
+use crate::window::OutOfWindow;
+use crate::{ipfs, profile, tokens, AppState};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RpcSettings {
+    pub ipfs_gateways: Vec<String>,
+    pub token_lists: Vec<String>,
+    // ERC-4337 bundler endpoint per chain id
+    pub bundlers: HashMap<u64, String>,
+    pub retry: RetryPolicy,
+    // Requests for blocks older than the light client can verify
+    pub out_of_window: OutOfWindow,
+    pub filters: FilterLimits,
+    // Connection pooling of the shared upstream HTTP client
+    pub http: HttpSettings,
+    // Sizes and lifetimes of the response caches
+    pub cache: CachePolicy,
+}
+
+impl Default for RpcSettings {
+    fn default() -> Self {
+        Self {
+            ipfs_gateways: ipfs::DEFAULT_GATEWAYS.iter().map(|g| g.to_string()).collect(),
+            token_lists: tokens::DEFAULT_TOKEN_LISTS.iter().map(|l| l.to_string()).collect(),
+            bundlers: HashMap::new(),
+            retry: RetryPolicy::default(),
+            out_of_window: OutOfWindow::default(),
+            filters: FilterLimits::default(),
+            http: HttpSettings::default(),
+            cache: CachePolicy::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NetworkSettings {
+    // Chain the client was last started on, reported to dapps before it's running again
+    pub chain_id: u64,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self { chain_id: crate::DEFAULT_CHAIN_ID }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PrivacySettings {
+    pub private_relay: RelayConfig,
+    // http://, socks5:// or socks5h:// proxy for all outbound traffic, e.g. Tor on socks5h://127.0.0.1:9050
+    pub proxy: Option<String>,
+    // Spreading account queries over several execution providers
+    pub distribution: DistributionSettings,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FeeSettings {
+    pub speed: FeeSpeed,
+    pub estimation: EstimationPolicy,
+    pub priority_fee: PriorityFeeSettings,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FeatureSettings {
+    pub local_tracing: bool,
+    pub unverified_passthrough: bool,
+    pub allow_eth_sign: bool,
+    // Attach the EIP-1186 proof and verified state root to balance and storage answers
+    pub export_proofs: bool,
+    // Refuse every signing, sending and key management request
+    pub read_only: bool,
+}
+
+// User preferences, persisted to settings.json and addressed by dotted camelCase keys like
+// `features.allowEthSign`. The live values are the AppState fields they are applied to
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub rpc: RpcSettings,
+    pub network: NetworkSettings,
+    pub privacy: PrivacySettings,
+    pub fees: FeeSettings,
+    pub features: FeatureSettings,
+    pub notifications: NotificationSettings,
+    // Disk footprint limit and history retention
+    pub storage: StorageSettings,
+    pub walletconnect: WalletConnectSettings,
+    pub wallet: WalletSettings,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SettingChanged<'a> {
+    key: &'a str,
+    value: serde_json::Value,
+}
+
+// The empty key addresses all settings at once
+fn pointer(key: &str) -> String {
+    match key {
+        "" => String::new(),
+        key => format!("/{}", key.replace('.', "/")),
+    }
+}
+
+fn check_url(url: &str, what: &str) -> Result<(), String> {
+    match url::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(()),
+        Ok(_) => Err(format!("{} must be an http(s) URL: {}", what, url)),
+        Err(e) => Err(format!("Invalid {} {}: {}", what, url, e)),
+    }
+}
+
+impl Settings {
+    pub fn from_state(state: &AppState) -> Self {
+        Self {
+            rpc: RpcSettings {
+                ipfs_gateways: state.ipfs_gateways.clone(),
+                token_lists: state.token_lists.clone(),
+                bundlers: state.bundlers.clone(),
+                retry: state.retry_policy.clone(),
+                out_of_window: state.out_of_window,
+                filters: state.filter_limits.clone(),
+                http: state.http_settings.clone(),
+                cache: state.cache_policy.clone(),
+            },
+            network: NetworkSettings {
+                chain_id: state.chain_id,
+            },
+            privacy: PrivacySettings {
+                private_relay: state.private_relay.clone(),
+                proxy: state.proxy.clone(),
+                distribution: state.distribution.clone(),
+            },
+            fees: FeeSettings {
+                speed: state.fee_speed,
+                estimation: state.gas_estimation.clone(),
+                priority_fee: state.priority_fee.clone(),
+            },
+            features: FeatureSettings {
+                local_tracing: state.local_tracing,
+                unverified_passthrough: state.unverified_passthrough,
+                allow_eth_sign: state.allow_eth_sign,
+                export_proofs: state.export_proofs,
+                read_only: state.read_only,
+            },
+            notifications: state.notifications.clone(),
+            storage: state.storage.clone(),
+            walletconnect: state.walletconnect.settings.clone(),
+            wallet: WalletSettings {
+                auto_lock_minutes: state.wallet.auto_lock().map(|timeout| timeout.as_secs() / 60),
+            },
+        }
+    }
+
+    pub fn apply(self, state: &mut AppState) {
+        state.ipfs_gateways = self.rpc.ipfs_gateways;
+        state.token_lists = self.rpc.token_lists;
+        state.bundlers = self.rpc.bundlers;
+        state.retry_policy = self.rpc.retry;
+        state.out_of_window = self.rpc.out_of_window;
+        state.filter_limits = self.rpc.filters;
+        outbound::configure(&self.rpc.http, self.privacy.proxy.as_deref());
+        state.http_settings = self.rpc.http;
+        state.cache_policy = self.rpc.cache;
+        state.chain_id = self.network.chain_id;
+        state.private_relay = self.privacy.private_relay;
+        state.proxy = self.privacy.proxy;
+        state.distribution = self.privacy.distribution;
+        state.fee_speed = self.fees.speed;
+        state.gas_estimation = self.fees.estimation;
+        state.priority_fee = self.fees.priority_fee;
+        state.local_tracing = self.features.local_tracing;
+        state.unverified_passthrough = self.features.unverified_passthrough;
+        state.allow_eth_sign = self.features.allow_eth_sign;
+        state.export_proofs = self.features.export_proofs;
+        state.read_only = self.features.read_only;
+        state.notifications = self.notifications;
+        state.storage = self.storage;
+        state.walletconnect.settings = self.walletconnect;
+        if let Ok(auto_lock) = self.wallet.auto_lock() {
+            state.wallet.set_auto_lock(auto_lock);
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.rpc.ipfs_gateways.is_empty() {
+            return Err("At least one IPFS gateway is required".to_string());
+        }
+        for gateway in &self.rpc.ipfs_gateways {
+            check_url(gateway, "IPFS gateway")?;
+        }
+        for list in &self.rpc.token_lists {
+            check_url(list, "token list URL")?;
+        }
+        for bundler in self.rpc.bundlers.values() {
+            check_url(bundler, "bundler URL")?;
+        }
+        self.rpc.retry.validate()?;
+        self.rpc.filters.validate()?;
+        self.rpc.http.validate()?;
+        self.rpc.cache.validate()?;
+        self.fees.estimation.validate()?;
+        self.fees.priority_fee.validate()?;
+        if let Some(proxy) = &self.privacy.proxy {
+            outbound::validate_proxy(proxy)?;
+        }
+        self.privacy.distribution.validate()?;
+        self.storage.validate()?;
+        self.walletconnect.validate()?;
+        self.wallet.validate()?;
+        for provider in &self.privacy.distribution.providers {
+            check_url(provider, "execution provider")?;
+        }
+        check_url(&self.privacy.private_relay.relay_url, "relay URL")?;
+        check_url(&self.privacy.private_relay.status_url, "relay status URL")
+    }
+
+    // What moving from `current` to these settings would loosen, if anything: blind signatures,
+    // unverified answers, leaving read-only mode and a longer auto-lock all need the user to
+    // authenticate
+    pub fn weakens(&self, current: &Settings) -> Option<&'static str> {
+        if self.wallet.loosens(&current.wallet) {
+            return Some("Extend auto-lock");
+        }
+        let (next, current) = (&self.features, &current.features);
+        if next.allow_eth_sign && !current.allow_eth_sign {
+            Some("Allow eth_sign")
+        } else if next.unverified_passthrough && !current.unverified_passthrough {
+            Some("Allow unverified passthrough")
+        } else if current.read_only && !next.read_only {
+            Some("Turn off read-only mode")
+        } else {
+            None
+        }
+    }
+
+    // A whole section or a single value, e.g. `rpc` or `rpc.retry.maxAttempts`
+    pub fn get(&self, key: &str) -> Result<serde_json::Value, String> {
+        let tree = serde_json::to_value(self).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        tree.pointer(&pointer(key))
+            .cloned()
+            .ok_or_else(|| format!("Unknown setting {}", key))
+    }
+
+    // Copy with `key` replaced, the value has to deserialize into the setting's type and pass validation
+    pub fn with(&self, key: &str, value: serde_json::Value) -> Result<Self, String> {
+        let mut tree = serde_json::to_value(self).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        let slot = tree.pointer_mut(&pointer(key)).ok_or_else(|| format!("Unknown setting {}", key))?;
+        *slot = value;
+        let settings: Self = serde_json::from_value(tree).map_err(|e| format!("Invalid value for {}: {}", key, e))?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub async fn load(path: &Path) -> Self {
+        match tokio::fs::read(path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring invalid settings file: {}", e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub async fn save(&self, path: &Path) -> Result<(), String> {
+        let bytes = serde_json::to_vec_pretty(self).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Failed to create data dir: {}", e))?;
+        }
+        tokio::fs::write(path, bytes)
+            .await
+            .map_err(|e| format!("Failed to write settings: {}", e))
+    }
+}
+
+pub fn settings_path(app: &AppHandle) -> Option<PathBuf> {
+    profile::data_dir(app).map(|dir| dir.join("settings.json"))
+}
+
+// Applies the saved settings at startup
+pub async fn restore(app: &AppHandle, state_guard: &mut AppState) {
+    if let Some(path) = settings_path(app) {
+        Settings::load(&path).await.apply(state_guard);
+    }
+}
+
+// Saves the state's settings and emits `settings-changed` with the new value of `key` to the main
+// window, dapp webviews have no business reading the wallet's configuration
+pub async fn commit(app: &AppHandle, state_guard: &AppState, key: &str) -> Result<(), String> {
+    let settings = Settings::from_state(state_guard);
+    if let Some(path) = settings_path(app) {
+        settings.save(&path).await?;
+    }
+    let value = settings.get(key)?;
+    let _ = app.emit_to(EventTarget::webview_window("main"), "settings-changed", SettingChanged { key, value });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loosening_security_switches_is_a_weakening() {
+        let current = Settings::default();
+        assert_eq!(current.weakens(&current), None);
+        let eth_sign = current.with("features.allowEthSign", serde_json::json!(true)).unwrap();
+        assert_eq!(eth_sign.weakens(&current), Some("Allow eth_sign"));
+        assert_eq!(current.weakens(&eth_sign), None);
+        let passthrough = current.with("features", serde_json::json!({ "unverifiedPassthrough": true })).unwrap();
+        assert_eq!(passthrough.weakens(&current), Some("Allow unverified passthrough"));
+        let read_only = current.with("features.readOnly", serde_json::json!(true)).unwrap();
+        assert_eq!(current.weakens(&read_only), Some("Turn off read-only mode"));
+        assert_eq!(read_only.weakens(&current), None);
+    }
+
+    #[test]
+    fn keeping_the_wallet_unlocked_longer_is_a_weakening() {
+        let current = Settings::default();
+        let shorter = current.with("wallet.autoLockMinutes", serde_json::json!(5)).unwrap();
+        assert_eq!(shorter.weakens(&current), None);
+        assert_eq!(current.weakens(&shorter), Some("Extend auto-lock"));
+        let disabled = current.with("wallet.autoLockMinutes", serde_json::json!(null)).unwrap();
+        assert_eq!(disabled.weakens(&current), Some("Extend auto-lock"));
+        assert_eq!(current.weakens(&disabled), None);
+        assert!(current.with("wallet.autoLockMinutes", serde_json::json!(0)).is_err());
+        assert!(current.with("wallet.autoLockMinutes", serde_json::json!(u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn retry_policy_is_bounded() {
+        let current = Settings::default();
+        assert!(current.with("rpc.retry.maxAttempts", serde_json::json!(10)).is_ok());
+        assert!(current.with("rpc.retry.maxAttempts", serde_json::json!(11)).is_err());
+        assert!(current.with("rpc.retry.maxAttempts", serde_json::json!(0)).is_err());
+        assert!(current.with("rpc.retry.maxDelayMs", serde_json::json!(60_001)).is_err());
+        assert!(current.with("rpc.retry", serde_json::json!({ "maxAttempts": 3, "baseDelayMs": 500, "maxDelayMs": 100 })).is_err());
+    }
+}
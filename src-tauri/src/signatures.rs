@@ -0,0 +1,242 @@
+use alloy::hex;
+use alloy::primitives::keccak256;
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::outbound;
+
+const OPENCHAIN_LOOKUP: &str = "https://api.openchain.xyz/signature-database/v1/lookup";
+// Asked when openchain doesn't know a selector
+const FOURBYTE_LOOKUP: &str = "https://www.4byte.directory/api/v1/signatures/";
+
+// Selectors the remote database didn't know are retried after a week
+const MISS_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+// Bumped whenever BUNDLED_SIGNATURES changes so existing caches get reseeded
+const BUNDLED_VERSION: i64 = 1;
+
+// Common functions that should decode even when offline
+const BUNDLED_SIGNATURES: &[&str] = &[
+    "transfer(address,uint256)",
+    "transferFrom(address,address,uint256)",
+    "approve(address,uint256)",
+    "increaseAllowance(address,uint256)",
+    "decreaseAllowance(address,uint256)",
+    "permit(address,address,uint256,uint256,uint8,bytes32,bytes32)",
+    "setApprovalForAll(address,bool)",
+    "safeTransferFrom(address,address,uint256)",
+    "safeTransferFrom(address,address,uint256,bytes)",
+    "safeTransferFrom(address,address,uint256,uint256,bytes)",
+    "safeBatchTransferFrom(address,address,uint256[],uint256[],bytes)",
+    "deposit()",
+    "withdraw(uint256)",
+    "multicall(bytes[])",
+    "multicall(uint256,bytes[])",
+    "aggregate3((address,bool,bytes)[])",
+    "execute(bytes,bytes[],uint256)",
+    "swapExactTokensForTokens(uint256,uint256,address[],address,uint256)",
+    "swapTokensForExactTokens(uint256,uint256,address[],address,uint256)",
+    "swapExactETHForTokens(uint256,address[],address,uint256)",
+    "swapExactTokensForETH(uint256,uint256,address[],address,uint256)",
+    "exactInputSingle((address,address,uint24,address,uint256,uint256,uint256,uint160))",
+    "exactInput((bytes,address,uint256,uint256,uint256))",
+    "setAddr(bytes32,address)",
+    "setText(bytes32,string,string)",
+    "register(string,address,uint256,bytes32,address,bytes[],bool,uint16)",
+    "renew(string,uint256)",
+];
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS signatures (
+        selector TEXT NOT NULL,
+        signature TEXT NOT NULL,
+        source TEXT NOT NULL,
+        PRIMARY KEY (selector, signature)
+    );
+    CREATE TABLE IF NOT EXISTS misses (
+        selector TEXT PRIMARY KEY,
+        checked_at INTEGER NOT NULL
+    );
+";
+
+// Subset of the openchain lookup response
+#[derive(Deserialize)]
+struct LookupResponse {
+    result: LookupResult,
+}
+
+#[derive(Deserialize)]
+struct LookupResult {
+    function: HashMap<String, Option<Vec<RemoteSignature>>>,
+}
+
+#[derive(Deserialize)]
+struct RemoteSignature {
+    name: String,
+}
+
+// Subset of the 4byte.directory signatures response
+#[derive(Deserialize)]
+struct FourByteResponse {
+    results: Vec<FourByteSignature>,
+}
+
+#[derive(Deserialize)]
+struct FourByteSignature {
+    text_signature: String,
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+pub fn selector(signature: &str) -> String {
+    format!("0x{}", hex::encode(&keccak256(signature.as_bytes())[..4]))
+}
+
+// SQLite cache of selector -> signature text, seeded with the bundled list
+pub struct SignatureDb {
+    conn: Connection,
+}
+
+impl SignatureDb {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create signature cache dir: {}", e))?;
+        }
+        let conn = Connection::open(path).map_err(|e| format!("Failed to open signature cache: {}", e))?;
+        conn.execute_batch(SCHEMA).map_err(|e| format!("Failed to create signature cache: {}", e))?;
+
+        let db = Self { conn };
+        db.seed()?;
+        Ok(db)
+    }
+
+    fn seed(&self) -> Result<(), String> {
+        let version: i64 = self.conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|e| format!("Failed to read signature cache version: {}", e))?;
+        if version >= BUNDLED_VERSION {
+            return Ok(());
+        }
+
+        for signature in BUNDLED_SIGNATURES {
+            self.store(&selector(signature), &[signature.to_string()], "bundled")?;
+        }
+        self.conn
+            .execute_batch(&format!("PRAGMA user_version = {}", BUNDLED_VERSION))
+            .map_err(|e| format!("Failed to update signature cache version: {}", e))
+    }
+
+    pub fn get(&self, selector: &str) -> Result<Vec<String>, String> {
+        let mut statement = self.conn
+            .prepare("SELECT signature FROM signatures WHERE selector = ?1 ORDER BY source = 'bundled' DESC, rowid")
+            .map_err(|e| format!("Failed to query signature cache: {}", e))?;
+        let rows = statement
+            .query_map(params![selector], |row| row.get(0))
+            .map_err(|e| format!("Failed to query signature cache: {}", e))?;
+        rows.collect::<Result<Vec<String>, _>>()
+            .map_err(|e| format!("Failed to read signature cache: {}", e))
+    }
+
+    pub fn store(&self, selector: &str, signatures: &[String], source: &str) -> Result<(), String> {
+        for signature in signatures {
+            self.conn
+                .execute(
+                    "INSERT OR IGNORE INTO signatures (selector, signature, source) VALUES (?1, ?2, ?3)",
+                    params![selector, signature, source],
+                )
+                .map_err(|e| format!("Failed to write signature cache: {}", e))?;
+        }
+        Ok(())
+    }
+
+    fn recently_missed(&self, selector: &str) -> bool {
+        self.conn
+            .query_row("SELECT checked_at FROM misses WHERE selector = ?1", params![selector], |row| row.get::<_, i64>(0))
+            .map(|checked_at| now() - checked_at < MISS_TTL_SECS as i64)
+            .unwrap_or(false)
+    }
+
+    fn record_miss(&self, selector: &str) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO misses (selector, checked_at) VALUES (?1, ?2)",
+                params![selector, now()],
+            )
+            .map(|_| ())
+            .map_err(|e| format!("Failed to write signature cache: {}", e))
+    }
+}
+
+async fn fetch_remote(selector: &str) -> Result<Vec<String>, String> {
+    let mut response = outbound::client()
+        .get(OPENCHAIN_LOOKUP)
+        .query(&[("function", selector), ("filter", "true")])
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Signature lookup failed: {}", e))?
+        .json::<LookupResponse>()
+        .await
+        .map_err(|e| format!("Invalid signature lookup response: {}", e))?;
+
+    Ok(response.result.function
+        .remove(selector)
+        .flatten()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|signature| signature.name)
+        .collect())
+}
+
+async fn fetch_fourbyte(selector: &str) -> Result<Vec<String>, String> {
+    let response = outbound::client()
+        .get(FOURBYTE_LOOKUP)
+        .query(&[("hex_signature", selector)])
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("4byte lookup failed: {}", e))?
+        .json::<FourByteResponse>()
+        .await
+        .map_err(|e| format!("Invalid 4byte lookup response: {}", e))?;
+
+    // 4byte lists the newest submission first, older ones are likelier to be the real function
+    Ok(response.results.into_iter().rev().map(|signature| signature.text_signature).collect())
+}
+
+// Resolves a selector to candidate signatures: local cache first, then openchain, then 4byte.
+// The connection is reopened around the network requests so no handle is held across awaits
+pub async fn lookup(cache: &Path, selector: &str) -> Result<Vec<String>, String> {
+    {
+        let db = SignatureDb::open(cache)?;
+        let local = db.get(selector)?;
+        if !local.is_empty() || db.recently_missed(selector) {
+            return Ok(local);
+        }
+    }
+
+    let openchain = fetch_remote(selector).await;
+    let (remote, source) = match &openchain {
+        Ok(remote) if !remote.is_empty() => (remote.clone(), "openchain"),
+        // Unknown to openchain or openchain unreachable, either way 4byte has the last word
+        _ => (fetch_fourbyte(selector).await?, "4byte"),
+    };
+    let db = SignatureDb::open(cache)?;
+    if remote.is_empty() {
+        // An unreachable openchain says nothing about the selector, so it's asked again next time
+        if openchain.is_ok() {
+            db.record_miss(selector)?;
+        }
+    } else {
+        db.store(selector, &remote, source)?;
+    }
+    Ok(remote)
+}
@@ -0,0 +1,218 @@
+use alloy::eips::eip2718::Encodable2718;
+use alloy::network::{EthereumWallet, TransactionBuilder};
+use alloy::primitives::{Address, Bytes, B256};
+use alloy::rpc::types::TransactionRequest;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use helios::core::types::BlockTag;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::{Duration, Instant};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Mutex;
+
+use crate::client::EthClientApi;
+use crate::auth::Authorization;
+use crate::distribution::AccountSource;
+use crate::gas::{self, GasEstimation, GasTier};
+use crate::AppState;
+
+const DEFAULT_AUTO_LOCK_MINUTES: u64 = 15;
+const DEFAULT_AUTO_LOCK: Duration = Duration::from_secs(DEFAULT_AUTO_LOCK_MINUTES * 60);
+const AUTO_LOCK_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WalletSettings {
+    // Idle minutes before the wallet locks itself, None disables auto-lock
+    pub auto_lock_minutes: Option<u64>,
+}
+
+impl Default for WalletSettings {
+    fn default() -> Self {
+        Self { auto_lock_minutes: Some(DEFAULT_AUTO_LOCK_MINUTES) }
+    }
+}
+
+impl WalletSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        self.auto_lock().map(|_| ())
+    }
+
+    pub fn auto_lock(&self) -> Result<Option<Duration>, String> {
+        match self.auto_lock_minutes {
+            None => Ok(None),
+            Some(0) => Err("Auto-lock timeout must be at least a minute".to_string()),
+            Some(minutes) => minutes.checked_mul(60)
+                .map(|secs| Some(Duration::from_secs(secs)))
+                .ok_or_else(|| format!("Auto-lock timeout of {} minutes is too long", minutes)),
+        }
+    }
+
+    // Whether these settings leave an unlocked wallet open longer than `current` does
+    pub fn loosens(&self, current: &WalletSettings) -> bool {
+        match (self.auto_lock_minutes, current.auto_lock_minutes) {
+            (_, None) => false,
+            (None, Some(_)) => true,
+            (Some(next), Some(current)) => next > current,
+        }
+    }
+}
+
+// Unlocked accounts available for signing. Locking drops the signers, whose keys are zeroized on drop
+pub struct Wallet {
+    signers: Vec<PrivateKeySigner>,
+    // Idle time before the wallet locks itself, None disables auto-lock
+    auto_lock: Option<Duration>,
+    last_active: Instant,
+}
+
+impl Default for Wallet {
+    fn default() -> Self {
+        Self {
+            signers: Vec::new(),
+            auto_lock: Some(DEFAULT_AUTO_LOCK),
+            last_active: Instant::now(),
+        }
+    }
+}
+
+impl Wallet {
+    pub fn is_locked(&self) -> bool {
+        self.signers.is_empty()
+    }
+
+    pub fn auto_lock(&self) -> Option<Duration> {
+        self.auto_lock
+    }
+
+    pub fn set_auto_lock(&mut self, auto_lock: Option<Duration>) {
+        self.auto_lock = auto_lock;
+    }
+
+    // Postpones auto-lock, called whenever the wallet is used
+    pub fn touch(&mut self) {
+        self.last_active = Instant::now();
+    }
+
+    // Returns whether there was anything to lock
+    pub fn lock(&mut self) -> bool {
+        let was_unlocked = !self.is_locked();
+        self.signers.clear();
+        was_unlocked
+    }
+
+    pub fn signer(&self, address: Address) -> Option<&PrivateKeySigner> {
+        self.signers.iter().find(|signer| signer.address() == address)
+    }
+
+    pub fn addresses(&self) -> Vec<Address> {
+        self.signers.iter().map(|signer| signer.address()).collect()
+    }
+
+    pub fn add(&mut self, signer: PrivateKeySigner) {
+        if self.signer(signer.address()).is_none() {
+            self.signers.push(signer);
+        }
+        self.touch();
+    }
+}
+
+// Locks the wallet once it has been idle for longer than the auto-lock timeout
+pub fn spawn_auto_lock(app: AppHandle) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(AUTO_LOCK_CHECK_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let state = app.state::<Mutex<AppState>>();
+            let mut state_guard = state.lock().await;
+            let wallet = &mut state_guard.wallet;
+            let idle = wallet.auto_lock.is_some_and(|timeout| wallet.last_active.elapsed() >= timeout);
+            if idle && wallet.lock() {
+                let _ = app.emit("wallet-locked", json!({ "reason": "idle" }));
+            }
+        }
+    })
+}
+
+// Completes a dapp's transaction request from verified state: chain id, nonce (after any
+// transactions still pending from this wallet), buffered gas limit and fees from the chosen gas oracle tier
+pub async fn fill_transaction(
+    client: &dyn EthClientApi,
+    accounts: &AccountSource,
+    mut tx: TransactionRequest,
+    next_pending_nonce: Option<u64>,
+    fee_tier: Option<&GasTier>,
+    estimation: &GasEstimation,
+) -> Result<TransactionRequest, String> {
+    let from = tx.from.ok_or("missing from address")?;
+
+    let chain_id = client.chain_id().await;
+    match tx.chain_id {
+        Some(requested) if requested != chain_id => {
+            return Err(format!("chainId {} doesn't match the active chain {}", requested, chain_id));
+        },
+        _ => tx.chain_id = Some(chain_id),
+    }
+
+    if tx.nonce.is_none() {
+        let nonce = accounts.account(client, from, BlockTag::Latest)
+            .await
+            .map_err(|e| format!("failed to get nonce: {}", e))?
+            .nonce;
+        tx.nonce = Some(nonce.max(next_pending_nonce.unwrap_or_default()));
+    }
+
+    if tx.gas.is_none() {
+        let gas = gas::estimate(client, &tx, estimation)
+            .await
+            .map_err(|e| format!("failed to estimate gas: {}", e))?;
+        tx.gas = Some(gas as u128);
+    }
+
+    if tx.gas_price.is_none() && tx.max_fee_per_gas.is_none() {
+        let (max_fee, priority_fee) = match fee_tier {
+            Some(tier) => (
+                tier.max_fee_per_gas.saturating_to::<u128>(),
+                tier.max_priority_fee_per_gas.saturating_to::<u128>(),
+            ),
+            None => {
+                let gas_price = client.get_gas_price()
+                    .await
+                    .map_err(|e| format!("failed to get gas price: {}", e))?
+                    .saturating_to::<u128>();
+                let priority_fee = client.get_priority_fee()
+                    .await
+                    .map_err(|e| format!("failed to get priority fee: {}", e))?
+                    .saturating_to::<u128>();
+                (gas_price * 2, priority_fee)
+            },
+        };
+        tx.max_fee_per_gas = Some(max_fee.max(priority_fee));
+        tx.max_priority_fee_per_gas = Some(tx.max_priority_fee_per_gas.unwrap_or(priority_fee));
+    }
+
+    Ok(tx)
+}
+
+// Signs a filled request and returns the EIP-2718 encoded transaction
+pub async fn sign_transaction(
+    signer: &PrivateKeySigner,
+    tx: TransactionRequest,
+    _authorization: &Authorization,
+) -> Result<Bytes, String> {
+    let wallet = EthereumWallet::from(signer.clone());
+    let envelope = tx.build(&wallet)
+        .await
+        .map_err(|e| format!("failed to sign transaction: {}", e))?;
+    Ok(envelope.encoded_2718().into())
+}
+
+// Raw eth_sign: signs the hash as given, without the EIP-191 prefix
+pub fn sign_hash(signer: &PrivateKeySigner, hash: B256, _authorization: &Authorization) -> Result<Bytes, String> {
+    let signature = signer.sign_hash_sync(&hash).map_err(|e| format!("failed to sign: {}", e))?;
+    Ok(signature.as_bytes().to_vec().into())
+}
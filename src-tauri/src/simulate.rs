@@ -0,0 +1,517 @@
+use alloy::primitives::{b256, keccak256, Address, Bytes, Log, B256, U256, U64};
+use alloy::rpc::types::TransactionRequest;
+use alloy::sol_types::decode_revert_reason;
+use helios::core::types::BlockTag;
+use revm::db::CacheDB;
+use revm::primitives::{BlockEnv, EvmState, ExecutionResult};
+use revm::Database;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+use crate::client::EthClientApi;
+use crate::approvals::{self, ApprovalWarning};
+use crate::decode::DecodedCall;
+use crate::distribution::AccountSource;
+use crate::evm::{self, BlockOverrides, StateOverride, VerifiedDb, VerifiedState};
+
+// Limits from the eth_simulateV1 spec
+const MAX_BLOCKS: usize = 256;
+const SECONDS_PER_BLOCK: u64 = 12;
+
+// eth_simulateV1 error codes
+const REVERTED: i32 = 3;
+const VM_ERROR: i32 = -32015;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatePayload {
+    pub block_state_calls: Vec<SimulateBlock>,
+    #[serde(default)]
+    pub validation: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulateBlock {
+    pub block_overrides: Option<BlockOverrides>,
+    pub state_overrides: Option<StateOverride>,
+    #[serde(default)]
+    pub calls: Vec<TransactionRequest>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedBlock {
+    pub number: U64,
+    pub hash: B256,
+    pub parent_hash: B256,
+    pub timestamp: U64,
+    pub gas_limit: U64,
+    pub gas_used: U64,
+    pub fee_recipient: Address,
+    pub base_fee_per_gas: U256,
+    pub calls: Vec<SimulatedCall>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedCall {
+    pub status: U64,
+    pub return_data: Bytes,
+    pub gas_used: U64,
+    pub logs: Vec<SimulatedLog>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<CallError>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedLog {
+    pub address: Address,
+    pub topics: Vec<B256>,
+    pub data: Bytes,
+    pub block_number: U64,
+    pub block_hash: B256,
+    pub transaction_index: U64,
+    pub log_index: U64,
+    pub removed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallError {
+    pub code: i32,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Bytes>,
+}
+
+// Outcome of running a transaction before it is broadcast, for the approval UI
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionSimulation {
+    pub success: bool,
+    pub gas_used: U64,
+    pub fee: U256,
+    pub return_data: Bytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub logs: Vec<Log>,
+    pub state_diff: Vec<AccountDiff>,
+    pub asset_changes: Vec<AssetChange>,
+    pub approvals: Vec<ApprovalWarning>,
+    // Best-guess function name, filled in from the signature database
+    pub call: Option<DecodedCall>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Change<T> {
+    pub before: T,
+    pub after: T,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageChange {
+    pub slot: B256,
+    pub before: B256,
+    pub after: B256,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDiff {
+    pub address: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<Change<U256>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<Change<u64>>,
+    pub code_changed: bool,
+    pub storage: Vec<StorageChange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetStandard {
+    Native,
+    Erc20,
+    Erc721,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    In,
+    Out,
+}
+
+// Net movement of one asset for the signing account, e.g. "you send 1.2 ETH".
+// Native changes exclude the gas fee, which is reported separately
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetChange {
+    pub standard: AssetStandard,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<Address>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<U256>,
+    pub direction: Direction,
+    pub amount: U256,
+}
+
+// Simulated blocks are never sealed, so they get a deterministic placeholder hash
+fn simulated_hash(parent_hash: B256, number: U256) -> B256 {
+    keccak256([parent_hash.as_slice(), &number.to_be_bytes::<32>()].concat())
+}
+
+fn simulated_call(result: ExecutionResult, block: &SimulatedBlock, index: usize, log_index: &mut u64) -> SimulatedCall {
+    let gas_used = U64::from(result.gas_used());
+    match result {
+        ExecutionResult::Success { output, logs, .. } => {
+            let logs = logs
+                .into_iter()
+                .map(|log| {
+                    let simulated = SimulatedLog {
+                        address: log.address,
+                        topics: log.data.topics().to_vec(),
+                        data: log.data.data,
+                        block_number: block.number,
+                        block_hash: block.hash,
+                        transaction_index: U64::from(index),
+                        log_index: U64::from(*log_index),
+                        removed: false,
+                    };
+                    *log_index += 1;
+                    simulated
+                })
+                .collect();
+            SimulatedCall { status: U64::from(1), return_data: output.into_data(), gas_used, logs, error: None }
+        },
+        ExecutionResult::Revert { output, .. } => SimulatedCall {
+            status: U64::ZERO,
+            return_data: output.clone(),
+            gas_used,
+            logs: Vec::new(),
+            error: Some(CallError {
+                code: REVERTED,
+                message: "execution reverted".to_string(),
+                data: Some(output),
+            }),
+        },
+        ExecutionResult::Halt { reason, .. } => SimulatedCall {
+            status: U64::ZERO,
+            return_data: Bytes::new(),
+            gas_used,
+            logs: Vec::new(),
+            error: Some(CallError {
+                code: VM_ERROR,
+                message: format!("{:?}", reason),
+                data: None,
+            }),
+        },
+    }
+}
+
+fn next_block_env(parent: &BlockEnv, overrides: Option<&BlockOverrides>, validation: bool) -> Result<BlockEnv, String> {
+    let mut env = parent.clone();
+    env.number += U256::from(1);
+    env.timestamp += U256::from(SECONDS_PER_BLOCK);
+    if !validation {
+        env.basefee = U256::ZERO;
+    }
+    if let Some(overrides) = overrides {
+        overrides.apply(&mut env);
+    }
+
+    if env.number <= parent.number {
+        return Err(format!("Block number {} must be greater than {}", env.number, parent.number));
+    }
+    if env.timestamp <= parent.timestamp {
+        return Err(format!("Block timestamp {} must be greater than {}", env.timestamp, parent.timestamp));
+    }
+    Ok(env)
+}
+
+// Runs eth_simulateV1 on a local EVM over verified state: blocks are built on top of `block_tag`
+// in order, each one seeing the state left by the previous calls and its own overrides
+pub async fn simulate_v1(
+    client: &dyn EthClientApi,
+    accounts: AccountSource,
+    payload: SimulatePayload,
+    block_tag: BlockTag,
+) -> Result<Vec<SimulatedBlock>, String> {
+    if payload.block_state_calls.len() > MAX_BLOCKS {
+        return Err(format!("Too many blocks, at most {} can be simulated", MAX_BLOCKS));
+    }
+
+    let base = client.get_block_by_number(block_tag, false)
+        .await
+        .map_err(|e| format!("Failed to get base block: {}", e))?
+        .ok_or("Base block not found")?;
+    let chain_id = client.chain_id().await;
+
+    tokio::task::block_in_place(|| {
+        let mut db = CacheDB::new(VerifiedState::new(client, accounts, block_tag));
+        let mut parent = evm::block_env(&base);
+        let mut parent_hash = base.hash;
+        let mut blocks = Vec::with_capacity(payload.block_state_calls.len());
+
+        for block in payload.block_state_calls {
+            let env = next_block_env(&parent, block.block_overrides.as_ref(), payload.validation)?;
+            if let Some(overrides) = &block.state_overrides {
+                evm::apply_state_overrides(&mut db, overrides)?;
+            }
+
+            let mut simulated = SimulatedBlock {
+                number: U64::from(env.number.to::<u64>()),
+                hash: simulated_hash(parent_hash, env.number),
+                parent_hash,
+                timestamp: U64::from(env.timestamp.to::<u64>()),
+                gas_limit: U64::from(env.gas_limit.to::<u64>()),
+                gas_used: U64::ZERO,
+                fee_recipient: env.coinbase,
+                base_fee_per_gas: env.basefee,
+                calls: Vec::with_capacity(block.calls.len()),
+            };
+
+            let block_gas_limit = env.gas_limit.to::<u64>();
+            let mut gas_used = 0u64;
+            let mut log_index = 0u64;
+            for (index, call) in block.calls.iter().enumerate() {
+                let remaining = block_gas_limit.saturating_sub(gas_used);
+                let tx = evm::tx_env(call, remaining.min(evm::DEFAULT_GAS_CAP), payload.validation);
+                if tx.gas_limit > remaining {
+                    return Err(format!("Call {} in block {} exceeds the block gas limit", index, env.number));
+                }
+
+                let result = evm::transact(&mut db, &env, tx, chain_id)?;
+                gas_used += result.gas_used();
+                let call = simulated_call(result, &simulated, index, &mut log_index);
+                simulated.calls.push(call);
+            }
+
+            simulated.gas_used = U64::from(gas_used);
+            parent_hash = simulated.hash;
+            parent = env;
+            blocks.push(simulated);
+        }
+
+        Ok(blocks)
+    })
+}
+
+// Compares the touched accounts against the cached pre-state, which execute() leaves untouched
+fn state_diff(db: &mut VerifiedDb<'_>, state: &EvmState) -> Result<Vec<AccountDiff>, String> {
+    let mut diffs = Vec::new();
+    for (address, account) in state.iter().filter(|(_, account)| account.is_touched()) {
+        let before = db.basic(*address)?.unwrap_or_default();
+        let after = &account.info;
+
+        let storage: Vec<StorageChange> = account.storage
+            .iter()
+            .filter(|(_, slot)| slot.is_changed())
+            .map(|(key, slot)| StorageChange {
+                slot: B256::from(key.to_be_bytes()),
+                before: B256::from(slot.original_value.to_be_bytes()),
+                after: B256::from(slot.present_value.to_be_bytes()),
+            })
+            .collect();
+        let balance = (before.balance != after.balance)
+            .then(|| Change { before: before.balance, after: after.balance });
+        let nonce = (before.nonce != after.nonce)
+            .then(|| Change { before: before.nonce, after: after.nonce });
+        let code_changed = before.code_hash != after.code_hash;
+
+        if balance.is_some() || nonce.is_some() || code_changed || !storage.is_empty() {
+            diffs.push(AccountDiff { address: *address, balance, nonce, code_changed, storage });
+        }
+    }
+    diffs.sort_by_key(|diff| diff.address);
+    Ok(diffs)
+}
+
+// Executes the transaction in the next block on top of verified latest state. Block overrides
+// replace the guessed next-block values, e.g. to check a call after a timelock expires
+pub async fn simulate_transaction(
+    client: &dyn EthClientApi,
+    accounts: AccountSource,
+    tx: &TransactionRequest,
+    block_overrides: Option<&BlockOverrides>,
+) -> Result<TransactionSimulation, String> {
+    let latest = client.get_block_by_number(BlockTag::Latest, false)
+        .await
+        .map_err(|e| format!("Failed to get latest block: {}", e))?
+        .ok_or("Latest block not found")?;
+    let chain_id = client.chain_id().await;
+
+    // State is read at the block the environment was built from, not whatever is latest by the time
+    // each account is loaded
+    tokio::task::block_in_place(|| {
+        let mut db = CacheDB::new(VerifiedState::new(client, accounts, BlockTag::Number(latest.number.to())));
+        let mut env = evm::block_env(&latest);
+        env.number += U256::from(1);
+        env.timestamp += U256::from(SECONDS_PER_BLOCK);
+        if let Some(overrides) = block_overrides {
+            overrides.apply(&mut env);
+        }
+
+        let gas_limit = env.gas_limit.to::<u64>().min(evm::DEFAULT_GAS_CAP);
+        let tx_env = evm::tx_env(tx, gas_limit, true);
+        let gas_price = match tx_env.gas_priority_fee {
+            Some(priority_fee) => tx_env.gas_price.min(env.basefee + priority_fee),
+            None => tx_env.gas_price,
+        };
+        let outcome = evm::execute(&mut db, &env, tx_env, chain_id)?;
+        let state_diff = state_diff(&mut db, &outcome.state)?;
+        let gas_used = outcome.result.gas_used();
+        let fee = gas_price * U256::from(gas_used);
+
+        let (success, return_data, error, logs) = match outcome.result {
+            ExecutionResult::Success { output, logs, .. } => (true, output.into_data(), None, logs),
+            ExecutionResult::Revert { output, .. } => {
+                let reason = decode_revert_reason(&output).unwrap_or_else(|| "execution reverted".to_string());
+                (false, output, Some(reason), Vec::new())
+            },
+            ExecutionResult::Halt { reason, .. } => (false, Bytes::new(), Some(format!("{:?}", reason)), Vec::new()),
+        };
+        let account = tx.from.unwrap_or_default();
+        let asset_changes = asset_changes(account, fee, &logs, &state_diff);
+        let approvals = approvals::detect(account, tx, &logs);
+
+        Ok(TransactionSimulation {
+            success,
+            gas_used: U64::from(gas_used),
+            fee,
+            return_data,
+            error,
+            logs,
+            state_diff,
+            asset_changes,
+            approvals,
+            call: None,
+        })
+    })
+}
+
+// eth_call with state or block overrides. The execution RPC can't prove overridden state, so the
+// call runs on a local EVM at `block_tag` with the overrides laid over verified state
+pub async fn call_with_overrides(
+    client: &dyn EthClientApi,
+    accounts: AccountSource,
+    tx: &TransactionRequest,
+    block_tag: BlockTag,
+    state_overrides: Option<&StateOverride>,
+    block_overrides: Option<&BlockOverrides>,
+) -> Result<Bytes, String> {
+    let block = client.get_block_by_number(block_tag, false)
+        .await
+        .map_err(|e| format!("Failed to get block: {}", e))?
+        .ok_or("Block not found")?;
+    let chain_id = client.chain_id().await;
+
+    tokio::task::block_in_place(|| {
+        let mut db = CacheDB::new(VerifiedState::new(client, accounts, block_tag));
+        if let Some(overrides) = state_overrides {
+            evm::apply_state_overrides(&mut db, overrides)?;
+        }
+
+        let mut env = evm::block_env(&block);
+        // Like geth, a call that doesn't set a gas price isn't held to the base fee
+        if tx.gas_price.is_none() && tx.max_fee_per_gas.is_none() {
+            env.basefee = U256::ZERO;
+        }
+        if let Some(overrides) = block_overrides {
+            overrides.apply(&mut env);
+        }
+        let gas_limit = env.gas_limit.to::<u64>().min(evm::DEFAULT_GAS_CAP);
+        let outcome = evm::execute(&mut db, &env, evm::tx_env(tx, gas_limit, false), chain_id)?;
+
+        match outcome.result {
+            ExecutionResult::Success { output, .. } => Ok(output.into_data()),
+            ExecutionResult::Revert { output, .. } => Err(match decode_revert_reason(&output) {
+                Some(reason) => format!("execution reverted: {}", reason),
+                None => format!("execution reverted: {}", output),
+            }),
+            ExecutionResult::Halt { reason, .. } => Err(format!("execution halted: {:?}", reason)),
+        }
+    })
+}
+
+// `Transfer(address,address,uint256)`, shared by ERC-20 and ERC-721. ERC-721 indexes the token id
+const TRANSFER_TOPIC: B256 = b256!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
+
+fn topic_address(topic: &B256) -> Address {
+    Address::from_word(*topic)
+}
+
+fn net_change(received: U256, sent: U256) -> Option<(Direction, U256)> {
+    match received.cmp(&sent) {
+        Ordering::Greater => Some((Direction::In, received - sent)),
+        Ordering::Less => Some((Direction::Out, sent - received)),
+        Ordering::Equal => None,
+    }
+}
+
+// Derives what the account gains and loses from the simulated Transfer events and its balance change
+fn asset_changes(account: Address, fee: U256, logs: &[Log], state_diff: &[AccountDiff]) -> Vec<AssetChange> {
+    let mut changes = Vec::new();
+
+    let native = state_diff
+        .iter()
+        .find(|diff| diff.address == account)
+        .and_then(|diff| diff.balance.as_ref())
+        .and_then(|balance| net_change(balance.after + fee, balance.before));
+    if let Some((direction, amount)) = native {
+        changes.push(AssetChange { standard: AssetStandard::Native, token: None, token_id: None, direction, amount });
+    }
+
+    // Fungible amounts are netted per token, NFTs are listed per token id
+    let mut fungible: Vec<(Address, U256, U256)> = Vec::new();
+    for log in logs {
+        let topics = log.data.topics();
+        if topics.first() != Some(&TRANSFER_TOPIC) || topics.len() < 3 {
+            continue;
+        }
+        let (from, to) = (topic_address(&topics[1]), topic_address(&topics[2]));
+        if from != account && to != account {
+            continue;
+        }
+
+        if let Some(token_id) = topics.get(3) {
+            changes.push(AssetChange {
+                standard: AssetStandard::Erc721,
+                token: Some(log.address),
+                token_id: Some(U256::from_be_bytes(token_id.0)),
+                direction: if to == account { Direction::In } else { Direction::Out },
+                amount: U256::from(1),
+            });
+            continue;
+        }
+
+        let Some(amount) = log.data.data.get(..32).map(U256::from_be_slice) else {
+            continue;
+        };
+        let index = match fungible.iter().position(|(token, _, _)| *token == log.address) {
+            Some(index) => index,
+            None => {
+                fungible.push((log.address, U256::ZERO, U256::ZERO));
+                fungible.len() - 1
+            }
+        };
+        if to == account {
+            fungible[index].1 += amount;
+        }
+        if from == account {
+            fungible[index].2 += amount;
+        }
+    }
+
+    for (token, received, sent) in fungible {
+        if let Some((direction, amount)) = net_change(received, sent) {
+            changes.push(AssetChange { standard: AssetStandard::Erc20, token: Some(token), token_id: None, direction, amount });
+        }
+    }
+
+    changes
+}
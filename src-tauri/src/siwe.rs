@@ -0,0 +1,173 @@
+use alloy::primitives::Address;
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::Serialize;
+
+const PREAMBLE_SUFFIX: &str = " wants you to sign in with your Ethereum account:";
+
+// EIP-4361 message fields
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SiweMessage {
+    pub scheme: Option<String>,
+    pub domain: String,
+    pub address: Address,
+    pub statement: Option<String>,
+    pub uri: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub nonce: String,
+    pub issued_at: DateTime<FixedOffset>,
+    pub expiration_time: Option<DateTime<FixedOffset>>,
+    pub not_before: Option<DateTime<FixedOffset>>,
+    pub request_id: Option<String>,
+    pub resources: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SiweVerification {
+    pub message: SiweMessage,
+    pub origin: String,
+    pub valid: bool,
+    pub errors: Vec<String>,
+}
+
+fn parse_time(field: &str, value: &str) -> Result<DateTime<FixedOffset>, String> {
+    DateTime::parse_from_rfc3339(value).map_err(|e| format!("Invalid {}: {}", field, e))
+}
+
+pub fn parse(message: &str) -> Result<SiweMessage, String> {
+    let mut lines = message.lines();
+
+    let preamble = lines.next().ok_or("Empty message")?;
+    let authority = preamble.strip_suffix(PREAMBLE_SUFFIX).ok_or("Missing SIWE preamble")?;
+    let (scheme, domain) = match authority.split_once("://") {
+        Some((scheme, domain)) => (Some(scheme.to_string()), domain.to_string()),
+        None => (None, authority.to_string()),
+    };
+
+    let address_line = lines.next().ok_or("Missing address")?;
+    let address = Address::parse_checksummed(address_line.trim(), None)
+        .map_err(|_| format!("Address is not a valid EIP-55 checksummed address: {}", address_line))?;
+
+    // Everything up to the URI field is the optional statement surrounded by blank lines
+    let mut statement_lines = Vec::new();
+    let mut uri = None;
+    for line in lines.by_ref() {
+        if let Some(value) = line.strip_prefix("URI: ") {
+            uri = Some(value.to_string());
+            break;
+        }
+        if !line.is_empty() {
+            statement_lines.push(line);
+        }
+    }
+    let uri = uri.ok_or("Missing URI")?;
+    let statement = (!statement_lines.is_empty()).then(|| statement_lines.join("\n"));
+
+    let mut version = None;
+    let mut chain_id = None;
+    let mut nonce = None;
+    let mut issued_at = None;
+    let mut expiration_time = None;
+    let mut not_before = None;
+    let mut request_id = None;
+    let mut resources = Vec::new();
+    let mut in_resources = false;
+
+    for line in lines {
+        if in_resources {
+            if let Some(resource) = line.strip_prefix("- ") {
+                resources.push(resource.to_string());
+                continue;
+            }
+            in_resources = false;
+        }
+
+        let (key, value) = match line.split_once(": ") {
+            Some(field) => field,
+            None if line == "Resources:" => {
+                in_resources = true;
+                continue;
+            },
+            None if line.is_empty() => continue,
+            None => return Err(format!("Unexpected line: {}", line)),
+        };
+
+        match key {
+            "Version" => version = Some(value.to_string()),
+            "Chain ID" => chain_id = Some(value.parse::<u64>().map_err(|_| format!("Invalid Chain ID: {}", value))?),
+            "Nonce" => nonce = Some(value.to_string()),
+            "Issued At" => issued_at = Some(parse_time("Issued At", value)?),
+            "Expiration Time" => expiration_time = Some(parse_time("Expiration Time", value)?),
+            "Not Before" => not_before = Some(parse_time("Not Before", value)?),
+            "Request ID" => request_id = Some(value.to_string()),
+            _ => return Err(format!("Unknown field: {}", key)),
+        }
+    }
+
+    Ok(SiweMessage {
+        scheme,
+        domain,
+        address,
+        statement,
+        uri,
+        version: version.ok_or("Missing Version")?,
+        chain_id: chain_id.ok_or("Missing Chain ID")?,
+        nonce: nonce.ok_or("Missing Nonce")?,
+        issued_at: issued_at.ok_or("Missing Issued At")?,
+        expiration_time,
+        not_before,
+        request_id,
+        resources,
+    })
+}
+
+// Checks the message against the origin that asked for the signature and the connected chain
+pub fn verify(
+    message: SiweMessage,
+    origin: &str,
+    chain_id: Option<u64>,
+) -> SiweVerification {
+    let mut errors = Vec::new();
+    let now = Utc::now();
+
+    let (origin_scheme, origin_authority) = origin.split_once("://").unwrap_or(("https", origin));
+    let origin_authority = origin_authority.trim_end_matches('/');
+    if message.domain != origin_authority {
+        errors.push(format!("Domain {} does not match requesting origin {}", message.domain, origin_authority));
+    }
+    if let Some(scheme) = &message.scheme {
+        if scheme != origin_scheme {
+            errors.push(format!("Scheme {} does not match requesting origin scheme {}", scheme, origin_scheme));
+        }
+    }
+    if message.version != "1" {
+        errors.push(format!("Unsupported version {}", message.version));
+    }
+    if message.nonce.len() < 8 || !message.nonce.chars().all(|c| c.is_ascii_alphanumeric()) {
+        errors.push("Nonce must be at least 8 alphanumeric characters".to_string());
+    }
+    if let Some(chain_id) = chain_id {
+        if message.chain_id != chain_id {
+            errors.push(format!("Chain ID {} does not match connected chain {}", message.chain_id, chain_id));
+        }
+    }
+    if let Some(expiration_time) = message.expiration_time {
+        if expiration_time <= now {
+            errors.push("Message has expired".to_string());
+        }
+    }
+    if let Some(not_before) = message.not_before {
+        if not_before > now {
+            errors.push("Message is not yet valid".to_string());
+        }
+    }
+
+    SiweVerification {
+        message,
+        origin: origin.to_string(),
+        valid: errors.is_empty(),
+        errors,
+    }
+}
@@ -0,0 +1,131 @@
+use alloy::primitives::Address;
+use alloy::transports::http::reqwest;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::outbound;
+
+const SOURCIFY_API: &str = "https://sourcify.dev/server";
+
+// Verification never goes away, but an unverified contract may be verified later
+const UNVERIFIED_TTL_SECS: u64 = 24 * 60 * 60;
+
+// Subset of the Sourcify v2 `/contract/{chainId}/{address}` response
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SourcifyContract {
+    #[serde(rename = "match")]
+    match_type: Option<String>,
+    abi: Option<serde_json::Value>,
+    compilation: Option<SourcifyCompilation>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SourcifyCompilation {
+    name: Option<String>,
+    compiler_version: Option<String>,
+    language: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractMetadata {
+    pub address: Address,
+    pub chain_id: u64,
+    pub verified: bool,
+    pub match_type: Option<String>,
+    pub name: Option<String>,
+    pub compiler_version: Option<String>,
+    pub language: Option<String>,
+    pub abi: Option<serde_json::Value>,
+    pub fetched_at: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn cache_path(cache_dir: &Path, chain_id: u64, address: Address) -> PathBuf {
+    cache_dir.join(chain_id.to_string()).join(format!("0x{:x}.json", address))
+}
+
+async fn read_cached(path: &Path) -> Option<ContractMetadata> {
+    let bytes = tokio::fs::read(path).await.ok()?;
+    let metadata: ContractMetadata = serde_json::from_slice(&bytes).ok()?;
+    let fresh = metadata.verified || now().saturating_sub(metadata.fetched_at) < UNVERIFIED_TTL_SECS;
+    fresh.then_some(metadata)
+}
+
+async fn write_cached(path: &Path, metadata: &ContractMetadata) {
+    let Ok(bytes) = serde_json::to_vec(metadata) else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        if tokio::fs::create_dir_all(parent).await.is_ok() {
+            let _ = tokio::fs::write(path, bytes).await;
+        }
+    }
+}
+
+async fn fetch(address: Address, chain_id: u64) -> Result<ContractMetadata, String> {
+    let url = format!(
+        "{}/v2/contract/{}/0x{:x}?fields=abi,compilation",
+        SOURCIFY_API, chain_id, address
+    );
+    let response = outbound::client()
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Sourcify request failed: {}", e))?;
+
+    let contract = if response.status() == reqwest::StatusCode::NOT_FOUND {
+        None
+    } else {
+        let contract = response
+            .error_for_status()
+            .map_err(|e| format!("Sourcify request failed: {}", e))?
+            .json::<SourcifyContract>()
+            .await
+            .map_err(|e| format!("Invalid Sourcify response: {}", e))?;
+        Some(contract)
+    };
+
+    let match_type = contract.as_ref().and_then(|c| c.match_type.clone());
+    let compilation = contract.as_ref().and_then(|c| c.compilation.as_ref());
+    Ok(ContractMetadata {
+        address,
+        chain_id,
+        verified: match_type.is_some(),
+        name: compilation.and_then(|c| c.name.clone()),
+        compiler_version: compilation.and_then(|c| c.compiler_version.clone()),
+        language: compilation.and_then(|c| c.language.clone()),
+        abi: contract.and_then(|c| c.abi),
+        match_type,
+        fetched_at: now(),
+    })
+}
+
+// Looks up verified source metadata, serving from the on-disk cache when possible
+pub async fn get_contract_metadata(
+    cache_dir: Option<&Path>,
+    address: Address,
+    chain_id: u64,
+) -> Result<ContractMetadata, String> {
+    let path = cache_dir.map(|dir| cache_path(dir, chain_id, address));
+    if let Some(path) = &path {
+        if let Some(metadata) = read_cached(path).await {
+            return Ok(metadata);
+        }
+    }
+
+    let metadata = fetch(address, chain_id).await?;
+    if let Some(path) = &path {
+        write_cached(path, &metadata).await;
+    }
+    Ok(metadata)
+}
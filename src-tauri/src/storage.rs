@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+
+use crate::cache::DiskCache;
+use crate::history::{self, HistoryDb};
+use crate::{logging, profile, AppState};
+
+const PRUNE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const DEFAULT_MAX_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+// Below this the caches would be pruned on every pass
+const MIN_MAX_BYTES: u64 = 64 * 1024 * 1024;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// Databases in the cache dir that are pruned by entry rather than deleted as files
+const CACHE_DATABASES: &[&str] = &["responses.sqlite", "signatures.sqlite"];
+
+// How much disk the app may use. A max of 0 lifts the limit, a retention of 0 keeps history forever
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StorageSettings {
+    pub max_bytes: u64,
+    pub history_retention_days: u64,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        Self { max_bytes: DEFAULT_MAX_BYTES, history_retention_days: 0 }
+    }
+}
+
+impl StorageSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_bytes != 0 && self.max_bytes < MIN_MAX_BYTES {
+            return Err(format!("Storage limit must be at least {} MiB", MIN_MAX_BYTES / 1024 / 1024));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageArea {
+    pub name: &'static str,
+    pub path: Option<String>,
+    pub bytes: u64,
+    // Whether the storage limit can reclaim space here. History only shrinks by retention, keys
+    // and settings never do
+    pub prunable: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageUsage {
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+    pub areas: Vec<StorageArea>,
+}
+
+// Where each kind of data lives
+struct Dirs {
+    light_client: PathBuf,
+    cache: Option<PathBuf>,
+    history: Option<PathBuf>,
+    logs: Option<PathBuf>,
+    data: Option<PathBuf>,
+}
+
+impl Dirs {
+    fn new(app: &AppHandle) -> Self {
+        Self {
+            light_client: profile::light_client_dir(),
+            cache: profile::cache_dir(app),
+            history: history::history_dir(app),
+            // Shared by all profiles
+            logs: app.path().app_log_dir().ok(),
+            data: profile::data_dir(app),
+        }
+    }
+}
+
+// Regular files under `dir` with their size and modification time, recursively. Other profiles'
+// dirs sit inside the default profile's and are left out
+fn files(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    let mut found = Vec::new();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return found;
+    };
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() && entry.file_name() != profile::PROFILES_DIR {
+            found.extend(files(&entry.path()));
+        } else if metadata.is_file() {
+            found.push((entry.path(), metadata.len(), metadata.modified().unwrap_or(UNIX_EPOCH)));
+        }
+    }
+    found
+}
+
+fn dir_size(dir: Option<&Path>) -> u64 {
+    dir.map_or(0, |dir| files(dir).iter().map(|(_, size, _)| size).sum())
+}
+
+fn usage(dirs: &Dirs, settings: &StorageSettings) -> StorageUsage {
+    let history = dir_size(dirs.history.as_deref());
+    let area = |name, path: Option<&Path>, bytes, prunable| StorageArea {
+        name,
+        path: path.map(|path| path.display().to_string()),
+        bytes,
+        prunable,
+    };
+    let areas = vec![
+        area("lightClient", Some(&dirs.light_client), dir_size(Some(&dirs.light_client)), false),
+        area("caches", dirs.cache.as_deref(), dir_size(dirs.cache.as_deref()), true),
+        area("history", dirs.history.as_deref(), history, false),
+        area("logs", dirs.logs.as_deref(), dir_size(dirs.logs.as_deref()), true),
+        // The history dir sits inside the data dir
+        area("appData", dirs.data.as_deref(), dir_size(dirs.data.as_deref()).saturating_sub(history), false),
+    ];
+    StorageUsage {
+        total_bytes: areas.iter().map(|area| area.bytes).sum(),
+        max_bytes: settings.max_bytes,
+        areas,
+    }
+}
+
+pub async fn storage_usage(app: &AppHandle, settings: StorageSettings) -> Result<StorageUsage, String> {
+    let dirs = Dirs::new(app);
+    tokio::task::spawn_blocking(move || usage(&dirs, &settings))
+        .await
+        .map_err(|e| format!("Storage usage task failed: {}", e))
+}
+
+fn prune_history(dir: &Path, retention_days: u64) {
+    let before = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+        .saturating_sub(retention_days * SECONDS_PER_DAY);
+    for (path, _, _) in files(dir).into_iter().filter(|(path, _, _)| path.extension().is_some_and(|ext| ext == "sqlite")) {
+        match HistoryDb::open(&path).and_then(|mut db| db.prune(before)) {
+            Ok(0) => {},
+            Ok(pruned) => tracing::info!("Pruned {} transactions from {}", pruned, path.display()),
+            Err(e) => tracing::warn!("{}", e),
+        }
+    }
+}
+
+// Frees `excess` bytes from the caches: least recently used responses first, then the oldest
+// cache files. Returns what was freed
+fn prune_caches(dir: &Path, excess: u64) -> u64 {
+    let mut freed = 0;
+    let responses = dir.join("responses.sqlite");
+    if let Ok(cache) = DiskCache::open(&responses) {
+        let before = std::fs::metadata(&responses).map(|m| m.len()).unwrap_or_default();
+        let evicted = cache.usage()
+            .and_then(|(_, bytes)| cache.evict(bytes.saturating_sub(excess)))
+            .and_then(|_| cache.compact());
+        if let Err(e) = evicted {
+            tracing::warn!("{}", e);
+        }
+        freed += before.saturating_sub(std::fs::metadata(&responses).map(|m| m.len()).unwrap_or_default());
+    }
+
+    let mut cached = files(dir);
+    cached.retain(|(path, _, _)| {
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        !CACHE_DATABASES.iter().any(|database| name.starts_with(database))
+    });
+    cached.sort_unstable_by_key(|(_, _, modified)| *modified);
+    for (path, size, _) in cached {
+        if freed >= excess {
+            break;
+        }
+        if std::fs::remove_file(&path).is_ok() {
+            freed += size;
+        }
+    }
+    freed
+}
+
+// History past its retention goes first. Then, if the app is still over its limit, caches and
+// old logs are cut back until it fits
+fn prune(dirs: &Dirs, settings: &StorageSettings) {
+    if let Some(dir) = dirs.history.as_deref().filter(|_| settings.history_retention_days > 0) {
+        prune_history(dir, settings.history_retention_days);
+    }
+    if settings.max_bytes == 0 {
+        return;
+    }
+    let total = usage(dirs, settings).total_bytes;
+    let mut excess = total.saturating_sub(settings.max_bytes);
+    if excess == 0 {
+        return;
+    }
+    if let Some(dir) = &dirs.cache {
+        excess = excess.saturating_sub(prune_caches(dir, excess));
+    }
+    if let Some(dir) = dirs.logs.as_deref().filter(|_| excess > 0) {
+        excess = excess.saturating_sub(logging::remove_oldest(dir, excess));
+    }
+    if excess > 0 {
+        tracing::warn!("Storage is {} bytes over its limit after pruning caches and logs", excess);
+    } else {
+        tracing::info!("Pruned storage back under its {} byte limit", settings.max_bytes);
+    }
+}
+
+pub fn spawn_pruner(app: AppHandle) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let settings = app.state::<Mutex<AppState>>().lock().await.storage.clone();
+            let dirs = Dirs::new(&app);
+            if let Err(e) = tokio::task::spawn_blocking(move || prune(&dirs, &settings)).await {
+                tracing::warn!("Storage pruning task failed: {}", e);
+            }
+        }
+    })
+}
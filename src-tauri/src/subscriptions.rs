@@ -0,0 +1,115 @@
+use alloy::primitives::U256;
+use alloy::rpc::types::Filter;
+use serde_json::json;
+use tauri::{AppHandle, Emitter, EventTarget};
+
+use crate::accounts::Caller;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscriptionKind {
+    Syncing,
+    // Transaction hashes from the execution RPC's mempool, unverified
+    NewPendingTransactions,
+    // Logs of each new verified block that match the filter
+    Logs { filter: Filter },
+}
+
+impl SubscriptionKind {
+    // The eth_subscribe params naming a subscription
+    pub fn parse(params: &[serde_json::Value]) -> Result<Self, String> {
+        match params.first().and_then(|kind| kind.as_str()) {
+            Some("syncing") => Ok(Self::Syncing),
+            Some("newPendingTransactions") if params.get(1).and_then(|full| full.as_bool()) == Some(true) => {
+                Err("Only transaction hashes are supported for newPendingTransactions".to_string())
+            },
+            Some("newPendingTransactions") => Ok(Self::NewPendingTransactions),
+            Some("logs") => match params.get(1).cloned().map(serde_json::from_value) {
+                Some(Ok(filter)) => Ok(Self::Logs { filter }),
+                Some(Err(e)) => Err(format!("Invalid params: {}", e)),
+                None => Ok(Self::Logs { filter: Filter::default() }),
+            },
+            Some(kind) => Err(format!("Unsupported subscription {}", kind)),
+            None => Err("Invalid params: expected a subscription type".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Subscription {
+    pub id: U256,
+    pub owner: Caller,
+    pub chain_id: u64,
+    pub kind: SubscriptionKind,
+}
+
+// eth_subscribe subscriptions of dapp windows. Notifications are pushed as `eth_subscription`
+// events to the webview that subscribed only
+#[derive(Default)]
+pub struct Subscriptions {
+    next_id: u64,
+    active: Vec<Subscription>,
+}
+
+impl Subscriptions {
+    pub fn add(&mut self, owner: Caller, chain_id: u64, kind: SubscriptionKind) -> U256 {
+        self.next_id += 1;
+        let id = U256::from(self.next_id);
+        self.active.push(Subscription { id, owner, chain_id, kind });
+        id
+    }
+
+    // Only the subscriber can cancel a subscription
+    pub fn remove(&mut self, id: U256, caller: &Caller) -> bool {
+        let before = self.active.len();
+        self.active.retain(|subscription| subscription.id != id || subscription.owner != *caller);
+        self.active.len() != before
+    }
+
+    pub fn matching(&self, chain_id: u64, kind: &SubscriptionKind) -> Vec<Subscription> {
+        self.active
+            .iter()
+            .filter(|subscription| subscription.chain_id == chain_id && subscription.kind == *kind)
+            .cloned()
+            .collect()
+    }
+
+    pub fn logs(&self, chain_id: u64) -> Vec<(Subscription, Filter)> {
+        self.active
+            .iter()
+            .filter(|subscription| subscription.chain_id == chain_id)
+            .filter_map(|subscription| match &subscription.kind {
+                SubscriptionKind::Logs { filter } => Some((subscription.clone(), filter.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // A page's subscriptions end when its window navigates to another origin
+    pub fn release_navigated(&mut self, webview: &str, origin: &str) {
+        self.active.retain(|subscription| {
+            subscription.owner.webview.as_deref() != Some(webview) || subscription.owner.origin == origin
+        });
+    }
+}
+
+pub fn notify(app: &AppHandle, subscription: &Subscription, result: &serde_json::Value) {
+    emit(app, subscription, json!({
+        "subscription": format!("0x{:x}", subscription.id),
+        "result": result,
+    }));
+}
+
+// For data relayed from an upstream service without verification
+pub fn notify_unverified(app: &AppHandle, subscription: &Subscription, result: &serde_json::Value) {
+    emit(app, subscription, json!({
+        "subscription": format!("0x{:x}", subscription.id),
+        "result": result,
+        "unverified": true,
+    }));
+}
+
+fn emit(app: &AppHandle, subscription: &Subscription, notification: serde_json::Value) {
+    if let Some(webview) = subscription.owner.webview.as_deref() {
+        let _ = app.emit_to(EventTarget::webview(webview), "eth_subscription", notification);
+    }
+}
@@ -0,0 +1,244 @@
+use alloy::primitives::{B256, U256};
+use alloy::rpc::types::SyncStatus as ClientSyncStatus;
+use helios::core::types::BlockTag;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Mutex;
+
+use crate::client::EthClientApi;
+use crate::headers::{HeaderStore, VerifiedHeader};
+use crate::receipts;
+use crate::subscriptions::{self, SubscriptionKind};
+use crate::window::VERIFICATION_WINDOW;
+use crate::AppState;
+
+const HEAD_POLL_INTERVAL: Duration = Duration::from_secs(4);
+// A verified head older than this means the client has fallen behind the chain
+const STALE_HEAD: Duration = Duration::from_secs(60);
+// Blocks whose sent logs are kept for retraction, far deeper than reorgs after the merge go
+const RETRACTABLE_BLOCKS: usize = 64;
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    pub status: &'static str,
+    pub consensus_rpc: String,
+}
+
+// Whether the light client is following the chain head, pushed to `syncing` subscribers and the
+// UI whenever it changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncState {
+    Synced,
+    Syncing {
+        starting_block: U256,
+        current_block: U256,
+        highest_block: U256,
+    },
+}
+
+impl SyncState {
+    // Helios reports its own sync progress, past that a head that stopped advancing counts as
+    // syncing too
+    async fn current(client: &dyn EthClientApi, head: Option<&VerifiedHeader>) -> Self {
+        if let Ok(ClientSyncStatus::Info(info)) = client.syncing().await {
+            return Self::Syncing {
+                starting_block: info.starting_block,
+                current_block: info.current_block,
+                highest_block: info.highest_block,
+            };
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        match head {
+            Some(head) if now.saturating_sub(head.timestamp) > STALE_HEAD.as_secs() => {
+                let number = U256::from(head.block_number);
+                Self::Syncing {
+                    starting_block: number,
+                    current_block: number,
+                    highest_block: number,
+                }
+            },
+            _ => Self::Synced,
+        }
+    }
+
+    // The `syncing` subscription result, shaped like geth's
+    pub fn notification(&self) -> serde_json::Value {
+        match self {
+            Self::Synced => json!(false),
+            Self::Syncing { starting_block, current_block, highest_block } => json!({
+                "syncing": true,
+                "status": {
+                    "startingBlock": starting_block,
+                    "currentBlock": current_block,
+                    "highestBlock": highest_block,
+                },
+            }),
+        }
+    }
+}
+
+// Verified headers from the store's tip, or the start of the light client's window, up to `head`.
+// When the next header doesn't extend the store the stored tip was reorged out, so this steps back
+// until it finds the fork point
+async fn missing_headers(client: &dyn EthClientApi, store: &HeaderStore, head: u64) -> Vec<VerifiedHeader> {
+    let oldest = head.saturating_sub(VERIFICATION_WINDOW - 1);
+    let mut from = store.latest()
+        .map_or(head, |latest| (latest.block_number + 1).min(head))
+        .max(oldest);
+
+    let mut headers = Vec::new();
+    loop {
+        let Ok(Some(block)) = client.get_block_by_number(BlockTag::Number(from), false).await else {
+            return headers;
+        };
+        let header = VerifiedHeader::from_block(&block);
+        if from == oldest || store.extends(&header) {
+            headers.push(header);
+            break;
+        }
+        from -= 1;
+    }
+    for number in from + 1..=head {
+        let Ok(Some(block)) = client.get_block_by_number(BlockTag::Number(number), false).await else {
+            break;
+        };
+        headers.push(VerifiedHeader::from_block(&block));
+    }
+    headers
+}
+
+// Logs sent to subscribers, newest block last, so they can be sent again with `removed: true` if
+// their block is reorged out
+#[derive(Default)]
+struct SentLogs(VecDeque<(B256, Vec<(U256, serde_json::Value)>)>);
+
+impl SentLogs {
+    fn record(&mut self, block_hash: B256, delivered: Vec<(U256, serde_json::Value)>) {
+        self.0.push_back((block_hash, delivered));
+        while self.0.len() > RETRACTABLE_BLOCKS {
+            self.0.pop_front();
+        }
+    }
+
+    fn take(&mut self, block_hash: B256) -> Vec<(U256, serde_json::Value)> {
+        match self.0.iter().position(|(hash, _)| *hash == block_hash) {
+            Some(index) => self.0.remove(index).map(|(_, delivered)| delivered).unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+}
+
+// Sends the logs of each new block to the logs subscribers they match. A block whose receipts
+// can't be checked against its header is skipped rather than sent unverified
+async fn notify_logs(app: &AppHandle, state_guard: &AppState, headers: &[VerifiedHeader], sent: &mut SentLogs) {
+    let subscribers = state_guard.subscriptions.logs(state_guard.chain_id);
+    let Some(client) = state_guard.client.as_deref() else {
+        return;
+    };
+    if subscribers.is_empty() {
+        return;
+    }
+    for header in headers {
+        let logs = match receipts::verified_logs(client, &state_guard.rpc_url, header, state_guard.receipt_concurrency).await {
+            Ok(logs) => logs,
+            Err(e) => {
+                tracing::warn!("Skipping logs of block {}: {}", header.block_number, e);
+                continue;
+            },
+        };
+        let mut delivered = Vec::new();
+        for (subscription, filter) in &subscribers {
+            for log in logs.iter().filter(|log| receipts::matches(filter, log)) {
+                subscriptions::notify(app, subscription, log);
+                delivered.push((subscription.id, log.clone()));
+            }
+        }
+        sent.record(header.block_hash, delivered);
+    }
+}
+
+// Sends the logs of reorged-out blocks again with `removed: true`, newest block first, to the
+// subscribers that got them and are still subscribed
+fn retract_logs(app: &AppHandle, state_guard: &AppState, removed: &[VerifiedHeader], sent: &mut SentLogs) {
+    let subscribers = state_guard.subscriptions.logs(state_guard.chain_id);
+    for header in removed.iter().rev() {
+        for (id, mut log) in sent.take(header.block_hash) {
+            if let Some((subscription, _)) = subscribers.iter().find(|(subscription, _)| subscription.id == id) {
+                log["removed"] = json!(true);
+                subscriptions::notify(app, subscription, &log);
+            }
+        }
+    }
+}
+
+// Polls the verified optimistic and finalized heads and emits an event whenever either advances.
+// Each new optimistic head is also recorded in the header store along with any blocks skipped.
+// Changes in sync state go to the `syncing` event and subscriptions, and logs of new blocks to
+// logs subscriptions, retracted again if the block is reorged out
+pub fn spawn_head_watcher(app: AppHandle) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        let mut optimistic: Option<u64> = None;
+        let mut finalized: Option<u64> = None;
+        let mut head: Option<VerifiedHeader> = None;
+        let mut sync_state: Option<SyncState> = None;
+        let mut sent = SentLogs::default();
+        let mut interval = tokio::time::interval(HEAD_POLL_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let state = app.state::<Mutex<AppState>>();
+            let mut state_guard = state.lock().await;
+            let Some(client) = state_guard.client.as_deref() else {
+                break;
+            };
+
+            if let Ok(Some(block)) = client.get_block_by_number(BlockTag::Latest, false).await {
+                let header = VerifiedHeader::from_block(&block);
+                if optimistic != Some(header.block_number) {
+                    optimistic = Some(header.block_number);
+                    let missing = missing_headers(client, &state_guard.header_store, header.block_number).await;
+                    let mut removed = Vec::new();
+                    for header in missing.iter().cloned() {
+                        removed.extend(state_guard.header_store.insert(header));
+                    }
+                    state_guard.header_store.adopt_staged();
+                    let _ = app.emit("optimistic-head", header.clone());
+                    retract_logs(&app, &state_guard, &removed, &mut sent);
+                    notify_logs(&app, &state_guard, &missing, &mut sent).await;
+                }
+                head = Some(header);
+            }
+
+            let Some(client) = state_guard.client.as_deref() else {
+                break;
+            };
+            if let Ok(Some(block)) = client.get_block_by_number(BlockTag::Finalized, false).await {
+                let header = VerifiedHeader::from_block(&block);
+                if finalized != Some(header.block_number) {
+                    finalized = Some(header.block_number);
+                    state_guard.finalized = finalized;
+                    let _ = app.emit("finalized-head", header);
+                }
+            }
+
+            let current = SyncState::current(client, head.as_ref()).await;
+            if sync_state.as_ref() != Some(&current) {
+                let notification = current.notification();
+                let _ = app.emit("syncing", &notification);
+                for subscription in state_guard.subscriptions.matching(state_guard.chain_id, &SubscriptionKind::Syncing) {
+                    subscriptions::notify(&app, &subscription, &notification);
+                }
+                sync_state = Some(current);
+            }
+        }
+    })
+}
@@ -0,0 +1,268 @@
+use alloy::primitives::{Address, U256};
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::client::EthClientApi;
+use crate::contract;
+use crate::nft;
+use crate::outbound;
+
+pub const DEFAULT_TOKEN_LISTS: &[&str] = &["https://tokens.uniswap.org"];
+
+const MAX_SEARCH_RESULTS: usize = 50;
+
+sol! {
+    function name() external view returns (string);
+    function symbol() external view returns (string);
+    function decimals() external view returns (uint8);
+}
+
+// Token entry in the Uniswap token list format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenInfo {
+    pub chain_id: u64,
+    pub address: Address,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    #[serde(rename = "logoURI", default, skip_serializing_if = "Option::is_none")]
+    pub logo_uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenList {
+    name: String,
+    tokens: Vec<TokenInfo>,
+}
+
+// Limits from the token list JSON schema
+fn validate(token: &TokenInfo) -> Result<(), String> {
+    if token.symbol.is_empty() || token.symbol.len() > 20 {
+        return Err(format!("invalid symbol for 0x{:x}", token.address));
+    }
+    if token.name.is_empty() || token.name.len() > 60 {
+        return Err(format!("invalid name for 0x{:x}", token.address));
+    }
+    if let Some(logo_uri) = &token.logo_uri {
+        if url::Url::parse(logo_uri).is_err() {
+            return Err(format!("invalid logoURI for 0x{:x}", token.address));
+        }
+    }
+    Ok(())
+}
+
+// Downloads a token list and keeps the entries that pass schema validation
+pub async fn fetch_list(url: &str) -> Result<Vec<TokenInfo>, String> {
+    let list = outbound::client()
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to fetch token list {}: {}", url, e))?
+        .json::<TokenList>()
+        .await
+        .map_err(|e| format!("Invalid token list {}: {}", url, e))?;
+
+    let mut tokens = Vec::with_capacity(list.tokens.len());
+    for token in list.tokens {
+        match validate(&token) {
+            Ok(()) => tokens.push(token),
+            Err(e) => tracing::warn!("Skipping token from {}: {}", list.name, e),
+        }
+    }
+    Ok(tokens)
+}
+
+// Reads ERC-20 metadata straight from the contract for tokens missing from every list
+pub async fn fetch_onchain(client: &dyn EthClientApi, address: Address, chain_id: u64) -> Result<TokenInfo, String> {
+    let decimals = contract::call(client, address, decimalsCall {}.abi_encode()).await?;
+    let decimals = decimalsCall::abi_decode_returns(&decimals, true)
+        .map_err(|e| format!("0x{:x} is not an ERC-20 token: {}", address, e))?
+        ._0;
+
+    // Some early tokens return bytes32 names, those just come back empty
+    let symbol = contract::call(client, address, symbolCall {}.abi_encode())
+        .await
+        .ok()
+        .and_then(|out| symbolCall::abi_decode_returns(&out, true).ok())
+        .map(|r| r._0)
+        .unwrap_or_default();
+    let name = contract::call(client, address, nameCall {}.abi_encode())
+        .await
+        .ok()
+        .and_then(|out| nameCall::abi_decode_returns(&out, true).ok())
+        .map(|r| r._0)
+        .unwrap_or_default();
+
+    Ok(TokenInfo {
+        chain_id,
+        address,
+        name,
+        symbol,
+        decimals,
+        logo_uri: None,
+    })
+}
+
+// EIP-747 `wallet_watchAsset` params
+#[derive(Debug, Clone, Deserialize)]
+pub struct WatchAssetParams {
+    #[serde(rename = "type")]
+    pub asset_type: String,
+    pub options: WatchAssetOptions,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchAssetOptions {
+    pub address: Address,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+    pub image: Option<String>,
+    pub token_id: Option<String>,
+}
+
+// Checks a proposed asset against verified on-chain metadata so a dapp can't register a token
+// under a misleading symbol or decimals. NFTs with a token id must belong to `owner`
+pub async fn verify_watch_asset(
+    client: &dyn EthClientApi,
+    params: &WatchAssetParams,
+    owner: Option<Address>,
+    chain_id: u64,
+) -> Result<TokenInfo, String> {
+    let options = &params.options;
+    let mut token = match params.asset_type.as_str() {
+        "ERC20" => {
+            let token = fetch_onchain(client, options.address, chain_id).await?;
+            if options.decimals.is_some_and(|decimals| decimals != token.decimals) {
+                return Err(format!("decimals don't match the contract's {}", token.decimals));
+            }
+            token
+        },
+        "ERC721" => {
+            if nft::detect_standard(client, options.address).await? != nft::NftStandard::Erc721 {
+                return Err(format!("0x{:x} is not an ERC-721 contract", options.address));
+            }
+            if let (Some(token_id), Some(owner)) = (&options.token_id, owner) {
+                let token_id = token_id.parse::<U256>().map_err(|_| "invalid tokenId".to_string())?;
+                if !nft::verify_ownership(client, owner, options.address, token_id).await?.owned {
+                    return Err(format!("token {} is not owned by 0x{:x}", token_id, owner));
+                }
+            }
+            let symbol = contract::call(client, options.address, symbolCall {}.abi_encode())
+                .await
+                .ok()
+                .and_then(|out| symbolCall::abi_decode_returns(&out, true).ok())
+                .map(|r| r._0)
+                .unwrap_or_default();
+            let name = contract::call(client, options.address, nameCall {}.abi_encode())
+                .await
+                .ok()
+                .and_then(|out| nameCall::abi_decode_returns(&out, true).ok())
+                .map(|r| r._0)
+                .unwrap_or_default();
+            TokenInfo { chain_id, address: options.address, name, symbol, decimals: 0, logo_uri: None }
+        },
+        other => return Err(format!("unsupported asset type {}", other)),
+    };
+
+    match &options.symbol {
+        Some(symbol) if token.symbol.is_empty() => token.symbol = symbol.clone(),
+        Some(symbol) if !symbol.eq_ignore_ascii_case(&token.symbol) => {
+            return Err(format!("symbol {} doesn't match the contract's {}", symbol, token.symbol));
+        },
+        _ => {},
+    }
+    if token.name.is_empty() {
+        token.name = token.symbol.clone();
+    }
+    token.logo_uri = options.image.clone();
+    validate(&token)?;
+    Ok(token)
+}
+
+// In-memory index of every known token, persisted to the app cache between runs
+#[derive(Default)]
+pub struct TokenRegistry {
+    tokens: HashMap<(u64, Address), TokenInfo>,
+    loaded: bool,
+}
+
+impl TokenRegistry {
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    pub async fn load(&mut self, cache: &Path) {
+        if let Ok(bytes) = tokio::fs::read(cache).await {
+            if let Ok(tokens) = serde_json::from_slice::<Vec<TokenInfo>>(&bytes) {
+                self.extend(tokens);
+            }
+        }
+        self.loaded = true;
+    }
+
+    pub async fn save(&self, cache: &Path) -> Result<(), String> {
+        let tokens: Vec<&TokenInfo> = self.tokens.values().collect();
+        let bytes = serde_json::to_vec(&tokens).map_err(|e| format!("Failed to serialize tokens: {}", e))?;
+        if let Some(parent) = cache.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Failed to create token cache dir: {}", e))?;
+        }
+        tokio::fs::write(cache, bytes)
+            .await
+            .map_err(|e| format!("Failed to write token cache: {}", e))
+    }
+
+    pub fn extend(&mut self, tokens: impl IntoIterator<Item = TokenInfo>) {
+        for token in tokens {
+            self.tokens.insert((token.chain_id, token.address), token);
+        }
+    }
+
+    pub fn get(&self, chain_id: u64, address: Address) -> Option<&TokenInfo> {
+        self.tokens.get(&(chain_id, address))
+    }
+
+    pub fn for_chain(&self, chain_id: u64) -> Vec<TokenInfo> {
+        self.tokens.values().filter(|token| token.chain_id == chain_id).cloned().collect()
+    }
+
+    // Exact symbol matches first, then symbol prefixes, then name substrings
+    pub fn search(&self, query: &str, chain_id: Option<u64>) -> Vec<TokenInfo> {
+        let query = query.trim().to_lowercase();
+        if let Ok(address) = query.parse::<Address>() {
+            return self.tokens
+                .values()
+                .filter(|token| token.address == address && chain_id.map_or(true, |id| token.chain_id == id))
+                .cloned()
+                .collect();
+        }
+
+        let mut matches: Vec<(u8, &TokenInfo)> = self.tokens
+            .values()
+            .filter(|token| chain_id.map_or(true, |id| token.chain_id == id))
+            .filter_map(|token| {
+                let symbol = token.symbol.to_lowercase();
+                let rank = if symbol == query {
+                    0
+                } else if symbol.starts_with(&query) {
+                    1
+                } else if token.name.to_lowercase().contains(&query) {
+                    2
+                } else {
+                    return None;
+                };
+                Some((rank, token))
+            })
+            .collect();
+        matches.sort_by(|(a_rank, a), (b_rank, b)| a_rank.cmp(b_rank).then_with(|| a.symbol.cmp(&b.symbol)));
+        matches.into_iter().take(MAX_SEARCH_RESULTS).map(|(_, token)| token.clone()).collect()
+    }
+}
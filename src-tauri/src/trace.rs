@@ -0,0 +1,233 @@
+use alloy::primitives::{Address, Bytes, B256, U256, U64};
+use helios::core::types::{BlockTag, Transactions};
+use revm::db::CacheDB;
+use revm::interpreter::{CallInputs, CallOutcome, CreateInputs, CreateOutcome, CreateScheme, Interpreter, InterpreterResult, OpCode};
+use revm::primitives::{ExecutionResult, Output};
+use revm::{Database, EvmContext, Inspector};
+use serde::{Deserialize, Serialize};
+
+use crate::client::EthClientApi;
+use crate::distribution::AccountSource;
+use crate::evm::{self, VerifiedState};
+
+const CALL_TRACER: &str = "callTracer";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceOptions {
+    pub tracer: Option<String>,
+    #[serde(default)]
+    pub disable_stack: bool,
+}
+
+// One executed opcode, in geth's default struct logger format
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLog {
+    pub pc: u64,
+    pub op: &'static str,
+    pub gas: u64,
+    pub gas_cost: u64,
+    pub depth: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stack: Option<Vec<U256>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLogTrace {
+    pub gas: u64,
+    pub failed: bool,
+    pub return_value: String,
+    pub struct_logs: Vec<StructLog>,
+}
+
+// Call frame in geth's callTracer format
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub from: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<Address>,
+    pub value: U256,
+    pub gas: U64,
+    pub gas_used: U64,
+    pub input: Bytes,
+    pub output: Bytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<CallFrame>,
+}
+
+// Collects struct logs and the call tree while a transaction is re-executed
+#[derive(Default)]
+struct Tracer {
+    struct_logs: bool,
+    capture_stack: bool,
+    logs: Vec<StructLog>,
+    frames: Vec<CallFrame>,
+    root: Option<CallFrame>,
+}
+
+impl Tracer {
+    fn new(struct_logs: bool, capture_stack: bool) -> Self {
+        Self {
+            struct_logs,
+            capture_stack,
+            ..Default::default()
+        }
+    }
+
+    fn enter(&mut self, kind: String, from: Address, to: Option<Address>, value: U256, gas: u64, input: Bytes) {
+        self.frames.push(CallFrame {
+            kind,
+            from,
+            to,
+            value,
+            gas: U64::from(gas),
+            gas_used: U64::ZERO,
+            input,
+            output: Bytes::new(),
+            error: None,
+            calls: Vec::new(),
+        });
+    }
+
+    fn exit(&mut self, result: &InterpreterResult, created: Option<Address>) {
+        let Some(mut frame) = self.frames.pop() else {
+            return;
+        };
+        frame.gas_used = U64::from(result.gas.spent());
+        frame.output = result.output.clone();
+        if created.is_some() {
+            frame.to = created;
+        }
+        if !result.is_ok() {
+            frame.error = Some(if result.is_revert() {
+                "execution reverted".to_string()
+            } else {
+                format!("{:?}", result.result)
+            });
+        }
+
+        match self.frames.last_mut() {
+            Some(parent) => parent.calls.push(frame),
+            None => self.root = Some(frame),
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for Tracer {
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        if !self.struct_logs {
+            return;
+        }
+        self.logs.push(StructLog {
+            pc: interp.program_counter() as u64,
+            op: OpCode::new(interp.current_opcode()).map(|op| op.as_str()).unwrap_or("INVALID"),
+            gas: interp.gas.remaining(),
+            gas_cost: 0,
+            depth: context.journaled_state.depth as u64,
+            stack: self.capture_stack.then(|| interp.stack.data().clone()),
+        });
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if let Some(log) = self.logs.last_mut().filter(|_| self.struct_logs) {
+            log.gas_cost = log.gas.saturating_sub(interp.gas.remaining());
+        }
+    }
+
+    fn call(&mut self, _context: &mut EvmContext<DB>, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        self.enter(
+            format!("{:?}", inputs.scheme).to_uppercase(),
+            inputs.caller,
+            Some(inputs.target_address),
+            inputs.call_value(),
+            inputs.gas_limit,
+            inputs.input.clone(),
+        );
+        None
+    }
+
+    fn call_end(&mut self, _context: &mut EvmContext<DB>, _inputs: &CallInputs, outcome: CallOutcome) -> CallOutcome {
+        self.exit(&outcome.result, None);
+        outcome
+    }
+
+    fn create(&mut self, _context: &mut EvmContext<DB>, inputs: &mut CreateInputs) -> Option<CreateOutcome> {
+        let kind = match inputs.scheme {
+            CreateScheme::Create2 { .. } => "CREATE2",
+            _ => "CREATE",
+        };
+        self.enter(kind.to_string(), inputs.caller, None, inputs.value, inputs.gas_limit, inputs.init_code.clone());
+        None
+    }
+
+    fn create_end(&mut self, _context: &mut EvmContext<DB>, _inputs: &CreateInputs, outcome: CreateOutcome) -> CreateOutcome {
+        self.exit(&outcome.result, outcome.address);
+        outcome
+    }
+}
+
+// Re-executes a mined transaction on a local EVM over the verified state of its parent block,
+// replaying the transactions before it, so the trace doesn't depend on the RPC's tracer
+pub async fn trace_transaction(
+    client: &dyn EthClientApi,
+    accounts: AccountSource,
+    hash: B256,
+    options: TraceOptions,
+) -> Result<serde_json::Value, String> {
+    let call_tracer = match options.tracer.as_deref() {
+        None => false,
+        Some(CALL_TRACER) => true,
+        Some(tracer) => return Err(format!("Unsupported tracer: {}", tracer)),
+    };
+
+    let tx = client.get_transaction_by_hash(hash)
+        .await
+        .ok_or_else(|| format!("Transaction 0x{:x} not found", hash))?;
+    let number = tx.block_number.ok_or("Transaction is still pending")?;
+    let block = client.get_block_by_number(BlockTag::Number(number), true)
+        .await
+        .map_err(|e| format!("Failed to get block {}: {}", number, e))?
+        .ok_or_else(|| format!("Block {} is not available from the light client", number))?;
+    let Transactions::Full(transactions) = &block.transactions else {
+        return Err(format!("Block {} was returned without transaction bodies", number));
+    };
+    let chain_id = client.chain_id().await;
+
+    tokio::task::block_in_place(|| {
+        let mut db = CacheDB::new(VerifiedState::new(client, accounts, BlockTag::Number(number.saturating_sub(1))));
+        let env = evm::block_env(&block);
+
+        for prior in transactions.iter().take_while(|prior| prior.hash != hash) {
+            evm::transact(&mut db, &env, evm::transaction_env(prior), chain_id)?;
+        }
+
+        let mut tracer = Tracer::new(!call_tracer, !options.disable_stack);
+        let result = evm::inspect(&mut db, &env, evm::transaction_env(&tx), chain_id, &mut tracer)?;
+
+        if call_tracer {
+            let root = tracer.root.ok_or("Transaction produced no call frame")?;
+            return serde_json::to_value(root).map_err(|e| format!("Failed to serialize trace: {}", e));
+        }
+
+        let return_value = match &result {
+            ExecutionResult::Success { output: Output::Call(data), .. } => data.clone(),
+            ExecutionResult::Success { output: Output::Create(data, _), .. } => data.clone(),
+            ExecutionResult::Revert { output, .. } => output.clone(),
+            ExecutionResult::Halt { .. } => Bytes::new(),
+        };
+        serde_json::to_value(StructLogTrace {
+            gas: result.gas_used(),
+            failed: !result.is_success(),
+            return_value: alloy::hex::encode(return_value),
+            struct_logs: tracer.logs,
+        })
+        .map_err(|e| format!("Failed to serialize trace: {}", e))
+    })
+}
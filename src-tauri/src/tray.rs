@@ -0,0 +1,220 @@
+use tauri::menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Listener, Manager, WebviewUrl, WebviewWindowBuilder, Wry};
+use tokio::sync::Mutex;
+
+use crate::rpc_server;
+use crate::AppState;
+
+const TRAY_ID: &str = "main";
+const NETWORK_PREFIX: &str = "network-";
+
+// Menu entries whose labels follow the client state
+struct TrayMenu {
+    status: MenuItem<Wry>,
+    toggle_client: MenuItem<Wry>,
+    networks: Submenu<Wry>,
+    rpc_server: CheckMenuItem<Wry>,
+    // syncing, synced or error, from the latest sync and health events
+    sync_state: std::sync::Mutex<&'static str>,
+}
+
+pub fn build(app: &AppHandle) -> tauri::Result<()> {
+    let status = MenuItem::with_id(app, "status", "Stopped", false, None::<&str>)?;
+    let open = MenuItem::with_id(app, "open", "Open Mana", true, None::<&str>)?;
+    let toggle_client = MenuItem::with_id(app, "toggle-client", "Start client", true, None::<&str>)?;
+    let networks = Submenu::with_id(app, "networks", "Network", true)?;
+    let rpc_server = CheckMenuItem::with_id(app, "rpc-server", "Local RPC server", true, false, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+    let menu = Menu::with_items(app, &[
+        &status,
+        &PredefinedMenuItem::separator(app)?,
+        &open,
+        &toggle_client,
+        &networks,
+        &rpc_server,
+        &PredefinedMenuItem::separator(app)?,
+        &quit,
+    ])?;
+
+    let mut tray = TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu)
+        .tooltip("Mana: stopped")
+        .on_menu_event(|app, event| {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move { handle_menu_event(app, event).await });
+        });
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app)?;
+
+    app.manage(TrayMenu {
+        status,
+        toggle_client,
+        networks,
+        rpc_server,
+        sync_state: std::sync::Mutex::new("stopped"),
+    });
+
+    let handle = app.clone();
+    app.listen_any("sync-status", move |event| {
+        let state = match event_status(event.payload()).as_deref() {
+            Some("synced") => "synced",
+            _ => "syncing",
+        };
+        set_sync_state(&handle, state);
+    });
+    let handle = app.clone();
+    app.listen_any("client-health", move |event| {
+        let state = match event_status(event.payload()).as_deref() {
+            Some("failed") => "error",
+            Some("recovered") => "synced",
+            _ => "syncing",
+        };
+        set_sync_state(&handle, state);
+    });
+    Ok(())
+}
+
+// `status` field of a SyncStatus or ClientHealth event payload
+fn event_status(payload: &str) -> Option<String> {
+    serde_json::from_str::<serde_json::Value>(payload)
+        .ok()?
+        .get("status")?
+        .as_str()
+        .map(str::to_string)
+}
+
+fn set_sync_state(app: &AppHandle, sync_state: &'static str) {
+    *app.state::<TrayMenu>().sync_state.lock().unwrap() = sync_state;
+    tauri::async_runtime::spawn(refresh(app.clone()));
+}
+
+// Brings the menu in line with the client, network and RPC server state
+async fn refresh(app: AppHandle) {
+    let menu = app.state::<TrayMenu>();
+    let state = app.state::<Mutex<AppState>>();
+    let (running, chain_id, mut networks, rpc_server) = {
+        let state_guard = state.lock().await;
+        (
+            state_guard.client.is_some(),
+            state_guard.config.as_ref().map(|config| config.chain_id),
+            state_guard.known_networks.keys().copied().collect::<Vec<u64>>(),
+            state_guard.rpc_server.is_some(),
+        )
+    };
+    networks.sort_unstable();
+
+    // A failed start leaves the client stopped but should still show as an error
+    let sync_state = match *menu.sync_state.lock().unwrap() {
+        "error" => "error",
+        sync_state if running => sync_state,
+        _ => "stopped",
+    };
+    let _ = menu.status.set_text(match sync_state {
+        "synced" => "Synced",
+        "syncing" => "Syncing",
+        "error" => "Error",
+        _ => "Stopped",
+    });
+    let _ = menu.toggle_client.set_text(if running { "Stop client" } else { "Start client" });
+    let _ = menu.rpc_server.set_checked(rpc_server);
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        let _ = tray.set_tooltip(Some(format!("Mana: {}", sync_state)));
+    }
+
+    if let Ok(items) = menu.networks.items() {
+        for item in items {
+            let _ = menu.networks.remove(&item);
+        }
+    }
+    for network in networks {
+        let label = crate::network_name(network);
+        let checked = running && chain_id == Some(network);
+        if let Ok(item) = CheckMenuItem::with_id(&app, format!("{}{}", NETWORK_PREFIX, network), label, true, checked, None::<&str>) {
+            let _ = menu.networks.append(&item);
+        }
+    }
+}
+
+async fn handle_menu_event(app: AppHandle, event: MenuEvent) {
+    let state = app.state::<Mutex<AppState>>();
+    let result = match event.id().as_ref() {
+        "open" => open_main_window(&app),
+        "toggle-client" => {
+            let running = state.lock().await.client.is_some();
+            if running {
+                crate::stop_client(&state).await;
+                Ok(())
+            } else {
+                let config = state.lock().await.config.clone();
+                match config {
+                    Some(config) => crate::relaunch_client(&app, &state, config).await,
+                    // Never started, so there is no RPC to start with yet
+                    None => open_main_window(&app),
+                }
+            }
+        },
+        "rpc-server" => {
+            let server = state.lock().await.rpc_server.take();
+            match server {
+                Some(server) => {
+                    server.stop();
+                    Ok(())
+                },
+                None => match rpc_server::serve(app.clone(), rpc_server::DEFAULT_RPC_PORT).await {
+                    Ok(server) => {
+                        state.lock().await.rpc_server = Some(server);
+                        Ok(())
+                    },
+                    Err(e) => Err(e),
+                },
+            }
+        },
+        "quit" => {
+            app.exit(0);
+            Ok(())
+        },
+        id => match id.strip_prefix(NETWORK_PREFIX).and_then(|chain_id| chain_id.parse::<u64>().ok()) {
+            Some(chain_id) => switch_network(&app, &state, chain_id).await,
+            None => Ok(()),
+        },
+    };
+    if let Err(e) = result {
+        tracing::error!("Tray action failed: {}", e);
+        if event.id().as_ref() == "toggle-client" || event.id().as_ref().starts_with(NETWORK_PREFIX) {
+            *app.state::<TrayMenu>().sync_state.lock().unwrap() = "error";
+        }
+    }
+    refresh(app).await;
+}
+
+// Restarts the client with the settings it last ran with on the chosen chain
+async fn switch_network(app: &AppHandle, state: &Mutex<AppState>, chain_id: u64) -> Result<(), String> {
+    let config = {
+        let state_guard = state.lock().await;
+        if state_guard.client.is_some() && state_guard.config.as_ref().is_some_and(|config| config.chain_id == chain_id) {
+            return Ok(());
+        }
+        state_guard.known_networks.get(&chain_id).cloned()
+    };
+    let config = config.ok_or_else(|| format!("Chain {} has never been started", chain_id))?;
+    crate::stop_client(state).await;
+    crate::relaunch_client(app, state, config).await
+}
+
+fn open_main_window(app: &AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        window.show().map_err(|e| e.to_string())?;
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+    // Headless mode starts without one
+    WebviewWindowBuilder::new(app, "main", WebviewUrl::default())
+        .title("Mana")
+        .inner_size(800.0, 600.0)
+        .build()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,243 @@
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+pub const CODEC_RAW: u64 = 0x55;
+pub const CODEC_DAG_PB: u64 = 0x70;
+const HASH_IDENTITY: u64 = 0x00;
+const HASH_SHA2_256: u64 = 0x12;
+
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+// UnixFS node types from unixfs.proto
+const UNIXFS_DIRECTORY: u64 = 1;
+const UNIXFS_HAMT_SHARD: u64 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cid {
+    pub codec: u64,
+    pub hash_code: u64,
+    pub digest: Vec<u8>,
+}
+
+impl Cid {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        if value.starts_with("Qm") {
+            let bytes = bs58::decode(value)
+                .into_vec()
+                .map_err(|e| format!("Invalid CIDv0 {}: {}", value, e))?;
+            Self::from_bytes(&bytes)
+        } else if let Some(encoded) = value.strip_prefix('b') {
+            Self::from_bytes(&base32_decode(encoded)?)
+        } else {
+            Err(format!("Unsupported CID encoding: {}", value))
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        // CIDv0 is a bare sha2-256 multihash of a dag-pb node
+        if bytes.len() == 34 && bytes.starts_with(&[0x12, 0x20]) {
+            return Ok(Cid {
+                codec: CODEC_DAG_PB,
+                hash_code: HASH_SHA2_256,
+                digest: bytes[2..].to_vec(),
+            });
+        }
+
+        let (version, rest) = read_varint(bytes)?;
+        if version != 1 {
+            return Err(format!("Unsupported CID version {}", version));
+        }
+        let (codec, rest) = read_varint(rest)?;
+        let (hash_code, rest) = read_varint(rest)?;
+        let (length, digest) = read_varint(rest)?;
+        if digest.len() as u64 != length {
+            return Err("CID digest length mismatch".to_string());
+        }
+
+        Ok(Cid { codec, hash_code, digest: digest.to_vec() })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.digest.len() + 8);
+        write_varint(&mut bytes, 1);
+        write_varint(&mut bytes, self.codec);
+        write_varint(&mut bytes, self.hash_code);
+        write_varint(&mut bytes, self.digest.len() as u64);
+        bytes.extend_from_slice(&self.digest);
+        bytes
+    }
+
+    // Checks that a block fetched from an untrusted source is the one this CID addresses
+    pub fn verify(&self, block: &[u8]) -> bool {
+        match self.hash_code {
+            HASH_SHA2_256 => Sha256::digest(block).as_slice() == self.digest.as_slice(),
+            HASH_IDENTITY => block == self.digest.as_slice(),
+            _ => false,
+        }
+    }
+
+    pub fn is_inline(&self) -> bool {
+        self.hash_code == HASH_IDENTITY
+    }
+}
+
+// Always rendered as base32 CIDv1, which is case-insensitive and safe to use as a URL host
+impl fmt::Display for Cid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "b{}", base32_encode(&self.to_bytes()))
+    }
+}
+
+pub fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut buffer: u16 = 0;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+fn base32_decode(encoded: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer: u16 = 0;
+    let mut bits = 0;
+    for c in encoded.bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a == c.to_ascii_lowercase())
+            .ok_or_else(|| format!("Invalid base32 character '{}'", c as char))?;
+        buffer = (buffer << 5) | value as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+fn read_varint(bytes: &[u8]) -> Result<(u64, &[u8]), String> {
+    let mut value: u64 = 0;
+    for (i, &byte) in bytes.iter().enumerate().take(10) {
+        value |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, &bytes[i + 1..]));
+        }
+    }
+    Err("Invalid varint".to_string())
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+// Iterates the (field number, payload) pairs of a protobuf message, skipping fixed-width fields
+fn protobuf_fields(mut bytes: &[u8]) -> Result<Vec<(u64, ProtobufValue<'_>)>, String> {
+    let mut fields = Vec::new();
+    while !bytes.is_empty() {
+        let (key, rest) = read_varint(bytes)?;
+        let (field, wire_type) = (key >> 3, key & 0x7);
+        bytes = match wire_type {
+            0 => {
+                let (value, rest) = read_varint(rest)?;
+                fields.push((field, ProtobufValue::Varint(value)));
+                rest
+            },
+            1 => rest.get(8..).ok_or("Truncated protobuf field")?,
+            2 => {
+                let (length, rest) = read_varint(rest)?;
+                let length = length as usize;
+                if rest.len() < length {
+                    return Err("Truncated protobuf field".to_string());
+                }
+                fields.push((field, ProtobufValue::Bytes(&rest[..length])));
+                &rest[length..]
+            },
+            5 => rest.get(4..).ok_or("Truncated protobuf field")?,
+            _ => return Err(format!("Unsupported protobuf wire type {}", wire_type)),
+        };
+    }
+    Ok(fields)
+}
+
+enum ProtobufValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+pub struct PbLink {
+    pub cid: Cid,
+    pub name: String,
+}
+
+// dag-pb node with its UnixFS payload decoded
+pub struct PbNode {
+    pub links: Vec<PbLink>,
+    pub kind: u64,
+    pub data: Vec<u8>,
+}
+
+impl PbNode {
+    pub fn decode(block: &[u8]) -> Result<Self, String> {
+        let mut links = Vec::new();
+        let mut kind = 0;
+        let mut data = Vec::new();
+
+        for (field, value) in protobuf_fields(block)? {
+            match (field, value) {
+                (1, ProtobufValue::Bytes(unixfs)) => {
+                    for (field, value) in protobuf_fields(unixfs)? {
+                        match (field, value) {
+                            (1, ProtobufValue::Varint(value)) => kind = value,
+                            (2, ProtobufValue::Bytes(bytes)) => data = bytes.to_vec(),
+                            _ => {}
+                        }
+                    }
+                },
+                (2, ProtobufValue::Bytes(link)) => {
+                    let mut cid = None;
+                    let mut name = String::new();
+                    for (field, value) in protobuf_fields(link)? {
+                        match (field, value) {
+                            (1, ProtobufValue::Bytes(hash)) => cid = Some(Cid::from_bytes(hash)?),
+                            (2, ProtobufValue::Bytes(bytes)) => name = String::from_utf8_lossy(bytes).into_owned(),
+                            _ => {}
+                        }
+                    }
+                    links.push(PbLink {
+                        cid: cid.ok_or("dag-pb link without hash")?,
+                        name,
+                    });
+                },
+                _ => {}
+            }
+        }
+
+        Ok(PbNode { links, kind, data })
+    }
+
+    pub fn is_directory(&self) -> bool {
+        self.kind == UNIXFS_DIRECTORY
+    }
+
+    pub fn is_sharded_directory(&self) -> bool {
+        self.kind == UNIXFS_HAMT_SHARD
+    }
+
+    pub fn link(&self, name: &str) -> Option<&Cid> {
+        self.links.iter().find(|link| link.name == name).map(|link| &link.cid)
+    }
+}
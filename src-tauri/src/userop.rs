@@ -0,0 +1,119 @@
+use alloy::primitives::{Address, Bytes, U256};
+use serde::Deserialize;
+
+// ERC-4337 bundler methods, forwarded to the bundler configured for the active chain
+const BUNDLER_METHODS: &[&str] = &[
+    "eth_sendUserOperation",
+    "eth_estimateUserOperationGas",
+    "eth_getUserOperationByHash",
+    "eth_getUserOperationReceipt",
+    "eth_supportedEntryPoints",
+];
+
+// Methods whose first param is a user operation and second the entry point
+const USER_OPERATION_METHODS: &[&str] = &["eth_sendUserOperation", "eth_estimateUserOperationGas"];
+
+pub fn is_bundler_method(method: &str) -> bool {
+    BUNDLER_METHODS.contains(&method)
+}
+
+// Union of the v0.6 (initCode/paymasterAndData) and v0.7 (factory/paymaster) layouts
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct UserOperation {
+    sender: Address,
+    #[serde(rename = "nonce")]
+    _nonce: U256,
+    call_data: Bytes,
+    call_gas_limit: Option<U256>,
+    verification_gas_limit: Option<U256>,
+    pre_verification_gas: Option<U256>,
+    max_fee_per_gas: Option<U256>,
+    max_priority_fee_per_gas: Option<U256>,
+    signature: Bytes,
+    // v0.6
+    init_code: Option<Bytes>,
+    paymaster_and_data: Option<Bytes>,
+    // v0.7
+    factory: Option<Address>,
+    factory_data: Option<Bytes>,
+    paymaster: Option<Address>,
+    paymaster_verification_gas_limit: Option<U256>,
+    paymaster_post_op_gas_limit: Option<U256>,
+    paymaster_data: Option<Bytes>,
+}
+
+impl UserOperation {
+    fn validate(&self, estimating: bool) -> Result<(), String> {
+        let v06 = self.init_code.is_some() || self.paymaster_and_data.is_some();
+        let v07 = self.factory.is_some()
+            || self.factory_data.is_some()
+            || self.paymaster.is_some()
+            || self.paymaster_verification_gas_limit.is_some()
+            || self.paymaster_post_op_gas_limit.is_some()
+            || self.paymaster_data.is_some();
+        if v06 && v07 {
+            return Err("user operation mixes v0.6 and v0.7 fields".to_string());
+        }
+
+        if self.sender == Address::ZERO {
+            return Err("sender must not be the zero address".to_string());
+        }
+        if !self.call_data.is_empty() && self.call_data.len() < 4 {
+            return Err("callData must be empty or start with a 4-byte selector".to_string());
+        }
+        for (name, code) in [("initCode", &self.init_code), ("paymasterAndData", &self.paymaster_and_data)] {
+            if code.as_ref().is_some_and(|code| !code.is_empty() && code.len() < 20) {
+                return Err(format!("{} must be empty or start with a 20-byte address", name));
+            }
+        }
+        if self.factory_data.as_ref().is_some_and(|data| !data.is_empty()) && self.factory.is_none() {
+            return Err("factoryData requires factory".to_string());
+        }
+        if self.paymaster.is_some()
+            && !estimating
+            && (self.paymaster_verification_gas_limit.is_none() || self.paymaster_post_op_gas_limit.is_none())
+        {
+            return Err("paymaster requires paymasterVerificationGasLimit and paymasterPostOpGasLimit".to_string());
+        }
+
+        // Gas fields may be left out when asking the bundler to estimate them
+        if !estimating {
+            let required = [
+                ("callGasLimit", &self.call_gas_limit),
+                ("verificationGasLimit", &self.verification_gas_limit),
+                ("preVerificationGas", &self.pre_verification_gas),
+                ("maxFeePerGas", &self.max_fee_per_gas),
+                ("maxPriorityFeePerGas", &self.max_priority_fee_per_gas),
+            ];
+            if let Some((name, _)) = required.iter().find(|(_, value)| value.is_none()) {
+                return Err(format!("missing {}", name));
+            }
+            if self.signature.is_empty() {
+                return Err("missing signature".to_string());
+            }
+        }
+        if let (Some(max_fee), Some(priority_fee)) = (self.max_fee_per_gas, self.max_priority_fee_per_gas) {
+            if priority_fee > max_fee {
+                return Err("maxPriorityFeePerGas exceeds maxFeePerGas".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+// Checks the user operation's structure before it leaves the wallet. Only the shape is checked
+// here, signature and gas validity are the bundler's and entry point's job
+pub fn validate_params(method: &str, params: &[serde_json::Value]) -> Result<(), String> {
+    if !USER_OPERATION_METHODS.contains(&method) {
+        return Ok(());
+    }
+    let operation = params.first().ok_or("missing user operation")?;
+    let operation: UserOperation = serde_json::from_value(operation.clone())
+        .map_err(|e| format!("invalid user operation: {}", e))?;
+    params.get(1)
+        .and_then(|entry_point| entry_point.as_str())
+        .and_then(|entry_point| entry_point.parse::<Address>().ok())
+        .ok_or("missing or invalid entry point address")?;
+    operation.validate(method == "eth_estimateUserOperationGas")
+}
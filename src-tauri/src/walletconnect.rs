@@ -0,0 +1,579 @@
+use alloy::hex;
+use alloy::primitives::{Address, B256};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use ed25519_dalek::{Signer, SigningKey};
+use futures::{SinkExt, StreamExt};
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, EventTarget, Manager};
+use tokio::sync::{mpsc, Mutex};
+use tokio_tungstenite::tungstenite::Message;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use crate::accounts::Caller;
+use crate::outbound::{self, WsStream};
+use crate::{errors, prompts};
+use crate::AppState;
+
+// Pairing proposals without an expiry are good for five minutes, as in the sign client
+const DEFAULT_PAIRING_TTL_SECS: u64 = 5 * 60;
+pub const DEFAULT_RELAY_URL: &str = "wss://relay.walletconnect.com";
+// Lifetime of the relay auth token and of a settled session, as the sign client uses
+const AUTH_TTL_SECS: u64 = 24 * 60 * 60;
+const SESSION_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+// How long the relay holds a published message for a peer that's offline
+const MESSAGE_TTL_SECS: u64 = 5 * 60;
+// Type 0 envelopes: a zero byte, the 12 byte nonce and the ChaCha20-Poly1305 sealed payload
+const ENVELOPE_TYPE_0: u8 = 0;
+const NONCE_LEN: usize = 12;
+// Multicodec prefix of an ed25519 public key in a did:key
+const ED25519_MULTICODEC: [u8; 2] = [0xed, 0x01];
+
+// Relay tags of the messages the wallet publishes
+const TAG_PAIRING_PING_RESPONSE: u32 = 1003;
+const TAG_SESSION_PROPOSE_RESPONSE: u32 = 1101;
+const TAG_SESSION_SETTLE: u32 = 1102;
+const TAG_SESSION_REQUEST_RESPONSE: u32 = 1109;
+const TAG_SESSION_DELETE_RESPONSE: u32 = 1113;
+const TAG_SESSION_PING_RESPONSE: u32 = 1115;
+
+// Sign protocol error codes
+const USER_REJECTED: i32 = 5000;
+const UNSUPPORTED_CHAINS: i32 = 5100;
+const UNSUPPORTED_NAMESPACE_KEY: i32 = 5104;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WalletConnectSettings {
+    // Cloud project id the relay requires, pairing is refused until it's set
+    pub project_id: Option<String>,
+    pub relay_url: String,
+}
+
+impl Default for WalletConnectSettings {
+    fn default() -> Self {
+        Self { project_id: None, relay_url: DEFAULT_RELAY_URL.to_string() }
+    }
+}
+
+impl WalletConnectSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        match url::Url::parse(&self.relay_url) {
+            Ok(parsed) if matches!(parsed.scheme(), "ws" | "wss") => Ok(()),
+            Ok(_) => Err(format!("WalletConnect relay must be a ws(s) URL: {}", self.relay_url)),
+            Err(e) => Err(format!("Invalid WalletConnect relay {}: {}", self.relay_url, e)),
+        }
+    }
+}
+
+// WalletConnect v2 pairing URI, `wc:{topic}@2?relay-protocol=irn&symKey={key}&expiryTimestamp={ts}`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pairing {
+    pub topic: String,
+    pub relay_protocol: String,
+    pub relay_data: Option<String>,
+    // Encrypts everything on the pairing topic, only handed to the wallet's own window
+    #[serde(skip)]
+    sym_key: B256,
+    pub expiry_timestamp: u64,
+    pub methods: Vec<String>,
+}
+
+// The URI itself carries the symKey, so only the topic is reported
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PairingError {
+    topic: Option<String>,
+    error: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionEvent<'a> {
+    topic: &'a str,
+    origin: &'a str,
+    peer: &'a serde_json::Value,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+pub fn parse(uri: &str) -> Result<Pairing, String> {
+    let rest = uri.strip_prefix("wc:").ok_or("not a wc: URI")?;
+    let (path, query) = rest.split_once('?').ok_or("missing pairing parameters")?;
+    let (topic, version) = path.split_once('@').ok_or("missing protocol version")?;
+    match version {
+        "2" => {},
+        "1" => return Err("WalletConnect v1 is no longer supported".to_string()),
+        other => return Err(format!("unsupported WalletConnect version {}", other)),
+    }
+    if topic.len() != 64 || !topic.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err("invalid pairing topic".to_string());
+    }
+
+    let mut relay_protocol = None;
+    let mut relay_data = None;
+    let mut sym_key = None;
+    let mut expiry_timestamp = None;
+    let mut methods = Vec::new();
+    for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+        match key.as_ref() {
+            "relay-protocol" => relay_protocol = Some(value.into_owned()),
+            "relay-data" => relay_data = Some(value.into_owned()),
+            "symKey" => sym_key = Some(value.parse::<B256>().map_err(|_| "symKey must be 32 bytes of hex".to_string())?),
+            "expiryTimestamp" => expiry_timestamp = Some(value.parse::<u64>().map_err(|_| "invalid expiryTimestamp".to_string())?),
+            // Groups like `[wc_sessionPropose],[wc_authRequest]`
+            "methods" => methods = value
+                .split(',')
+                .map(|group| group.trim_matches(|c| c == '[' || c == ']').to_string())
+                .filter(|group| !group.is_empty())
+                .collect(),
+            _ => {},
+        }
+    }
+
+    Ok(Pairing {
+        topic: topic.to_lowercase(),
+        relay_protocol: relay_protocol.ok_or("missing relay-protocol")?,
+        relay_data,
+        sym_key: sym_key.ok_or("missing symKey")?,
+        expiry_timestamp: expiry_timestamp.unwrap_or_else(|| now() + DEFAULT_PAIRING_TTL_SECS),
+        methods,
+    })
+}
+
+// Pairings the user accepted, keyed by topic
+#[derive(Default)]
+pub struct Pairings {
+    pairings: Vec<Pairing>,
+    pub settings: WalletConnectSettings,
+}
+
+impl Pairings {
+    pub fn list(&mut self) -> Vec<Pairing> {
+        let now = now();
+        self.pairings.retain(|pairing| pairing.expiry_timestamp > now);
+        self.pairings.clone()
+    }
+
+    fn insert(&mut self, pairing: Pairing) {
+        self.pairings.retain(|existing| existing.topic != pairing.topic);
+        self.pairings.push(pairing);
+    }
+}
+
+async fn pair(app: &AppHandle, uri: &str) -> Result<Option<Pairing>, String> {
+    let pairing = parse(uri)?;
+    if pairing.expiry_timestamp <= now() {
+        return Err("pairing URI has expired".to_string());
+    }
+    if pairing.relay_protocol != "irn" {
+        return Err(format!("unsupported relay protocol {}", pairing.relay_protocol));
+    }
+
+    if !prompts::ask(app, "wc_pair", json!({ "pairing": pairing })).await {
+        return Ok(None);
+    }
+    let state = app.state::<Mutex<AppState>>();
+    state.lock().await.walletconnect.insert(pairing.clone());
+    Ok(Some(pairing))
+}
+
+// Deep-link entry point for `wc:` URIs: asks the user, records the pairing and serves it over the
+// relay until its sessions end. The symKey never leaves this process
+pub async fn handle_deep_link(app: AppHandle, uri: String) {
+    let error = match pair(&app, &uri).await {
+        Ok(Some(pairing)) => {
+            let _ = app.emit_to(EventTarget::webview_window("main"), "walletconnect-paired", &pairing);
+            let settings = app.state::<Mutex<AppState>>().lock().await.walletconnect.settings.clone();
+            match serve(&app, &pairing, settings).await {
+                Ok(()) => return,
+                Err(error) => error,
+            }
+        },
+        Ok(None) => return,
+        Err(error) => error,
+    };
+    tracing::warn!("WalletConnect pairing failed: {}", error);
+    let topic = parse(&uri).ok().map(|pairing| pairing.topic);
+    let _ = app.emit_to(EventTarget::webview_window("main"), "walletconnect-pairing-error", PairingError { topic, error });
+}
+
+fn payload_id() -> u64 {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    millis * 1000 + rand::random::<u64>() % 1000
+}
+
+fn topic_of(key: &B256) -> String {
+    hex::encode(Sha256::digest(key))
+}
+
+fn seal(key: &B256, payload: &serde_json::Value) -> Result<String, String> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key.as_slice()));
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let sealed = cipher
+        .encrypt(Nonce::from_slice(&nonce), payload.to_string().as_bytes())
+        .map_err(|_| "Failed to encrypt WalletConnect message".to_string())?;
+    let mut envelope = Vec::with_capacity(1 + NONCE_LEN + sealed.len());
+    envelope.push(ENVELOPE_TYPE_0);
+    envelope.extend_from_slice(&nonce);
+    envelope.extend(sealed);
+    Ok(STANDARD.encode(envelope))
+}
+
+fn open(key: &B256, message: &str) -> Result<serde_json::Value, String> {
+    let envelope = STANDARD.decode(message).map_err(|e| format!("Invalid WalletConnect envelope: {}", e))?;
+    let sealed = match envelope.split_first() {
+        Some((&ENVELOPE_TYPE_0, rest)) if rest.len() > NONCE_LEN => rest,
+        Some((&ENVELOPE_TYPE_0, _)) | None => return Err("Truncated WalletConnect envelope".to_string()),
+        Some((kind, _)) => return Err(format!("Unsupported WalletConnect envelope type {}", kind)),
+    };
+    let (nonce, sealed) = sealed.split_at(NONCE_LEN);
+    let plaintext = ChaCha20Poly1305::new(Key::from_slice(key.as_slice()))
+        .decrypt(Nonce::from_slice(nonce), sealed)
+        .map_err(|_| "Failed to decrypt WalletConnect message".to_string())?;
+    serde_json::from_slice(&plaintext).map_err(|e| format!("Invalid WalletConnect payload: {}", e))
+}
+
+// Session key: X25519 with the proposer's key, stretched with HKDF-SHA256 without salt or info
+fn session_key(secret: EphemeralSecret, peer: &str) -> Result<B256, String> {
+    let peer = peer.parse::<B256>().map_err(|_| "Invalid proposer public key".to_string())?;
+    let shared = secret.diffie_hellman(&PublicKey::from(peer.0));
+    let mut key = B256::ZERO;
+    Hkdf::<Sha256>::new(None, shared.as_bytes())
+        .expand(&[], key.as_mut_slice())
+        .map_err(|_| "Failed to derive the session key".to_string())?;
+    Ok(key)
+}
+
+// EdDSA JWT the relay authenticates clients with, issued by the client key's did:key
+fn relay_auth(key: &SigningKey, relay_url: &str) -> String {
+    let mut multicodec = ED25519_MULTICODEC.to_vec();
+    multicodec.extend_from_slice(key.verifying_key().as_bytes());
+    let issued = now();
+    let header = URL_SAFE_NO_PAD.encode(json!({ "alg": "EdDSA", "typ": "JWT" }).to_string());
+    let claims = URL_SAFE_NO_PAD.encode(json!({
+        "iss": format!("did:key:z{}", bs58::encode(multicodec).into_string()),
+        "sub": hex::encode(rand::random::<[u8; 32]>()),
+        "aud": relay_url,
+        "iat": issued,
+        "exp": issued + AUTH_TTL_SECS,
+    }).to_string());
+    let signed = format!("{}.{}", header, claims);
+    let signature = URL_SAFE_NO_PAD.encode(key.sign(signed.as_bytes()).to_bytes());
+    format!("{}.{}", signed, signature)
+}
+
+fn rpc_result(id: &serde_json::Value, result: serde_json::Value) -> serde_json::Value {
+    json!({ "id": id, "jsonrpc": "2.0", "result": result })
+}
+
+fn rpc_error(id: &serde_json::Value, code: i32, message: &str) -> serde_json::Value {
+    json!({ "id": id, "jsonrpc": "2.0", "error": { "code": code, "message": message } })
+}
+
+// `eip155:1` style chain ids of a namespace
+fn namespace_chains(namespace: &serde_json::Value) -> Vec<String> {
+    namespace["chains"]
+        .as_array()
+        .map(|chains| chains.iter().filter_map(|chain| chain.as_str().map(str::to_string)).collect())
+        .unwrap_or_default()
+}
+
+// Union of a list field over the eip155 namespaces of a proposal
+fn requested(required: &serde_json::Value, optional: &serde_json::Value, field: &str) -> Vec<String> {
+    let mut values: Vec<String> = Vec::new();
+    for namespace in [&required["eip155"], &optional["eip155"]] {
+        for value in namespace[field].as_array().into_iter().flatten().filter_map(|v| v.as_str()) {
+            if !values.iter().any(|existing| existing == value) {
+                values.push(value.to_string());
+            }
+        }
+    }
+    values
+}
+
+enum Flow {
+    Continue,
+    Done,
+}
+
+// One relay connection serving a pairing and the sessions settled over it
+struct Relay {
+    socket: WsStream,
+    // Symmetric key of every subscribed topic
+    keys: HashMap<String, B256>,
+    // Session topic to the origin its requests are dispatched as
+    sessions: HashMap<String, String>,
+}
+
+impl Relay {
+    async fn send(&mut self, frame: serde_json::Value) -> Result<(), String> {
+        self.socket
+            .send(Message::Text(frame.to_string()))
+            .await
+            .map_err(|e| format!("WalletConnect relay connection failed: {}", e))
+    }
+
+    async fn subscribe(&mut self, topic: &str, key: B256) -> Result<(), String> {
+        self.keys.insert(topic.to_string(), key);
+        self.send(json!({ "id": payload_id(), "jsonrpc": "2.0", "method": "irn_subscribe", "params": { "topic": topic } })).await
+    }
+
+    async fn publish(&mut self, topic: &str, payload: &serde_json::Value, tag: u32) -> Result<(), String> {
+        let key = self.keys.get(topic).ok_or_else(|| format!("Not subscribed to {}", topic))?;
+        let message = seal(key, payload)?;
+        self.send(json!({
+            "id": payload_id(),
+            "jsonrpc": "2.0",
+            "method": "irn_publish",
+            "params": { "topic": topic, "message": message, "ttl": MESSAGE_TTL_SECS, "tag": tag, "prompt": false },
+        })).await
+    }
+
+    async fn receive(
+        &mut self,
+        app: &AppHandle,
+        pairing: &Pairing,
+        topic: &str,
+        message: &str,
+        answers: &mpsc::UnboundedSender<(String, serde_json::Value)>,
+    ) -> Result<Flow, String> {
+        let Some(key) = self.keys.get(topic) else { return Ok(Flow::Continue) };
+        let payload = open(key, message)?;
+        let id = payload["id"].clone();
+        // Responses, e.g. the dapp acknowledging the settlement
+        let Some(method) = payload["method"].as_str() else { return Ok(Flow::Continue) };
+
+        if topic == pairing.topic {
+            match method {
+                "wc_sessionPropose" => self.propose(app, pairing, &id, &payload["params"]).await?,
+                "wc_pairingPing" => self.publish(topic, &rpc_result(&id, json!(true)), TAG_PAIRING_PING_RESPONSE).await?,
+                "wc_pairingDelete" => {
+                    self.keys.remove(topic);
+                    if self.sessions.is_empty() {
+                        return Ok(Flow::Done);
+                    }
+                },
+                _ => {},
+            }
+            return Ok(Flow::Continue);
+        }
+
+        let Some(origin) = self.sessions.get(topic).cloned() else { return Ok(Flow::Continue) };
+        match method {
+            "wc_sessionRequest" => {
+                let request = &payload["params"]["request"];
+                let call = json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "method": request["method"],
+                    "params": request.get("params").cloned().unwrap_or(json!([])),
+                });
+                // Prompts can take minutes, so requests are answered from their own task
+                let (app, answers, topic) = (app.clone(), answers.clone(), topic.to_string());
+                tauri::async_runtime::spawn(async move {
+                    let caller = Caller { origin, webview: None };
+                    let answer = match crate::handle_request(app.clone(), caller, app.state::<Mutex<AppState>>(), call).await {
+                        Ok(response) => response,
+                        Err(e) => rpc_error(&id, errors::INTERNAL_ERROR, &e),
+                    };
+                    let _ = answers.send((topic, answer));
+                });
+            },
+            "wc_sessionPing" => self.publish(topic, &rpc_result(&id, json!(true)), TAG_SESSION_PING_RESPONSE).await?,
+            "wc_sessionDelete" => {
+                self.publish(topic, &rpc_result(&id, json!(true)), TAG_SESSION_DELETE_RESPONSE).await?;
+                self.keys.remove(topic);
+                self.sessions.remove(topic);
+                app.state::<Mutex<AppState>>().lock().await.connections.revoke(&origin);
+                if self.sessions.is_empty() {
+                    return Ok(Flow::Done);
+                }
+            },
+            _ => {},
+        }
+        Ok(Flow::Continue)
+    }
+
+    // Asks the user about the dapp's proposal and settles a session on the app's chain for the unlocked accounts
+    async fn propose(&mut self, app: &AppHandle, pairing: &Pairing, id: &serde_json::Value, params: &serde_json::Value) -> Result<(), String> {
+        let required = &params["requiredNamespaces"];
+        let optional = &params["optionalNamespaces"];
+        let peer = &params["proposer"]["metadata"];
+        let proposer_key = params["proposer"]["publicKey"].as_str().ok_or("Proposal without a proposer key")?;
+        // The peer's URL is only its claim, so its sessions never share a browser origin's connection
+        let origin = format!("{} (WalletConnect)", peer["url"].as_str().unwrap_or("unknown dapp"));
+
+        let (chain_id, accounts) = {
+            let state = app.state::<Mutex<AppState>>();
+            let state_guard = state.lock().await;
+            (state_guard.chain_id, state_guard.wallet.addresses())
+        };
+        let chain = format!("eip155:{}", chain_id);
+        let refusal = if required.as_object().is_some_and(|namespaces| namespaces.keys().any(|key| key != "eip155")) {
+            Some((UNSUPPORTED_NAMESPACE_KEY, "Only eip155 namespaces are supported".to_string()))
+        } else if namespace_chains(&required["eip155"]).iter().any(|required| *required != chain) {
+            Some((UNSUPPORTED_CHAINS, format!("Only {} is supported", chain)))
+        } else if accounts.is_empty() {
+            Some((USER_REJECTED, "Wallet is locked".to_string()))
+        } else if !prompts::ask(app, "wc_sessionPropose", json!({ "origin": origin, "peer": peer, "accounts": accounts, "chainId": chain_id })).await {
+            Some((USER_REJECTED, "User rejected.".to_string()))
+        } else {
+            None
+        };
+        if let Some((code, message)) = refusal {
+            return self.publish(&pairing.topic, &rpc_error(id, code, &message), TAG_SESSION_PROPOSE_RESPONSE).await;
+        }
+
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public_key = hex::encode(PublicKey::from(&secret).as_bytes());
+        let key = session_key(secret, proposer_key)?;
+        let session_topic = topic_of(&key);
+        self.subscribe(&session_topic, key).await?;
+        let approval = json!({ "relay": { "protocol": "irn" }, "responderPublicKey": public_key });
+        self.publish(&pairing.topic, &rpc_result(id, approval), TAG_SESSION_PROPOSE_RESPONSE).await?;
+
+        let settle = json!({
+            "id": payload_id(),
+            "jsonrpc": "2.0",
+            "method": "wc_sessionSettle",
+            "params": {
+                "relay": { "protocol": "irn" },
+                "namespaces": {
+                    "eip155": {
+                        "chains": [chain],
+                        "accounts": accounts.iter().map(|account: &Address| format!("{}:{}", chain, account)).collect::<Vec<_>>(),
+                        "methods": requested(required, optional, "methods"),
+                        "events": requested(required, optional, "events"),
+                    },
+                },
+                "requiredNamespaces": required,
+                "optionalNamespaces": optional,
+                "pairingTopic": pairing.topic,
+                "controller": {
+                    "publicKey": public_key,
+                    "metadata": {
+                        "name": app.package_info().name,
+                        "description": "Light client wallet",
+                        "url": "https://github.com/evmts/chrome",
+                        "icons": [],
+                    },
+                },
+                "expiry": now() + SESSION_TTL_SECS,
+            },
+        });
+        self.publish(&session_topic, &settle, TAG_SESSION_SETTLE).await?;
+
+        app.state::<Mutex<AppState>>().lock().await.connections.connect(&origin, None, accounts, Some(chain_id));
+        let _ = app.emit_to(
+            EventTarget::webview_window("main"),
+            "walletconnect-session",
+            SessionEvent { topic: &session_topic, origin: &origin, peer },
+        );
+        self.sessions.insert(session_topic, origin);
+        Ok(())
+    }
+}
+
+// Connects to the relay with a fresh client key, waits for the dapp's proposal on the pairing topic
+// and answers the requests of the sessions settled over it until they're deleted
+async fn serve(app: &AppHandle, pairing: &Pairing, settings: WalletConnectSettings) -> Result<(), String> {
+    let project_id = settings.project_id.ok_or("Set walletconnect.projectId to pair with WalletConnect dapps")?;
+    let client_key = SigningKey::generate(&mut OsRng);
+    let url = format!(
+        "{}/?auth={}&projectId={}",
+        settings.relay_url.trim_end_matches('/'),
+        relay_auth(&client_key, &settings.relay_url),
+        project_id,
+    );
+    let socket = outbound::connect_ws(&url).await?;
+    let mut relay = Relay { socket, keys: HashMap::new(), sessions: HashMap::new() };
+    relay.subscribe(&pairing.topic, pairing.sym_key).await?;
+
+    let (answers_tx, mut answers) = mpsc::unbounded_channel();
+    let expires_in = Duration::from_secs(pairing.expiry_timestamp.saturating_sub(now()));
+    let expiry = tokio::time::sleep(expires_in);
+    tokio::pin!(expiry);
+    loop {
+        tokio::select! {
+            frame = relay.socket.next() => {
+                let text = match frame {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => return Err("WalletConnect relay closed the connection".to_string()),
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(format!("WalletConnect relay connection failed: {}", e)),
+                };
+                let Ok(frame) = serde_json::from_str::<serde_json::Value>(&text) else { continue };
+                // Everything else is the relay acknowledging our own subscribe and publish calls
+                if frame["method"] != "irn_subscription" {
+                    continue;
+                }
+                relay.send(rpc_result(&frame["id"], json!(true))).await?;
+                let data = &frame["params"]["data"];
+                let (Some(topic), Some(message)) = (data["topic"].as_str(), data["message"].as_str()) else { continue };
+                match relay.receive(app, pairing, topic, message, &answers_tx).await {
+                    Ok(Flow::Continue) => {},
+                    Ok(Flow::Done) => return Ok(()),
+                    Err(e) => tracing::warn!("Dropped WalletConnect message on {}: {}", topic, e),
+                }
+            },
+            Some((topic, answer)) = answers.recv() => relay.publish(&topic, &answer, TAG_SESSION_REQUEST_RESPONSE).await?,
+            _ = &mut expiry, if relay.sessions.is_empty() => {
+                return Err("The pairing expired before the dapp proposed a session".to_string());
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelopes_round_trip_and_reject_other_keys() {
+        let key = B256::random();
+        let payload = json!({ "id": 1, "jsonrpc": "2.0", "method": "wc_sessionPing", "params": {} });
+        let message = seal(&key, &payload).unwrap();
+        assert_eq!(open(&key, &message).unwrap(), payload);
+        assert!(open(&B256::random(), &message).is_err());
+    }
+
+    #[test]
+    fn both_sides_derive_the_same_session_topic() {
+        let wallet = EphemeralSecret::random_from_rng(OsRng);
+        let dapp = EphemeralSecret::random_from_rng(OsRng);
+        let wallet_public = hex::encode(PublicKey::from(&wallet).as_bytes());
+        let dapp_public = hex::encode(PublicKey::from(&dapp).as_bytes());
+        let wallet_key = session_key(wallet, &dapp_public).unwrap();
+        let dapp_key = session_key(dapp, &wallet_public).unwrap();
+        assert_eq!(topic_of(&wallet_key), topic_of(&dapp_key));
+    }
+
+    #[test]
+    fn uri_parsing_keeps_the_key_out_of_serialized_pairings() {
+        let key = "587d5484ce2a2a6ee3ba1962fdd7e8588e06200c46823bd18fbd67def96ad303";
+        let uri = format!(
+            "wc:7f6e504bfad60b485450578e05678ed3e8e8c4751d3c6160be17160d63ec90f9@2?relay-protocol=irn&symKey={}",
+            key
+        );
+        let pairing = parse(&uri).unwrap();
+        assert!(!serde_json::to_string(&pairing).unwrap().contains(key));
+    }
+}
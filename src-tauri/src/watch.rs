@@ -0,0 +1,187 @@
+use alloy::primitives::utils::format_ether;
+use alloy::primitives::{Address, U256};
+use helios::core::types::BlockTag;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Mutex;
+
+use crate::notify;
+use crate::profile;
+use crate::AppState;
+
+const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(4);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchedAddress {
+    pub address: Address,
+    pub label: Option<String>,
+    // Smallest balance change in wei worth reporting, nonce changes are always reported
+    #[serde(default)]
+    pub threshold: U256,
+    #[serde(default)]
+    pub notify: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressChange {
+    pub address: Address,
+    pub label: Option<String>,
+    pub block_number: u64,
+    pub previous_balance: U256,
+    pub balance: U256,
+    pub previous_nonce: u64,
+    pub nonce: u64,
+}
+
+pub fn watch_list_path(app: &AppHandle) -> Option<PathBuf> {
+    profile::data_dir(app).map(|dir| dir.join("watched.json"))
+}
+
+// Watched addresses, persisted to the app data dir between runs
+#[derive(Default)]
+pub struct WatchList {
+    addresses: Vec<WatchedAddress>,
+    loaded: bool,
+}
+
+impl WatchList {
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    pub async fn load(&mut self, path: &Path) {
+        if let Ok(bytes) = tokio::fs::read(path).await {
+            if let Ok(addresses) = serde_json::from_slice(&bytes) {
+                self.addresses = addresses;
+            }
+        }
+        self.loaded = true;
+    }
+
+    pub async fn save(&self, path: &Path) -> Result<(), String> {
+        let bytes = serde_json::to_vec(&self.addresses).map_err(|e| format!("Failed to serialize watch list: {}", e))?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Failed to create data dir: {}", e))?;
+        }
+        tokio::fs::write(path, bytes)
+            .await
+            .map_err(|e| format!("Failed to write watch list: {}", e))
+    }
+
+    pub fn list(&self) -> &[WatchedAddress] {
+        &self.addresses
+    }
+
+    pub fn upsert(&mut self, watched: WatchedAddress) {
+        match self.addresses.iter_mut().find(|entry| entry.address == watched.address) {
+            Some(entry) => *entry = watched,
+            None => self.addresses.push(watched),
+        }
+    }
+
+    pub fn remove(&mut self, address: Address) -> bool {
+        let before = self.addresses.len();
+        self.addresses.retain(|entry| entry.address != address);
+        self.addresses.len() != before
+    }
+}
+
+pub async fn load_watch_list(app: &AppHandle, list: &mut WatchList) {
+    if let (false, Some(path)) = (list.is_loaded(), watch_list_path(app)) {
+        list.load(&path).await;
+    }
+}
+
+fn describe(change: &AddressChange) -> String {
+    let name = change.label.clone().unwrap_or_else(|| change.address.to_checksum(None));
+    if change.balance > change.previous_balance {
+        format!("{} received {} ETH", name, format_ether(change.balance - change.previous_balance))
+    } else if change.balance < change.previous_balance {
+        format!("{} sent {} ETH", name, format_ether(change.previous_balance - change.balance))
+    } else {
+        format!("{} sent a transaction (nonce {})", name, change.nonce)
+    }
+}
+
+// Reads balance and nonce of every watched address at each new verified head and emits
+// `watched-address-changed` when either moves past the address's threshold
+pub fn spawn_address_watcher(app: AppHandle) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        let mut head: Option<u64> = None;
+        let mut snapshots: HashMap<Address, (U256, u64)> = HashMap::new();
+        let mut interval = tokio::time::interval(WATCH_POLL_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let state = app.state::<Mutex<AppState>>();
+            let mut state_guard = state.lock().await;
+            load_watch_list(&app, &mut state_guard.watched).await;
+            let accounts = state_guard.account_source(state_guard.chain_id);
+            let Some(client) = state_guard.client.as_ref() else {
+                break;
+            };
+
+            let number = match client.get_block_by_number(BlockTag::Latest, false).await {
+                Ok(Some(block)) => block.number.to::<u64>(),
+                _ => continue,
+            };
+            if head == Some(number) {
+                continue;
+            }
+            head = Some(number);
+
+            let watched = state_guard.watched.list();
+            snapshots.retain(|address, _| watched.iter().any(|entry| entry.address == *address));
+
+            for entry in watched {
+                let tag = BlockTag::Number(number);
+                let (balance, nonce) = match accounts.account(client.as_ref(), entry.address, tag).await {
+                    Ok(account) => (account.balance, account.nonce),
+                    Err(e) => {
+                        tracing::warn!("Failed to read watched address 0x{:x}: {}", entry.address, e);
+                        continue;
+                    },
+                };
+
+                let Some((previous_balance, previous_nonce)) = snapshots.get(&entry.address).copied() else {
+                    snapshots.insert(entry.address, (balance, nonce));
+                    continue;
+                };
+                let delta = if balance > previous_balance { balance - previous_balance } else { previous_balance - balance };
+                let significant = nonce != previous_nonce || (!delta.is_zero() && delta >= entry.threshold);
+                if !significant {
+                    continue;
+                }
+                snapshots.insert(entry.address, (balance, nonce));
+
+                let change = AddressChange {
+                    address: entry.address,
+                    label: entry.label.clone(),
+                    block_number: number,
+                    previous_balance,
+                    balance,
+                    previous_nonce,
+                    nonce,
+                };
+                if entry.notify {
+                    let (category, title) = if balance > previous_balance && nonce == previous_nonce {
+                        (notify::Category::IncomingTransfer, "Incoming transfer")
+                    } else {
+                        (notify::Category::WatchedActivity, "Watched address activity")
+                    };
+                    notify::show(&app, &state_guard.notifications, category, title, describe(&change));
+                }
+                let _ = app.emit("watched-address-changed", change);
+            }
+        }
+    })
+}
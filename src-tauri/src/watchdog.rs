@@ -0,0 +1,95 @@
+use helios::core::types::BlockTag;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Mutex;
+
+use crate::AppState;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const STALL_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const MAX_CONSECUTIVE_ERRORS: u32 = 5;
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientHealth {
+    pub status: &'static str,
+    pub reason: Option<String>,
+}
+
+// Restarts the client from its saved config when verified heads stop advancing or it keeps erroring
+pub fn spawn_watchdog(app: AppHandle) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        let mut last_head: Option<u64> = None;
+        let mut last_advance = Instant::now();
+        let mut consecutive_errors = 0;
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let state = app.state::<Mutex<AppState>>();
+            let head = {
+                let state_guard = state.lock().await;
+                let Some(client) = state_guard.client.as_ref() else {
+                    // Nothing to watch while stopped, start the stall timer over once a client is back
+                    last_head = None;
+                    last_advance = Instant::now();
+                    continue;
+                };
+                client.get_block_by_number(BlockTag::Latest, false).await
+            };
+
+            let reason = match head {
+                Ok(Some(block)) => {
+                    consecutive_errors = 0;
+                    let number = block.number.to::<u64>();
+                    if last_head != Some(number) {
+                        last_head = Some(number);
+                        last_advance = Instant::now();
+                        continue;
+                    }
+                    if last_advance.elapsed() < STALL_TIMEOUT {
+                        continue;
+                    }
+                    format!("No new verified head since block {} for {}s", number, last_advance.elapsed().as_secs())
+                },
+                Ok(None) => continue,
+                Err(e) => {
+                    consecutive_errors += 1;
+                    if consecutive_errors < MAX_CONSECUTIVE_ERRORS {
+                        continue;
+                    }
+                    format!("{} consecutive errors, last: {}", consecutive_errors, e)
+                }
+            };
+
+            tracing::warn!("Light client degraded, restarting: {}", reason);
+            let _ = app.emit("client-health", ClientHealth {
+                status: "degraded",
+                reason: Some(reason),
+            });
+
+            match crate::restart_client(&app, &state).await {
+                Ok(()) => {
+                    let _ = app.emit("client-health", ClientHealth {
+                        status: "recovered",
+                        reason: None,
+                    });
+                },
+                Err(e) => {
+                    tracing::error!("Failed to restart light client: {}", e);
+                    let _ = app.emit("client-health", ClientHealth {
+                        status: "failed",
+                        reason: Some(e),
+                    });
+                }
+            }
+
+            last_head = None;
+            last_advance = Instant::now();
+            consecutive_errors = 0;
+        }
+    })
+}
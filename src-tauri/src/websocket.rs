@@ -0,0 +1,172 @@
+use futures::{SinkExt, StreamExt};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::metrics;
+use crate::outbound::{self, WsStream};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+// After a failed connection, requests go over HTTP for this long before the socket is retried
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// WebSocket endpoint configured for each HTTP execution RPC. passthrough::forward only knows the
+// HTTP url, so like the upstream metrics these live in a static
+static ENDPOINTS: std::sync::Mutex<BTreeMap<String, Arc<Connection>>> = std::sync::Mutex::new(BTreeMap::new());
+
+type Reply = oneshot::Sender<Result<serde_json::Value, String>>;
+
+struct Request {
+    method: String,
+    params: Vec<serde_json::Value>,
+    reply: Reply,
+}
+
+#[derive(Default)]
+struct Link {
+    // Feeds the task that owns the socket, closed once the socket drops
+    sender: Option<mpsc::UnboundedSender<Request>>,
+    failed_at: Option<Instant>,
+}
+
+struct Connection {
+    ws_url: String,
+    link: Mutex<Link>,
+}
+
+// Routes upstream calls for `rpc_url` over `ws_url`, or back to plain HTTP when it's None
+pub fn configure(rpc_url: &str, ws_url: Option<&str>) {
+    let mut endpoints = ENDPOINTS.lock().unwrap();
+    match ws_url {
+        Some(ws_url) if endpoints.get(rpc_url).is_some_and(|connection| connection.ws_url == ws_url) => {},
+        Some(ws_url) => {
+            endpoints.insert(rpc_url.to_string(), Arc::new(Connection {
+                ws_url: ws_url.to_string(),
+                link: Mutex::default(),
+            }));
+        },
+        None => {
+            endpoints.remove(rpc_url);
+        },
+    }
+}
+
+// Sends the request over the persistent connection configured for `rpc_url`. None means there is
+// no usable connection and the caller should fall back to HTTP
+pub async fn request(rpc_url: &str, method: &str, params: &[serde_json::Value]) -> Option<serde_json::Value> {
+    let connection = ENDPOINTS.lock().unwrap().get(rpc_url).cloned()?;
+    let sender = connection.sender().await?;
+
+    let (reply, response) = oneshot::channel();
+    let request = Request { method: method.to_string(), params: params.to_vec(), reply };
+    sender.send(request).ok()?;
+    match tokio::time::timeout(REQUEST_TIMEOUT, response).await {
+        Ok(Ok(Ok(response))) => Some(response),
+        Ok(Ok(Err(e))) => {
+            tracing::warn!("WebSocket request to {} failed, using HTTP: {}", connection.ws_url, e);
+            None
+        },
+        Ok(Err(_)) | Err(_) => {
+            tracing::warn!("WebSocket request to {} got no answer, using HTTP", connection.ws_url);
+            None
+        },
+    }
+}
+
+impl Connection {
+    // Live sender to the socket task, connecting first if the last socket dropped
+    async fn sender(&self) -> Option<mpsc::UnboundedSender<Request>> {
+        let mut link = self.link.lock().await;
+        if let Some(sender) = link.sender.as_ref().filter(|sender| !sender.is_closed()) {
+            return Some(sender.clone());
+        }
+        link.sender = None;
+        if link.failed_at.is_some_and(|failed_at| failed_at.elapsed() < RECONNECT_DELAY) {
+            return None;
+        }
+
+        let socket = match tokio::time::timeout(CONNECT_TIMEOUT, outbound::connect_ws(&self.ws_url)).await {
+            Ok(Ok(socket)) => socket,
+            Ok(Err(e)) => {
+                tracing::warn!("{}, using HTTP", e);
+                link.failed_at = Some(Instant::now());
+                return None;
+            },
+            Err(_) => {
+                tracing::warn!("Timed out connecting to {}, using HTTP", self.ws_url);
+                link.failed_at = Some(Instant::now());
+                return None;
+            },
+        };
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tauri::async_runtime::spawn(drive(socket, receiver, self.ws_url.clone()));
+        link.sender = Some(sender.clone());
+        link.failed_at = None;
+        Some(sender)
+    }
+}
+
+// Owns the socket: writes requests under fresh ids and hands each response to its caller. Ends
+// when the socket drops, failing whatever is still in flight so the callers fall back to HTTP
+async fn drive(socket: WsStream, mut receiver: mpsc::UnboundedReceiver<Request>, ws_url: String) {
+    let (mut sink, mut stream) = socket.split();
+    let mut next_id: u64 = 0;
+    // Reply channel and request size of each request awaiting its response
+    let mut in_flight: HashMap<u64, (Reply, usize)> = HashMap::new();
+
+    let error = loop {
+        tokio::select! {
+            request = receiver.recv() => {
+                let Some(request) = request else {
+                    break "Connection no longer used".to_string();
+                };
+                next_id += 1;
+                let text = json!({
+                    "jsonrpc": "2.0",
+                    "method": request.method,
+                    "params": request.params,
+                    "id": next_id
+                }).to_string();
+                let sent = text.len();
+                if let Err(e) = sink.send(Message::Text(text)).await {
+                    metrics::record_upstream(&ws_url, sent, 0, true);
+                    let _ = request.reply.send(Err(e.to_string()));
+                    break e.to_string();
+                }
+                in_flight.insert(next_id, (request.reply, sent));
+            },
+            message = stream.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => break "Connection closed".to_string(),
+                    Some(Err(e)) => break e.to_string(),
+                    // Pings are answered by tungstenite
+                    Some(Ok(_)) => continue,
+                };
+                let Ok(response) = serde_json::from_str::<serde_json::Value>(&text) else {
+                    continue;
+                };
+                let Some(id) = response.get("id").and_then(|id| id.as_u64()) else {
+                    continue;
+                };
+                if let Some((reply, sent)) = in_flight.remove(&id) {
+                    metrics::record_upstream(&ws_url, sent, text.len(), false);
+                    let _ = reply.send(Ok(response));
+                }
+            },
+        }
+    };
+
+    receiver.close();
+    for (_, (reply, sent)) in in_flight {
+        metrics::record_upstream(&ws_url, sent, 0, true);
+        let _ = reply.send(Err(error.clone()));
+    }
+    while let Ok(request) = receiver.try_recv() {
+        let _ = request.reply.send(Err(error.clone()));
+    }
+}
@@ -0,0 +1,174 @@
+use alloy::primitives::B256;
+use helios::core::types::BlockTag;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::client::EthClientApi;
+use crate::errors;
+
+// Helios keeps execution payloads for the most recent 64 blocks, older state can't be proven
+pub const VERIFICATION_WINDOW: u64 = 64;
+
+// What to do when a request names a block outside the verification window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OutOfWindow {
+    #[default]
+    Error,
+    // Forward to the execution RPC and tag the response `unverified`
+    Unverified,
+}
+
+// Position of the block parameter for methods that read state or blocks at a given height
+pub fn block_param_index(method: &str) -> Option<usize> {
+    match method {
+        "eth_getBlockByNumber" | "eth_getBlockTransactionCountByNumber" | "eth_getBlockReceipts" => Some(0),
+        "eth_getBalance" | "eth_getCode" | "eth_getTransactionCount" | "eth_call" | "eth_estimateGas"
+        | "eth_simulateV1" => Some(1),
+        "eth_getStorageAt" | "eth_getProof" => Some(2),
+        _ => None,
+    }
+}
+
+// Explicit block number in a block parameter. Tags always resolve inside the window, and a hash
+// the client can't place fails in the method itself
+pub fn requested_number(param: &serde_json::Value) -> Option<u64> {
+    let value = param.get("blockNumber").unwrap_or(param).as_str()?;
+    match value {
+        "earliest" => Some(0),
+        value if value.len() < 66 => u64::from_str_radix(value.strip_prefix("0x")?, 16).ok(),
+        _ => None,
+    }
+}
+
+// Block a request reaches back to, checked against the window before any handler runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Requested {
+    Number(u64),
+    Hash(B256),
+}
+
+// The explicit block number or hash a request names: its block parameter, the older end of an
+// eth_getLogs range or its blockHash, or the block of a by-hash lookup
+pub fn requested_block(method: &str, params: &[serde_json::Value]) -> Option<Requested> {
+    let hash = |param: &serde_json::Value| param.as_str()?.parse::<B256>().ok().map(Requested::Hash);
+    match method {
+        "eth_getBlockByHash" | "eth_getBlockTransactionCountByHash" | "eth_getTransactionByBlockHashAndIndex" => {
+            hash(params.first()?)
+        },
+        "eth_getLogs" => {
+            let filter = params.first()?;
+            if let Some(block_hash) = filter.get("blockHash") {
+                return hash(block_hash);
+            }
+            ["fromBlock", "toBlock"]
+                .iter()
+                .filter_map(|bound| filter.get(*bound))
+                .filter_map(requested_number)
+                .min()
+                .map(Requested::Number)
+        },
+        _ => {
+            let param = params.get(block_param_index(method)?)?;
+            requested_number(param)
+                .map(Requested::Number)
+                .or_else(|| hash(param.get("blockHash").unwrap_or(param)))
+        },
+    }
+}
+
+// Blocks whose state the light client can currently prove
+#[derive(Debug, Clone, Copy)]
+pub struct Window {
+    pub oldest: u64,
+    pub latest: u64,
+}
+
+impl Window {
+    pub async fn current(client: &dyn EthClientApi) -> Result<Self, String> {
+        let latest = client.get_block_by_number(BlockTag::Latest, false)
+            .await
+            .map_err(|e| format!("failed to get latest block: {}", e))?
+            .ok_or("no verified block yet")?
+            .number
+            .to::<u64>();
+        Ok(Self {
+            oldest: latest.saturating_sub(VERIFICATION_WINDOW - 1),
+            latest,
+        })
+    }
+
+    pub fn contains(&self, number: u64) -> bool {
+        (self.oldest..=self.latest).contains(&number)
+    }
+
+    // The light client only holds the blocks of its window, so a hash it can't place is outside it
+    // or not on the chain at all
+    pub async fn place(&self, client: &dyn EthClientApi, requested: Requested) -> Result<(), serde_json::Value> {
+        let number = match requested {
+            Requested::Number(number) => number,
+            Requested::Hash(hash) => match client.get_block_by_hash(hash, false).await {
+                Ok(Some(block)) => block.number.to::<u64>(),
+                Ok(None) => return Err(self.unplaced(hash)),
+                // The handler reports the failure
+                Err(_) => return Ok(()),
+            },
+        };
+        if self.contains(number) {
+            Ok(())
+        } else {
+            Err(self.error(number))
+        }
+    }
+
+    fn unplaced(&self, hash: B256) -> serde_json::Value {
+        json!({
+            "code": errors::RESOURCE_NOT_FOUND,
+            "message": format!(
+                "Block 0x{:x} is not in the verifiable range {}..={}",
+                hash, self.oldest, self.latest
+            ),
+            "data": {
+                "requestedBlockHash": hash,
+                "oldestVerifiable": format!("0x{:x}", self.oldest),
+                "latestVerifiable": format!("0x{:x}", self.latest),
+            },
+        })
+    }
+
+    pub fn error(&self, number: u64) -> serde_json::Value {
+        json!({
+            "code": errors::RESOURCE_NOT_FOUND,
+            "message": format!(
+                "Block {} is outside the verifiable range {}..={}",
+                number, self.oldest, self.latest
+            ),
+            "data": {
+                "requestedBlock": format!("0x{:x}", number),
+                "oldestVerifiable": format!("0x{:x}", self.oldest),
+                "latestVerifiable": format!("0x{:x}", self.latest),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logs_ranges_and_hashes_are_checked_against_the_window() {
+        let hash = B256::repeat_byte(0xaa);
+        let range = json!({ "fromBlock": "0x10", "toBlock": "latest" });
+        assert_eq!(requested_block("eth_getLogs", &[range]), Some(Requested::Number(0x10)));
+        assert_eq!(requested_block("eth_getLogs", &[json!({ "blockHash": hash })]), Some(Requested::Hash(hash)));
+        assert_eq!(requested_block("eth_getLogs", &[json!({ "toBlock": "latest" })]), None);
+        assert_eq!(requested_block("eth_getBlockByHash", &[json!(hash), json!(false)]), Some(Requested::Hash(hash)));
+        assert_eq!(
+            requested_block("eth_getBalance", &[json!("0x00"), json!({ "blockHash": hash })]),
+            Some(Requested::Hash(hash))
+        );
+        assert_eq!(requested_block("eth_getBalance", &[json!("0x00"), json!("earliest")]), Some(Requested::Number(0)));
+        assert_eq!(requested_block("eth_getBalance", &[json!("0x00"), json!("latest")]), None);
+    }
+}